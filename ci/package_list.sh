@@ -10,6 +10,7 @@ RELEASE_PACKAGES=(
     tedge_apama_plugin
     c8y_log_plugin
     c8y_configuration_plugin
+    tedge_store
 )
 export RELEASE_PACKAGES
 
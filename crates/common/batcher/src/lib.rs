@@ -5,6 +5,7 @@ mod batchable;
 mod batcher;
 mod config;
 mod driver;
+mod window;
 
 pub use crate::batchable::Batchable;
 pub use crate::batcher::Batcher;
@@ -16,3 +17,4 @@ pub use crate::config::EventBatchConfigBuilder;
 pub use crate::driver::BatchDriver;
 pub use crate::driver::BatchDriverInput;
 pub use crate::driver::BatchDriverOutput;
+pub use crate::window::TimeWindow;
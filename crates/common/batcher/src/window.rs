@@ -0,0 +1,98 @@
+use time::{Duration, OffsetDateTime};
+
+/// A fixed time window, used to group the events of a series over a regular period.
+///
+/// The windows of a given duration are aligned on multiples of this duration since the Unix epoch,
+/// so the events of different series are grouped over the same periods.
+///
+/// Unlike the batches of a [`Batcher`](crate::Batcher), these windows don't depend on the events:
+/// they are neither opened around a first event with some jitter nor split on a duplicate event,
+/// hence a [`BatchConfig`](crate::BatchConfig) doesn't apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimeWindow {
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+}
+
+impl TimeWindow {
+    /// The window of the given duration that contains the given time.
+    ///
+    /// A duration shorter than a nanosecond is rounded up to a nanosecond.
+    pub fn containing(time: OffsetDateTime, duration: Duration) -> TimeWindow {
+        let duration_nanos = duration.whole_nanoseconds().max(1);
+        let time_nanos = time.unix_timestamp_nanos();
+        let start_nanos = time_nanos - time_nanos.rem_euclid(duration_nanos);
+        let at = |nanos| {
+            OffsetDateTime::from_unix_timestamp_nanos(nanos).expect("a time window within range")
+        };
+
+        TimeWindow {
+            start: at(start_nanos),
+            end: at(start_nanos + duration_nanos),
+        }
+    }
+
+    /// Get the start of the window, included in the window.
+    pub fn start(&self) -> OffsetDateTime {
+        self.start
+    }
+
+    /// Get the end of the window, excluded from the window.
+    pub fn end(&self) -> OffsetDateTime {
+        self.end
+    }
+
+    /// Check if the given time is in the window.
+    pub fn contains(&self, time: OffsetDateTime) -> bool {
+        self.start <= time && time < self.end
+    }
+
+    /// Check if the window is over at the given time.
+    pub fn is_closed_at(&self, time: OffsetDateTime) -> bool {
+        self.end <= time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn windows_are_aligned_on_their_duration() {
+        let window = TimeWindow::containing(datetime!(2021-04-08 10:12:34.5 UTC), Duration::MINUTE);
+
+        assert_eq!(window.start(), datetime!(2021-04-08 10:12:00 UTC));
+        assert_eq!(window.end(), datetime!(2021-04-08 10:13:00 UTC));
+    }
+
+    #[test]
+    fn the_window_start_is_included_and_the_window_end_excluded() {
+        let start = datetime!(2021-04-08 10:00:00 UTC);
+        let window = TimeWindow::containing(start, Duration::minutes(15));
+
+        assert!(window.contains(start));
+        assert!(window.contains(start + Duration::minutes(15) - Duration::nanoseconds(1)));
+        assert!(!window.contains(start + Duration::minutes(15)));
+        assert_eq!(
+            TimeWindow::containing(start + Duration::minutes(15), Duration::minutes(15)).start(),
+            start + Duration::minutes(15)
+        );
+    }
+
+    #[test]
+    fn windows_are_closed_at_their_end() {
+        let window = TimeWindow::containing(datetime!(2021-04-08 10:12:34 UTC), Duration::MINUTE);
+
+        assert!(!window.is_closed_at(datetime!(2021-04-08 10:12:59 UTC)));
+        assert!(window.is_closed_at(datetime!(2021-04-08 10:13:00 UTC)));
+    }
+
+    #[test]
+    fn windows_are_aligned_on_utc() {
+        let window = TimeWindow::containing(datetime!(2021-04-08 10:12:34 +05:30), Duration::HOUR);
+
+        assert_eq!(window.start(), datetime!(2021-04-08 04:00:00 UTC));
+        assert_eq!(window.end(), datetime!(2021-04-08 05:00:00 UTC));
+    }
+}
@@ -18,6 +18,15 @@ pub enum DownloadError {
     #[error(transparent)]
     FromNix(#[from] nix::Error),
 
+    #[error("Invalid mirror mapping: {mapping:?}. Expected: <url-prefix>=<target-prefix>")]
+    InvalidMirror { mapping: String },
+
+    #[error("Invalid file URL: {url:?}")]
+    InvalidFileUrl { url: String },
+
+    #[error("Local files can only be downloaded from a configured mirror: {url:?}")]
+    LocalFileNotMirrored { url: String },
+
     #[error("Not enough disk space")]
     InsufficientSpace,
 }
@@ -1,7 +1,11 @@
 mod download;
 mod error;
+mod settings;
 
 pub use crate::download::Auth;
 pub use crate::download::DownloadInfo;
 pub use crate::download::Downloader;
 pub use crate::error::DownloadError;
+pub use crate::settings::ClientIdentity;
+pub use crate::settings::DownloadSettings;
+pub use crate::settings::Mirror;
@@ -1,4 +1,11 @@
+use crate::DiskQueueConfig;
+use crate::Message;
+use crate::Metrics;
+use crate::MqttError;
+use crate::OverflowPolicy;
 use crate::TopicFilter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Configuration of an MQTT connection
 #[derive(Debug, Clone)]
@@ -41,6 +48,100 @@ pub struct Config {
     ///
     /// Default: `1024 * 1024`.
     pub max_packet_size: usize,
+
+    /// Root certificates used to authenticate the broker
+    ///
+    /// Either a PEM file or a directory of PEM files.
+    /// When set, the connection is established over TLS.
+    ///
+    /// Default: None, i.e. a plain TCP connection is used.
+    pub ca_path: Option<PathBuf>,
+
+    /// Certificate and private key used to authenticate the client
+    ///
+    /// Only used over TLS, i.e. when a `ca_path` is also provided.
+    ///
+    /// Default: None
+    pub client_auth: Option<ClientAuth>,
+
+    /// Username and password used to connect the broker
+    ///
+    /// Default: None
+    pub credentials: Option<Credentials>,
+
+    /// Disk-backed queue where the outgoing messages are stored while the broker is not reachable
+    ///
+    /// The queued messages are published in order on reconnect,
+    /// including the messages queued before a restart of the process.
+    /// The queue of a connection is named after its session name:
+    /// this queue is not used by connections without a session name.
+    ///
+    /// Default: None, i.e. the outgoing messages are only kept in memory.
+    pub disk_queue: Option<DiskQueueConfig>,
+
+    /// Message published by the connection on each connect, e.g. to tell the client is up
+    ///
+    /// Default: None
+    pub birth_message: Option<BirthMessage>,
+
+    /// Message published by the broker on behalf of the client, if the connection is lost
+    ///
+    /// This message is also published by the connection itself when closed.
+    ///
+    /// Default: None
+    pub last_will_message: Option<Message>,
+
+    /// Metrics where the connection records the MQTT traffic
+    ///
+    /// The messages received and published are counted per topic prefix,
+    /// along the number of reconnects and the depth of the disk queue.
+    ///
+    /// Default: None, i.e. the metrics of the connection are not exposed.
+    pub metrics: Option<Metrics>,
+}
+
+/// PEM encoded certificate and private key of an MQTT client
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ClientAuth {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+}
+
+/// Username and password of an MQTT client
+#[derive(Clone, Eq, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"********")
+            .finish()
+    }
+}
+
+/// A message published by a connection on each connect
+///
+/// The message is built anew for each connection, so it can tell when the connection was established.
+#[derive(Clone)]
+pub struct BirthMessage {
+    build: Arc<dyn Fn() -> Message + Send + Sync>,
+}
+
+impl BirthMessage {
+    /// The message to be published on a new connection
+    pub fn build(&self) -> Message {
+        (self.build)()
+    }
+}
+
+impl std::fmt::Debug for BirthMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BirthMessage").finish_non_exhaustive()
+    }
 }
 
 /// By default a client connects the local MQTT broker.
@@ -54,6 +155,13 @@ impl Default for Config {
             clean_session: false,
             queue_capacity: 1024,
             max_packet_size: 1024 * 1024,
+            ca_path: None,
+            client_auth: None,
+            credentials: None,
+            disk_queue: None,
+            birth_message: None,
+            last_will_message: None,
+            metrics: None,
         }
     }
 }
@@ -120,8 +228,103 @@ impl Config {
         }
     }
 
-    /// Wrap this config into an internal set of options for `rumqttc`.
-    pub(crate) fn mqtt_options(&self) -> rumqttc::MqttOptions {
+    /// Connect the broker over TLS, authenticating the broker with the given root certificates
+    ///
+    /// The `ca_path` can be either a PEM file or a directory of PEM files.
+    pub fn with_ca_path(self, ca_path: impl Into<PathBuf>) -> Self {
+        Self {
+            ca_path: Some(ca_path.into()),
+            ..self
+        }
+    }
+
+    /// Authenticate the client with a certificate
+    ///
+    /// This is only effective for a TLS connection, i.e. when a CA path is also provided.
+    pub fn with_client_auth(
+        self,
+        cert_file: impl Into<PathBuf>,
+        key_file: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            client_auth: Some(ClientAuth {
+                cert_file: cert_file.into(),
+                key_file: key_file.into(),
+            }),
+            ..self
+        }
+    }
+
+    /// Set the username and password used to connect the broker
+    pub fn with_credentials(
+        self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Self {
+            credentials: Some(Credentials {
+                username: username.into(),
+                password: password.into(),
+            }),
+            ..self
+        }
+    }
+
+    /// Set a disk-backed queue for the outgoing messages
+    pub fn with_disk_queue(
+        self,
+        dir: impl Into<PathBuf>,
+        max_messages: usize,
+        overflow_policy: OverflowPolicy,
+    ) -> Self {
+        Self {
+            disk_queue: Some(DiskQueueConfig {
+                dir: dir.into(),
+                max_messages,
+                overflow_policy,
+            }),
+            ..self
+        }
+    }
+
+    /// Set a message to be published on each connect
+    pub fn with_birth_message(self, message: Message) -> Self {
+        self.with_birth_message_builder(move || message.clone())
+    }
+
+    /// Set a function building the message to be published on each connect
+    pub fn with_birth_message_builder<F>(self, build: F) -> Self
+    where
+        F: Fn() -> Message + Send + Sync + 'static,
+    {
+        Self {
+            birth_message: Some(BirthMessage {
+                build: Arc::new(build),
+            }),
+            ..self
+        }
+    }
+
+    /// Set the last will message of the connection
+    pub fn with_last_will_message(self, message: Message) -> Self {
+        Self {
+            last_will_message: Some(message),
+            ..self
+        }
+    }
+
+    /// Set the metrics where the connection records the MQTT traffic
+    pub fn with_metrics(self, metrics: Metrics) -> Self {
+        Self {
+            metrics: Some(metrics),
+            ..self
+        }
+    }
+
+    /// Wrap this config into a set of options for `rumqttc`.
+    ///
+    /// This fails if the TLS certificates and keys cannot be read.
+    pub fn mqtt_options(&self) -> Result<rumqttc::MqttOptions, MqttError> {
         let id = match &self.session_name {
             None => std::iter::repeat_with(fastrand::lowercase)
                 .take(10)
@@ -140,6 +343,205 @@ impl Config {
 
         mqtt_options.set_max_packet_size(self.max_packet_size, self.max_packet_size);
 
-        mqtt_options
+        if let Some(tls_config) = self.tls_config()? {
+            mqtt_options.set_transport(rumqttc::Transport::tls_with_config(tls_config));
+        }
+
+        if let Some(credentials) = &self.credentials {
+            mqtt_options.set_credentials(&credentials.username, &credentials.password);
+        }
+
+        Ok(mqtt_options)
+    }
+
+    fn tls_config(&self) -> Result<Option<rumqttc::TlsConfiguration>, MqttError> {
+        let ca_path = match (&self.ca_path, &self.client_auth) {
+            (Some(ca_path), _) => ca_path,
+            (None, None) => return Ok(None),
+            (None, Some(_)) => {
+                return Err(MqttError::InvalidTlsConfig {
+                    reason: "a client certificate is provided but no CA path to connect over TLS"
+                        .to_string(),
+                })
+            }
+        };
+
+        let ca = read_pem_files(ca_path)?;
+        let client_auth = match &self.client_auth {
+            Some(client_auth) => Some(client_auth.rumqttc_config()?),
+            None => None,
+        };
+
+        Ok(Some(rumqttc::TlsConfiguration::Simple {
+            ca,
+            alpn: None,
+            client_auth,
+        }))
+    }
+}
+
+impl ClientAuth {
+    fn rumqttc_config(&self) -> Result<(Vec<u8>, rumqttc::Key), MqttError> {
+        let cert = read_file(&self.cert_file)?;
+        let key = private_key(&self.key_file, read_file(&self.key_file)?)?;
+        Ok((cert, key))
+    }
+}
+
+/// The private key of a PEM file, typed after the first key found in the file.
+///
+/// rumqttc expects either a PKCS#1 RSA key or a PKCS#8 key.
+fn private_key(path: &Path, pem: Vec<u8>) -> Result<rumqttc::Key, MqttError> {
+    let invalid_key = |reason: String| MqttError::InvalidTlsConfig {
+        reason: format!("invalid private key {:?}: {}", path, reason),
+    };
+    let items = rustls_pemfile::read_all(&mut pem.as_slice())
+        .map_err(|err| invalid_key(err.to_string()))?;
+    for item in items {
+        match item {
+            rustls_pemfile::Item::RSAKey(_) => return Ok(rumqttc::Key::RSA(pem)),
+            rustls_pemfile::Item::PKCS8Key(_) => return Ok(rumqttc::Key::ECC(pem)),
+            rustls_pemfile::Item::ECKey(_) => {
+                return Err(invalid_key(
+                    "SEC1 EC keys are not supported, the key must be converted to PKCS#8".into(),
+                ))
+            }
+            _ => continue,
+        }
+    }
+    Err(invalid_key(
+        "no PKCS#1 RSA key nor PKCS#8 key found".to_string(),
+    ))
+}
+
+/// Read all the certificates of a PEM file or of the PEM files of a directory.
+///
+/// When a directory is given, only the files with a `.pem` or `.crt` extension are read.
+fn read_pem_files(path: &Path) -> Result<Vec<u8>, MqttError> {
+    if !path.is_dir() {
+        return read_file(path);
+    }
+
+    let read_dir_err = |err: std::io::Error| MqttError::InvalidTlsConfig {
+        reason: format!("failed to read {:?}: {}", path, err),
+    };
+    let mut pems = Vec::new();
+    for entry in std::fs::read_dir(path).map_err(read_dir_err)? {
+        let file_path = entry.map_err(read_dir_err)?.path();
+        let is_pem = matches!(
+            file_path.extension().and_then(|ext| ext.to_str()),
+            Some("pem") | Some("crt")
+        );
+        if is_pem && file_path.is_file() {
+            pems.append(&mut read_file(&file_path)?);
+            pems.push(b'\n');
+        }
+    }
+    Ok(pems)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, MqttError> {
+    std::fs::read(path).map_err(|err| MqttError::InvalidTlsConfig {
+        reason: format!("failed to read {:?}: {}", path, err),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_tcp_is_used_by_default() {
+        let mqtt_options = Config::default().mqtt_options().unwrap();
+
+        assert!(matches!(mqtt_options.transport(), rumqttc::Transport::Tcp));
+        assert_eq!(mqtt_options.credentials(), None);
+    }
+
+    #[test]
+    fn tls_is_used_when_a_ca_path_is_provided() -> anyhow::Result<()> {
+        let ca_dir = tempfile::TempDir::new()?;
+        std::fs::write(ca_dir.path().join("ca.pem"), "some root certificate")?;
+
+        let mqtt_options = Config::default()
+            .with_ca_path(ca_dir.path())
+            .mqtt_options()?;
+
+        assert!(matches!(
+            mqtt_options.transport(),
+            rumqttc::Transport::Tls(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn a_missing_ca_file_is_reported() {
+        let err = Config::default()
+            .with_ca_path("/does/not/exist.pem")
+            .mqtt_options()
+            .unwrap_err();
+
+        assert!(err.to_string().contains("/does/not/exist.pem"));
+    }
+
+    #[test]
+    fn a_client_certificate_requires_a_ca_path() {
+        let result = Config::default()
+            .with_client_auth("/some/cert.pem", "/some/key.pem")
+            .mqtt_options();
+
+        assert!(matches!(result, Err(MqttError::InvalidTlsConfig { .. })));
+    }
+
+    fn pem(label: &str) -> Vec<u8> {
+        format!("-----BEGIN {label}-----\nAAAA\n-----END {label}-----\n").into_bytes()
+    }
+
+    #[test]
+    fn the_key_type_is_given_by_the_key_file() {
+        let path = Path::new("key.pem");
+
+        assert!(matches!(
+            private_key(path, pem("RSA PRIVATE KEY")),
+            Ok(rumqttc::Key::RSA(_))
+        ));
+        assert!(matches!(
+            private_key(path, pem("PRIVATE KEY")),
+            Ok(rumqttc::Key::ECC(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_keys_are_reported() {
+        let path = Path::new("key.pem");
+
+        for pem in [
+            pem("EC PRIVATE KEY"),
+            pem("CERTIFICATE"),
+            b"not a key".to_vec(),
+        ] {
+            let err = private_key(path, pem).unwrap_err();
+            assert!(err.to_string().contains("key.pem"));
+        }
+    }
+
+    #[test]
+    fn credentials_are_passed_to_the_broker() {
+        let mqtt_options = Config::default()
+            .with_credentials("user", "secret")
+            .mqtt_options()
+            .unwrap();
+
+        assert_eq!(
+            mqtt_options.credentials(),
+            Some(("user".to_string(), "secret".to_string()))
+        );
+    }
+
+    #[test]
+    fn passwords_are_not_displayed() {
+        let config = Config::default().with_credentials("user", "secret");
+
+        assert!(!format!("{:?}", config).contains("secret"));
     }
 }
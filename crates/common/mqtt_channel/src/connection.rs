@@ -1,13 +1,29 @@
-use crate::{Config, ErrChannel, Message, MqttError, PubChannel, SubChannel};
+use crate::disk_queue::DiskQueue;
+use crate::metrics::topic_prefix;
+use crate::{
+    BirthMessage, Config, ErrChannel, Message, Metrics, MqttError, PubChannel, SubChannel,
+    SubscriptionHandle,
+};
 use futures::channel::mpsc;
 use futures::channel::oneshot;
-use futures::{SinkExt, StreamExt};
+use futures::{FutureExt, SinkExt, StreamExt};
 use rumqttc::{
-    AsyncClient, ConnectionError, Event, EventLoop, Incoming, Outgoing, Packet, StateError,
+    AsyncClient, ConnectionError, Event, EventLoop, Incoming, LastWill, Outgoing, Packet,
+    StateError,
 };
+use std::collections::{HashMap, VecDeque};
 use std::time::Duration;
 use tokio::time::sleep;
 
+const MESSAGES_RECEIVED: &str = "mqtt_messages_received_total";
+const MESSAGES_PUBLISHED: &str = "mqtt_messages_published_total";
+const RECONNECTS: &str = "mqtt_reconnects_total";
+const CONNECTION_ERRORS: &str = "mqtt_connection_errors_total";
+const QUEUE_DEPTH: &str = "mqtt_queue_depth";
+
+/// Maximum number of queued messages published and not acknowledged yet
+const REPLAY_WINDOW: usize = 32;
+
 /// A connection to some MQTT server
 pub struct Connection {
     /// The channel of the input messages received by this connection.
@@ -21,6 +37,9 @@ pub struct Connection {
 
     /// A channel to notify that all the published messages have been actually published.
     pub pub_done: oneshot::Receiver<()>,
+
+    /// A handle to add and remove subscriptions while the connection is live.
+    pub subscriptions: SubscriptionHandle,
 }
 
 impl Connection {
@@ -74,19 +93,41 @@ impl Connection {
         let (published_sender, published_receiver) = mpsc::unbounded();
         let (error_sender, error_receiver) = mpsc::unbounded();
         let (pub_done_sender, pub_done_receiver) = oneshot::channel();
+        let (status_sender, status_receiver) = mpsc::unbounded();
 
-        let (mqtt_client, event_loop) =
-            Connection::open(config, received_sender.clone(), error_sender.clone()).await?;
+        let disk_queue = match (&config.disk_queue, &config.session_name) {
+            (Some(queue_config), Some(session_name)) => {
+                Some(DiskQueue::open(queue_config, session_name).await?)
+            }
+            _ => None,
+        };
+
+        let metrics = config.metrics.clone().unwrap_or_default();
+        let (mqtt_client, event_loop) = Connection::open(
+            config,
+            &metrics,
+            received_sender.clone(),
+            error_sender.clone(),
+        )
+        .await?;
+        let subscriptions = SubscriptionHandle::new(mqtt_client.clone(), &config.subscriptions);
         tokio::spawn(Connection::receiver_loop(
             mqtt_client.clone(),
-            config.clone(),
+            subscriptions.clone(),
+            metrics.clone(),
             event_loop,
             received_sender,
+            status_sender,
             error_sender.clone(),
         ));
         tokio::spawn(Connection::sender_loop(
             mqtt_client,
+            config.birth_message.clone(),
+            config.last_will_message.clone(),
             published_receiver,
+            status_receiver,
+            disk_queue,
+            metrics,
             error_sender,
             pub_done_sender,
         ));
@@ -96,6 +137,7 @@ impl Connection {
             published: published_sender,
             errors: error_receiver,
             pub_done: pub_done_receiver,
+            subscriptions,
         })
     }
 
@@ -106,10 +148,19 @@ impl Connection {
 
     async fn open(
         config: &Config,
+        metrics: &Metrics,
         mut message_sender: mpsc::UnboundedSender<Message>,
         mut error_sender: mpsc::UnboundedSender<MqttError>,
     ) -> Result<(AsyncClient, EventLoop), MqttError> {
-        let mqtt_options = config.mqtt_options();
+        let mut mqtt_options = config.mqtt_options()?;
+        if let Some(message) = &config.last_will_message {
+            mqtt_options.set_last_will(LastWill::new(
+                &message.topic.name,
+                message.payload_bytes(),
+                message.qos,
+                message.retain,
+            ));
+        }
         let (mqtt_client, mut event_loop) = AsyncClient::new(mqtt_options, config.queue_capacity);
 
         loop {
@@ -118,6 +169,7 @@ impl Connection {
                     if let Some(err) = MqttError::maybe_connection_error(&ack) {
                         return Err(err);
                     };
+
                     let subscriptions = config.subscriptions.filters();
 
                     // Need check here otherwise it will hang waiting for a SubAck, and none will come when there is no subscription.
@@ -138,7 +190,9 @@ impl Connection {
                 Ok(Event::Incoming(Packet::Publish(msg))) => {
                     // Messages can be received before a sub ack
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
-                    let _ = message_sender.send(msg.into()).await;
+                    let _ = message_sender
+                        .send(Connection::received(metrics, msg.into()))
+                        .await;
                 }
 
                 Err(err) => {
@@ -160,9 +214,11 @@ impl Connection {
 
     async fn receiver_loop(
         mqtt_client: AsyncClient,
-        config: Config,
+        subscriptions: SubscriptionHandle,
+        metrics: Metrics,
         mut event_loop: EventLoop,
         mut message_sender: mpsc::UnboundedSender<Message>,
+        status_sender: mpsc::UnboundedSender<ConnectionEvent>,
         mut error_sender: mpsc::UnboundedSender<MqttError>,
     ) -> Result<(), MqttError> {
         loop {
@@ -170,25 +226,57 @@ impl Connection {
                 Ok(Event::Incoming(Packet::Publish(msg))) => {
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
                     // One has to continue the loop though, because rumqttc relies on this polling.
-                    let _ = message_sender.send(msg.into()).await;
+                    let _ = message_sender
+                        .send(Connection::received(&metrics, msg.into()))
+                        .await;
                 }
 
                 Ok(Event::Incoming(Packet::ConnAck(ack))) => {
                     if let Some(err) = MqttError::maybe_connection_error(&ack) {
                         eprintln!("ERROR: Connection Error {}", err);
-                    }
-                    // Workaround for  https://github.com/bytebeamio/rumqtt/issues/250
-                    // If session_name is not provided, then re-subscribe
-                    else if config.session_name.is_none() {
-                        let subscriptions = config.subscriptions.filters();
-                        // Need check here otherwise it will hang waiting for a SubAck, and none will come when there is no subscription.
-                        if subscriptions.is_empty() {
-                            break;
+                    } else {
+                        metrics.increment(RECONNECTS, &[]);
+
+                        // Let the sender loop publish the birth message
+                        // and the messages queued while disconnected
+                        let _ = status_sender.unbounded_send(ConnectionEvent::Connected);
+
+                        // Workaround for  https://github.com/bytebeamio/rumqtt/issues/250
+                        // If the broker has no session for this client, then re-subscribe,
+                        // including to the topics subscribed since the connection was established.
+                        //
+                        // The requests to the client are only processed by polling this event loop,
+                        // so the subscription must not be awaited here: this would block if the request queue is full.
+                        if !ack.session_present {
+                            let subscriptions = subscriptions.filters();
+                            if !subscriptions.is_empty() {
+                                let mqtt_client = mqtt_client.clone();
+                                let mut error_sender = error_sender.clone();
+                                tokio::spawn(async move {
+                                    if let Err(err) =
+                                        Connection::subscribe_to_topics(&mqtt_client, subscriptions)
+                                            .await
+                                    {
+                                        let _ = error_sender.send(err).await;
+                                    }
+                                });
+                            }
                         }
-                        Connection::subscribe_to_topics(&mqtt_client, subscriptions).await?;
                     }
                 }
 
+                Ok(Event::Outgoing(Outgoing::Publish(pkid))) => {
+                    let _ = status_sender.unbounded_send(ConnectionEvent::Published(pkid));
+                }
+
+                Ok(Event::Incoming(Packet::PubAck(ack))) => {
+                    let _ = status_sender.unbounded_send(ConnectionEvent::Acknowledged(ack.pkid));
+                }
+
+                Ok(Event::Incoming(Packet::PubComp(comp))) => {
+                    let _ = status_sender.unbounded_send(ConnectionEvent::Acknowledged(comp.pkid));
+                }
+
                 Ok(Event::Incoming(Incoming::Disconnect))
                 | Ok(Event::Outgoing(Outgoing::Disconnect)) => {
                     // The connection has been closed
@@ -196,6 +284,8 @@ impl Connection {
                 }
 
                 Err(err) => {
+                    metrics.increment(CONNECTION_ERRORS, &[]);
+                    let _ = status_sender.unbounded_send(ConnectionEvent::Disconnected);
                     let delay = Connection::pause_on_error(&err);
 
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
@@ -214,34 +304,235 @@ impl Connection {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn sender_loop(
         mqtt_client: AsyncClient,
+        birth_message: Option<BirthMessage>,
+        last_will_message: Option<Message>,
         mut messages_receiver: mpsc::UnboundedReceiver<Message>,
+        mut status_receiver: mpsc::UnboundedReceiver<ConnectionEvent>,
+        mut disk_queue: Option<DiskQueue>,
+        metrics: Metrics,
         mut error_sender: mpsc::UnboundedSender<MqttError>,
         done: oneshot::Sender<()>,
     ) {
+        let mut in_flight = InFlight::default();
+
+        // All the messages are published by this loop, starting with the birth message,
+        // and then the messages queued by a previous run
+        let mut connected = true;
+        if let Some(birth_message) = birth_message.as_ref() {
+            Connection::publish(
+                &mqtt_client,
+                birth_message.build(),
+                None,
+                &mut in_flight,
+                &metrics,
+                &mut error_sender,
+            )
+            .await;
+        }
+        if let Some(queue) = disk_queue.as_mut() {
+            Connection::publish_queued_messages(
+                &mqtt_client,
+                queue,
+                &mut in_flight,
+                &metrics,
+                &mut error_sender,
+            )
+            .await;
+        }
+
+        let mut status_closed = false;
         loop {
-            match messages_receiver.next().await {
-                None => {
-                    // The sender channel has been closed by the client
-                    // No more messages will be published by the client
-                    break;
-                }
-                Some(message) => {
-                    let payload = Vec::from(message.payload_bytes());
-                    if let Err(err) = mqtt_client
-                        .publish(message.topic, message.qos, message.retain, payload)
-                        .await
-                    {
-                        let _ = error_sender.send(err.into()).await;
+            // While queued messages are waiting to be published, the new messages are left in the channel,
+            // to be published after them, without being written to disk as long as the connection is up.
+            let replaying = connected
+                && disk_queue
+                    .as_ref()
+                    .map_or(false, |queue| in_flight.has_unpublished_entries(queue));
+
+            tokio::select! {
+                message = messages_receiver.next(), if !replaying => match message {
+                    None => {
+                        // The sender channel has been closed by the client
+                        // No more messages will be published by the client
+                        break;
                     }
-                }
+                    Some(message) => match disk_queue.as_mut() {
+                        Some(queue) if !connected => {
+                            // The messages already sent by the client are queued together
+                            let mut messages = vec![message];
+                            while let Some(Some(message)) = messages_receiver.next().now_or_never() {
+                                messages.push(message);
+                            }
+                            if let Err(err) = queue.push_all(&messages).await {
+                                let _ = error_sender.send(err).await;
+                            }
+                            metrics.set_gauge(QUEUE_DEPTH, &[], queue.len() as f64);
+                        }
+                        _ => {
+                            Connection::publish(
+                                &mqtt_client,
+                                message,
+                                None,
+                                &mut in_flight,
+                                &metrics,
+                                &mut error_sender,
+                            )
+                            .await
+                        }
+                    }
+                },
+                event = status_receiver.next(), if !status_closed => {
+                    // The events already received are processed together,
+                    // so the acknowledged messages are removed from the queue as a batch
+                    let mut acknowledged_entries = Vec::new();
+                    let mut event = event;
+                    loop {
+                        match event {
+                            Some(ConnectionEvent::Connected) => {
+                                connected = true;
+                                if let Some(birth_message) = birth_message.as_ref() {
+                                    Connection::publish(
+                                        &mqtt_client,
+                                        birth_message.build(),
+                                        None,
+                                        &mut in_flight,
+                                        &metrics,
+                                        &mut error_sender,
+                                    )
+                                    .await;
+                                }
+                            }
+                            Some(ConnectionEvent::Disconnected) => connected = false,
+                            Some(ConnectionEvent::Published(pkid)) => {
+                                acknowledged_entries.extend(in_flight.sent(pkid))
+                            }
+                            Some(ConnectionEvent::Acknowledged(pkid)) => {
+                                acknowledged_entries.extend(in_flight.acknowledged(pkid))
+                            }
+                            None => {
+                                status_closed = true;
+                                break;
+                            }
+                        }
+                        match status_receiver.next().now_or_never() {
+                            Some(next_event) => event = next_event,
+                            None => break,
+                        }
+                    }
+                    if let Some(queue) = disk_queue.as_mut() {
+                        if let Err(err) = queue.remove_all(&acknowledged_entries).await {
+                            let _ = error_sender.send(err).await;
+                        }
+                        if connected {
+                            Connection::publish_queued_messages(
+                                &mqtt_client,
+                                queue,
+                                &mut in_flight,
+                                &metrics,
+                                &mut error_sender,
+                            )
+                            .await;
+                        }
+                    }
+                },
+                else => break,
             }
         }
+
+        // On a graceful disconnect, the broker doesn't publish the last will
+        if let Some(message) = last_will_message {
+            Connection::publish(
+                &mqtt_client,
+                message,
+                None,
+                &mut in_flight,
+                &metrics,
+                &mut error_sender,
+            )
+            .await;
+        }
         let _ = mqtt_client.disconnect().await;
         let _ = done.send(());
     }
 
+    async fn publish(
+        mqtt_client: &AsyncClient,
+        message: Message,
+        queue_entry: Option<u64>,
+        in_flight: &mut InFlight,
+        metrics: &Metrics,
+        error_sender: &mut mpsc::UnboundedSender<MqttError>,
+    ) {
+        let prefix = topic_prefix(&message.topic).to_string();
+        let payload = Vec::from(message.payload_bytes());
+        match mqtt_client
+            .publish(message.topic, message.qos, message.retain, payload)
+            .await
+        {
+            Ok(()) => {
+                in_flight.requested(queue_entry);
+                metrics.increment(MESSAGES_PUBLISHED, &[("prefix", &prefix)]);
+            }
+            Err(err) => {
+                let _ = error_sender.send(err.into()).await;
+            }
+        }
+    }
+
+    fn received(metrics: &Metrics, message: Message) -> Message {
+        metrics.increment(
+            MESSAGES_RECEIVED,
+            &[("prefix", topic_prefix(&message.topic))],
+        );
+        message
+    }
+
+    /// Publish in order the queued messages not in-flight yet,
+    /// with at most `REPLAY_WINDOW` queued messages in-flight at a time.
+    ///
+    /// The queued messages are only removed from the queue once acknowledged by the broker.
+    async fn publish_queued_messages(
+        mqtt_client: &AsyncClient,
+        queue: &mut DiskQueue,
+        in_flight: &mut InFlight,
+        metrics: &Metrics,
+        error_sender: &mut mpsc::UnboundedSender<MqttError>,
+    ) {
+        let window = REPLAY_WINDOW.saturating_sub(in_flight.queue_entries_count());
+        let entries: Vec<u64> = queue
+            .entries()
+            .filter(|entry| !in_flight.has_queue_entry(*entry))
+            .take(window)
+            .collect();
+        for entry in entries {
+            match queue.read(entry).await {
+                Ok(message) => {
+                    Connection::publish(
+                        mqtt_client,
+                        message,
+                        Some(entry),
+                        in_flight,
+                        metrics,
+                        error_sender,
+                    )
+                    .await;
+                }
+                // A message that cannot be read is moved aside, so the next ones can be published
+                Err(err) => {
+                    let _ = error_sender.send(err).await;
+                    if let Err(err) = queue.quarantine(entry).await {
+                        let _ = error_sender.send(err).await;
+                        break;
+                    }
+                }
+            }
+        }
+        metrics.set_gauge(QUEUE_DEPTH, &[], queue.len() as f64);
+    }
+
     pub(crate) fn pause_on_error(err: &ConnectionError) -> bool {
         match &err {
             rumqttc::ConnectionError::Io(_) => true,
@@ -269,3 +560,214 @@ impl Connection {
             .map_err(MqttError::ClientError)
     }
 }
+
+/// Events forwarded by the receiver loop to the sender loop
+#[derive(Debug)]
+enum ConnectionEvent {
+    Connected,
+    Disconnected,
+
+    /// A publish packet has been sent to the broker, with the given packet id (0 for QoS 0)
+    Published(u16),
+
+    /// A QoS 1 or 2 publish packet has been acknowledged by the broker
+    Acknowledged(u16),
+}
+
+/// The messages published by the sender loop and not acknowledged yet by the broker
+///
+/// The packet ids are assigned by the event loop as the publish requests are processed,
+/// i.e. in the order of the requests.
+/// A packet id that is already known is that of a message re-sent after a reconnect.
+#[derive(Default)]
+struct InFlight {
+    /// Publish requests not yet processed by the event loop, with their queue entry if any
+    requested: VecDeque<Option<u64>>,
+
+    /// Publish packets sent but not yet acknowledged, with their queue entry if any
+    sent: HashMap<u16, Option<u64>>,
+}
+
+impl InFlight {
+    fn requested(&mut self, queue_entry: Option<u64>) {
+        self.requested.push_back(queue_entry);
+    }
+
+    fn has_queue_entry(&self, queue_entry: u64) -> bool {
+        let entry = Some(queue_entry);
+        self.requested.contains(&entry) || self.sent.values().any(|sent| *sent == entry)
+    }
+
+    /// The number of queued messages in-flight
+    fn queue_entries_count(&self) -> usize {
+        let requested = self
+            .requested
+            .iter()
+            .filter(|entry| entry.is_some())
+            .count();
+        let sent = self.sent.values().filter(|entry| entry.is_some()).count();
+        requested + sent
+    }
+
+    /// Tell if some messages of the queue are still to be published
+    ///
+    /// The queued messages being published in order, the in-flight ones are at the front of the queue.
+    fn has_unpublished_entries(&self, queue: &DiskQueue) -> bool {
+        queue.entries().any(|entry| !self.has_queue_entry(entry))
+    }
+
+    /// Record a sent packet, returning the queue entry of the message if there is no ack to wait for.
+    fn sent(&mut self, pkid: u16) -> Option<u64> {
+        if pkid == 0 {
+            // QoS 0: nothing more will be heard about this message
+            return self.requested.pop_front().flatten();
+        }
+        if !self.sent.contains_key(&pkid) {
+            if let Some(queue_entry) = self.requested.pop_front() {
+                self.sent.insert(pkid, queue_entry);
+            }
+        }
+        None
+    }
+
+    /// Record an ack, returning the queue entry of the acknowledged message, if any.
+    fn acknowledged(&mut self, pkid: u16) -> Option<u64> {
+        self.sent.remove(&pkid).flatten()
+    }
+}
+
+#[cfg(test)]
+mod in_flight_tests {
+    use super::*;
+
+    #[test]
+    fn queued_messages_are_released_on_ack() {
+        let mut in_flight = InFlight::default();
+        in_flight.requested(None);
+        in_flight.requested(Some(7));
+        assert!(in_flight.has_queue_entry(7));
+
+        assert_eq!(in_flight.sent(1), None);
+        assert_eq!(in_flight.sent(2), None);
+        // A message re-sent after a reconnect keeps its packet id
+        assert_eq!(in_flight.sent(2), None);
+        assert!(in_flight.has_queue_entry(7));
+
+        assert_eq!(in_flight.acknowledged(1), None);
+        assert_eq!(in_flight.acknowledged(2), Some(7));
+        assert!(!in_flight.has_queue_entry(7));
+    }
+
+    #[test]
+    fn queued_messages_published_with_qos_0_are_released_when_sent() {
+        let mut in_flight = InFlight::default();
+        in_flight.requested(Some(3));
+        in_flight.requested(None);
+
+        assert_eq!(in_flight.sent(0), Some(3));
+        assert_eq!(in_flight.sent(0), None);
+        assert!(!in_flight.has_queue_entry(3));
+    }
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::*;
+    use crate::{DiskQueueConfig, OverflowPolicy, Topic};
+    use rumqttc::Request;
+    use tempfile::TempDir;
+
+    fn message(payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked("tedge/measurements"), payload)
+    }
+
+    /// A broker acknowledging the published messages in batches, once no more publish requests are pending.
+    ///
+    /// Return the payloads of the published messages, along the maximum number of messages in-flight.
+    async fn fake_broker(
+        requests: flume::Receiver<Request>,
+        events: mpsc::UnboundedSender<ConnectionEvent>,
+    ) -> (Vec<String>, usize) {
+        let mut published = Vec::new();
+        let mut pending_acks = Vec::new();
+        let mut max_in_flight = 0;
+        let mut next_pkid = 1;
+        while let Ok(request) = requests.recv_async().await {
+            match request {
+                Request::Publish(publish) => {
+                    published.push(String::from_utf8(publish.payload.to_vec()).unwrap());
+                    let _ = events.unbounded_send(ConnectionEvent::Published(next_pkid));
+                    pending_acks.push(next_pkid);
+                    next_pkid += 1;
+                    max_in_flight = max_in_flight.max(pending_acks.len());
+                }
+                Request::Disconnect => break,
+                _ => {}
+            }
+            if requests.is_empty() {
+                tokio::task::yield_now().await;
+                for pkid in pending_acks.drain(..) {
+                    let _ = events.unbounded_send(ConnectionEvent::Acknowledged(pkid));
+                }
+            }
+        }
+        (published, max_in_flight)
+    }
+
+    #[tokio::test]
+    async fn the_backlog_drains_while_new_messages_keep_arriving() {
+        let dir = TempDir::new().unwrap();
+        let queue_config = DiskQueueConfig {
+            dir: dir.path().to_path_buf(),
+            max_messages: 1000,
+            overflow_policy: OverflowPolicy::DropOldest,
+        };
+        let mut queue = DiskQueue::open(&queue_config, "test").await.unwrap();
+        let backlog: Vec<Message> = (0..100).map(|i| message(&format!("queued-{i}"))).collect();
+        queue.push_all(&backlog).await.unwrap();
+
+        let (requests_sender, requests) = flume::bounded(10);
+        let (events_sender, events) = mpsc::unbounded();
+        let (mut messages, messages_receiver) = mpsc::unbounded();
+        let (error_sender, _errors) = mpsc::unbounded();
+        let (done_sender, done) = oneshot::channel();
+        let metrics = Metrics::default();
+        let broker = tokio::spawn(fake_broker(requests, events_sender));
+        tokio::spawn(Connection::sender_loop(
+            AsyncClient::from_senders(requests_sender),
+            None,
+            None,
+            messages_receiver,
+            events,
+            Some(queue),
+            metrics.clone(),
+            error_sender,
+            done_sender,
+        ));
+
+        for i in 0..100 {
+            messages.send(message(&format!("new-{i}"))).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while metrics.gauge(QUEUE_DEPTH, &[]) != Some(0.0) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the backlog to be drained");
+        messages.close_channel();
+        done.await.unwrap();
+
+        let (published, max_in_flight) = broker.await.unwrap();
+        let expected: Vec<String> = (0..100)
+            .map(|i| format!("queued-{i}"))
+            .chain((0..100).map(|i| format!("new-{i}")))
+            .collect();
+        assert_eq!(published, expected);
+        assert!(max_in_flight > 1);
+
+        let queue = DiskQueue::open(&queue_config, "test").await.unwrap();
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,520 @@
+use crate::{Message, MqttError, PubChannel, QoS, Topic};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Configuration of a disk-backed queue of outgoing messages
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DiskQueueConfig {
+    /// Directory where the queues are stored, one sub-directory per session name
+    pub dir: PathBuf,
+
+    /// Maximum number of messages kept in the queue
+    pub max_messages: usize,
+
+    /// What to do when a message is pushed on a full queue
+    pub overflow_policy: OverflowPolicy,
+}
+
+/// What to do when a message is pushed on a full queue
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// Discard the oldest message of the queue to make room for the new one
+    DropOldest,
+
+    /// Discard the new message
+    DropNewest,
+}
+
+/// A queue of outgoing messages persisted on disk
+///
+/// Each message is stored in its own file, named after a sequence number,
+/// so the messages can be replayed in order after a restart.
+///
+/// A message is only removed from the queue when explicitly told so,
+/// i.e. once the message has been acknowledged and not simply read.
+///
+/// The files are written and synced to disk by the blocking threads of the runtime.
+/// The messages pushed or removed together are processed by a single blocking task,
+/// the directory being synced once per batch.
+pub(crate) struct DiskQueue {
+    dir: PathBuf,
+    max_messages: usize,
+    overflow_policy: OverflowPolicy,
+    entries: VecDeque<u64>,
+    next_entry: u64,
+}
+
+impl DiskQueue {
+    /// Open the queue of the given session, loading the messages persisted by a previous run.
+    pub async fn open(config: &DiskQueueConfig, session_name: &str) -> Result<Self, MqttError> {
+        let dir = config.dir.join(session_name);
+        let entries = blocking(move || load_entries(dir)).await?;
+        let next_entry = entries.last().map(|seq| seq + 1).unwrap_or(0);
+
+        Ok(DiskQueue {
+            dir: config.dir.join(session_name),
+            max_messages: config.max_messages,
+            overflow_policy: config.overflow_policy,
+            entries: entries.into(),
+            next_entry,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Persist a message at the end of the queue, applying the overflow policy if the queue is full.
+    pub async fn push(&mut self, message: &Message) -> Result<(), MqttError> {
+        self.push_all(std::slice::from_ref(message)).await
+    }
+
+    /// Persist messages at the end of the queue, applying the overflow policy to each message in turn.
+    pub async fn push_all(&mut self, messages: &[Message]) -> Result<(), MqttError> {
+        let mut dropped = Vec::new();
+        let mut written = Vec::new();
+        for message in messages {
+            if self.entries.len() >= self.max_messages {
+                match self.overflow_policy {
+                    OverflowPolicy::DropNewest => continue,
+                    OverflowPolicy::DropOldest => {
+                        while self.entries.len() >= self.max_messages.max(1) {
+                            if let Some(seq) = self.entries.pop_front() {
+                                dropped.push(seq);
+                            }
+                        }
+                    }
+                }
+            }
+            let seq = self.next_entry;
+            self.next_entry += 1;
+            self.entries.push_back(seq);
+            written.push((seq, message));
+        }
+
+        // A message pushed and dropped by the same batch is never written
+        let to_write: Vec<(PathBuf, Vec<u8>)> = written
+            .iter()
+            .filter(|(seq, _)| !dropped.contains(seq))
+            .map(|(seq, message)| (self.entry_path(*seq), encode(message)))
+            .collect();
+        let to_remove: Vec<PathBuf> = dropped
+            .iter()
+            .filter(|dropped| !written.iter().any(|(seq, _)| seq == *dropped))
+            .map(|seq| self.entry_path(*seq))
+            .collect();
+        if to_write.is_empty() && to_remove.is_empty() {
+            return Ok(());
+        }
+
+        let dir = self.dir.clone();
+        let result = blocking(move || {
+            remove_entries(to_remove)?;
+            write_entries(&dir, to_write)
+        })
+        .await;
+        if result.is_err() {
+            // Only keep the entries actually persisted
+            let dir = self.dir.clone();
+            self.entries.retain(|seq| entry_path(&dir, *seq).exists());
+        }
+        result
+    }
+
+    /// The sequence numbers of the queued messages, in order.
+    pub fn entries(&self) -> impl Iterator<Item = u64> + '_ {
+        self.entries.iter().copied()
+    }
+
+    /// The sequence number of the message at the front of the queue, if any.
+    pub fn front_entry(&self) -> Option<u64> {
+        self.entries.front().copied()
+    }
+
+    /// Read the message with the given sequence number, without removing it.
+    ///
+    /// A message that cannot be read has to be [quarantined](DiskQueue::quarantine)
+    /// for the next ones to be read.
+    pub async fn read(&self, seq: u64) -> Result<Message, MqttError> {
+        let path = self.entry_path(seq);
+        blocking(move || read_entry(path)).await
+    }
+
+    /// Read the message at the front of the queue, without removing it.
+    ///
+    /// A message that cannot be read has to be [quarantined](DiskQueue::quarantine_front)
+    /// for the next ones to be read.
+    pub async fn front(&self) -> Result<Option<Message>, MqttError> {
+        match self.entries.front() {
+            None => Ok(None),
+            Some(seq) => self.read(*seq).await.map(Some),
+        }
+    }
+
+    /// Remove the message with the given sequence number, if still in the queue.
+    pub async fn remove(&mut self, seq: u64) -> Result<(), MqttError> {
+        self.remove_all(&[seq]).await
+    }
+
+    /// Remove the messages with the given sequence numbers, those still in the queue.
+    pub async fn remove_all(&mut self, seqs: &[u64]) -> Result<(), MqttError> {
+        let mut removed = Vec::new();
+        self.entries.retain(|entry| {
+            let remove = seqs.contains(entry);
+            if remove {
+                removed.push(*entry);
+            }
+            !remove
+        });
+        if removed.is_empty() {
+            return Ok(());
+        }
+
+        let paths = removed.iter().map(|seq| self.entry_path(*seq)).collect();
+        blocking(move || remove_entries(paths)).await
+    }
+
+    /// Remove the message at the front of the queue.
+    pub async fn remove_front(&mut self) -> Result<(), MqttError> {
+        match self.front_entry() {
+            Some(seq) => self.remove(seq).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Move aside the message at the front of the queue,
+    /// keeping the file for later inspection but no more as a queued message.
+    pub async fn quarantine_front(&mut self) -> Result<(), MqttError> {
+        match self.front_entry() {
+            Some(seq) => self.quarantine(seq).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Move aside the message with the given sequence number,
+    /// keeping the file for later inspection but no more as a queued message.
+    pub async fn quarantine(&mut self, seq: u64) -> Result<(), MqttError> {
+        if let Some(index) = self.entries.iter().position(|entry| *entry == seq) {
+            self.entries.remove(index);
+            let path = self.entry_path(seq);
+            blocking(move || quarantine_entry(path)).await?;
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, seq: u64) -> PathBuf {
+        entry_path(&self.dir, seq)
+    }
+}
+
+/// A store of messages persisted on disk, to be forwarded later in order
+///
+/// A message is only removed from the store once handed over to the channel it is forwarded to.
+pub struct MessageStore {
+    queue: DiskQueue,
+}
+
+impl MessageStore {
+    /// Open the store with the given name, loading the messages persisted by a previous run.
+    pub async fn open(config: &DiskQueueConfig, name: &str) -> Result<Self, MqttError> {
+        let queue = DiskQueue::open(config, name).await?;
+        Ok(MessageStore { queue })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Persist a message at the end of the store, applying the overflow policy if the store is full.
+    pub async fn push(&mut self, message: &Message) -> Result<(), MqttError> {
+        self.queue.push(message).await
+    }
+
+    /// Send the oldest stored message over the given channel, returning false if there is none.
+    ///
+    /// The message is removed from the store only if successfully sent.
+    /// A message that cannot be read is moved aside, so the next call can proceed with the next message.
+    pub async fn forward_next(&mut self, output: &mut impl PubChannel) -> Result<bool, MqttError> {
+        match self.queue.front().await {
+            Ok(None) => Ok(false),
+            Ok(Some(message)) => {
+                output.publish(message).await?;
+                self.queue.remove_front().await?;
+                Ok(true)
+            }
+            Err(err) => {
+                self.queue.quarantine_front().await?;
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Run some file system operations on the blocking threads of the runtime
+async fn blocking<T, F>(operation: F) -> Result<T, MqttError>
+where
+    F: FnOnce() -> Result<T, MqttError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(operation)
+        .await
+        .map_err(|err| MqttError::QueueError {
+            path: PathBuf::new(),
+            reason: err.to_string(),
+        })?
+}
+
+fn entry_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("{:020}", seq))
+}
+
+fn load_entries(dir: PathBuf) -> Result<Vec<u64>, MqttError> {
+    std::fs::create_dir_all(&dir).map_err(|err| MqttError::new_queue_error(&dir, err))?;
+
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(&dir).map_err(|err| MqttError::new_queue_error(&dir, err))? {
+        let entry = entry.map_err(|err| MqttError::new_queue_error(&dir, err))?;
+        // Any other file, notably a partially written or a quarantined message, is ignored
+        if let Some(seq) = entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+            entries.push(seq);
+        }
+    }
+    entries.sort_unstable();
+    Ok(entries)
+}
+
+// Each message is written in a temporary file, that is synced to disk before being renamed,
+// so a message file is either missing or complete, even on a power loss.
+// The directory is synced once, after all the messages of the batch have been renamed.
+fn write_entries(dir: &Path, entries: Vec<(PathBuf, Vec<u8>)>) -> Result<(), MqttError> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    for (path, bytes) in entries.iter() {
+        let tmp_path = path.with_extension("tmp");
+        File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(bytes)?;
+                file.sync_data()
+            })
+            .and_then(|()| std::fs::rename(&tmp_path, path))
+            .map_err(|err| MqttError::new_queue_error(path, err))?;
+    }
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|err| MqttError::new_queue_error(dir, err))
+}
+
+fn read_entry(path: PathBuf) -> Result<Message, MqttError> {
+    let bytes = std::fs::read(&path).map_err(|err| MqttError::new_queue_error(&path, err))?;
+    decode(&bytes).ok_or(MqttError::InvalidQueuedMessage { path })
+}
+
+fn remove_entries(paths: Vec<PathBuf>) -> Result<(), MqttError> {
+    for path in paths {
+        std::fs::remove_file(&path).map_err(|err| MqttError::new_queue_error(&path, err))?;
+    }
+    Ok(())
+}
+
+fn quarantine_entry(path: PathBuf) -> Result<(), MqttError> {
+    std::fs::rename(&path, path.with_extension("invalid"))
+        .map_err(|err| MqttError::new_queue_error(&path, err))
+}
+
+// A queued message is stored as: qos (1 byte), retain (1 byte), topic length (4 bytes), topic, payload
+fn encode(message: &Message) -> Vec<u8> {
+    let topic = message.topic.name.as_bytes();
+    let payload = message.payload_bytes();
+    let mut bytes = Vec::with_capacity(6 + topic.len() + payload.len());
+    bytes.push(message.qos as u8);
+    bytes.push(message.retain as u8);
+    bytes.extend_from_slice(&(topic.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(topic);
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Option<Message> {
+    let qos = match bytes.first()? {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        2 => QoS::ExactlyOnce,
+        _ => return None,
+    };
+    let retain = *bytes.get(1)? != 0;
+    let topic_len = u32::from_be_bytes(bytes.get(2..6)?.try_into().ok()?) as usize;
+    let topic = std::str::from_utf8(bytes.get(6..6 + topic_len)?).ok()?;
+    let payload = &bytes[6 + topic_len..];
+
+    let message = Message::new(&Topic::new_unchecked(topic), payload).with_qos(qos);
+    Some(if retain {
+        message.with_retain()
+    } else {
+        message
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn config(
+        dir: &TempDir,
+        max_messages: usize,
+        overflow_policy: OverflowPolicy,
+    ) -> DiskQueueConfig {
+        DiskQueueConfig {
+            dir: dir.path().to_path_buf(),
+            max_messages,
+            overflow_policy,
+        }
+    }
+
+    fn message(payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked("tedge/measurements"), payload)
+    }
+
+    async fn drain(queue: &mut DiskQueue) -> Vec<String> {
+        let mut payloads = vec![];
+        while let Some(message) = queue.front().await.unwrap() {
+            payloads.push(message.payload_str().unwrap().to_string());
+            queue.remove_front().await.unwrap();
+        }
+        payloads
+    }
+
+    #[tokio::test]
+    async fn messages_are_replayed_in_order() {
+        let dir = TempDir::new().unwrap();
+        let mut queue = DiskQueue::open(&config(&dir, 10, OverflowPolicy::DropOldest), "test")
+            .await
+            .unwrap();
+
+        queue.push(&message("1")).await.unwrap();
+        queue.push(&message("2")).await.unwrap();
+        queue.push(&message("3")).await.unwrap();
+
+        assert_eq!(drain(&mut queue).await, vec!["1", "2", "3"]);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn messages_are_persisted_across_restarts() {
+        let dir = TempDir::new().unwrap();
+        let config = config(&dir, 10, OverflowPolicy::DropOldest);
+        let retained = message("2").with_qos(QoS::ExactlyOnce).with_retain();
+        {
+            let mut queue = DiskQueue::open(&config, "test").await.unwrap();
+            queue.push(&message("1")).await.unwrap();
+            queue.push(&retained).await.unwrap();
+        }
+
+        let mut queue = DiskQueue::open(&config, "test").await.unwrap();
+        queue.push(&message("3")).await.unwrap();
+
+        assert_eq!(queue.front().await.unwrap(), Some(message("1")));
+        queue.remove_front().await.unwrap();
+        assert_eq!(queue.front().await.unwrap(), Some(retained));
+        assert_eq!(drain(&mut queue).await, vec!["2", "3"]);
+    }
+
+    #[tokio::test]
+    async fn sessions_have_their_own_queue() {
+        let dir = TempDir::new().unwrap();
+        let config = config(&dir, 10, OverflowPolicy::DropOldest);
+        let mut queue_a = DiskQueue::open(&config, "a").await.unwrap();
+        let mut queue_b = DiskQueue::open(&config, "b").await.unwrap();
+
+        queue_a.push(&message("a")).await.unwrap();
+
+        assert_eq!(drain(&mut queue_b).await, Vec::<String>::new());
+        assert_eq!(drain(&mut queue_a).await, vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn the_oldest_messages_are_dropped_on_overflow() {
+        let dir = TempDir::new().unwrap();
+        let mut queue = DiskQueue::open(&config(&dir, 2, OverflowPolicy::DropOldest), "test")
+            .await
+            .unwrap();
+
+        queue.push(&message("1")).await.unwrap();
+        queue.push(&message("2")).await.unwrap();
+        queue.push(&message("3")).await.unwrap();
+
+        assert_eq!(drain(&mut queue).await, vec!["2", "3"]);
+    }
+
+    #[tokio::test]
+    async fn the_newest_messages_are_dropped_on_overflow() {
+        let dir = TempDir::new().unwrap();
+        let mut queue = DiskQueue::open(&config(&dir, 2, OverflowPolicy::DropNewest), "test")
+            .await
+            .unwrap();
+
+        queue.push(&message("1")).await.unwrap();
+        queue.push(&message("2")).await.unwrap();
+        queue.push(&message("3")).await.unwrap();
+
+        assert_eq!(drain(&mut queue).await, vec!["1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn messages_are_kept_until_removed() {
+        let dir = TempDir::new().unwrap();
+        let config = config(&dir, 10, OverflowPolicy::DropOldest);
+        {
+            let mut queue = DiskQueue::open(&config, "test").await.unwrap();
+            queue.push(&message("1")).await.unwrap();
+            queue.push(&message("2")).await.unwrap();
+            assert_eq!(queue.front().await.unwrap(), Some(message("1")));
+        }
+
+        let mut queue = DiskQueue::open(&config, "test").await.unwrap();
+        let first = queue.front_entry().unwrap();
+        assert_eq!(queue.front().await.unwrap(), Some(message("1")));
+
+        queue.remove(first).await.unwrap();
+        // Removing an entry twice is a no-op
+        queue.remove(first).await.unwrap();
+
+        assert_eq!(drain(&mut queue).await, vec!["2"]);
+    }
+
+    #[tokio::test]
+    async fn invalid_messages_are_quarantined() {
+        let dir = TempDir::new().unwrap();
+        let config = config(&dir, 10, OverflowPolicy::DropOldest);
+        let mut queue = DiskQueue::open(&config, "test").await.unwrap();
+        queue.push(&message("1")).await.unwrap();
+        queue.push(&message("2")).await.unwrap();
+
+        let invalid_entry = queue.entry_path(queue.front_entry().unwrap());
+        std::fs::write(&invalid_entry, [42u8]).unwrap();
+
+        assert!(matches!(
+            queue.front().await,
+            Err(MqttError::InvalidQueuedMessage { .. })
+        ));
+        queue.quarantine_front().await.unwrap();
+
+        assert_eq!(drain(&mut queue).await, vec!["2"]);
+        assert!(invalid_entry.with_extension("invalid").exists());
+
+        let queue = DiskQueue::open(&config, "test").await.unwrap();
+        assert!(queue.is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 /// An MQTT related error
 #[derive(thiserror::Error, Debug)]
 pub enum MqttError {
@@ -10,6 +12,15 @@ pub enum MqttError {
     #[error("Invalid session: a session name must be provided")]
     InvalidSessionConfig,
 
+    #[error("Invalid TLS configuration: {reason}")]
+    InvalidTlsConfig { reason: String },
+
+    #[error("Failed to access the queue of outgoing messages {path:?}: {reason}")]
+    QueueError { path: PathBuf, reason: String },
+
+    #[error("Invalid message in the queue of outgoing messages: {path:?}")]
+    InvalidQueuedMessage { path: PathBuf },
+
     #[error("MQTT client error: {0}")]
     ClientError(#[from] rumqttc::ClientError),
 
@@ -29,6 +40,12 @@ pub enum MqttError {
         from: std::str::Utf8Error,
     },
 
+    #[error("No response received in {timeout:?} for the request published on {topic:?}")]
+    RequestTimeout {
+        topic: String,
+        timeout: std::time::Duration,
+    },
+
     #[error(
         "The read channel of the connection has been closed and no more messages can be received"
     )]
@@ -68,6 +85,13 @@ impl MqttError {
         }
     }
 
+    pub(crate) fn new_queue_error(path: &Path, err: std::io::Error) -> MqttError {
+        MqttError::QueueError {
+            path: path.to_path_buf(),
+            reason: err.to_string(),
+        }
+    }
+
     fn input_prefix(input: &str, len: usize) -> String {
         input
             .chars()
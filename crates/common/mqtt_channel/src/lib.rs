@@ -32,9 +32,14 @@
 mod channel;
 mod config;
 mod connection;
+mod disk_queue;
 mod errors;
 mod messages;
+mod metrics;
+mod request_client;
+mod router;
 mod session;
+mod subscriptions;
 mod topics;
 
 mod tests;
@@ -42,9 +47,14 @@ mod tests;
 pub use channel::*;
 pub use config::*;
 pub use connection::*;
+pub use disk_queue::{DiskQueueConfig, MessageStore, OverflowPolicy};
 pub use errors::*;
 pub use messages::*;
+pub use metrics::*;
+pub use request_client::*;
+pub use router::{Captures, Handler, Router};
 pub use session::*;
+pub use subscriptions::*;
 pub use topics::*;
 
 pub use futures::{
@@ -0,0 +1,489 @@
+use crate::{Message, Topic};
+use futures::channel::mpsc;
+use futures::SinkExt;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Upper bounds of the histogram buckets, in seconds
+const HISTOGRAM_BUCKETS: [f64; 10] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0,
+];
+
+/// Metrics collected by a component: counters, gauges and histograms
+///
+/// A metric series is identified by a name and a list of labels.
+/// The clones of a `Metrics` value share the same series.
+///
+/// The metrics are published as JSON on MQTT using `publish_periodically`,
+/// and can also be rendered for Prometheus using `to_prometheus`.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Mutex<Registry>>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    counters: BTreeMap<Series, u64>,
+    gauges: BTreeMap<Series, f64>,
+    histograms: BTreeMap<Series, Histogram>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+struct Series {
+    name: String,
+    labels: Vec<(String, String)>,
+}
+
+/// A series as published in JSON
+#[derive(Serialize)]
+struct JsonSeries<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    labels: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    buckets: Option<Vec<(f64, u64)>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; HISTOGRAM_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Metrics {
+    /// Increment by one the counter with the given name and labels
+    pub fn increment(&self, name: &str, labels: &[(&str, &str)]) {
+        self.with_registry(|registry| {
+            *registry
+                .counters
+                .entry(Series::new(name, labels))
+                .or_default() += 1
+        })
+    }
+
+    /// Set the value of the gauge with the given name and labels
+    pub fn set_gauge(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.with_registry(|registry| {
+            registry.gauges.insert(Series::new(name, labels), value);
+        })
+    }
+
+    /// Record a value, typically a duration in seconds, in the histogram with the given name and labels
+    pub fn observe(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.with_registry(|registry| {
+            let histogram = registry
+                .histograms
+                .entry(Series::new(name, labels))
+                .or_default();
+            for (bucket, bound) in histogram.buckets.iter_mut().zip(HISTOGRAM_BUCKETS) {
+                if value <= bound {
+                    *bucket += 1;
+                }
+            }
+            histogram.count += 1;
+            histogram.sum += value;
+        })
+    }
+
+    /// The current value of a counter, 0 if never incremented
+    pub fn counter(&self, name: &str, labels: &[(&str, &str)]) -> u64 {
+        self.with_registry(|registry| {
+            registry
+                .counters
+                .get(&Series::new(name, labels))
+                .copied()
+                .unwrap_or(0)
+        })
+    }
+
+    /// The current value of a gauge, if ever set
+    pub fn gauge(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        self.with_registry(|registry| registry.gauges.get(&Series::new(name, labels)).copied())
+    }
+
+    /// The number of values recorded by a histogram
+    pub fn observations(&self, name: &str, labels: &[(&str, &str)]) -> u64 {
+        self.with_registry(|registry| {
+            registry
+                .histograms
+                .get(&Series::new(name, labels))
+                .map(|histogram| histogram.count)
+                .unwrap_or(0)
+        })
+    }
+
+    /// All the series as a JSON array
+    ///
+    /// ```json
+    /// [
+    ///   {"name":"mqtt_messages_received_total","type":"counter","labels":{"prefix":"tedge/measurements"},"value":12},
+    ///   {"name":"mapper_conversion_duration_seconds","type":"histogram","labels":{},"count":12,"sum":0.0042,"buckets":[[0.0005,10],...]}
+    /// ]
+    /// ```
+    pub fn to_json(&self) -> String {
+        self.with_registry(|registry| {
+            let mut series = vec![];
+            for (key, value) in registry.counters.iter() {
+                series.push(JsonSeries {
+                    value: Some((*value).into()),
+                    ..key.json_series("counter")
+                });
+            }
+            for (key, value) in registry.gauges.iter() {
+                series.push(JsonSeries {
+                    value: Some((*value).into()),
+                    ..key.json_series("gauge")
+                });
+            }
+            for (key, histogram) in registry.histograms.iter() {
+                let buckets = HISTOGRAM_BUCKETS
+                    .iter()
+                    .copied()
+                    .zip(histogram.buckets.iter().copied())
+                    .collect();
+                series.push(JsonSeries {
+                    count: Some(histogram.count),
+                    sum: Some(histogram.sum),
+                    buckets: Some(buckets),
+                    ..key.json_series("histogram")
+                });
+            }
+            // The series are made of strings and numbers, non-finite numbers being serialized as null
+            serde_json::to_string(&series).unwrap_or_else(|_| "[]".to_string())
+        })
+    }
+
+    /// All the series using the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        self.with_registry(|registry| registry.to_prometheus())
+    }
+
+    /// The series of several metrics, merged using the Prometheus text exposition format.
+    ///
+    /// Each series is tagged with a `label` set to the name given along its source,
+    /// so the series of different sources with the same name and labels are kept apart.
+    pub fn merge_to_prometheus(label: &str, sources: &[(String, Metrics)]) -> String {
+        let mut merged = Registry::default();
+        for (source, metrics) in sources {
+            metrics.with_registry(|registry| {
+                let tag = |series: &Series| series.with_label(label, source);
+                for (key, value) in registry.counters.iter() {
+                    merged.counters.insert(tag(key), *value);
+                }
+                for (key, value) in registry.gauges.iter() {
+                    merged.gauges.insert(tag(key), *value);
+                }
+                for (key, histogram) in registry.histograms.iter() {
+                    merged.histograms.insert(tag(key), histogram.clone());
+                }
+            })
+        }
+        merged.to_prometheus()
+    }
+
+    /// Publish the metrics as JSON on the given topic, at the given period.
+    ///
+    /// The task stops when the `published` channel is closed.
+    pub fn publish_periodically(
+        &self,
+        topic: Topic,
+        period: Duration,
+        mut published: mpsc::UnboundedSender<Message>,
+    ) -> JoinHandle<()> {
+        let metrics = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(period).await;
+                let message = Message::new(&topic, metrics.to_json());
+                if published.send(message).await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn with_registry<T>(&self, f: impl FnOnce(&mut Registry) -> T) -> T {
+        // The updates cannot panic half-way, so the registry is consistent even if the lock is poisoned
+        let mut registry = self
+            .registry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut registry)
+    }
+}
+
+impl Registry {
+    fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut last_name = "";
+        for (key, value) in self.counters.iter() {
+            if key.name != last_name {
+                text.push_str(&format!("# TYPE {} counter\n", key.name));
+                last_name = &key.name;
+            }
+            text.push_str(&format!(
+                "{}{} {}\n",
+                key.name,
+                key.prometheus_labels(None),
+                value
+            ));
+        }
+        for (key, value) in self.gauges.iter() {
+            if key.name != last_name {
+                text.push_str(&format!("# TYPE {} gauge\n", key.name));
+                last_name = &key.name;
+            }
+            text.push_str(&format!(
+                "{}{} {}\n",
+                key.name,
+                key.prometheus_labels(None),
+                value
+            ));
+        }
+        for (key, histogram) in self.histograms.iter() {
+            if key.name != last_name {
+                text.push_str(&format!("# TYPE {} histogram\n", key.name));
+                last_name = &key.name;
+            }
+            for (bound, count) in HISTOGRAM_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                let le = bound.to_string();
+                text.push_str(&format!(
+                    "{}_bucket{} {}\n",
+                    key.name,
+                    key.prometheus_labels(Some(&le)),
+                    count
+                ));
+            }
+            text.push_str(&format!(
+                "{}_bucket{} {}\n",
+                key.name,
+                key.prometheus_labels(Some("+Inf")),
+                histogram.count
+            ));
+            let labels = key.prometheus_labels(None);
+            text.push_str(&format!("{}_sum{} {}\n", key.name, labels, histogram.sum));
+            text.push_str(&format!(
+                "{}_count{} {}\n",
+                key.name, labels, histogram.count
+            ));
+        }
+        text
+    }
+}
+
+/// The first two levels of a topic, used to group the metrics of related topics
+///
+/// For instance, the prefix of `tedge/measurements/child1` is `tedge/measurements`.
+pub fn topic_prefix(topic: &Topic) -> &str {
+    let name = topic.name.as_str();
+    match name.match_indices('/').nth(1) {
+        Some((index, _)) => &name[..index],
+        None => name,
+    }
+}
+
+impl Series {
+    fn new(name: &str, labels: &[(&str, &str)]) -> Self {
+        Series {
+            name: name.to_string(),
+            labels: labels
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn with_label(&self, key: &str, value: &str) -> Self {
+        let mut labels = vec![(key.to_string(), value.to_string())];
+        labels.extend(self.labels.iter().cloned());
+        Series {
+            name: self.name.clone(),
+            labels,
+        }
+    }
+
+    fn json_series(&self, kind: &'static str) -> JsonSeries<'_> {
+        JsonSeries {
+            name: &self.name,
+            kind,
+            labels: self
+                .labels
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect(),
+            value: None,
+            count: None,
+            sum: None,
+            buckets: None,
+        }
+    }
+
+    fn prometheus_labels(&self, le: Option<&str>) -> String {
+        let mut labels: Vec<String> = self
+            .labels
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, prometheus_escape(value)))
+            .collect();
+        if let Some(le) = le {
+            labels.push(format!("le=\"{}\"", le));
+        }
+        if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels.join(","))
+        }
+    }
+}
+
+fn prometheus_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_incremented_per_series() {
+        let metrics = Metrics::default();
+
+        metrics.increment("received", &[("prefix", "tedge/measurements")]);
+        metrics.increment("received", &[("prefix", "tedge/measurements")]);
+        metrics.increment("received", &[("prefix", "tedge/alarms")]);
+
+        assert_eq!(
+            metrics.counter("received", &[("prefix", "tedge/measurements")]),
+            2
+        );
+        assert_eq!(
+            metrics.counter("received", &[("prefix", "tedge/alarms")]),
+            1
+        );
+        assert_eq!(
+            metrics.counter("received", &[("prefix", "tedge/events")]),
+            0
+        );
+    }
+
+    #[test]
+    fn the_series_are_shared_by_clones() {
+        let metrics = Metrics::default();
+        let clone = metrics.clone();
+
+        clone.increment("reconnects", &[]);
+        clone.set_gauge("queue_depth", &[], 3.0);
+
+        assert_eq!(metrics.counter("reconnects", &[]), 1);
+        assert_eq!(metrics.gauge("queue_depth", &[]), Some(3.0));
+    }
+
+    #[test]
+    fn metrics_are_formatted_as_json() {
+        let metrics = Metrics::default();
+        metrics.increment("received", &[("prefix", "tedge/\"quoted\"")]);
+        metrics.set_gauge("queue_depth", &[], 2.0);
+        metrics.observe("latency", &[], 0.003);
+
+        assert_eq!(
+            metrics.to_json(),
+            concat!(
+                r#"[{"name":"received","type":"counter","labels":{"prefix":"tedge/\"quoted\""},"value":1},"#,
+                r#"{"name":"queue_depth","type":"gauge","labels":{},"value":2.0},"#,
+                r#"{"name":"latency","type":"histogram","labels":{},"count":1,"sum":0.003,"buckets":["#,
+                r#"[0.0005,0],[0.001,0],[0.0025,0],[0.005,1],[0.01,1],[0.025,1],[0.05,1],[0.1,1],[0.25,1],[1.0,1]]}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn metrics_are_formatted_for_prometheus() {
+        let metrics = Metrics::default();
+        metrics.increment("received_total", &[("prefix", "tedge/alarms")]);
+        metrics.increment("received_total", &[("prefix", "tedge/events")]);
+        metrics.observe("latency_seconds", &[("prefix", "tedge/alarms")], 0.2);
+
+        let text = metrics.to_prometheus();
+
+        assert!(text.starts_with(concat!(
+            "# TYPE received_total counter\n",
+            "received_total{prefix=\"tedge/alarms\"} 1\n",
+            "received_total{prefix=\"tedge/events\"} 1\n",
+            "# TYPE latency_seconds histogram\n",
+            "latency_seconds_bucket{prefix=\"tedge/alarms\",le=\"0.0005\"} 0\n",
+        )));
+        assert!(text.contains("latency_seconds_bucket{prefix=\"tedge/alarms\",le=\"0.25\"} 1\n"));
+        assert!(text.ends_with(concat!(
+            "latency_seconds_bucket{prefix=\"tedge/alarms\",le=\"+Inf\"} 1\n",
+            "latency_seconds_sum{prefix=\"tedge/alarms\"} 0.2\n",
+            "latency_seconds_count{prefix=\"tedge/alarms\"} 1\n",
+        )));
+    }
+
+    #[test]
+    fn merged_metrics_are_tagged_by_source() {
+        let c8y = Metrics::default();
+        let az = Metrics::default();
+        c8y.increment("received_total", &[("prefix", "tedge/alarms")]);
+        az.increment("received_total", &[("prefix", "tedge/alarms")]);
+        az.increment("received_total", &[("prefix", "tedge/alarms")]);
+
+        let text = Metrics::merge_to_prometheus(
+            "mapper",
+            &[("c8y".to_string(), c8y), ("az".to_string(), az)],
+        );
+
+        assert_eq!(
+            text,
+            concat!(
+                "# TYPE received_total counter\n",
+                "received_total{mapper=\"az\",prefix=\"tedge/alarms\"} 2\n",
+                "received_total{mapper=\"c8y\",prefix=\"tedge/alarms\"} 1\n",
+            )
+        );
+    }
+
+    #[test]
+    fn topics_are_grouped_by_prefix() {
+        for (topic, prefix) in [
+            ("tedge/measurements", "tedge/measurements"),
+            ("tedge/measurements/child1", "tedge/measurements"),
+            ("tedge/alarms/critical/temperature", "tedge/alarms"),
+            ("c8y", "c8y"),
+        ] {
+            assert_eq!(topic_prefix(&Topic::new_unchecked(topic)), prefix);
+        }
+    }
+
+    #[tokio::test]
+    async fn metrics_are_published_periodically() {
+        use futures::StreamExt;
+
+        let metrics = Metrics::default();
+        metrics.increment("reconnects", &[]);
+        let (published, mut received) = mpsc::unbounded();
+
+        let topic = Topic::new_unchecked("tedge/health/test/metrics");
+        metrics.publish_periodically(topic.clone(), Duration::from_millis(10), published);
+
+        let message = received.next().await.unwrap();
+        assert_eq!(message.topic, topic);
+        assert_eq!(
+            message.payload_str().unwrap(),
+            r#"[{"name":"reconnects","type":"counter","labels":{},"value":1}]"#
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{Config, Connection, Message, MqttError, PubChannel, TopicFilter};
+use futures::channel::{mpsc, oneshot};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The requests awaiting a response, by correlation id
+///
+/// Set to `None` once the connection is closed, so no more requests are accepted.
+type PendingRequests = Arc<Mutex<Option<HashMap<String, Vec<PendingRequest>>>>>;
+
+/// A request awaiting a response
+struct PendingRequest {
+    /// Tell if a message with the correlation id of the request is actually a response to this request
+    is_response: Box<dyn Fn(&Message) -> bool + Send>,
+    response: oneshot::Sender<Message>,
+}
+
+/// A client publishing requests and awaiting the correlated responses.
+///
+/// The responses are expected on the subscriptions of the underlying connection.
+/// How a response is related to its request is protocol specific:
+/// a `correlation` function given on creation extracts the correlation id of a response,
+/// which is matched against the correlation id given along each request.
+///
+/// A request can further filter the responses with the same correlation id,
+/// e.g. to ignore a stale response published before the request.
+///
+/// Several requests can be pending at the same time, the client being cloned for each concurrent task.
+/// The received messages that are not responses to a pending request are not discarded,
+/// but forwarded to the channel of unrelated messages returned along the client.
+///
+/// ```no_run
+/// # use mqtt_channel::{Config, Message, MqttError, RequestClient, Topic};
+/// # use std::convert::TryInto;
+/// # use std::time::Duration;
+/// # #[tokio::main]
+/// # async fn request() -> Result<Message, MqttError> {
+/// let (client, _unrelated_messages) = RequestClient::connect(
+///     &Config::default(),
+///     "c8y/s/dat".try_into()?,
+///     |response| {
+///         let payload = response.payload_str().ok()?;
+///         payload.starts_with("71,").then(|| "71".to_string())
+///     },
+///     Duration::from_secs(10),
+/// )
+/// .await?;
+///
+/// let request = Message::new(&Topic::new("c8y/s/uat")?, "");
+/// let token = client.request("71", request).await?;
+/// # Ok(token)
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RequestClient {
+    requests: mpsc::UnboundedSender<Message>,
+    pending: PendingRequests,
+    timeout: Duration,
+}
+
+impl RequestClient {
+    /// Connect to the MQTT broker, subscribing to the `response_topics`.
+    ///
+    /// The errors of this connection are not reported, the requests failing with a timeout instead.
+    pub async fn connect<F>(
+        config: &Config,
+        response_topics: TopicFilter,
+        correlation: F,
+        timeout: Duration,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<Message>), MqttError>
+    where
+        F: Fn(&Message) -> Option<String> + Send + 'static,
+    {
+        let config = config.clone().with_subscriptions(response_topics);
+        let mut connection = Connection::new(&config).await?;
+        connection.errors.close();
+        Ok(RequestClient::new(connection, correlation, timeout))
+    }
+
+    /// Send requests over the given connection, waiting at most `timeout` for each response.
+    pub fn new<F>(
+        connection: Connection,
+        correlation: F,
+        timeout: Duration,
+    ) -> (Self, mpsc::UnboundedReceiver<Message>)
+    where
+        F: Fn(&Message) -> Option<String> + Send + 'static,
+    {
+        RequestClient::from_channels(
+            connection.published,
+            connection.received,
+            correlation,
+            timeout,
+        )
+    }
+
+    /// Send requests on the `requests` channel and await the responses on the `responses` channel.
+    ///
+    /// Return the client along the channel of the received messages that are not responses.
+    pub fn from_channels<F>(
+        requests: mpsc::UnboundedSender<Message>,
+        responses: mpsc::UnboundedReceiver<Message>,
+        correlation: F,
+        timeout: Duration,
+    ) -> (Self, mpsc::UnboundedReceiver<Message>)
+    where
+        F: Fn(&Message) -> Option<String> + Send + 'static,
+    {
+        let pending: PendingRequests = Arc::new(Mutex::new(Some(HashMap::new())));
+        let (unrelated_sender, unrelated_receiver) = mpsc::unbounded();
+        tokio::spawn(RequestClient::dispatch_responses(
+            responses,
+            correlation,
+            pending.clone(),
+            unrelated_sender,
+        ));
+
+        let client = RequestClient {
+            requests,
+            pending,
+            timeout,
+        };
+        (client, unrelated_receiver)
+    }
+
+    /// Publish a request and wait for the response with the given correlation id.
+    ///
+    /// Fails with `MqttError::RequestTimeout` if no response is received in time.
+    pub async fn request(
+        &self,
+        correlation_id: impl Into<String>,
+        request: Message,
+    ) -> Result<Message, MqttError> {
+        self.request_matching(correlation_id, request, |_| true)
+            .await
+    }
+
+    /// Publish a request and wait for a response with the given correlation id that satisfies `is_response`.
+    ///
+    /// The messages with this correlation id that are rejected by `is_response`
+    /// are forwarded to the channel of unrelated messages, the request still waiting for its response.
+    ///
+    /// Fails with `MqttError::RequestTimeout` if no response is received in time.
+    pub async fn request_matching<F>(
+        &self,
+        correlation_id: impl Into<String>,
+        request: Message,
+        is_response: F,
+    ) -> Result<Message, MqttError>
+    where
+        F: Fn(&Message) -> bool + Send + 'static,
+    {
+        let correlation_id = correlation_id.into();
+        let request_topic = request.topic.name.clone();
+
+        // The request is registered before being sent, not to miss a prompt response
+        let (response_sender, response_receiver) = oneshot::channel();
+        let pending_request = PendingRequest {
+            is_response: Box::new(is_response),
+            response: response_sender,
+        };
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending
+                .entry(correlation_id.clone())
+                .or_default()
+                .push(pending_request),
+            None => return Err(MqttError::ReadOnClosedConnection),
+        }
+        self.requests.clone().publish(request).await?;
+
+        match tokio::time::timeout(self.timeout, response_receiver).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(MqttError::ReadOnClosedConnection),
+            Err(_) => {
+                self.forget_closed_requests(&correlation_id);
+                Err(MqttError::RequestTimeout {
+                    topic: request_topic,
+                    timeout: self.timeout,
+                })
+            }
+        }
+    }
+
+    fn forget_closed_requests(&self, correlation_id: &str) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            if let Some(requests) = pending.get_mut(correlation_id) {
+                requests.retain(|request| !request.response.is_canceled());
+                if requests.is_empty() {
+                    pending.remove(correlation_id);
+                }
+            }
+        }
+    }
+
+    async fn dispatch_responses<F>(
+        mut responses: mpsc::UnboundedReceiver<Message>,
+        correlation: F,
+        pending: PendingRequests,
+        unrelated: mpsc::UnboundedSender<Message>,
+    ) where
+        F: Fn(&Message) -> Option<String>,
+    {
+        while let Some(message) = responses.next().await {
+            let responded = match correlation(&message) {
+                Some(correlation_id) => match pending.lock().unwrap().as_mut() {
+                    Some(pending) => take_responded_requests(pending, &correlation_id, &message),
+                    None => vec![],
+                },
+                None => vec![],
+            };
+            if responded.is_empty() {
+                // Errors are ignored: it just means the unrelated messages are of no interest
+                let _ = unrelated.unbounded_send(message);
+            } else {
+                for request in responded {
+                    let _ = request.response.send(message.clone());
+                }
+            }
+        }
+
+        // Dropping the pending requests notifies them that the connection is closed
+        pending.lock().unwrap().take();
+    }
+}
+
+/// Remove from the pending requests with the given correlation id those the message is a response to
+fn take_responded_requests(
+    pending: &mut HashMap<String, Vec<PendingRequest>>,
+    correlation_id: &str,
+    message: &Message,
+) -> Vec<PendingRequest> {
+    let requests = match pending.remove(correlation_id) {
+        Some(requests) => requests,
+        None => return vec![],
+    };
+    let (responded, awaiting): (Vec<_>, Vec<_>) = requests
+        .into_iter()
+        .partition(|request| (request.is_response)(message));
+    if !awaiting.is_empty() {
+        pending.insert(correlation_id.to_string(), awaiting);
+    }
+    responded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Topic;
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn message(topic: &str, payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload)
+    }
+
+    // The responses are prefixed by the id of the request: "<id>:<response>"
+    fn response_id(message: &Message) -> Option<String> {
+        let payload = message.payload_str().ok()?;
+        payload.split_once(':').map(|(id, _)| id.to_string())
+    }
+
+    fn client() -> (
+        RequestClient,
+        mpsc::UnboundedReceiver<Message>,
+        mpsc::UnboundedSender<Message>,
+        mpsc::UnboundedReceiver<Message>,
+    ) {
+        let (requests, published) = mpsc::unbounded();
+        let (responder, responses) = mpsc::unbounded();
+        let (client, unrelated) =
+            RequestClient::from_channels(requests, responses, response_id, TIMEOUT);
+        (client, published, responder, unrelated)
+    }
+
+    /// Respond to each request with `<request>:done`
+    fn spawn_responder(
+        mut published: mpsc::UnboundedReceiver<Message>,
+        mut responder: mpsc::UnboundedSender<Message>,
+    ) {
+        tokio::spawn(async move {
+            while let Some(request) = published.next().await {
+                let response = format!("{}:done", request.payload_str().unwrap());
+                let _ = responder.publish(message("test/res", &response)).await;
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn the_request_is_published() {
+        let (client, mut published, mut responder, _unrelated) = client();
+
+        let request =
+            tokio::spawn(async move { client.request("1", message("test/req", "1")).await });
+        assert_eq!(published.next().await, Some(message("test/req", "1")));
+        responder
+            .publish(message("test/res", "1:done"))
+            .await
+            .unwrap();
+
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response, message("test/res", "1:done"));
+    }
+
+    #[tokio::test]
+    async fn unrelated_messages_are_forwarded() {
+        let (client, published, mut responder, mut unrelated) = client();
+
+        responder
+            .publish(message("test/res", "0:stale"))
+            .await
+            .unwrap();
+        spawn_responder(published, responder);
+        let response = client.request("2", message("test/req", "2")).await.unwrap();
+
+        assert_eq!(response.payload_str().unwrap(), "2:done");
+        assert_eq!(unrelated.next().await, Some(message("test/res", "0:stale")));
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_get_their_own_response() {
+        let (client, published, responder, _unrelated) = client();
+        spawn_responder(published, responder);
+
+        let other_client = client.clone();
+        let (response_a, response_b) = tokio::join!(
+            client.request("a", message("test/req", "a")),
+            other_client.request("b", message("test/req", "b")),
+        );
+
+        assert_eq!(response_a.unwrap().payload_str().unwrap(), "a:done");
+        assert_eq!(response_b.unwrap().payload_str().unwrap(), "b:done");
+    }
+
+    #[tokio::test]
+    async fn a_request_with_no_response_times_out() {
+        let (client, _published, mut responder, _unrelated) = client();
+
+        responder
+            .publish(message("test/res", "0:stale"))
+            .await
+            .unwrap();
+        let result = client.request("3", message("test/req", "3")).await;
+
+        assert!(matches!(
+            result,
+            Err(MqttError::RequestTimeout { topic, .. }) if topic == "test/req"
+        ));
+    }
+
+    #[tokio::test]
+    async fn a_request_waits_for_a_matching_response() {
+        let (client, mut published, mut responder, mut unrelated) = client();
+
+        let request = tokio::spawn(async move {
+            client
+                .request_matching("5", message("test/req", "5"), |response| {
+                    response.payload_str().unwrap() != "5:stale"
+                })
+                .await
+        });
+        assert_eq!(published.next().await, Some(message("test/req", "5")));
+        responder
+            .publish(message("test/res", "5:stale"))
+            .await
+            .unwrap();
+        responder
+            .publish(message("test/res", "5:done"))
+            .await
+            .unwrap();
+
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response.payload_str().unwrap(), "5:done");
+        assert_eq!(unrelated.next().await, Some(message("test/res", "5:stale")));
+    }
+
+    #[tokio::test]
+    async fn a_closed_connection_is_reported() {
+        let (client, _published, responder, _unrelated) = client();
+
+        drop(responder);
+        let result = client.request("4", message("test/req", "4")).await;
+
+        assert!(matches!(result, Err(MqttError::ReadOnClosedConnection)));
+    }
+}
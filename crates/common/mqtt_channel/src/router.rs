@@ -0,0 +1,352 @@
+use crate::{Message, MqttError, Topic, TopicFilter};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+
+/// A router dispatching messages to async handlers, according to their topic.
+///
+/// Each handler is registered along a topic pattern,
+/// where a level can be captured under a name, as in `tedge/measurements/{child}`.
+/// The other levels are either literal or MQTT wildcards: `+` and a trailing `#`,
+/// the levels matched by a trailing `#` being captured too.
+///
+/// A handler is given a mutable reference to some state `S`,
+/// the message and the values captured from the message topic.
+/// It returns a future of `R`.
+///
+/// ```
+/// # use mqtt_channel::{Captures, Message, MqttError, Router, Topic};
+/// # async fn route() -> Result<(), MqttError> {
+/// async fn measurement(count: &mut usize, captures: &Captures) -> String {
+///     *count += 1;
+///     captures.get("child").unwrap_or("main").to_string()
+/// }
+///
+/// let router = Router::new()
+///     .route("tedge/measurements", |count, _, captures| {
+///         Box::pin(measurement(count, captures))
+///     })?
+///     .route("tedge/measurements/{child}", |count, _, captures| {
+///         Box::pin(measurement(count, captures))
+///     })?;
+///
+/// let mut count = 0;
+/// let message = Message::new(&Topic::new("tedge/measurements/child1")?, "{}");
+/// assert_eq!(router.dispatch(&mut count, &message).await, Some("child1".to_string()));
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Router<S, R> {
+    routes: Vec<(TopicPattern, Handler<S, R>)>,
+}
+
+/// An async message handler
+pub type Handler<S, R> =
+    Box<dyn for<'a> Fn(&'a mut S, &'a Message, &'a Captures) -> BoxFuture<'a, R> + Send + Sync>;
+
+impl<S, R> Router<S, R> {
+    /// A router with no routes
+    pub fn new() -> Self {
+        Router { routes: vec![] }
+    }
+
+    /// Register a handler for the messages which topic matches the given pattern.
+    ///
+    /// When several patterns match a topic, the first registered route is used.
+    pub fn route<F>(mut self, pattern: &str, handler: F) -> Result<Self, MqttError>
+    where
+        F: for<'a> Fn(&'a mut S, &'a Message, &'a Captures) -> BoxFuture<'a, R>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let pattern = TopicPattern::new(pattern)?;
+        self.routes.push((pattern, Box::new(handler)));
+        Ok(self)
+    }
+
+    /// The topic filter matching all the routes, to be used to subscribe.
+    pub fn topic_filter(&self) -> TopicFilter {
+        let mut filter = TopicFilter::empty();
+        for (pattern, _) in self.routes.iter() {
+            filter.add_unchecked(&pattern.filter);
+        }
+        filter
+    }
+
+    /// Find the handler for the given topic, along the values captured from the topic.
+    pub fn matching(&self, topic: &Topic) -> Option<(&Handler<S, R>, Captures)> {
+        self.routes.iter().find_map(|(pattern, handler)| {
+            pattern
+                .captures(&topic.name)
+                .map(|captures| (handler, captures))
+        })
+    }
+
+    /// Dispatch the message to the matching handler.
+    ///
+    /// Return `None` if no route matches the message topic.
+    pub async fn dispatch(&self, state: &mut S, message: &Message) -> Option<R> {
+        match self.matching(&message.topic) {
+            Some((handler, captures)) => Some(handler(state, message, &captures).await),
+            None => None,
+        }
+    }
+}
+
+impl<S, R> Default for Router<S, R> {
+    fn default() -> Self {
+        Router::new()
+    }
+}
+
+impl<S, R> std::fmt::Debug for Router<S, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.routes.iter().map(|(pattern, _)| &pattern.pattern))
+            .finish()
+    }
+}
+
+/// The topic levels captured by a route
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Captures {
+    values: HashMap<String, String>,
+    remaining_levels: Vec<String>,
+}
+
+impl Captures {
+    /// The topic level captured under the given name, if any
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// The topic levels matched by a trailing `#`, empty if none
+    pub fn remaining_levels(&self) -> Vec<&str> {
+        self.remaining_levels.iter().map(String::as_str).collect()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Level {
+    Literal(String),
+    Capture(String),
+    SingleLevelWildcard,
+    MultiLevelWildcard,
+}
+
+/// A topic filter, where some levels are captured under a name
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct TopicPattern {
+    pattern: String,
+    filter: String,
+    levels: Vec<Level>,
+}
+
+impl TopicPattern {
+    fn new(pattern: &str) -> Result<Self, MqttError> {
+        let levels: Vec<Level> = pattern
+            .split('/')
+            .map(|level| match level {
+                "+" => Level::SingleLevelWildcard,
+                "#" => Level::MultiLevelWildcard,
+                _ => match level.strip_prefix('{').and_then(|l| l.strip_suffix('}')) {
+                    Some(name) => Level::Capture(name.to_string()),
+                    None => Level::Literal(level.to_string()),
+                },
+            })
+            .collect();
+        let filter = levels
+            .iter()
+            .map(|level| match level {
+                Level::Literal(name) => name.as_str(),
+                Level::Capture(_) | Level::SingleLevelWildcard => "+",
+                Level::MultiLevelWildcard => "#",
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let valid_captures = levels.iter().all(|level| match level {
+            Level::Capture(name) => !name.is_empty() && !name.contains(['{', '}'].as_ref()),
+            Level::Literal(name) => !name.contains(['{', '}'].as_ref()),
+            _ => true,
+        });
+        if !valid_captures || !rumqttc::valid_filter(&filter) {
+            return Err(MqttError::InvalidFilter {
+                pattern: pattern.to_string(),
+            });
+        }
+
+        Ok(TopicPattern {
+            pattern: pattern.to_string(),
+            filter,
+            levels,
+        })
+    }
+
+    fn captures(&self, topic: &str) -> Option<Captures> {
+        let mut captures = Captures::default();
+        let mut topic_levels = topic.split('/');
+        for level in self.levels.iter() {
+            match level {
+                // As for MQTT, `a/#` matches `a` as well as `a/b/c`
+                Level::MultiLevelWildcard => {
+                    captures.remaining_levels = topic_levels.map(str::to_string).collect();
+                    return Some(captures);
+                }
+                Level::SingleLevelWildcard => {
+                    topic_levels.next()?;
+                }
+                Level::Capture(name) => {
+                    let value = topic_levels.next()?;
+                    captures.values.insert(name.clone(), value.to_string());
+                }
+                Level::Literal(name) => {
+                    if topic_levels.next()? != name {
+                        return None;
+                    }
+                }
+            }
+        }
+        match topic_levels.next() {
+            None => Some(captures),
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(topic: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), "")
+    }
+
+    async fn tag(tags: &mut Vec<String>, tag: &str, captures: &Captures) -> String {
+        let child = captures.get("child").unwrap_or("main");
+        tags.push(format!("{}:{}", tag, child));
+        child.to_string()
+    }
+
+    fn router() -> Router<Vec<String>, String> {
+        Router::new()
+            .route("tedge/measurements", |tags, _, captures| {
+                Box::pin(tag(tags, "measurement", captures))
+            })
+            .unwrap()
+            .route("tedge/measurements/{child}", |tags, _, captures| {
+                Box::pin(tag(tags, "measurement", captures))
+            })
+            .unwrap()
+            .route("tedge/alarms/+/+/{child}", |tags, _, captures| {
+                Box::pin(tag(tags, "alarm", captures))
+            })
+            .unwrap()
+            .route("tedge/#", |tags, _, captures| {
+                Box::pin(tag(tags, "other", captures))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn named_levels_are_captured() {
+        let pattern = TopicPattern::new("tedge/{kind}/+/{child}").unwrap();
+
+        let captures = pattern.captures("tedge/alarms/critical/child1").unwrap();
+
+        assert_eq!(captures.get("kind"), Some("alarms"));
+        assert_eq!(captures.get("child"), Some("child1"));
+        assert_eq!(captures.get("critical"), None);
+    }
+
+    #[test]
+    fn a_pattern_matches_only_topics_with_the_same_levels() {
+        let pattern = TopicPattern::new("tedge/measurements/{child}").unwrap();
+
+        assert!(pattern.captures("tedge/measurements/child1").is_some());
+        assert!(pattern.captures("tedge/measurements").is_none());
+        assert!(pattern
+            .captures("tedge/measurements/child1/extra")
+            .is_none());
+        assert!(pattern.captures("tedge/events/child1").is_none());
+    }
+
+    #[test]
+    fn a_multi_level_wildcard_matches_the_remaining_levels() {
+        let pattern = TopicPattern::new("tedge/{kind}/#").unwrap();
+
+        let captures = pattern.captures("tedge/alarms").unwrap();
+        assert!(captures.remaining_levels().is_empty());
+
+        let captures = pattern
+            .captures("tedge/alarms/critical/temperature")
+            .unwrap();
+        assert_eq!(captures.get("kind"), Some("alarms"));
+        assert_eq!(captures.remaining_levels(), vec!["critical", "temperature"]);
+
+        assert!(pattern.captures("c8y/alarms").is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        for pattern in ["tedge/{}", "tedge/{child", "tedge/#/{child}", "tedge/a{b}c"] {
+            assert!(
+                TopicPattern::new(pattern).is_err(),
+                "{} should be rejected",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn the_topic_filter_covers_all_the_routes() {
+        let filter = router().topic_filter();
+
+        assert_eq!(
+            filter.patterns,
+            vec![
+                "tedge/measurements",
+                "tedge/measurements/+",
+                "tedge/alarms/+/+/+",
+                "tedge/#"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn messages_are_dispatched_to_the_first_matching_route() {
+        let router = router();
+        let mut tags = vec![];
+
+        for topic in [
+            "tedge/measurements",
+            "tedge/measurements/child1",
+            "tedge/alarms/critical/temperature/child2",
+            "tedge/alarms/critical/temperature",
+        ] {
+            router.dispatch(&mut tags, &message(topic)).await;
+        }
+
+        assert_eq!(
+            tags,
+            vec![
+                "measurement:main",
+                "measurement:child1",
+                "alarm:child2",
+                "other:main"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn messages_with_no_matching_route_are_not_dispatched() {
+        let router = router();
+        let mut tags = vec![];
+
+        let result = router.dispatch(&mut tags, &message("c8y/s/ds")).await;
+
+        assert_eq!(result, None);
+        assert!(tags.is_empty());
+    }
+}
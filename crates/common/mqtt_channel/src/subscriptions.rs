@@ -0,0 +1,166 @@
+use crate::{MqttError, TopicFilter};
+use rumqttc::{AsyncClient, SubscribeFilter};
+use std::sync::{Arc, Mutex};
+
+/// A handle to add and remove the subscriptions of a live connection
+///
+/// The subscriptions are tracked by the connection,
+/// so they are restored on re-connection when the broker has not persisted the session.
+///
+/// ```no_run
+/// # use mqtt_channel::{Config, Connection, MqttError};
+/// # use std::convert::TryInto;
+/// # #[tokio::main]
+/// # async fn subscribe() -> Result<(), MqttError> {
+/// let connection = Connection::new(&Config::default()).await?;
+///
+/// connection.subscriptions.subscribe("c8y/s/dc/child1".try_into()?)?;
+/// connection.subscriptions.unsubscribe("c8y/s/dc/child1".try_into()?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SubscriptionHandle {
+    mqtt_client: Option<AsyncClient>,
+    subscriptions: Arc<Mutex<Vec<SubscribeFilter>>>,
+}
+
+impl SubscriptionHandle {
+    pub(crate) fn new(mqtt_client: AsyncClient, subscriptions: &TopicFilter) -> Self {
+        SubscriptionHandle {
+            mqtt_client: Some(mqtt_client),
+            subscriptions: Arc::new(Mutex::new(subscriptions.filters())),
+        }
+    }
+
+    /// A handle that is not attached to any connection, only tracking the subscriptions.
+    ///
+    /// This is used to test the components updating the subscriptions of a connection.
+    pub fn detached(subscriptions: &TopicFilter) -> Self {
+        SubscriptionHandle {
+            mqtt_client: None,
+            subscriptions: Arc::new(Mutex::new(subscriptions.filters())),
+        }
+    }
+
+    /// Subscribe to the topics of the given filter.
+    ///
+    /// A pattern that is already subscribed is subscribed again with the QoS of the filter.
+    pub fn subscribe(&self, filter: TopicFilter) -> Result<(), MqttError> {
+        let filters = filter.filters();
+        if filters.is_empty() {
+            return Ok(());
+        }
+
+        self.with_subscriptions(|subscriptions| {
+            for filter in filters.iter() {
+                subscriptions.retain(|subscription| subscription.path != filter.path);
+                subscriptions.push(filter.clone());
+            }
+        });
+        if let Some(mqtt_client) = &self.mqtt_client {
+            mqtt_client.try_subscribe_many(filters)?;
+        }
+        Ok(())
+    }
+
+    /// Unsubscribe from the topics of the given filter.
+    pub fn unsubscribe(&self, filter: TopicFilter) -> Result<(), MqttError> {
+        self.with_subscriptions(|subscriptions| {
+            subscriptions.retain(|subscription| !filter.patterns.contains(&subscription.path))
+        });
+        if let Some(mqtt_client) = &self.mqtt_client {
+            for pattern in filter.patterns {
+                mqtt_client.try_unsubscribe(pattern)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The patterns currently subscribed by the connection.
+    pub fn patterns(&self) -> Vec<String> {
+        self.filters()
+            .into_iter()
+            .map(|filter| filter.path)
+            .collect()
+    }
+
+    /// The list of `SubscribeFilter` to be restored on re-connection
+    pub(crate) fn filters(&self) -> Vec<SubscribeFilter> {
+        self.with_subscriptions(|subscriptions| subscriptions.clone())
+    }
+
+    fn with_subscriptions<T>(&self, f: impl FnOnce(&mut Vec<SubscribeFilter>) -> T) -> T {
+        // The updates cannot panic half-way, so the list is consistent even if the lock is poisoned
+        let mut subscriptions = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut subscriptions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rumqttc::MqttOptions;
+    use std::convert::TryInto;
+
+    fn handle(subscriptions: &str) -> (SubscriptionHandle, rumqttc::EventLoop) {
+        let (mqtt_client, event_loop) =
+            AsyncClient::new(MqttOptions::new("test", "localhost", 1883), 10);
+        let subscriptions = subscriptions.try_into().unwrap();
+        (
+            SubscriptionHandle::new(mqtt_client, &subscriptions),
+            event_loop,
+        )
+    }
+
+    #[test]
+    fn subscriptions_are_added_and_removed() {
+        let (handle, _event_loop) = handle("a/b");
+
+        handle
+            .subscribe(vec!["c/+", "d/#"].try_into().unwrap())
+            .unwrap();
+        assert_eq!(handle.patterns(), vec!["a/b", "c/+", "d/#"]);
+
+        handle
+            .unsubscribe(vec!["a/b", "d/#"].try_into().unwrap())
+            .unwrap();
+        assert_eq!(handle.patterns(), vec!["c/+"]);
+    }
+
+    #[test]
+    fn a_pattern_is_subscribed_only_once() {
+        let (handle, _event_loop) = handle("a/b");
+
+        handle
+            .subscribe(TopicFilter::new_unchecked("a/b").with_qos(rumqttc::QoS::ExactlyOnce))
+            .unwrap();
+
+        let filters = handle.filters();
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].qos, rumqttc::QoS::ExactlyOnce);
+    }
+
+    #[test]
+    fn the_subscriptions_are_shared_by_the_clones_of_a_handle() {
+        let (handle, _event_loop) = handle("a/b");
+        let clone = handle.clone();
+
+        clone.subscribe("c/d".try_into().unwrap()).unwrap();
+
+        assert_eq!(handle.patterns(), vec!["a/b", "c/d"]);
+    }
+
+    #[test]
+    fn a_detached_handle_tracks_the_subscriptions() {
+        let handle = SubscriptionHandle::detached(&"a/b".try_into().unwrap());
+
+        handle.subscribe("c/d".try_into().unwrap()).unwrap();
+        handle.unsubscribe("a/b".try_into().unwrap()).unwrap();
+
+        assert_eq!(handle.patterns(), vec!["c/d"]);
+    }
+}
@@ -202,6 +202,108 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn publishing_birth_and_last_will_messages() -> Result<(), anyhow::Error> {
+        // Given an MQTT broker
+        let broker = mqtt_tests::test_mqtt_broker();
+        let mut status_messages = broker.messages_published_on("test/client/status").await;
+
+        // A client can be configured to publish a birth message on connect
+        // and to publish a last will message when disconnected
+        let status_topic = Topic::new("test/client/status")?;
+        let mqtt_config = Config::default()
+            .with_port(broker.port)
+            .with_session_name("client_with_a_last_will")
+            .with_birth_message(Message::new(&status_topic, "up"))
+            .with_last_will_message(Message::new(&status_topic, "down"));
+
+        let con = Connection::new(&mqtt_config).await?;
+        mqtt_tests::assert_received(&mut status_messages, TIMEOUT, vec!["up"]).await;
+
+        con.close().await;
+        mqtt_tests::assert_received(&mut status_messages, TIMEOUT, vec!["down"]).await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn the_mqtt_traffic_is_recorded_in_the_metrics() -> Result<(), anyhow::Error> {
+        // Given an MQTT broker
+        let broker = mqtt_tests::test_mqtt_broker();
+        let mut out_messages = broker.messages_published_on("test/output/#").await;
+
+        // A client with metrics
+        let metrics = Metrics::default();
+        let mqtt_config = Config::default()
+            .with_port(broker.port)
+            .with_session_name("client_with_metrics")
+            .with_subscriptions("test/input/#".try_into()?)
+            .with_metrics(metrics.clone());
+        let mut con = Connection::new(&mqtt_config).await?;
+
+        // Counts the messages received and published, per topic prefix
+        broker.publish("test/input/child", "in").await?;
+        assert_eq!(
+            MaybeMessage::Next(message("test/input/child", "in")),
+            next_message(&mut con.received).await
+        );
+        con.published
+            .send(message("test/output/child", "out"))
+            .await?;
+        mqtt_tests::assert_received(&mut out_messages, TIMEOUT, vec!["out"]).await;
+
+        assert_eq!(
+            metrics.counter("mqtt_messages_received_total", &[("prefix", "test/input")]),
+            1
+        );
+        assert_eq!(
+            metrics.counter(
+                "mqtt_messages_published_total",
+                &[("prefix", "test/output")]
+            ),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn subscribing_to_topics_on_a_live_connection() -> Result<(), anyhow::Error> {
+        // Given an MQTT broker
+        let broker = mqtt_tests::test_mqtt_broker();
+        let mqtt_config = Config::default().with_port(broker.port);
+
+        // A client subscribed to a topic on connect
+        let mqtt_config = mqtt_config
+            .with_session_name("live_subscriptions")
+            .with_subscriptions("test/static".try_into()?);
+        let mut con = Connection::new(&mqtt_config).await?;
+
+        // Can subscribe to new topics
+        con.subscriptions.subscribe("test/dynamic".try_into()?)?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        broker.publish("test/dynamic", "dynamic msg").await?;
+        assert_eq!(
+            MaybeMessage::Next(message("test/dynamic", "dynamic msg")),
+            next_message(&mut con.received).await
+        );
+
+        // And unsubscribe from any topic
+        con.subscriptions.unsubscribe("test/static".try_into()?)?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        broker.publish("test/static", "ignored msg").await?;
+        broker.publish("test/dynamic", "last msg").await?;
+        assert_eq!(
+            MaybeMessage::Next(message("test/dynamic", "last msg")),
+            next_message(&mut con.received).await
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn receiving_messages_while_not_connected() -> Result<(), anyhow::Error> {
@@ -0,0 +1,44 @@
+use crate::*;
+
+impl TEdgeConfig {
+    /// The settings to be used by the thin-edge components to download files
+    ///
+    /// The proxy, root certificates and mirrors are taken from `download.*`.
+    /// A client certificate is only used along its private key:
+    /// setting only one of `download.client_cert_path` and `download.client_key_path` is an error.
+    pub fn download_settings(&self) -> Result<download::DownloadSettings, ConfigSettingError> {
+        let mut download_settings = download::DownloadSettings::default();
+
+        if let Some(proxy_url) = self.query_optional(DownloadProxyUrlSetting)? {
+            download_settings = download_settings.with_proxy(proxy_url);
+        }
+
+        if let Some(root_cert_path) = self.query_optional(DownloadRootCertPathSetting)? {
+            download_settings = download_settings.with_trusted_root_cert(root_cert_path);
+        }
+
+        match (
+            self.query_optional(DownloadClientCertPathSetting)?,
+            self.query_optional(DownloadClientKeyPathSetting)?,
+        ) {
+            (Some(cert_path), Some(key_path)) => {
+                download_settings = download_settings.with_client_identity(cert_path, key_path);
+            }
+            (Some(_), None) => {
+                return Err(ConfigSettingError::ConfigNotSet {
+                    key: DownloadClientKeyPathSetting::KEY,
+                })
+            }
+            (None, Some(_)) => {
+                return Err(ConfigSettingError::ConfigNotSet {
+                    key: DownloadClientCertPathSetting::KEY,
+                })
+            }
+            (None, None) => {}
+        }
+
+        download_settings.mirrors = self.query(DownloadMirrorsSetting)?.0;
+
+        Ok(download_settings)
+    }
+}
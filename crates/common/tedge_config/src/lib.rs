@@ -1,6 +1,8 @@
 mod config_setting;
+mod download_config;
 mod error;
 mod models;
+mod mqtt_config;
 mod settings;
 mod tedge_config;
 mod tedge_config_defaults;
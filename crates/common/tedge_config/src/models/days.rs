@@ -0,0 +1,61 @@
+use std::convert::{TryFrom, TryInto};
+use std::time::Duration;
+
+/// A number of days
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Days(pub u32);
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid number of days: '{input}'.")]
+pub struct InvalidDays {
+    input: String,
+}
+
+impl TryFrom<String> for Days {
+    type Error = InvalidDays;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        input
+            .as_str()
+            .parse::<u32>()
+            .map_err(|_| InvalidDays { input })
+            .map(Days)
+    }
+}
+
+impl TryInto<String> for Days {
+    type Error = std::convert::Infallible;
+
+    fn try_into(self) -> Result<String, Self::Error> {
+        Ok(format!("{}", self.0))
+    }
+}
+
+impl From<Days> for u32 {
+    fn from(val: Days) -> Self {
+        val.0
+    }
+}
+
+impl From<Days> for Duration {
+    fn from(val: Days) -> Self {
+        Duration::from_secs(u64::from(val.0) * 24 * 60 * 60)
+    }
+}
+
+#[cfg(test)]
+use assert_matches::*;
+#[test]
+fn conversion_from_valid_number_of_days_succeeds() {
+    assert_matches!(Days::try_from("7".to_string()), Ok(Days(7)));
+}
+
+#[test]
+fn conversion_from_a_negative_number_of_days_fails() {
+    assert_matches!(Days::try_from("-1".to_string()), Err(_));
+}
+
+#[test]
+fn days_are_converted_into_a_duration() {
+    assert_eq!(Duration::from(Days(2)), Duration::from_secs(2 * 86400));
+}
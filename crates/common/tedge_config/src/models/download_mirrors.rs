@@ -0,0 +1,80 @@
+use download::Mirror;
+use std::convert::{TryFrom, TryInto};
+
+/// The URL prefixes rewritten before any download, each given as `<url-prefix>=<target-prefix>`
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct DownloadMirrors(pub Vec<Mirror>);
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid download mirror: '{input}'. Expected: <url-prefix>=<target-prefix>")]
+pub struct InvalidDownloadMirror {
+    input: String,
+}
+
+impl TryFrom<Vec<String>> for DownloadMirrors {
+    type Error = InvalidDownloadMirror;
+
+    fn try_from(mappings: Vec<String>) -> Result<Self, Self::Error> {
+        mappings
+            .into_iter()
+            .map(|input| input.parse().map_err(|_| InvalidDownloadMirror { input }))
+            .collect::<Result<Vec<Mirror>, _>>()
+            .map(DownloadMirrors)
+    }
+}
+
+impl From<DownloadMirrors> for Vec<String> {
+    fn from(val: DownloadMirrors) -> Self {
+        val.0.iter().map(Mirror::to_string).collect()
+    }
+}
+
+impl TryFrom<String> for DownloadMirrors {
+    type Error = InvalidDownloadMirror;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        let mappings: Vec<String> = input
+            .split(',')
+            .map(str::trim)
+            .filter(|mapping| !mapping.is_empty())
+            .map(String::from)
+            .collect();
+        DownloadMirrors::try_from(mappings)
+    }
+}
+
+impl TryInto<String> for DownloadMirrors {
+    type Error = std::convert::Infallible;
+
+    fn try_into(self) -> Result<String, Self::Error> {
+        let mappings: Vec<String> = self.into();
+        Ok(mappings.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_comma_separated_mirrors() {
+        let mirrors = DownloadMirrors::try_from(
+            "https://a.com/=file:///media/usb/, https://b.com/=http://cache.lan/".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(mirrors.0.len(), 2);
+        assert_eq!(mirrors.0[1].url_prefix, "https://b.com/");
+        assert_eq!(
+            TryInto::<String>::try_into(mirrors).unwrap(),
+            "https://a.com/=file:///media/usb/,https://b.com/=http://cache.lan/"
+        );
+    }
+
+    #[test]
+    fn reject_invalid_mirrors() {
+        assert!(DownloadMirrors::try_from("https://a.com/".to_string()).is_err());
+        assert!(DownloadMirrors::try_from("=file:///media/usb/".to_string()).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::convert::TryFrom;
+
+/// How the mappers forward their error reports, beside publishing them on `tedge/errors`
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorForwarding {
+    None,
+    Event,
+    Alarm,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid error forwarding: '{input}'. Supported values are: none, event, alarm")]
+pub struct InvalidErrorForwarding {
+    input: String,
+}
+
+impl TryFrom<String> for ErrorForwarding {
+    type Error = InvalidErrorForwarding;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        match input.as_str() {
+            "none" => Ok(ErrorForwarding::None),
+            "event" => Ok(ErrorForwarding::Event),
+            "alarm" => Ok(ErrorForwarding::Alarm),
+            _ => Err(InvalidErrorForwarding { input }),
+        }
+    }
+}
+
+impl From<ErrorForwarding> for String {
+    fn from(value: ErrorForwarding) -> Self {
+        match value {
+            ErrorForwarding::None => "none".to_string(),
+            ErrorForwarding::Event => "event".to_string(),
+            ErrorForwarding::Alarm => "alarm".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::*;
+
+    #[test]
+    fn error_forwardings_are_converted_from_and_to_strings() {
+        for forwarding in [
+            ErrorForwarding::None,
+            ErrorForwarding::Event,
+            ErrorForwarding::Alarm,
+        ] {
+            let string: String = forwarding.into();
+            assert_eq!(ErrorForwarding::try_from(string).unwrap(), forwarding);
+        }
+        assert_matches!(
+            ErrorForwarding::try_from("log".to_string()),
+            Err(InvalidErrorForwarding { .. })
+        );
+    }
+}
@@ -1,8 +1,17 @@
 pub mod connect_url;
+pub mod days;
+pub mod download_mirrors;
+pub mod error_forwarding;
 pub mod file_path;
 pub mod flag;
 pub mod ipaddress;
 pub mod port;
+pub mod queue;
+pub mod seconds;
+pub mod string_list;
 pub mod templates_set;
 
-pub use self::{connect_url::*, file_path::*, flag::*, ipaddress::*, port::*, templates_set::*};
+pub use self::{
+    connect_url::*, days::*, download_mirrors::*, error_forwarding::*, file_path::*, flag::*,
+    ipaddress::*, port::*, queue::*, seconds::*, string_list::*, templates_set::*,
+};
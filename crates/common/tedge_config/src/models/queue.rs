@@ -0,0 +1,112 @@
+use std::convert::{TryFrom, TryInto};
+
+/// The maximum number of messages kept in a queue
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct QueueSize(pub usize);
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid queue size: '{input}'.")]
+pub struct InvalidQueueSize {
+    input: String,
+}
+
+impl TryFrom<String> for QueueSize {
+    type Error = InvalidQueueSize;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        input
+            .as_str()
+            .parse::<usize>()
+            .map_err(|_| InvalidQueueSize { input })
+            .map(QueueSize)
+    }
+}
+
+impl TryInto<String> for QueueSize {
+    type Error = std::convert::Infallible;
+
+    fn try_into(self) -> Result<String, Self::Error> {
+        Ok(format!("{}", self.0))
+    }
+}
+
+impl From<QueueSize> for usize {
+    fn from(val: QueueSize) -> Self {
+        val.0
+    }
+}
+
+/// What to do when a message is pushed on a full queue
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+    DropOldest,
+    DropNewest,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid queue policy: '{input}'. Supported values are: drop_oldest, drop_newest")]
+pub struct InvalidQueuePolicy {
+    input: String,
+}
+
+impl TryFrom<String> for QueuePolicy {
+    type Error = InvalidQueuePolicy;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        match input.as_str() {
+            "drop_oldest" => Ok(QueuePolicy::DropOldest),
+            "drop_newest" => Ok(QueuePolicy::DropNewest),
+            _ => Err(InvalidQueuePolicy { input }),
+        }
+    }
+}
+
+impl From<QueuePolicy> for String {
+    fn from(value: QueuePolicy) -> Self {
+        match value {
+            QueuePolicy::DropOldest => "drop_oldest".to_string(),
+            QueuePolicy::DropNewest => "drop_newest".to_string(),
+        }
+    }
+}
+
+impl From<QueuePolicy> for mqtt_channel::OverflowPolicy {
+    fn from(value: QueuePolicy) -> Self {
+        match value {
+            QueuePolicy::DropOldest => mqtt_channel::OverflowPolicy::DropOldest,
+            QueuePolicy::DropNewest => mqtt_channel::OverflowPolicy::DropNewest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::*;
+
+    #[test]
+    fn conversion_from_valid_queue_size_succeeds() {
+        assert_matches!(QueueSize::try_from("1000".to_string()), Ok(QueueSize(1000)));
+    }
+
+    #[test]
+    fn conversion_from_negative_queue_size_fails() {
+        assert_matches!(
+            QueueSize::try_from("-1".to_string()),
+            Err(InvalidQueueSize { .. })
+        );
+    }
+
+    #[test]
+    fn queue_policies_are_converted_from_and_to_strings() {
+        for policy in [QueuePolicy::DropOldest, QueuePolicy::DropNewest] {
+            let string: String = policy.into();
+            assert_eq!(QueuePolicy::try_from(string).unwrap(), policy);
+        }
+        assert_matches!(
+            QueuePolicy::try_from("drop_all".to_string()),
+            Err(InvalidQueuePolicy { .. })
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::convert::{TryFrom, TryInto};
+use std::time::Duration;
+
+/// A number of seconds
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Seconds(pub u64);
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid number of seconds: '{input}'.")]
+pub struct InvalidSeconds {
+    input: String,
+}
+
+impl TryFrom<String> for Seconds {
+    type Error = InvalidSeconds;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        input
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| InvalidSeconds { input })
+            .map(Seconds)
+    }
+}
+
+impl TryInto<String> for Seconds {
+    type Error = std::convert::Infallible;
+
+    fn try_into(self) -> Result<String, Self::Error> {
+        Ok(format!("{}", self.0))
+    }
+}
+
+impl From<Seconds> for u64 {
+    fn from(val: Seconds) -> Self {
+        val.0
+    }
+}
+
+impl From<Seconds> for Duration {
+    fn from(val: Seconds) -> Self {
+        Duration::from_secs(val.0)
+    }
+}
+
+#[cfg(test)]
+use assert_matches::*;
+#[test]
+fn conversion_from_valid_number_of_seconds_succeeds() {
+    assert_matches!(Seconds::try_from("300".to_string()), Ok(Seconds(300)));
+}
+
+#[test]
+fn conversion_from_an_invalid_number_of_seconds_fails() {
+    assert_matches!(Seconds::try_from("5min".to_string()), Err(_));
+}
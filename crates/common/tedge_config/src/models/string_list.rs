@@ -0,0 +1,62 @@
+use std::convert::TryInto;
+
+/// A list of names, given on the command line as a comma separated list
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct StringList(pub Vec<String>);
+
+impl From<Vec<String>> for StringList {
+    fn from(items: Vec<String>) -> Self {
+        StringList(items)
+    }
+}
+
+impl From<StringList> for Vec<String> {
+    fn from(val: StringList) -> Self {
+        val.0
+    }
+}
+
+impl From<String> for StringList {
+    fn from(input: String) -> Self {
+        StringList(
+            input
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect(),
+        )
+    }
+}
+
+impl TryInto<String> for StringList {
+    type Error = std::convert::Infallible;
+
+    fn try_into(self) -> Result<String, Self::Error> {
+        Ok(self.0.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_comma_separated_list() {
+        let list = StringList::from(" c8y, collectd,,az ".to_string());
+
+        assert_eq!(list.0, vec!["c8y", "collectd", "az"]);
+        assert_eq!(
+            TryInto::<String>::try_into(list).unwrap(),
+            "c8y,collectd,az"
+        );
+    }
+
+    #[test]
+    fn parse_empty_list() {
+        let list = StringList::from("".to_string());
+
+        assert!(list.0.is_empty());
+    }
+}
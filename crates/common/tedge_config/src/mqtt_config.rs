@@ -0,0 +1,75 @@
+use crate::*;
+use std::path::PathBuf;
+
+impl TEdgeConfig {
+    /// The configuration to be used by the thin-edge components to connect the MQTT broker
+    ///
+    /// The broker is reached on `mqtt.client.host` and `mqtt.client.port`,
+    /// over TLS if `mqtt.client.ca_path` is set,
+    /// authenticating the client with `mqtt.client.auth.*` if set.
+    /// The certificate and key files, as the username and password, go in pairs:
+    /// setting only one of a pair is an error.
+    ///
+    /// If `mqtt.client.queue.size` is set, the outgoing messages are queued under `data.path`
+    /// while the broker is not reachable.
+    pub fn mqtt_config(&self) -> Result<mqtt_channel::Config, ConfigSettingError> {
+        let mut mqtt_config = mqtt_channel::Config::default()
+            .with_host(self.query(MqttClientHostSetting)?)
+            .with_port(self.query(MqttClientPortSetting)?.into());
+
+        if let Some(ca_path) = self.query_optional(MqttClientCaPathSetting)? {
+            mqtt_config = mqtt_config.with_ca_path(ca_path);
+        }
+
+        match (
+            self.query_optional(MqttClientAuthCertfileSetting)?,
+            self.query_optional(MqttClientAuthKeyfileSetting)?,
+        ) {
+            (Some(cert_file), Some(key_file)) => {
+                mqtt_config = mqtt_config.with_client_auth(cert_file, key_file);
+            }
+            (Some(_), None) => {
+                return Err(ConfigSettingError::ConfigNotSet {
+                    key: MqttClientAuthKeyfileSetting::KEY,
+                })
+            }
+            (None, Some(_)) => {
+                return Err(ConfigSettingError::ConfigNotSet {
+                    key: MqttClientAuthCertfileSetting::KEY,
+                })
+            }
+            (None, None) => {}
+        }
+
+        match (
+            self.query_optional(MqttClientAuthUsernameSetting)?,
+            self.query_optional(MqttClientAuthPasswordSetting)?,
+        ) {
+            (Some(username), Some(password)) => {
+                mqtt_config = mqtt_config.with_credentials(username, password);
+            }
+            (Some(_), None) => {
+                return Err(ConfigSettingError::ConfigNotSet {
+                    key: MqttClientAuthPasswordSetting::KEY,
+                })
+            }
+            (None, Some(_)) => {
+                return Err(ConfigSettingError::ConfigNotSet {
+                    key: MqttClientAuthUsernameSetting::KEY,
+                })
+            }
+            (None, None) => {}
+        }
+
+        if let Some(queue_size) = self.query_optional(MqttClientQueueSizeSetting)? {
+            let data_path: PathBuf = self.query(DataPathSetting)?.into();
+            mqtt_config = mqtt_config.with_disk_queue(
+                data_path.join("mqtt-queue"),
+                queue_size.into(),
+                self.query(MqttClientQueuePolicySetting)?.into(),
+            );
+        }
+
+        Ok(mqtt_config)
+    }
+}
@@ -259,6 +259,74 @@ impl ConfigSettingAccessor<AzureMapperTimestamp> for TEdgeConfig {
     }
 }
 
+impl ConfigSettingAccessor<AwsUrlSetting> for TEdgeConfig {
+    fn query(&self, _setting: AwsUrlSetting) -> ConfigSettingResult<ConnectUrl> {
+        self.data
+            .aws
+            .url
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: AwsUrlSetting::KEY,
+            })
+    }
+
+    fn update(&mut self, _setting: AwsUrlSetting, value: ConnectUrl) -> ConfigSettingResult<()> {
+        self.data.aws.url = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: AwsUrlSetting) -> ConfigSettingResult<()> {
+        self.data.aws.url = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<AwsRootCertPathSetting> for TEdgeConfig {
+    fn query(&self, _setting: AwsRootCertPathSetting) -> ConfigSettingResult<FilePath> {
+        Ok(self
+            .data
+            .aws
+            .root_cert_path
+            .clone()
+            .unwrap_or_else(|| self.config_defaults.default_aws_root_cert_path.clone()))
+    }
+
+    fn update(
+        &mut self,
+        _setting: AwsRootCertPathSetting,
+        value: FilePath,
+    ) -> ConfigSettingResult<()> {
+        self.data.aws.root_cert_path = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: AwsRootCertPathSetting) -> ConfigSettingResult<()> {
+        self.data.aws.root_cert_path = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<AwsMapperTimestamp> for TEdgeConfig {
+    fn query(&self, _setting: AwsMapperTimestamp) -> ConfigSettingResult<Flag> {
+        Ok(self
+            .data
+            .aws
+            .mapper_timestamp
+            .map(Flag)
+            .unwrap_or_else(|| self.config_defaults.default_mapper_timestamp.clone()))
+    }
+
+    fn update(&mut self, _setting: AwsMapperTimestamp, value: Flag) -> ConfigSettingResult<()> {
+        self.data.aws.mapper_timestamp = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: AwsMapperTimestamp) -> ConfigSettingResult<()> {
+        self.data.aws.mapper_timestamp = None;
+        Ok(())
+    }
+}
+
 impl ConfigSettingAccessor<C8yRootCertPathSetting> for TEdgeConfig {
     fn query(&self, _setting: C8yRootCertPathSetting) -> ConfigSettingResult<FilePath> {
         Ok(self
@@ -486,6 +554,297 @@ impl ConfigSettingAccessor<MqttExternalKeyfileSetting> for TEdgeConfig {
     }
 }
 
+impl ConfigSettingAccessor<MqttClientHostSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientHostSetting) -> ConfigSettingResult<String> {
+        match &self.data.mqtt.client_host {
+            Some(host) => Ok(host.clone()),
+            None => Ok(self.query(MqttBindAddressSetting)?.to_string()),
+        }
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientHostSetting,
+        value: String,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_host = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientHostSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_host = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientPortSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientPortSetting) -> ConfigSettingResult<Port> {
+        match self.data.mqtt.client_port {
+            Some(port) => Ok(Port(port)),
+            None => self.query(MqttPortSetting),
+        }
+    }
+
+    fn update(&mut self, _setting: MqttClientPortSetting, value: Port) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_port = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientPortSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_port = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientCaPathSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientCaPathSetting) -> ConfigSettingResult<FilePath> {
+        self.data
+            .mqtt
+            .client_ca_path
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: MqttClientCaPathSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientCaPathSetting,
+        value: FilePath,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_ca_path = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientCaPathSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_ca_path = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientAuthCertfileSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientAuthCertfileSetting) -> ConfigSettingResult<FilePath> {
+        self.data
+            .mqtt
+            .client_auth_certfile
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: MqttClientAuthCertfileSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientAuthCertfileSetting,
+        value: FilePath,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_certfile = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientAuthCertfileSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_certfile = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientAuthKeyfileSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientAuthKeyfileSetting) -> ConfigSettingResult<FilePath> {
+        self.data
+            .mqtt
+            .client_auth_keyfile
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: MqttClientAuthKeyfileSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientAuthKeyfileSetting,
+        value: FilePath,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_keyfile = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientAuthKeyfileSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_keyfile = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientAuthUsernameSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientAuthUsernameSetting) -> ConfigSettingResult<String> {
+        self.data
+            .mqtt
+            .client_auth_username
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: MqttClientAuthUsernameSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientAuthUsernameSetting,
+        value: String,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_username = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientAuthUsernameSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_username = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientAuthPasswordSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientAuthPasswordSetting) -> ConfigSettingResult<String> {
+        self.data
+            .mqtt
+            .client_auth_password
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: MqttClientAuthPasswordSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientAuthPasswordSetting,
+        value: String,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_password = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientAuthPasswordSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_auth_password = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientQueueSizeSetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientQueueSizeSetting) -> ConfigSettingResult<QueueSize> {
+        self.data
+            .mqtt
+            .client_queue_size
+            .map(QueueSize)
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: MqttClientQueueSizeSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientQueueSizeSetting,
+        value: QueueSize,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_queue_size = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientQueueSizeSetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_queue_size = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MqttClientQueuePolicySetting> for TEdgeConfig {
+    fn query(&self, _setting: MqttClientQueuePolicySetting) -> ConfigSettingResult<QueuePolicy> {
+        Ok(self
+            .data
+            .mqtt
+            .client_queue_policy
+            .unwrap_or(QueuePolicy::DropOldest))
+    }
+
+    fn update(
+        &mut self,
+        _setting: MqttClientQueuePolicySetting,
+        value: QueuePolicy,
+    ) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_queue_policy = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MqttClientQueuePolicySetting) -> ConfigSettingResult<()> {
+        self.data.mqtt.client_queue_policy = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MapperErrorsForwardSetting> for TEdgeConfig {
+    fn query(&self, _setting: MapperErrorsForwardSetting) -> ConfigSettingResult<ErrorForwarding> {
+        Ok(self
+            .data
+            .mapper
+            .errors_forward
+            .unwrap_or(ErrorForwarding::None))
+    }
+
+    fn update(
+        &mut self,
+        _setting: MapperErrorsForwardSetting,
+        value: ErrorForwarding,
+    ) -> ConfigSettingResult<()> {
+        self.data.mapper.errors_forward = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MapperErrorsForwardSetting) -> ConfigSettingResult<()> {
+        self.data.mapper.errors_forward = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MapperMultiNamesSetting> for TEdgeConfig {
+    fn query(&self, _setting: MapperMultiNamesSetting) -> ConfigSettingResult<StringList> {
+        Ok(self.data.mapper.multi_names.clone().unwrap_or_default())
+    }
+
+    fn update(
+        &mut self,
+        _setting: MapperMultiNamesSetting,
+        value: StringList,
+    ) -> ConfigSettingResult<()> {
+        self.data.mapper.multi_names = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MapperMultiNamesSetting) -> ConfigSettingResult<()> {
+        self.data.mapper.multi_names = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MapperSyncWindowSecondsSetting> for TEdgeConfig {
+    fn query(&self, _setting: MapperSyncWindowSecondsSetting) -> ConfigSettingResult<Seconds> {
+        Ok(Seconds(
+            self.data
+                .mapper
+                .sync_window_seconds
+                .unwrap_or(DEFAULT_MAPPER_SYNC_WINDOW_SECONDS),
+        ))
+    }
+
+    fn update(
+        &mut self,
+        _setting: MapperSyncWindowSecondsSetting,
+        value: Seconds,
+    ) -> ConfigSettingResult<()> {
+        self.data.mapper.sync_window_seconds = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MapperSyncWindowSecondsSetting) -> ConfigSettingResult<()> {
+        self.data.mapper.sync_window_seconds = None;
+        Ok(())
+    }
+}
+
 impl ConfigSettingAccessor<SoftwarePluginDefaultSetting> for TEdgeConfig {
     fn query(&self, _setting: SoftwarePluginDefaultSetting) -> ConfigSettingResult<String> {
         self.data
@@ -596,3 +955,447 @@ impl ConfigSettingAccessor<RunPathSetting> for TEdgeConfig {
         Ok(())
     }
 }
+
+impl ConfigSettingAccessor<DataPathSetting> for TEdgeConfig {
+    fn query(&self, _setting: DataPathSetting) -> ConfigSettingResult<FilePath> {
+        Ok(self
+            .data
+            .data
+            .dir_path
+            .clone()
+            .unwrap_or_else(|| self.config_defaults.default_data_path.clone()))
+    }
+
+    fn update(&mut self, _setting: DataPathSetting, value: FilePath) -> ConfigSettingResult<()> {
+        self.data.data.dir_path = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: DataPathSetting) -> ConfigSettingResult<()> {
+        self.data.data.dir_path = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<DownloadProxyUrlSetting> for TEdgeConfig {
+    fn query(&self, _setting: DownloadProxyUrlSetting) -> ConfigSettingResult<String> {
+        self.data
+            .download
+            .proxy_url
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: DownloadProxyUrlSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: DownloadProxyUrlSetting,
+        value: String,
+    ) -> ConfigSettingResult<()> {
+        self.data.download.proxy_url = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: DownloadProxyUrlSetting) -> ConfigSettingResult<()> {
+        self.data.download.proxy_url = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<DownloadRootCertPathSetting> for TEdgeConfig {
+    fn query(&self, _setting: DownloadRootCertPathSetting) -> ConfigSettingResult<FilePath> {
+        self.data
+            .download
+            .root_cert_path
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: DownloadRootCertPathSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: DownloadRootCertPathSetting,
+        value: FilePath,
+    ) -> ConfigSettingResult<()> {
+        self.data.download.root_cert_path = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: DownloadRootCertPathSetting) -> ConfigSettingResult<()> {
+        self.data.download.root_cert_path = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<DownloadClientCertPathSetting> for TEdgeConfig {
+    fn query(&self, _setting: DownloadClientCertPathSetting) -> ConfigSettingResult<FilePath> {
+        self.data
+            .download
+            .client_cert_path
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: DownloadClientCertPathSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: DownloadClientCertPathSetting,
+        value: FilePath,
+    ) -> ConfigSettingResult<()> {
+        self.data.download.client_cert_path = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: DownloadClientCertPathSetting) -> ConfigSettingResult<()> {
+        self.data.download.client_cert_path = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<DownloadClientKeyPathSetting> for TEdgeConfig {
+    fn query(&self, _setting: DownloadClientKeyPathSetting) -> ConfigSettingResult<FilePath> {
+        self.data
+            .download
+            .client_key_path
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: DownloadClientKeyPathSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: DownloadClientKeyPathSetting,
+        value: FilePath,
+    ) -> ConfigSettingResult<()> {
+        self.data.download.client_key_path = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: DownloadClientKeyPathSetting) -> ConfigSettingResult<()> {
+        self.data.download.client_key_path = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<DownloadMirrorsSetting> for TEdgeConfig {
+    fn query(&self, _setting: DownloadMirrorsSetting) -> ConfigSettingResult<DownloadMirrors> {
+        Ok(self.data.download.mirrors.clone().unwrap_or_default())
+    }
+
+    fn update(
+        &mut self,
+        _setting: DownloadMirrorsSetting,
+        value: DownloadMirrors,
+    ) -> ConfigSettingResult<()> {
+        self.data.download.mirrors = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: DownloadMirrorsSetting) -> ConfigSettingResult<()> {
+        self.data.download.mirrors = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MetricsPrometheusPortSetting> for TEdgeConfig {
+    fn query(&self, _setting: MetricsPrometheusPortSetting) -> ConfigSettingResult<Port> {
+        self.data
+            .metrics
+            .prometheus_port
+            .map(Port)
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: MetricsPrometheusPortSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: MetricsPrometheusPortSetting,
+        value: Port,
+    ) -> ConfigSettingResult<()> {
+        self.data.metrics.prometheus_port = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MetricsPrometheusPortSetting) -> ConfigSettingResult<()> {
+        self.data.metrics.prometheus_port = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<MetricsPrometheusBindAddressSetting> for TEdgeConfig {
+    fn query(
+        &self,
+        _setting: MetricsPrometheusBindAddressSetting,
+    ) -> ConfigSettingResult<IpAddress> {
+        Ok(self
+            .data
+            .metrics
+            .prometheus_bind_address
+            .clone()
+            .unwrap_or_default())
+    }
+
+    fn update(
+        &mut self,
+        _setting: MetricsPrometheusBindAddressSetting,
+        value: IpAddress,
+    ) -> ConfigSettingResult<()> {
+        self.data.metrics.prometheus_bind_address = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: MetricsPrometheusBindAddressSetting) -> ConfigSettingResult<()> {
+        self.data.metrics.prometheus_bind_address = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<StoreRetentionDaysSetting> for TEdgeConfig {
+    fn query(&self, _setting: StoreRetentionDaysSetting) -> ConfigSettingResult<Days> {
+        Ok(Days(
+            self.data
+                .store
+                .retention_days
+                .unwrap_or(DEFAULT_STORE_RETENTION_DAYS),
+        ))
+    }
+
+    fn update(
+        &mut self,
+        _setting: StoreRetentionDaysSetting,
+        value: Days,
+    ) -> ConfigSettingResult<()> {
+        self.data.store.retention_days = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: StoreRetentionDaysSetting) -> ConfigSettingResult<()> {
+        self.data.store.retention_days = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<StoreHttpPortSetting> for TEdgeConfig {
+    fn query(&self, _setting: StoreHttpPortSetting) -> ConfigSettingResult<Port> {
+        Ok(Port(
+            self.data.store.http_port.unwrap_or(DEFAULT_STORE_HTTP_PORT),
+        ))
+    }
+
+    fn update(&mut self, _setting: StoreHttpPortSetting, value: Port) -> ConfigSettingResult<()> {
+        self.data.store.http_port = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: StoreHttpPortSetting) -> ConfigSettingResult<()> {
+        self.data.store.http_port = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<StoreHttpBindAddressSetting> for TEdgeConfig {
+    fn query(&self, _setting: StoreHttpBindAddressSetting) -> ConfigSettingResult<IpAddress> {
+        Ok(self
+            .data
+            .store
+            .http_bind_address
+            .clone()
+            .unwrap_or_default())
+    }
+
+    fn update(
+        &mut self,
+        _setting: StoreHttpBindAddressSetting,
+        value: IpAddress,
+    ) -> ConfigSettingResult<()> {
+        self.data.store.http_bind_address = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: StoreHttpBindAddressSetting) -> ConfigSettingResult<()> {
+        self.data.store.http_bind_address = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<PrometheusHttpPortSetting> for TEdgeConfig {
+    fn query(&self, _setting: PrometheusHttpPortSetting) -> ConfigSettingResult<Port> {
+        Ok(Port(
+            self.data
+                .prometheus
+                .http_port
+                .unwrap_or(DEFAULT_PROMETHEUS_HTTP_PORT),
+        ))
+    }
+
+    fn update(
+        &mut self,
+        _setting: PrometheusHttpPortSetting,
+        value: Port,
+    ) -> ConfigSettingResult<()> {
+        self.data.prometheus.http_port = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: PrometheusHttpPortSetting) -> ConfigSettingResult<()> {
+        self.data.prometheus.http_port = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<PrometheusHttpBindAddressSetting> for TEdgeConfig {
+    fn query(&self, _setting: PrometheusHttpBindAddressSetting) -> ConfigSettingResult<IpAddress> {
+        Ok(self
+            .data
+            .prometheus
+            .http_bind_address
+            .clone()
+            .unwrap_or_default())
+    }
+
+    fn update(
+        &mut self,
+        _setting: PrometheusHttpBindAddressSetting,
+        value: IpAddress,
+    ) -> ConfigSettingResult<()> {
+        self.data.prometheus.http_bind_address = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: PrometheusHttpBindAddressSetting) -> ConfigSettingResult<()> {
+        self.data.prometheus.http_bind_address = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<PrometheusStalenessSecondsSetting> for TEdgeConfig {
+    fn query(&self, _setting: PrometheusStalenessSecondsSetting) -> ConfigSettingResult<Seconds> {
+        Ok(Seconds(
+            self.data
+                .prometheus
+                .staleness_seconds
+                .unwrap_or(DEFAULT_PROMETHEUS_STALENESS_SECONDS),
+        ))
+    }
+
+    fn update(
+        &mut self,
+        _setting: PrometheusStalenessSecondsSetting,
+        value: Seconds,
+    ) -> ConfigSettingResult<()> {
+        self.data.prometheus.staleness_seconds = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: PrometheusStalenessSecondsSetting) -> ConfigSettingResult<()> {
+        self.data.prometheus.staleness_seconds = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<InfluxDbHttpUrlSetting> for TEdgeConfig {
+    fn query(&self, _setting: InfluxDbHttpUrlSetting) -> ConfigSettingResult<String> {
+        self.data
+            .influxdb
+            .http_url
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: InfluxDbHttpUrlSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: InfluxDbHttpUrlSetting,
+        value: String,
+    ) -> ConfigSettingResult<()> {
+        self.data.influxdb.http_url = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: InfluxDbHttpUrlSetting) -> ConfigSettingResult<()> {
+        self.data.influxdb.http_url = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<InfluxDbHttpTokenSetting> for TEdgeConfig {
+    fn query(&self, _setting: InfluxDbHttpTokenSetting) -> ConfigSettingResult<String> {
+        self.data
+            .influxdb
+            .http_token
+            .clone()
+            .ok_or(ConfigSettingError::ConfigNotSet {
+                key: InfluxDbHttpTokenSetting::KEY,
+            })
+    }
+
+    fn update(
+        &mut self,
+        _setting: InfluxDbHttpTokenSetting,
+        value: String,
+    ) -> ConfigSettingResult<()> {
+        self.data.influxdb.http_token = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: InfluxDbHttpTokenSetting) -> ConfigSettingResult<()> {
+        self.data.influxdb.http_token = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<InfluxDbMqttTopicSetting> for TEdgeConfig {
+    fn query(&self, _setting: InfluxDbMqttTopicSetting) -> ConfigSettingResult<String> {
+        Ok(self
+            .data
+            .influxdb
+            .mqtt_topic
+            .clone()
+            .unwrap_or_else(|| DEFAULT_INFLUXDB_MQTT_TOPIC.into()))
+    }
+
+    fn update(
+        &mut self,
+        _setting: InfluxDbMqttTopicSetting,
+        value: String,
+    ) -> ConfigSettingResult<()> {
+        self.data.influxdb.mqtt_topic = Some(value);
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: InfluxDbMqttTopicSetting) -> ConfigSettingResult<()> {
+        self.data.influxdb.mqtt_topic = None;
+        Ok(())
+    }
+}
+
+impl ConfigSettingAccessor<InfluxDbBatchPeriodSecondsSetting> for TEdgeConfig {
+    fn query(&self, _setting: InfluxDbBatchPeriodSecondsSetting) -> ConfigSettingResult<Seconds> {
+        Ok(Seconds(
+            self.data
+                .influxdb
+                .batch_period_seconds
+                .unwrap_or(DEFAULT_INFLUXDB_BATCH_PERIOD_SECONDS),
+        ))
+    }
+
+    fn update(
+        &mut self,
+        _setting: InfluxDbBatchPeriodSecondsSetting,
+        value: Seconds,
+    ) -> ConfigSettingResult<()> {
+        self.data.influxdb.batch_period_seconds = Some(value.into());
+        Ok(())
+    }
+
+    fn unset(&mut self, _setting: InfluxDbBatchPeriodSecondsSetting) -> ConfigSettingResult<()> {
+        self.data.influxdb.batch_period_seconds = None;
+        Ok(())
+    }
+}
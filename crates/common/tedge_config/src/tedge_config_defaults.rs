@@ -9,6 +9,14 @@ const DEFAULT_PORT: u16 = 1883;
 const DEFAULT_TMP_PATH: &str = "/tmp";
 pub const DEFAULT_LOG_PATH: &str = "/var/log";
 pub const DEFAULT_RUN_PATH: &str = "/run";
+pub const DEFAULT_DATA_PATH: &str = "/var/tedge";
+pub const DEFAULT_STORE_RETENTION_DAYS: u32 = 7;
+pub const DEFAULT_STORE_HTTP_PORT: u16 = 8090;
+pub const DEFAULT_PROMETHEUS_HTTP_PORT: u16 = 9464;
+pub const DEFAULT_PROMETHEUS_STALENESS_SECONDS: u64 = 300;
+pub const DEFAULT_MAPPER_SYNC_WINDOW_SECONDS: u64 = 3;
+pub const DEFAULT_INFLUXDB_MQTT_TOPIC: &str = "influxdb/measurements";
+pub const DEFAULT_INFLUXDB_BATCH_PERIOD_SECONDS: u64 = 1;
 const DEFAULT_DEVICE_TYPE: &str = "thin-edge.io";
 
 /// Stores default values for use by `TEdgeConfig` in case no configuration setting
@@ -34,6 +42,9 @@ pub struct TEdgeConfigDefaults {
     /// Default path for azure root certificates
     pub default_azure_root_cert_path: FilePath,
 
+    /// Default path for aws root certificates
+    pub default_aws_root_cert_path: FilePath,
+
     /// Default path for c8y root certificates
     pub default_c8y_root_cert_path: FilePath,
 
@@ -55,6 +66,9 @@ pub struct TEdgeConfigDefaults {
     /// Default run path
     pub default_run_path: FilePath,
 
+    /// Default data path
+    pub default_data_path: FilePath,
+
     /// Default device type
     pub default_device_type: String,
 
@@ -68,6 +82,7 @@ impl From<&TEdgeConfigLocation> for TEdgeConfigDefaults {
         let tmp_path = Path::new(DEFAULT_TMP_PATH);
         let logs_path = Path::new(DEFAULT_LOG_PATH);
         let run_path = Path::new(DEFAULT_RUN_PATH);
+        let data_path = Path::new(DEFAULT_DATA_PATH);
         Self {
             default_device_cert_path: config_location
                 .tedge_config_root_path()
@@ -80,12 +95,14 @@ impl From<&TEdgeConfigLocation> for TEdgeConfigDefaults {
                 .join("tedge-private-key.pem")
                 .into(),
             default_azure_root_cert_path: system_cert_path.clone().into(),
+            default_aws_root_cert_path: system_cert_path.clone().into(),
             default_c8y_root_cert_path: system_cert_path.into(),
             default_mapper_timestamp: Flag(true),
             default_mqtt_port: Port(DEFAULT_PORT),
             default_tmp_path: tmp_path.into(),
             default_logs_path: logs_path.into(),
             default_run_path: run_path.into(),
+            default_data_path: data_path.into(),
             default_device_type: DEFAULT_DEVICE_TYPE.into(),
             default_mqtt_bind_address: IpAddress::default(),
             default_c8y_smartrest_templates: TemplatesSet::default(),
@@ -108,12 +125,14 @@ fn test_from_tedge_config_location() {
                 "/opt/etc/_tedge/device-certs/tedge-private-key.pem"
             ),
             default_azure_root_cert_path: FilePath::from("/etc/ssl/certs"),
+            default_aws_root_cert_path: FilePath::from("/etc/ssl/certs"),
             default_c8y_root_cert_path: FilePath::from("/etc/ssl/certs"),
             default_mapper_timestamp: Flag(true),
             default_mqtt_port: Port(DEFAULT_PORT),
             default_tmp_path: FilePath::from("/tmp"),
             default_logs_path: FilePath::from("/var/log"),
             default_run_path: FilePath::from("/run"),
+            default_data_path: FilePath::from("/var/tedge"),
             default_device_type: DEFAULT_DEVICE_TYPE.into(),
             default_mqtt_bind_address: IpAddress::default(),
             default_c8y_smartrest_templates: TemplatesSet::default(),
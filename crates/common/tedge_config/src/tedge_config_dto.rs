@@ -17,12 +17,18 @@ pub(crate) struct TEdgeConfigDto {
     #[serde(default, alias = "azure")] // for version 0.1.0 compatibility
     pub(crate) az: AzureConfigDto,
 
+    #[serde(default)]
+    pub(crate) aws: AwsConfigDto,
+
     #[serde(default)]
     pub(crate) mqtt: MqttConfigDto,
 
     #[serde(default)]
     pub(crate) software: SoftwareConfigDto,
 
+    #[serde(default)]
+    pub(crate) mapper: MapperConfigDto,
+
     #[serde(default)]
     pub(crate) tmp: PathConfigDto,
 
@@ -31,6 +37,24 @@ pub(crate) struct TEdgeConfigDto {
 
     #[serde(default)]
     pub(crate) run: PathConfigDto,
+
+    #[serde(default)]
+    pub(crate) data: PathConfigDto,
+
+    #[serde(default)]
+    pub(crate) download: DownloadConfigDto,
+
+    #[serde(default)]
+    pub(crate) metrics: MetricsConfigDto,
+
+    #[serde(default)]
+    pub(crate) store: StoreConfigDto,
+
+    #[serde(default)]
+    pub(crate) prometheus: PrometheusConfigDto,
+
+    #[serde(default)]
+    pub(crate) influxdb: InfluxDbConfigDto,
 }
 
 /// Represents the device specific configurations defined in the [device] section
@@ -86,6 +110,15 @@ pub(crate) struct AzureConfigDto {
     pub(crate) mapper_timestamp: Option<bool>,
 }
 
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AwsConfigDto {
+    pub(crate) connect: Option<String>,
+    pub(crate) url: Option<ConnectUrl>,
+    pub(crate) root_cert_path: Option<FilePath>,
+    pub(crate) mapper_timestamp: Option<bool>,
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct MqttConfigDto {
@@ -97,6 +130,15 @@ pub(crate) struct MqttConfigDto {
     pub(crate) external_capath: Option<FilePath>,
     pub(crate) external_certfile: Option<FilePath>,
     pub(crate) external_keyfile: Option<FilePath>,
+    pub(crate) client_host: Option<String>,
+    pub(crate) client_port: Option<u16>,
+    pub(crate) client_ca_path: Option<FilePath>,
+    pub(crate) client_auth_certfile: Option<FilePath>,
+    pub(crate) client_auth_keyfile: Option<FilePath>,
+    pub(crate) client_auth_username: Option<String>,
+    pub(crate) client_auth_password: Option<String>,
+    pub(crate) client_queue_size: Option<usize>,
+    pub(crate) client_queue_policy: Option<QueuePolicy>,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize)]
@@ -111,3 +153,65 @@ pub(crate) struct PathConfigDto {
     #[serde(rename = "path")]
     pub(crate) dir_path: Option<FilePath>,
 }
+
+/// Represents the proxy, TLS and mirror settings used for downloads,
+/// as defined in the [download] section of the thin edge configuration TOML file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DownloadConfigDto {
+    pub(crate) proxy_url: Option<String>,
+    pub(crate) root_cert_path: Option<FilePath>,
+    pub(crate) client_cert_path: Option<FilePath>,
+    pub(crate) client_key_path: Option<FilePath>,
+    pub(crate) mirrors: Option<DownloadMirrors>,
+}
+
+/// Represents the settings shared by all the mappers,
+/// as defined in the [mapper] section of the thin edge configuration TOML file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MapperConfigDto {
+    pub(crate) errors_forward: Option<ErrorForwarding>,
+    pub(crate) multi_names: Option<StringList>,
+    pub(crate) sync_window_seconds: Option<u64>,
+}
+
+/// Represents the settings of the metrics exposed by the thin-edge daemons,
+/// as defined in the [metrics] section of the thin edge configuration TOML file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MetricsConfigDto {
+    pub(crate) prometheus_port: Option<u16>,
+    pub(crate) prometheus_bind_address: Option<IpAddress>,
+}
+
+/// Represents the retention policy and the HTTP endpoint of the local measurement store,
+/// as defined in the [store] section of the thin edge configuration TOML file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct StoreConfigDto {
+    pub(crate) retention_days: Option<u32>,
+    pub(crate) http_port: Option<u16>,
+    pub(crate) http_bind_address: Option<IpAddress>,
+}
+
+/// Represents the HTTP endpoint of the Prometheus mapper,
+/// as defined in the [prometheus] section of the thin edge configuration TOML file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PrometheusConfigDto {
+    pub(crate) http_port: Option<u16>,
+    pub(crate) http_bind_address: Option<IpAddress>,
+    pub(crate) staleness_seconds: Option<u64>,
+}
+
+/// Represents the output of the InfluxDB mapper,
+/// as defined in the [influxdb] section of the thin edge configuration TOML file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct InfluxDbConfigDto {
+    pub(crate) http_url: Option<String>,
+    pub(crate) http_token: Option<String>,
+    pub(crate) mqtt_topic: Option<String>,
+    pub(crate) batch_period_seconds: Option<u64>,
+}
@@ -136,6 +136,7 @@ bind_address = "0.0.0.0"
     let config_defaults = TEdgeConfigDefaults {
         default_c8y_root_cert_path: FilePath::from("default_c8y_root_cert_path"),
         default_azure_root_cert_path: FilePath::from("default_azure_root_cert_path"),
+        default_aws_root_cert_path: FilePath::from("default_aws_root_cert_path"),
         ..dummy_tedge_config_defaults()
     };
 
@@ -289,6 +290,7 @@ fn test_parse_config_with_only_device_configuration() -> Result<(), TEdgeConfigE
         default_device_key_path: FilePath::from("/etc/ssl/certs/tedge-private-key.pem"),
         default_c8y_root_cert_path: FilePath::from("/etc/ssl/certs"),
         default_azure_root_cert_path: FilePath::from("/etc/ssl/certs"),
+        default_aws_root_cert_path: FilePath::from("/etc/ssl/certs"),
         ..dummy_tedge_config_defaults()
     };
 
@@ -480,6 +482,604 @@ bind_address = "1.2.3.4"
     Ok(())
 }
 
+#[test]
+fn test_mqtt_client_config_defaults_to_the_local_broker() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mqtt]
+port = 2222
+bind_address = "1.2.3.4"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(MqttClientHostSetting)?, "1.2.3.4");
+    assert_eq!(config.query(MqttClientPortSetting)?, Port(2222));
+
+    let mqtt_config = config.mqtt_config()?;
+    assert_eq!(mqtt_config.host, "1.2.3.4");
+    assert_eq!(mqtt_config.port, 2222);
+    assert_eq!(mqtt_config.ca_path, None);
+    assert_eq!(mqtt_config.client_auth, None);
+    assert_eq!(mqtt_config.credentials, None);
+    assert_eq!(mqtt_config.disk_queue, None);
+    Ok(())
+}
+
+#[test]
+fn test_mqtt_client_config_with_tls_and_credentials() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mqtt]
+client_host = "broker.local"
+client_port = 8883
+client_ca_path = "/etc/ssl/certs"
+client_auth_certfile = "/etc/tedge/device-certs/tedge-certificate.pem"
+client_auth_keyfile = "/etc/tedge/device-certs/tedge-private-key.pem"
+client_auth_username = "tedge"
+client_auth_password = "secret"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    let mqtt_config = config.mqtt_config()?;
+    assert_eq!(mqtt_config.host, "broker.local");
+    assert_eq!(mqtt_config.port, 8883);
+    assert_eq!(
+        mqtt_config.ca_path,
+        Some(std::path::PathBuf::from("/etc/ssl/certs"))
+    );
+    assert_eq!(
+        mqtt_config.client_auth,
+        Some(mqtt_channel::ClientAuth {
+            cert_file: "/etc/tedge/device-certs/tedge-certificate.pem".into(),
+            key_file: "/etc/tedge/device-certs/tedge-private-key.pem".into(),
+        })
+    );
+    assert_eq!(
+        mqtt_config.credentials,
+        Some(mqtt_channel::Credentials {
+            username: "tedge".into(),
+            password: "secret".into(),
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mqtt_client_config_with_a_disk_queue() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mqtt]
+client_queue_size = 1000
+client_queue_policy = "drop_newest"
+
+[data]
+path = "/data/tedge"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(MqttClientQueueSizeSetting)?, QueueSize(1000));
+    assert_eq!(
+        config.query(MqttClientQueuePolicySetting)?,
+        QueuePolicy::DropNewest
+    );
+
+    let mqtt_config = config.mqtt_config()?;
+    assert_eq!(
+        mqtt_config.disk_queue,
+        Some(mqtt_channel::DiskQueueConfig {
+            dir: "/data/tedge/mqtt-queue".into(),
+            max_messages: 1000,
+            overflow_policy: mqtt_channel::OverflowPolicy::DropNewest,
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mqtt_client_certfile_without_keyfile_is_an_error() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mqtt]
+client_ca_path = "/etc/ssl/certs"
+client_auth_certfile = "/etc/tedge/device-certs/tedge-certificate.pem"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_matches!(
+        config.mqtt_config(),
+        Err(ConfigSettingError::ConfigNotSet {
+            key: "mqtt.client.auth.keyfile"
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mqtt_client_password_without_username_is_an_error() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mqtt]
+client_auth_password = "secret"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_matches!(
+        config.mqtt_config(),
+        Err(ConfigSettingError::ConfigNotSet {
+            key: "mqtt.client.auth.username"
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_config_with_only_download_configuration() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[download]
+proxy_url = "http://proxy.local:3128"
+root_cert_path = "/etc/ssl/certs/download-roots"
+client_cert_path = "/etc/tedge/device-certs/download-cert.pem"
+client_key_path = "/etc/tedge/device-certs/download-key.pem"
+mirrors = ["https://example.com/binaries/=file:///media/usb/"]
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(
+        config.query(DownloadProxyUrlSetting)?,
+        "http://proxy.local:3128"
+    );
+    assert_eq!(
+        config.query(DownloadRootCertPathSetting)?,
+        FilePath::from("/etc/ssl/certs/download-roots")
+    );
+    assert_eq!(
+        config.query(DownloadClientCertPathSetting)?,
+        FilePath::from("/etc/tedge/device-certs/download-cert.pem")
+    );
+    assert_eq!(
+        config.query(DownloadClientKeyPathSetting)?,
+        FilePath::from("/etc/tedge/device-certs/download-key.pem")
+    );
+    assert_eq!(
+        config.query_string(DownloadMirrorsSetting)?,
+        "https://example.com/binaries/=file:///media/usb/"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_download_settings_are_not_set_by_default() -> Result<(), TEdgeConfigError> {
+    let (_tempdir, config_location) = create_temp_tedge_config("")?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert!(config.query_optional(DownloadProxyUrlSetting)?.is_none());
+    assert!(config
+        .query_optional(DownloadRootCertPathSetting)?
+        .is_none());
+    assert!(config
+        .query_optional(DownloadClientCertPathSetting)?
+        .is_none());
+    assert!(config
+        .query_optional(DownloadClientKeyPathSetting)?
+        .is_none());
+    assert_eq!(
+        config.query(DownloadMirrorsSetting)?,
+        DownloadMirrors::default()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_download_mirrors_are_rejected() -> Result<(), TEdgeConfigError> {
+    let (_tempdir, config_location) = create_temp_tedge_config("")?;
+    let mut config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_matches!(
+        config.update_string(DownloadMirrorsSetting, "https://example.com/".into()),
+        Err(ConfigSettingError::ConversionFromStringFailed)
+    );
+
+    config.update_string(
+        DownloadMirrorsSetting,
+        "https://a.com/=file:///media/usb/,https://b.com/=http://cache.lan/".into(),
+    )?;
+    assert_eq!(config.query(DownloadMirrorsSetting)?.0.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_download_settings_from_config() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[download]
+proxy_url = "http://proxy.local:3128"
+client_cert_path = "/etc/tedge/device-certs/download-cert.pem"
+client_key_path = "/etc/tedge/device-certs/download-key.pem"
+mirrors = ["https://example.com/binaries/=file:///media/usb/"]
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    let download_settings = config.download_settings()?;
+    assert_eq!(
+        download_settings.proxy_url.as_deref(),
+        Some("http://proxy.local:3128")
+    );
+    assert!(download_settings.trusted_root_certs.is_empty());
+    assert!(download_settings.client_identity.is_some());
+    assert_eq!(download_settings.mirrors.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_download_client_cert_without_key_is_an_error() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[download]
+client_cert_path = "/etc/tedge/device-certs/download-cert.pem"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_matches!(
+        config.download_settings(),
+        Err(ConfigSettingError::ConfigNotSet {
+            key: "download.client.key.path"
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_config_with_only_metrics_configuration() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[metrics]
+prometheus_port = 9100
+prometheus_bind_address = "0.0.0.0"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(MetricsPrometheusPortSetting)?, Port(9100));
+    assert_eq!(
+        config.query(MetricsPrometheusBindAddressSetting)?,
+        IpAddress(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_prometheus_metrics_are_not_served_by_default() -> Result<(), TEdgeConfigError> {
+    let (_tempdir, config_location) = create_temp_tedge_config("")?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert!(config
+        .query_optional(MetricsPrometheusPortSetting)?
+        .is_none());
+    assert_eq!(
+        config.query(MetricsPrometheusBindAddressSetting)?,
+        IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_config_with_only_aws_configuration() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[aws]
+url = "your-endpoint.amazonaws.com"
+root_cert_path = "/path/to/aws/root/cert"
+mapper_timestamp = false
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(
+        config.query(AwsUrlSetting)?.as_str(),
+        "your-endpoint.amazonaws.com"
+    );
+    assert_eq!(
+        config.query(AwsRootCertPathSetting)?,
+        FilePath::from("/path/to/aws/root/cert")
+    );
+    assert_eq!(config.query(AwsMapperTimestamp)?, Flag(false));
+    Ok(())
+}
+
+#[test]
+fn test_crud_aws_config_values() -> Result<(), TEdgeConfigError> {
+    let (_tempdir, config_location) = create_temp_tedge_config("")?;
+    let mut config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert!(config.query_optional(AwsUrlSetting)?.is_none());
+    assert_eq!(
+        config.query(AwsRootCertPathSetting)?,
+        FilePath::from("/dev/null")
+    );
+    assert_eq!(config.query(AwsMapperTimestamp)?, Flag(true));
+
+    let aws_url = ConnectUrl::try_from("your-endpoint.amazonaws.com")?;
+    config.update(AwsUrlSetting, aws_url.clone())?;
+    config.update(
+        AwsRootCertPathSetting,
+        FilePath::from("/path/to/aws/root/cert"),
+    )?;
+    config.update(AwsMapperTimestamp, Flag(false))?;
+
+    assert_eq!(config.query(AwsUrlSetting)?, aws_url);
+    assert_eq!(
+        config.query(AwsRootCertPathSetting)?,
+        FilePath::from("/path/to/aws/root/cert")
+    );
+    assert_eq!(config.query(AwsMapperTimestamp)?, Flag(false));
+
+    config.unset(AwsUrlSetting)?;
+    config.unset(AwsRootCertPathSetting)?;
+    config.unset(AwsMapperTimestamp)?;
+
+    assert!(config.query_optional(AwsUrlSetting)?.is_none());
+    assert_eq!(
+        config.query(AwsRootCertPathSetting)?,
+        FilePath::from("/dev/null")
+    );
+    assert_eq!(config.query(AwsMapperTimestamp)?, Flag(true));
+    Ok(())
+}
+
+#[test]
+fn test_parse_config_with_only_store_configuration() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[store]
+retention_days = 30
+http_port = 8091
+http_bind_address = "0.0.0.0"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(StoreRetentionDaysSetting)?, Days(30));
+    assert_eq!(config.query(StoreHttpPortSetting)?, Port(8091));
+    assert_eq!(
+        config.query(StoreHttpBindAddressSetting)?,
+        IpAddress(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_store_default_settings() -> Result<(), TEdgeConfigError> {
+    let (_tempdir, config_location) = create_temp_tedge_config("")?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(StoreRetentionDaysSetting)?, Days(7));
+    assert_eq!(config.query(StoreHttpPortSetting)?, Port(8090));
+    assert_eq!(
+        config.query(StoreHttpBindAddressSetting)?,
+        IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_config_with_only_prometheus_configuration() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[prometheus]
+http_port = 9100
+http_bind_address = "0.0.0.0"
+staleness_seconds = 60
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(PrometheusHttpPortSetting)?, Port(9100));
+    assert_eq!(
+        config.query(PrometheusHttpBindAddressSetting)?,
+        IpAddress(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    );
+    assert_eq!(
+        config.query(PrometheusStalenessSecondsSetting)?,
+        Seconds(60)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_prometheus_default_settings() -> Result<(), TEdgeConfigError> {
+    let (_tempdir, config_location) = create_temp_tedge_config("")?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(PrometheusHttpPortSetting)?, Port(9464));
+    assert_eq!(
+        config.query(PrometheusHttpBindAddressSetting)?,
+        IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    );
+    assert_eq!(
+        config.query(PrometheusStalenessSecondsSetting)?,
+        Seconds(300)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_config_with_only_influxdb_configuration() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[influxdb]
+http_url = "http://localhost:8086/api/v2/write?org=acme&bucket=tedge"
+http_token = "secret"
+mqtt_topic = "telegraf/measurements"
+batch_period_seconds = 5
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(
+        config.query(InfluxDbHttpUrlSetting)?,
+        "http://localhost:8086/api/v2/write?org=acme&bucket=tedge"
+    );
+    assert_eq!(config.query(InfluxDbHttpTokenSetting)?, "secret");
+    assert_eq!(
+        config.query(InfluxDbMqttTopicSetting)?,
+        "telegraf/measurements"
+    );
+    assert_eq!(config.query(InfluxDbBatchPeriodSecondsSetting)?, Seconds(5));
+    Ok(())
+}
+
+#[test]
+fn test_influxdb_default_settings() -> Result<(), TEdgeConfigError> {
+    let (_tempdir, config_location) = create_temp_tedge_config("")?;
+    let config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_matches!(
+        config.query(InfluxDbHttpUrlSetting),
+        Err(ConfigSettingError::ConfigNotSet { .. })
+    );
+    assert_matches!(
+        config.query(InfluxDbHttpTokenSetting),
+        Err(ConfigSettingError::ConfigNotSet { .. })
+    );
+    assert_eq!(
+        config.query(InfluxDbMqttTopicSetting)?,
+        "influxdb/measurements"
+    );
+    assert_eq!(config.query(InfluxDbBatchPeriodSecondsSetting)?, Seconds(1));
+    Ok(())
+}
+
+#[test]
+fn test_mapper_errors_forward_setting() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mapper]
+errors_forward = "alarm"
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let config_repo =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults());
+    let mut config = config_repo.load()?;
+
+    assert_eq!(
+        config.query(MapperErrorsForwardSetting)?,
+        ErrorForwarding::Alarm
+    );
+
+    config.unset(MapperErrorsForwardSetting)?;
+    assert_eq!(
+        config.query(MapperErrorsForwardSetting)?,
+        ErrorForwarding::None
+    );
+
+    config.update(MapperErrorsForwardSetting, ErrorForwarding::Event)?;
+    config_repo.store(&config)?;
+    assert_eq!(
+        config_repo.load()?.query(MapperErrorsForwardSetting)?,
+        ErrorForwarding::Event
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mapper_multi_names_setting() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mapper]
+multi_names = ["c8y", "collectd"]
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let mut config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(
+        config.query(MapperMultiNamesSetting)?,
+        StringList(vec!["c8y".into(), "collectd".into()])
+    );
+
+    config.update_string(MapperMultiNamesSetting, "az, influxdb".into())?;
+    assert_eq!(
+        config.query(MapperMultiNamesSetting)?,
+        StringList(vec!["az".into(), "influxdb".into()])
+    );
+
+    config.unset(MapperMultiNamesSetting)?;
+    assert_eq!(
+        config.query(MapperMultiNamesSetting)?,
+        StringList::default()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mapper_sync_window_seconds_setting() -> Result<(), TEdgeConfigError> {
+    let toml_conf = r#"
+[mapper]
+sync_window_seconds = 30
+"#;
+
+    let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
+    let mut config =
+        TEdgeConfigRepository::new_with_defaults(config_location, dummy_tedge_config_defaults())
+            .load()?;
+
+    assert_eq!(config.query(MapperSyncWindowSecondsSetting)?, Seconds(30));
+
+    config.unset(MapperSyncWindowSecondsSetting)?;
+    assert_eq!(config.query(MapperSyncWindowSecondsSetting)?, Seconds(3));
+    Ok(())
+}
+
 #[test]
 fn read_az_keys_from_old_version_config() -> Result<(), TEdgeConfigError> {
     let toml_conf = r#"
@@ -517,6 +1117,7 @@ fn set_az_keys_from_old_version_config() -> Result<(), TEdgeConfigError> {
     let (_tempdir, config_location) = create_temp_tedge_config(toml_conf)?;
     let config_defaults = TEdgeConfigDefaults {
         default_azure_root_cert_path: FilePath::from("default_azure_root_cert_path"),
+        default_aws_root_cert_path: FilePath::from("default_aws_root_cert_path"),
         ..dummy_tedge_config_defaults()
     };
     let config_repo = TEdgeConfigRepository::new_with_defaults(config_location, config_defaults);
@@ -563,6 +1164,7 @@ fn test_parse_config_empty_file() -> Result<(), TEdgeConfigError> {
         default_device_key_path: FilePath::from("/etc/ssl/certs/tedge-private-key.pem"),
         default_c8y_root_cert_path: FilePath::from("/etc/ssl/certs"),
         default_azure_root_cert_path: FilePath::from("/etc/ssl/certs"),
+        default_aws_root_cert_path: FilePath::from("/etc/ssl/certs"),
         ..dummy_tedge_config_defaults()
     };
 
@@ -720,6 +1322,7 @@ port = 1024
     let config_defaults = TEdgeConfigDefaults {
         default_c8y_root_cert_path: FilePath::from("/etc/ssl/certs"),
         default_azure_root_cert_path: FilePath::from("/etc/ssl/certs"),
+        default_aws_root_cert_path: FilePath::from("/etc/ssl/certs"),
         ..dummy_tedge_config_defaults()
     };
 
@@ -891,11 +1494,13 @@ fn dummy_tedge_config_defaults() -> TEdgeConfigDefaults {
         default_device_key_path: FilePath::from("/dev/null"),
         default_c8y_root_cert_path: FilePath::from("/dev/null"),
         default_azure_root_cert_path: FilePath::from("/dev/null"),
+        default_aws_root_cert_path: FilePath::from("/dev/null"),
         default_mapper_timestamp: Flag(true),
         default_mqtt_port: Port(1883),
         default_tmp_path: FilePath::from("/tmp"),
         default_logs_path: FilePath::from("/var/log"),
         default_run_path: FilePath::from("/run"),
+        default_data_path: FilePath::from("/var/tedge"),
         default_device_type: String::from("test"),
         default_mqtt_bind_address: IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST)),
         default_c8y_smartrest_templates: TemplatesSet::default(),
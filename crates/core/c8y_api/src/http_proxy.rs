@@ -6,13 +6,13 @@ use crate::json_c8y::{
 use async_trait::async_trait;
 use c8y_smartrest::{error::SMCumulocityMapperError, smartrest_deserializer::SmartRestJwtResponse};
 use mockall::automock;
-use mqtt_channel::{Connection, PubChannel, StreamExt, Topic, TopicFilter};
+use mqtt_channel::{Connection, MqttError, RequestClient, Topic, TopicFilter};
 use reqwest::Url;
 use std::path::Path;
 use std::{collections::HashMap, time::Duration};
 use tedge_config::{
     C8yRootCertPathSetting, C8yUrlSetting, ConfigSettingAccessor, ConfigSettingAccessorStringExt,
-    DeviceIdSetting, MqttBindAddressSetting, MqttPortSetting, TEdgeConfig,
+    DeviceIdSetting, TEdgeConfig,
 };
 use time::OffsetDateTime;
 
@@ -35,6 +35,15 @@ pub trait C8YHttpProxy: Send + Sync {
         c8y_event: C8yCreateEvent,
     ) -> Result<String, SMCumulocityMapperError>;
 
+    /// Create a measurement given as c8y json, on behalf of the device or of one of its child devices.
+    ///
+    /// Used for the measurements too large to be sent over MQTT.
+    async fn send_measurement(
+        &mut self,
+        c8y_measurement: serde_json::Value,
+        child_device_id: Option<String>,
+    ) -> Result<(), SMCumulocityMapperError>;
+
     async fn send_software_list_http(
         &mut self,
         c8y_software_list: &C8yUpdateSoftwareListResponse,
@@ -106,6 +115,13 @@ impl C8yEndPoint {
         url_create_event
     }
 
+    fn get_url_for_create_measurement(&self) -> String {
+        let mut url_create_measurement = self.get_base_url();
+        url_create_measurement.push_str("/measurement/measurements/");
+
+        url_create_measurement
+    }
+
     fn get_url_for_event_binary_upload(&self, event_id: &str) -> String {
         let mut url_event_binary = self.get_url_for_create_event();
         url_event_binary.push_str(event_id);
@@ -147,37 +163,44 @@ pub trait C8yJwtTokenRetriever: Send + Sync {
 }
 
 pub struct C8yMqttJwtTokenRetriever {
-    mqtt_con: mqtt_channel::Connection,
+    mqtt_client: RequestClient,
 }
 
+/// The SmartREST code of the JWT token responses, used to correlate the responses to the requests
+const JWT_TOKEN_RESPONSE: &str = "71";
+
 impl C8yMqttJwtTokenRetriever {
     pub fn new(mqtt_con: mqtt_channel::Connection) -> Self {
-        C8yMqttJwtTokenRetriever { mqtt_con }
+        // The other SmartREST messages received on the connection are of no interest
+        let (mqtt_client, _other_messages) =
+            RequestClient::new(mqtt_con, jwt_token_response, Duration::from_secs(10));
+        C8yMqttJwtTokenRetriever { mqtt_client }
     }
 }
 
+fn jwt_token_response(message: &mqtt_channel::Message) -> Option<String> {
+    let payload = message.payload_str().ok()?;
+    let (code, _) = payload.split_once(',')?;
+    (code == JWT_TOKEN_RESPONSE).then(|| JWT_TOKEN_RESPONSE.to_string())
+}
+
 #[async_trait]
 impl C8yJwtTokenRetriever for C8yMqttJwtTokenRetriever {
     async fn get_jwt_token(&mut self) -> Result<SmartRestJwtResponse, SMCumulocityMapperError> {
-        self.mqtt_con
-            .published
-            .publish(mqtt_channel::Message::new(
-                &Topic::new_unchecked("c8y/s/uat"),
-                "".to_string(),
-            ))
-            .await?;
-        let token_smartrest = match tokio::time::timeout(
-            Duration::from_secs(10),
-            self.mqtt_con.received.next(),
-        )
-        .await
-        {
-            Ok(Some(msg)) => msg.payload_str()?.to_string(),
-            Ok(None) => return Err(SMCumulocityMapperError::InvalidMqttMessage),
-            Err(_elapsed) => return Err(SMCumulocityMapperError::RequestTimeout),
-        };
-
-        Ok(SmartRestJwtResponse::try_new(&token_smartrest)?)
+        let request = mqtt_channel::Message::new(&Topic::new_unchecked("c8y/s/uat"), "");
+        let token_smartrest = self
+            .mqtt_client
+            .request(JWT_TOKEN_RESPONSE, request)
+            .await
+            .map_err(|err| match err {
+                MqttError::RequestTimeout { .. } => SMCumulocityMapperError::RequestTimeout,
+                MqttError::ReadOnClosedConnection => SMCumulocityMapperError::InvalidMqttMessage,
+                err => err.into(),
+            })?;
+
+        Ok(SmartRestJwtResponse::try_new(
+            token_smartrest.payload_str()?,
+        )?)
     }
 }
 
@@ -228,13 +251,10 @@ impl JwtAuthHttpProxy {
             false => client_builder.build()?,
         };
 
-        let mqtt_port = tedge_config.query(MqttPortSetting)?.into();
-        let mqtt_host = tedge_config.query(MqttBindAddressSetting)?.to_string();
         let topic = TopicFilter::new("c8y/s/dat")?;
-        let mqtt_config = mqtt_channel::Config::default()
-            .with_port(mqtt_port)
+        let mqtt_config = tedge_config
+            .mqtt_config()?
             .with_clean_session(true)
-            .with_host(mqtt_host)
             .with_subscriptions(topic);
 
         let mut mqtt_con = Connection::new(&mqtt_config).await?;
@@ -380,6 +400,39 @@ impl C8YHttpProxy for JwtAuthHttpProxy {
         self.send_event_internal(c8y_event).await
     }
 
+    async fn send_measurement(
+        &mut self,
+        mut c8y_measurement: serde_json::Value,
+        child_device_id: Option<String>,
+    ) -> Result<(), SMCumulocityMapperError> {
+        let device_internal_id = match child_device_id {
+            Some(child_device_id) => self.get_c8y_internal_child_id(child_device_id).await?,
+            None => self.end_point.c8y_internal_id.clone(),
+        };
+        // Over HTTP, the source of a measurement is given by its internal id
+        if let Some(fields) = c8y_measurement.as_object_mut() {
+            fields.remove("externalSource");
+            fields.insert(
+                "source".to_string(),
+                serde_json::json!({ "id": device_internal_id }),
+            );
+        }
+
+        let token = self.get_jwt_token().await?;
+        let request = self
+            .http_con
+            .post(self.end_point.get_url_for_create_measurement())
+            .json(&c8y_measurement)
+            .bearer_auth(token.token())
+            .header("Accept", "application/json")
+            .timeout(Duration::from_millis(10000))
+            .build()?;
+
+        let response = self.http_con.execute(request).await?;
+        let _ = response.error_for_status_ref()?;
+        Ok(())
+    }
+
     async fn send_software_list_http(
         &mut self,
         c8y_software_list: &C8yUpdateSoftwareListResponse,
@@ -601,6 +654,55 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn send_measurement_of_child_device() -> anyhow::Result<()> {
+        let device_id = "test-device";
+        let child_id = "test-child";
+
+        // Mock endpoint to return C8Y internal id of the child device
+        let _get_internal_id_mock = mock("GET", "/identity/externalIds/c8y_Serial/test-child")
+            .with_status(200)
+            .with_body(
+                json!({ "externalId": child_id, "managedObject": { "id": "789" } }).to_string(),
+            )
+            .create();
+
+        let create_measurement_mock = mock("POST", "/measurement/measurements/")
+            .match_body(Matcher::Json(json!({
+                "type": "ThinEdgeMeasurement",
+                "temperature": { "temperature": { "value": 25.0 } },
+                "source": { "id": "789" },
+            })))
+            .with_status(201)
+            .create();
+
+        let mut jwt_token_retriver = Box::new(MockC8yJwtTokenRetriever::new());
+        jwt_token_retriver
+            .expect_get_jwt_token()
+            .returning(|| Ok(SmartRestJwtResponse::default()));
+
+        let http_client = reqwest::ClientBuilder::new().build().unwrap();
+        let mut http_proxy = JwtAuthHttpProxy::new(
+            jwt_token_retriver,
+            http_client,
+            mockito::server_url().as_str(),
+            device_id,
+        );
+
+        // The external source of the c8y json measurement is replaced by the internal id
+        let c8y_measurement = json!({
+            "type": "ThinEdgeMeasurement",
+            "externalSource": { "externalId": child_id, "type": "c8y_Serial" },
+            "temperature": { "temperature": { "value": 25.0 } },
+        });
+        http_proxy
+            .send_measurement(c8y_measurement, Some(child_id.to_string()))
+            .await?;
+
+        create_measurement_mock.assert();
+        Ok(())
+    }
+
     #[tokio::test]
     async fn upload_config_file() -> anyhow::Result<()> {
         let device_id = "test-device";
@@ -8,6 +8,7 @@ use c8y_smartrest::error::SMCumulocityMapperError;
 use download::DownloadInfo;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use thin_edge_json::alarm::{AlarmSeverity, ThinEdgeAlarm};
 use thin_edge_json::event::ThinEdgeEvent;
 use time::OffsetDateTime;
 
@@ -31,6 +32,23 @@ pub struct C8yCreateEvent {
     pub extras: HashMap<String, Value>,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct C8yCreateAlarm {
+    #[serde(rename = "type")]
+    pub alarm_type: String,
+
+    pub severity: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+
+    pub text: String,
+
+    #[serde(flatten)]
+    pub extras: HashMap<String, Value>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 /// used to retrieve the id of a log event
@@ -164,6 +182,52 @@ impl TryFrom<ThinEdgeEvent> for C8yCreateEvent {
 
 impl<'a> Jsonify<'a> for C8yCreateEvent {}
 
+impl TryFrom<ThinEdgeAlarm> for C8yCreateAlarm {
+    type Error = SMCumulocityMapperError;
+
+    fn try_from(alarm: ThinEdgeAlarm) -> Result<Self, SMCumulocityMapperError> {
+        let alarm_type = alarm.name;
+        let severity = match alarm.severity {
+            AlarmSeverity::Critical => "CRITICAL",
+            AlarmSeverity::Major => "MAJOR",
+            AlarmSeverity::Minor => "MINOR",
+            AlarmSeverity::Warning => "WARNING",
+        };
+        let text;
+        let time;
+        let mut extras;
+        match alarm.data {
+            None => {
+                text = alarm_type.clone();
+                time = OffsetDateTime::now_utc();
+                extras = HashMap::new();
+            }
+            Some(alarm_data) => {
+                text = alarm_data.text.unwrap_or_else(|| alarm_type.clone());
+                time = alarm_data.time.unwrap_or_else(OffsetDateTime::now_utc);
+                extras = alarm_data.extras;
+            }
+        }
+
+        // An external source given along the alarm takes precedence over the child device of the topic
+        if let Some(source) = alarm.source {
+            if !extras.contains_key("externalSource") {
+                update_the_external_source_event(&mut extras, &source)?;
+            }
+        }
+
+        Ok(Self {
+            alarm_type,
+            severity: severity.into(),
+            time,
+            text,
+            extras,
+        })
+    }
+}
+
+impl<'a> Jsonify<'a> for C8yCreateAlarm {}
+
 fn combine_version_and_type(
     version: &Option<SoftwareVersion>,
     module_type: &Option<SoftwareType>,
@@ -210,7 +274,9 @@ fn update_the_external_source_event(
 mod tests {
     use anyhow::Result;
     use assert_matches::assert_matches;
+    use serde_json::json;
     use test_case::test_case;
+    use thin_edge_json::alarm::ThinEdgeAlarmData;
     use thin_edge_json::event::ThinEdgeEventData;
     use time::macros::datetime;
 
@@ -427,6 +493,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn alarm_translation_keeps_custom_fragments() -> Result<()> {
+        let tedge_alarm = ThinEdgeAlarm {
+            name: "temperature_alarm".into(),
+            severity: AlarmSeverity::Major,
+            data: Some(ThinEdgeAlarmData {
+                text: None,
+                time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::from([("threshold".to_string(), json!(40))]),
+            }),
+            source: Some("sensor".into()),
+        };
+
+        let actual_c8y_alarm = C8yCreateAlarm::try_from(tedge_alarm)?;
+
+        assert_eq!(
+            serde_json::to_value(&actual_c8y_alarm)?,
+            json!({
+                "type": "temperature_alarm",
+                "severity": "MAJOR",
+                "time": "2021-04-23T19:00:00+05:00",
+                "text": "temperature_alarm",
+                "threshold": 40,
+                "externalSource": {"externalId": "sensor", "type": "c8y_Serial"},
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn alarm_translation_keeps_a_given_external_source() -> Result<()> {
+        let external_source = json!({"externalId": "plc", "type": "c8y_Serial"});
+        let tedge_alarm = ThinEdgeAlarm {
+            name: "temperature_alarm".into(),
+            severity: AlarmSeverity::Critical,
+            data: Some(ThinEdgeAlarmData {
+                text: Some("Too hot".into()),
+                time: None,
+                extras: HashMap::from([("externalSource".to_string(), external_source.clone())]),
+            }),
+            source: Some("sensor".into()),
+        };
+
+        let actual_c8y_alarm = C8yCreateAlarm::try_from(tedge_alarm)?;
+
+        assert_eq!(actual_c8y_alarm.severity, "CRITICAL");
+        assert_eq!(actual_c8y_alarm.text, "Too hot");
+        assert_eq!(actual_c8y_alarm.extras["externalSource"], external_source);
+
+        Ok(())
+    }
+
     #[test]
     fn event_translation_empty_json_payload_generates_timestamp() -> Result<()> {
         let tedge_event = ThinEdgeEvent {
@@ -38,6 +38,7 @@ mod tests {
     use super::*;
     use assert_matches::assert_matches;
     use serde::Deserialize;
+    use std::collections::HashMap;
     use test_case::test_case;
     use thin_edge_json::alarm::ThinEdgeAlarmData;
     use time::macros::datetime;
@@ -49,7 +50,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: Some("I raised it".into()),
                 time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::new(),
             }),
+            source: None,
         },
         "301,temperature_alarm,\"I raised it\",2021-04-23T19:00:00+05:00"
         ;"critical alarm translation"
@@ -61,7 +64,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: Some("I raised it".into()),
                 time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::new(),
             }),
+            source: None,
         },
         "302,temperature_alarm,\"I raised it\",2021-04-23T19:00:00+05:00"
         ;"major alarm translation"
@@ -73,7 +78,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: None,
                 time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::new(),
             }),
+            source: None,
         },
         "303,temperature_alarm,\"\",2021-04-23T19:00:00+05:00"
         ;"minor alarm translation without message"
@@ -85,7 +92,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: Some("I, raised, it".into()),
                 time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::new(),
             }),
+            source: None,
         },
         "304,temperature_alarm,\"I, raised, it\",2021-04-23T19:00:00+05:00"
         ;"warning alarm translation with commas in message"
@@ -95,6 +104,7 @@ mod tests {
             name: "temperature_alarm".into(),
             severity: AlarmSeverity::Minor,
             data: None,
+            source: None,
         },
         "306,temperature_alarm"
         ;"clear alarm translation"
@@ -121,7 +131,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: Some("I raised it".into()),
                 time: None,
+                extras: HashMap::new(),
             }),
+            source: None,
         };
 
         let smartrest_message = serialize_alarm(alarm).unwrap();
@@ -15,6 +15,7 @@
 
 use crate::serializer;
 use clock::{Clock, WallClock};
+use thin_edge_json::group::MeasurementGroup;
 use thin_edge_json::parser::*;
 use time::{self, OffsetDateTime};
 
@@ -44,6 +45,17 @@ pub fn from_thin_edge_json_with_child(
     Ok(c8y_vec)
 }
 
+/// Converts a group of measurements to c8y_json, using the given timestamp if the group has none
+pub fn from_measurement_group(
+    group: &MeasurementGroup,
+    default_timestamp: OffsetDateTime,
+    maybe_child_id: Option<&str>,
+) -> Result<String, CumulocityJsonError> {
+    let mut serializer = serializer::C8yJsonSerializer::new(default_timestamp, maybe_child_id);
+    group.accept(&mut serializer)?;
+    Ok(serializer.into_string()?)
+}
+
 fn from_thin_edge_json_with_timestamp(
     input: &str,
     timestamp: OffsetDateTime,
@@ -284,4 +296,29 @@ mod tests {
             expected_output
         );
     }
+
+    #[test]
+    fn check_measurement_group_translation() {
+        let mut grouper = thin_edge_json::group::MeasurementGrouper::new();
+        parse_str(
+            r#"{"temperature": 23.0, "location": {"x": 1.0, "y": 2.0}}"#,
+            &mut grouper,
+        )
+        .unwrap();
+        let group = grouper.end().unwrap();
+        let timestamp = datetime!(2021-04-08 0:00:0 +05:00);
+
+        let output = from_measurement_group(&group, timestamp, Some("child1")).unwrap();
+
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output.as_str()).unwrap(),
+            json!({
+                "type": "ThinEdgeMeasurement",
+                "externalSource": {"externalId": "child1","type": "c8y_Serial",},
+                "time": "2021-04-08T00:00:00+05:00",
+                "temperature": {"temperature": {"value": 23.0}},
+                "location": {"x": {"value": 1.0}, "y": {"value": 2.0}}
+            })
+        );
+    }
 }
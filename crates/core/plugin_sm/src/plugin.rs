@@ -1,7 +1,7 @@
 use agent_interface::*;
 use async_trait::async_trait;
 use csv::ReaderBuilder;
-use download::Downloader;
+use download::{DownloadSettings, Downloader};
 use logged_command::LoggedCommand;
 use serde::Deserialize;
 use std::path::Path;
@@ -50,14 +50,21 @@ pub trait Plugin {
         update: &SoftwareModuleUpdate,
         logger: &mut BufWriter<File>,
         download_path: &Path,
+        download_settings: &DownloadSettings,
     ) -> Result<(), SoftwareError> {
         match update.clone() {
             SoftwareModuleUpdate::Install { mut module } => {
                 let module_url = module.url.clone();
                 match module_url {
                     Some(url) => {
-                        self.install_from_url(&mut module, &url, logger, download_path)
-                            .await?
+                        self.install_from_url(
+                            &mut module,
+                            &url,
+                            logger,
+                            download_path,
+                            download_settings,
+                        )
+                        .await?
                     }
                     None => self.install(&module, logger).await?,
                 }
@@ -73,6 +80,7 @@ pub trait Plugin {
         mut updates: Vec<SoftwareModuleUpdate>,
         logger: &mut BufWriter<File>,
         download_path: &Path,
+        download_settings: &DownloadSettings,
     ) -> Vec<SoftwareError> {
         let mut failed_updates = Vec::new();
 
@@ -91,7 +99,15 @@ pub trait Plugin {
             };
             let module_url = module.url.clone();
             if let Some(url) = module_url {
-                match Self::download_from_url(module, &url, logger, download_path).await {
+                match Self::download_from_url(
+                    module,
+                    &url,
+                    logger,
+                    download_path,
+                    download_settings,
+                )
+                .await
+                {
                     Err(prepare_error) => {
                         failed_updates.push(prepare_error);
                         break;
@@ -106,7 +122,10 @@ pub trait Plugin {
             let outcome = self.update_list(&updates, logger).await;
             if let Err(SoftwareError::UpdateListNotSupported(_)) = outcome {
                 for update in updates.iter() {
-                    if let Err(error) = self.apply(update, logger, download_path).await {
+                    if let Err(error) = self
+                        .apply(update, logger, download_path, download_settings)
+                        .await
+                    {
                         failed_updates.push(error);
                     };
                 }
@@ -137,8 +156,10 @@ pub trait Plugin {
         url: &DownloadInfo,
         logger: &mut BufWriter<File>,
         download_path: &Path,
+        download_settings: &DownloadSettings,
     ) -> Result<(), SoftwareError> {
-        let downloader = Self::download_from_url(module, url, logger, download_path).await?;
+        let downloader =
+            Self::download_from_url(module, url, logger, download_path, download_settings).await?;
         let result = self.install(module, logger).await;
         Self::cleanup_downloaded_artefacts(downloader, logger).await?;
 
@@ -150,8 +171,10 @@ pub trait Plugin {
         url: &DownloadInfo,
         logger: &mut BufWriter<File>,
         download_path: &Path,
+        download_settings: &DownloadSettings,
     ) -> Result<Downloader, SoftwareError> {
-        let downloader = Downloader::new(&module.name, &module.version, &download_path);
+        let downloader = Downloader::new(&module.name, &module.version, &download_path)
+            .with_settings(download_settings.clone());
 
         logger
             .write_all(
@@ -1,8 +1,8 @@
 use crate::plugin::{Plugin, LIST};
 use crate::{log_file::LogFile, plugin::ExternalPluginCommand};
 use agent_interface::{
-    SoftwareError, SoftwareListRequest, SoftwareListResponse, SoftwareType, SoftwareUpdateRequest,
-    SoftwareUpdateResponse, DEFAULT,
+    DownloadSettings, SoftwareError, SoftwareListRequest, SoftwareListResponse, SoftwareType,
+    SoftwareUpdateRequest, SoftwareUpdateResponse, DEFAULT,
 };
 use std::path::Path;
 use std::{
@@ -229,6 +229,7 @@ impl ExternalPlugins {
         request: &SoftwareUpdateRequest,
         mut log_file: LogFile,
         download_path: &Path,
+        download_settings: &DownloadSettings,
     ) -> SoftwareUpdateResponse {
         let mut response = SoftwareUpdateResponse::new(request);
         let logger = log_file.buffer();
@@ -237,7 +238,9 @@ impl ExternalPlugins {
         for software_type in request.modules_types() {
             let errors = if let Some(plugin) = self.by_software_type(&software_type) {
                 let updates = request.updates_for(&software_type);
-                plugin.apply_all(updates, logger, download_path).await
+                plugin
+                    .apply_all(updates, logger, download_path, download_settings)
+                    .await
             } else {
                 vec![SoftwareError::UnknownSoftwareType {
                     software_type: software_type.clone(),
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
 
-    use agent_interface::{SoftwareError, SoftwareModule, SoftwareModuleUpdate};
+    use agent_interface::{DownloadSettings, SoftwareError, SoftwareModule, SoftwareModuleUpdate};
     use plugin_sm::plugin::{deserialize_module_info, ExternalPluginCommand, Plugin};
     use serial_test::serial;
     use std::{fs, io::Write, path::PathBuf, str::FromStr};
@@ -371,6 +371,7 @@ mod tests {
                 ],
                 &mut logger,
                 &download,
+                &DownloadSettings::default(),
             )
             .await;
 
@@ -49,6 +49,9 @@ impl ConfigKey {
             config_key!(AzureUrlSetting),
             config_key!(AzureRootCertPathSetting),
             config_key!(AzureMapperTimestamp),
+            config_key!(AwsUrlSetting),
+            config_key!(AwsRootCertPathSetting),
+            config_key!(AwsMapperTimestamp),
             config_key!(MqttBindAddressSetting),
             config_key!(MqttPortSetting),
             config_key!(MqttExternalPortSetting),
@@ -57,10 +60,40 @@ impl ConfigKey {
             config_key!(MqttExternalCAPathSetting),
             config_key!(MqttExternalCertfileSetting),
             config_key!(MqttExternalKeyfileSetting),
+            config_key!(MqttClientHostSetting),
+            config_key!(MqttClientPortSetting),
+            config_key!(MqttClientCaPathSetting),
+            config_key!(MqttClientAuthCertfileSetting),
+            config_key!(MqttClientAuthKeyfileSetting),
+            config_key!(MqttClientAuthUsernameSetting),
+            config_key!(MqttClientAuthPasswordSetting),
+            config_key!(MqttClientQueueSizeSetting),
+            config_key!(MqttClientQueuePolicySetting),
+            config_key!(MapperErrorsForwardSetting),
+            config_key!(MapperMultiNamesSetting),
+            config_key!(MapperSyncWindowSecondsSetting),
             config_key!(SoftwarePluginDefaultSetting),
             config_key!(TmpPathSetting),
             config_key!(LogPathSetting),
             config_key!(RunPathSetting),
+            config_key!(DataPathSetting),
+            config_key!(DownloadProxyUrlSetting),
+            config_key!(DownloadRootCertPathSetting),
+            config_key!(DownloadClientCertPathSetting),
+            config_key!(DownloadClientKeyPathSetting),
+            config_key!(DownloadMirrorsSetting),
+            config_key!(MetricsPrometheusPortSetting),
+            config_key!(MetricsPrometheusBindAddressSetting),
+            config_key!(StoreRetentionDaysSetting),
+            config_key!(StoreHttpPortSetting),
+            config_key!(StoreHttpBindAddressSetting),
+            config_key!(PrometheusHttpPortSetting),
+            config_key!(PrometheusHttpBindAddressSetting),
+            config_key!(PrometheusStalenessSecondsSetting),
+            config_key!(InfluxDbHttpUrlSetting),
+            config_key!(InfluxDbHttpTokenSetting),
+            config_key!(InfluxDbMqttTopicSetting),
+            config_key!(InfluxDbBatchPeriodSecondsSetting),
         ]
     }
 }
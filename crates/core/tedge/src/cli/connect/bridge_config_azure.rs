@@ -60,6 +60,7 @@ impl From<BridgeConfigAzureParams> for BridgeConfig {
                 sub_msg_topic,
                 r##"twin/res/# in 1 az/ $iothub/"##.into(),
                 r#"twin/GET/?$rid=1 out 1 az/ $iothub/"#.into(),
+                r##"twin/PATCH/properties/reported/# out 1 az/ $iothub/"##.into(),
             ],
         }
     }
@@ -99,6 +100,7 @@ fn test_bridge_config_from_azure_params() -> anyhow::Result<()> {
             r##"messages/devicebound/# out 1 az/ devices/alpha/"##.into(),
             r##"twin/res/# in 1 az/ $iothub/"##.into(),
             r#"twin/GET/?$rid=1 out 1 az/ $iothub/"#.into(),
+            r##"twin/PATCH/properties/reported/# out 1 az/ $iothub/"##.into(),
         ],
         try_private: false,
         start_type: "automatic".into(),
@@ -52,6 +52,7 @@ impl From<BridgeConfigC8yParams> for BridgeConfig {
             r#"inventory/managedObjects/update/# out 2 c8y/ """#.into(),
             r#"measurement/measurements/create out 2 c8y/ """#.into(),
             r#"event/events/create out 2 c8y/ """#.into(),
+            r#"alarm/alarms/create out 2 c8y/ """#.into(),
             r#"error in 2 c8y/ """#.into(),
             // c8y JWT token retrieval
             r#"s/uat/# out 2 c8y/ """#.into(),
@@ -153,6 +154,7 @@ fn test_bridge_config_from_c8y_params() -> anyhow::Result<()> {
             r#"inventory/managedObjects/update/# out 2 c8y/ """#.into(),
             r#"measurement/measurements/create out 2 c8y/ """#.into(),
             r#"event/events/create out 2 c8y/ """#.into(),
+            r#"alarm/alarms/create out 2 c8y/ """#.into(),
             r#"error in 2 c8y/ """#.into(),
             // c8y JWT token retrieval
             r#"s/uat/# out 2 c8y/ """#.into(),
@@ -4,6 +4,7 @@ use crate::system_services::service_manager;
 
 const C8Y_CONFIG_FILENAME: &str = "c8y-bridge.conf";
 const AZURE_CONFIG_FILENAME: &str = "az-bridge.conf";
+const AWS_CONFIG_FILENAME: &str = "aws-bridge.conf";
 
 #[derive(clap::Subcommand, Debug)]
 pub enum TEdgeDisconnectBridgeCli {
@@ -11,6 +12,8 @@ pub enum TEdgeDisconnectBridgeCli {
     C8y,
     /// Remove bridge connection to Azure.
     Az,
+    /// Remove bridge connection to AWS.
+    Aws,
 }
 
 impl BuildCommand for TEdgeDisconnectBridgeCli {
@@ -32,6 +35,14 @@ impl BuildCommand for TEdgeDisconnectBridgeCli {
                 use_agent: false,
                 service_manager: service_manager(context.config_location.tedge_config_root_path)?,
             },
+            TEdgeDisconnectBridgeCli::Aws => DisconnectBridgeCommand {
+                config_location: context.config_location.clone(),
+                config_file: AWS_CONFIG_FILENAME.into(),
+                cloud: Cloud::Aws,
+                use_mapper: true,
+                use_agent: false,
+                service_manager: service_manager(context.config_location.tedge_config_root_path)?,
+            },
         };
         Ok(cmd.into_boxed())
     }
@@ -39,11 +39,7 @@ pub enum TEdgeMqttCli {
 
 impl BuildCommand for TEdgeMqttCli {
     fn build_command(self, context: BuildContext) -> Result<Box<dyn Command>, crate::ConfigError> {
-        let port = context.config_repository.load()?.query(MqttPortSetting)?;
-        let host = context
-            .config_repository
-            .load()?
-            .query(MqttBindAddressSetting)?;
+        let mqtt_config = context.config_repository.load()?.mqtt_config()?;
         let cmd = {
             match self {
                 TEdgeMqttCli::Pub {
@@ -52,8 +48,7 @@ impl BuildCommand for TEdgeMqttCli {
                     qos,
                     retain,
                 } => MqttPublishCommand {
-                    host: host.to_string(),
-                    port: port.into(),
+                    mqtt_config: mqtt_config.clone(),
                     topic,
                     message,
                     qos,
@@ -67,8 +62,7 @@ impl BuildCommand for TEdgeMqttCli {
                     qos,
                     hide_topic,
                 } => MqttSubscribeCommand {
-                    host: host.to_string(),
-                    port: port.into(),
+                    mqtt_config,
                     topic,
                     qos,
                     hide_topic,
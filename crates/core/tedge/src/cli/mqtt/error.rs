@@ -3,6 +3,9 @@ pub enum MqttError {
     #[error("MQTT error")]
     FromRumqttClient(#[from] rumqttc::ClientError),
 
+    #[error(transparent)]
+    FromMqttChannel(#[from] mqtt_channel::MqttError),
+
     #[error("I/O error")]
     FromIo(#[from] std::io::Error),
 
@@ -1,14 +1,13 @@
 use crate::cli::mqtt::MqttError;
 use crate::command::Command;
 use rumqttc::QoS;
-use rumqttc::{Client, Event, Incoming, MqttOptions, Packet};
+use rumqttc::{Client, Event, Incoming, Packet};
 
 const DEFAULT_QUEUE_CAPACITY: usize = 10;
 const MAX_PACKET_SIZE: usize = 1048575;
 
 pub struct MqttSubscribeCommand {
-    pub host: String,
-    pub port: u16,
+    pub mqtt_config: mqtt_channel::Config,
     pub topic: String,
     pub qos: QoS,
     pub hide_topic: bool,
@@ -29,9 +28,13 @@ impl Command for MqttSubscribeCommand {
 }
 
 fn subscribe(cmd: &MqttSubscribeCommand) -> Result<(), MqttError> {
-    let mut options = MqttOptions::new(cmd.client_id.as_str(), &cmd.host, cmd.port);
-    options.set_clean_session(true);
-    options.set_max_packet_size(MAX_PACKET_SIZE, MAX_PACKET_SIZE);
+    let options = cmd
+        .mqtt_config
+        .clone()
+        .with_session_name(cmd.client_id.as_str())
+        .with_clean_session(true)
+        .with_max_packet_size(MAX_PACKET_SIZE)
+        .mqtt_options()?;
 
     let (mut client, mut connection) = Client::new(options, DEFAULT_QUEUE_CAPACITY);
 
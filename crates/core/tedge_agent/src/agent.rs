@@ -8,9 +8,10 @@ use crate::{
     },
 };
 use agent_interface::{
-    control_filter_topic, software_filter_topic, Jsonify, OperationStatus, RestartOperationRequest,
-    RestartOperationResponse, SoftwareError, SoftwareListRequest, SoftwareListResponse,
-    SoftwareRequestResponse, SoftwareType, SoftwareUpdateRequest, SoftwareUpdateResponse,
+    control_filter_topic, software_filter_topic, DownloadSettings, Jsonify, OperationStatus,
+    RestartOperationRequest, RestartOperationResponse, SoftwareError, SoftwareListRequest,
+    SoftwareListResponse, SoftwareRequestResponse, SoftwareType, SoftwareUpdateRequest,
+    SoftwareUpdateResponse,
 };
 use flockfile::{check_another_instance_is_not_running, Flockfile};
 
@@ -25,12 +26,12 @@ use std::process::Command;
 use std::{convert::TryInto, fmt::Debug, path::PathBuf, sync::Arc};
 use tedge_config::{
     ConfigRepository, ConfigSettingAccessor, ConfigSettingAccessorStringExt, LogPathSetting,
-    MqttBindAddressSetting, MqttExternalBindAddressSetting, MqttPortSetting, RunPathSetting,
+    MqttBindAddressSetting, MqttExternalBindAddressSetting, RunPathSetting,
     SoftwarePluginDefaultSetting, TEdgeConfigLocation, TmpPathSetting, DEFAULT_LOG_PATH,
     DEFAULT_RUN_PATH,
 };
 use tedge_utils::file::create_directory_with_user_group;
-use thin_edge_json::health::{health_check_topics, send_health_status};
+use thin_edge_json::health::{health_check_topics, send_health_status, with_health_status};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, instrument, warn};
 
@@ -61,6 +62,7 @@ pub struct SmAgentConfig {
     pub run_dir: PathBuf,
     config_location: TEdgeConfigLocation,
     pub download_dir: PathBuf,
+    pub download_settings: DownloadSettings,
     pub http_config: HttpConfig,
 }
 
@@ -125,6 +127,7 @@ impl Default for SmAgentConfig {
             run_dir,
             config_location,
             download_dir,
+            download_settings: DownloadSettings::default(),
             http_config: HttpConfig::default(),
         }
     }
@@ -136,9 +139,8 @@ impl SmAgentConfig {
             tedge_config::TEdgeConfigRepository::new(tedge_config_location.clone());
         let tedge_config = config_repository.load()?;
 
-        let mqtt_config = mqtt_channel::Config::default()
-            .with_host(tedge_config.query(MqttBindAddressSetting)?.to_string())
-            .with_port(tedge_config.query(MqttPortSetting)?.into())
+        let mqtt_config = tedge_config
+            .mqtt_config()?
             .with_max_packet_size(10 * 1024 * 1024);
 
         let tedge_config_path = config_repository
@@ -147,6 +149,7 @@ impl SmAgentConfig {
             .to_path_buf();
 
         let tedge_download_dir = tedge_config.query_string(TmpPathSetting)?.into();
+        let download_settings = tedge_config.download_settings()?;
 
         let tedge_log_dir: String = tedge_config.query_string(LogPathSetting)?;
         let tedge_log_dir = PathBuf::from(&format!("{tedge_log_dir}/{AGENT_LOG_PATH}"));
@@ -169,6 +172,7 @@ impl SmAgentConfig {
             .with_mqtt_config(mqtt_config)
             .with_config_location(tedge_config_location)
             .with_download_directory(tedge_download_dir)
+            .with_download_settings(download_settings)
             .with_log_directory(tedge_log_dir)
             .with_run_directory(tedge_run_dir)
             .with_http_config(http_config))
@@ -199,6 +203,13 @@ impl SmAgentConfig {
         }
     }
 
+    pub fn with_download_settings(self, download_settings: DownloadSettings) -> Self {
+        Self {
+            download_settings,
+            ..self
+        }
+    }
+
     pub fn with_log_directory(self, tmp_dir: PathBuf) -> Self {
         Self {
             log_dir: tmp_dir,
@@ -237,8 +248,7 @@ impl SmAgent {
         let persistence_store = AgentStateRepository::new(config.sm_home.clone());
         let operation_logs = OperationLogs::try_new(config.log_dir.clone())?;
 
-        config.mqtt_config = config
-            .mqtt_config
+        config.mqtt_config = with_health_status(config.mqtt_config, name)
             .with_session_name(name)
             .with_subscriptions(config.request_topics.clone());
 
@@ -515,7 +525,12 @@ impl SmAgent {
                 plugins
                     .lock()
                     .await
-                    .process(&request, log_file, &self.config.download_dir)
+                    .process(
+                        &request,
+                        log_file,
+                        &self.config.download_dir,
+                        &self.config.download_settings,
+                    )
                     .await
             }
             Err(err) => {
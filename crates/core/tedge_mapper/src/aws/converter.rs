@@ -0,0 +1,394 @@
+use crate::aws::mapper::AWS_MAPPER_NAME;
+use crate::core::{converter::*, error::*, size_threshold::SizeThreshold};
+
+use async_trait::async_trait;
+use clock::Clock;
+use mqtt_channel::{Message, MqttError, Router, Topic, TopicFilter};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use thin_edge_json::{
+    alarm::ThinEdgeAlarm, event::ThinEdgeEvent, serialize::ThinEdgeJsonSerializer,
+};
+use time::format_description;
+
+type AwsRouter = Router<AwsConverter, Result<Vec<Message>, ConversionError>>;
+
+/// The prefix of the local topics forwarded by the bridge to `$aws/things/<thing name>/td/`
+const AWS_TELEMETRY_PREFIX: &str = "aws/td";
+
+pub struct AwsConverter {
+    pub(crate) add_timestamp: bool,
+    pub(crate) clock: Box<dyn Clock>,
+    pub(crate) size_threshold: SizeThreshold,
+    pub(crate) mapper_config: MapperConfig,
+    router: Arc<AwsRouter>,
+}
+
+impl AwsConverter {
+    pub fn new(add_timestamp: bool, clock: Box<dyn Clock>, size_threshold: SizeThreshold) -> Self {
+        let router = Self::router().expect("Invalid AWS mapper routes");
+        let mapper_config = MapperConfig {
+            mapper_name: AWS_MAPPER_NAME.to_string(),
+            in_topic_filter: router.topic_filter(),
+            out_topic: make_valid_topic_or_panic("aws/td/measurements"),
+            errors_topic: make_valid_topic_or_panic("tedge/errors"),
+        };
+        AwsConverter {
+            add_timestamp,
+            clock,
+            size_threshold,
+            mapper_config,
+            router: Arc::new(router),
+        }
+    }
+
+    pub fn in_topic_filter() -> TopicFilter {
+        Self::router()
+            .expect("Invalid AWS mapper routes")
+            .topic_filter()
+    }
+
+    /// The routes of the thin-edge messages converted for AWS
+    fn router() -> Result<AwsRouter, MqttError> {
+        AwsRouter::new()
+            .route("tedge/measurements", |converter, message, _| {
+                Box::pin(async move { converter.convert_measurement(message) })
+            })?
+            .route("tedge/measurements/{child}", |converter, message, _| {
+                Box::pin(async move { converter.convert_measurement(message) })
+            })?
+            .route(
+                "tedge/alarms/{severity}/{alarm_type}",
+                |converter, message, _| Box::pin(async move { converter.convert_alarm(message) }),
+            )?
+            .route(
+                "tedge/alarms/{severity}/{alarm_type}/{child}",
+                |converter, message, _| Box::pin(async move { converter.convert_alarm(message) }),
+            )?
+            .route("tedge/events/{event_type}", |converter, message, _| {
+                Box::pin(async move { converter.convert_event(message) })
+            })?
+            .route(
+                "tedge/events/{event_type}/{child}",
+                |converter, message, _| Box::pin(async move { converter.convert_event(message) }),
+            )
+    }
+
+    fn convert_measurement(&self, input: &Message) -> Result<Vec<Message>, ConversionError> {
+        let default_timestamp = self.add_timestamp.then(|| self.clock.now());
+        let mut serializer = ThinEdgeJsonSerializer::new_with_timestamp(default_timestamp);
+        thin_edge_json::parser::parse_str(input.payload_str()?, &mut serializer)?;
+
+        let payload = serializer.into_string()?;
+        Ok(vec![Message::new(&aws_topic(&input.topic), payload)])
+    }
+
+    /// An alarm is published with its type, severity and status along the fields of the thin-edge alarm.
+    ///
+    /// A thin-edge alarm with an empty payload is published as a `cleared` alarm.
+    fn convert_alarm(&self, input: &Message) -> Result<Vec<Message>, ConversionError> {
+        let payload = input.payload_str()?;
+        let alarm = ThinEdgeAlarm::try_from(&input.topic.name, payload)?;
+        let severity = input.topic.name.split('/').nth(2).unwrap_or_default();
+        let status = if alarm.data.is_some() {
+            "active"
+        } else {
+            "cleared"
+        };
+
+        let mut fields = json_object(input)?;
+        fields.insert("type".into(), alarm.name.into());
+        fields.insert("severity".into(), severity.into());
+        fields.insert("status".into(), status.into());
+        self.add_timestamp_if_missing(&mut fields)?;
+
+        let payload = serde_json::to_string(&fields)?;
+        Ok(vec![Message::new(&aws_topic(&input.topic), payload)])
+    }
+
+    /// An event is published with its type along the fields of the thin-edge event.
+    fn convert_event(&self, input: &Message) -> Result<Vec<Message>, ConversionError> {
+        let payload = input.payload_str()?;
+        let event = ThinEdgeEvent::try_from(&input.topic.name, payload)?;
+
+        let mut fields = json_object(input)?;
+        fields.insert("type".into(), event.name.into());
+        self.add_timestamp_if_missing(&mut fields)?;
+
+        let payload = serde_json::to_string(&fields)?;
+        Ok(vec![Message::new(&aws_topic(&input.topic), payload)])
+    }
+
+    fn add_timestamp_if_missing(
+        &self,
+        fields: &mut Map<String, Value>,
+    ) -> Result<(), ConversionError> {
+        if self.add_timestamp && !fields.contains_key("time") {
+            let time = self
+                .clock
+                .now()
+                .format(&format_description::well_known::Rfc3339)?;
+            fields.insert("time".into(), time.into());
+        }
+        Ok(())
+    }
+}
+
+/// The AWS topic of a thin-edge message, as `aws/td/alarms/critical/temperature` for `tedge/alarms/critical/temperature`
+fn aws_topic(tedge_topic: &Topic) -> Topic {
+    let suffix = tedge_topic
+        .name
+        .strip_prefix("tedge/")
+        .unwrap_or(&tedge_topic.name);
+    Topic::new_unchecked(&format!("{}/{}", AWS_TELEMETRY_PREFIX, suffix))
+}
+
+/// The fields of a JSON object payload, an empty payload having no fields
+fn json_object(input: &Message) -> Result<Map<String, Value>, ConversionError> {
+    let payload = input.payload_str()?;
+    if payload.is_empty() {
+        return Ok(Map::new());
+    }
+    match serde_json::from_str(payload)? {
+        Value::Object(fields) => Ok(fields),
+        _ => Err(ConversionError::NotAJsonObject {
+            topic: input.topic.name.clone(),
+        }),
+    }
+}
+
+#[async_trait]
+impl Converter for AwsConverter {
+    type Error = ConversionError;
+
+    fn get_mapper_config(&self) -> &MapperConfig {
+        &self.mapper_config
+    }
+
+    async fn try_convert(&mut self, input: &Message) -> Result<Vec<Message>, Self::Error> {
+        self.size_threshold.validate(input)?;
+        let router = self.router.clone();
+        match router.dispatch(self, input).await {
+            Some(result) => result,
+            None => Err(ConversionError::UnsupportedTopic(input.topic.name.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_json_diff::*;
+    use assert_matches::*;
+    use serde_json::json;
+    use time::macros::datetime;
+
+    struct TestClock;
+
+    impl Clock for TestClock {
+        fn now(&self) -> clock::Timestamp {
+            datetime!(2021-04-08 00:00:00 +05:00)
+        }
+    }
+
+    fn converter(add_timestamp: bool) -> AwsConverter {
+        AwsConverter::new(
+            add_timestamp,
+            Box::new(TestClock),
+            SizeThreshold(128 * 1024),
+        )
+    }
+
+    fn new_tedge_message(topic: &str, payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload)
+    }
+
+    fn assert_single_message(output: Vec<Message>, topic: &str, payload: serde_json::Value) {
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, topic);
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(output[0].payload_str().unwrap()).unwrap(),
+            payload
+        );
+    }
+
+    #[tokio::test]
+    async fn converting_measurements_adds_a_timestamp() {
+        let mut converter = converter(true);
+
+        let output = converter
+            .try_convert(&new_tedge_message(
+                "tedge/measurements",
+                r#"{"temperature": 23.0}"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_single_message(
+            output,
+            "aws/td/measurements",
+            json!({"temperature": 23.0, "time": "2021-04-08T00:00:00+05:00"}),
+        );
+    }
+
+    #[tokio::test]
+    async fn converting_child_measurements_without_timestamp() {
+        let mut converter = converter(false);
+
+        let output = converter
+            .try_convert(&new_tedge_message(
+                "tedge/measurements/child1",
+                r#"{"temperature": 23.0}"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_single_message(
+            output,
+            "aws/td/measurements/child1",
+            json!({"temperature": 23.0}),
+        );
+    }
+
+    #[tokio::test]
+    async fn converting_invalid_measurements_is_an_error() {
+        let mut converter = converter(true);
+
+        let result = converter
+            .try_convert(&new_tedge_message("tedge/measurements", "not json"))
+            .await;
+
+        assert_matches!(result, Err(ConversionError::FromThinEdgeJsonParser(_)));
+    }
+
+    #[tokio::test]
+    async fn converting_an_alarm() {
+        let mut converter = converter(true);
+
+        let output = converter
+            .try_convert(&new_tedge_message(
+                "tedge/alarms/critical/temperature_high/child1",
+                r#"{"text": "Temperature is too high", "time": "2021-04-23T19:00:00+05:00"}"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_single_message(
+            output,
+            "aws/td/alarms/critical/temperature_high/child1",
+            json!({
+                "type": "temperature_high",
+                "severity": "critical",
+                "status": "active",
+                "text": "Temperature is too high",
+                "time": "2021-04-23T19:00:00+05:00"
+            }),
+        );
+    }
+
+    #[tokio::test]
+    async fn converting_a_cleared_alarm() {
+        let mut converter = converter(true);
+
+        let output = converter
+            .try_convert(&new_tedge_message("tedge/alarms/major/door_open", ""))
+            .await
+            .unwrap();
+
+        assert_single_message(
+            output,
+            "aws/td/alarms/major/door_open",
+            json!({
+                "type": "door_open",
+                "severity": "major",
+                "status": "cleared",
+                "time": "2021-04-08T00:00:00+05:00"
+            }),
+        );
+    }
+
+    #[tokio::test]
+    async fn converting_an_alarm_with_an_unknown_severity_is_an_error() {
+        let mut converter = converter(true);
+
+        let result = converter
+            .try_convert(&new_tedge_message("tedge/alarms/fatal/door_open", "{}"))
+            .await;
+
+        assert_matches!(
+            result,
+            Err(ConversionError::FromThinEdgeJsonAlarmDeserialization(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn converting_an_event_keeps_the_custom_fields() {
+        let mut converter = converter(true);
+
+        let output = converter
+            .try_convert(&new_tedge_message(
+                "tedge/events/click_event",
+                r#"{"text": "Someone clicked", "button": "left"}"#,
+            ))
+            .await
+            .unwrap();
+
+        assert_single_message(
+            output,
+            "aws/td/events/click_event",
+            json!({
+                "type": "click_event",
+                "text": "Someone clicked",
+                "button": "left",
+                "time": "2021-04-08T00:00:00+05:00"
+            }),
+        );
+    }
+
+    #[tokio::test]
+    async fn converting_an_event_that_is_not_a_json_object_is_an_error() {
+        let mut converter = converter(true);
+
+        let result = converter
+            .try_convert(&new_tedge_message("tedge/events/click_event", "[1, 2]"))
+            .await;
+
+        assert_matches!(
+            result,
+            Err(ConversionError::FromThinEdgeJsonEventDeserialization(_))
+        );
+    }
+
+    #[tokio::test]
+    async fn exceeding_threshold_returns_error() {
+        let mut converter = AwsConverter::new(false, Box::new(TestClock), SizeThreshold(1));
+
+        let result = converter
+            .try_convert(&new_tedge_message("tedge/measurements", "ABC"))
+            .await;
+
+        assert_matches!(
+            result,
+            Err(ConversionError::SizeThresholdExceeded {
+                topic: _,
+                actual_size: 3,
+                threshold: 1
+            })
+        );
+    }
+
+    #[test]
+    fn the_converter_subscribes_to_measurements_alarms_and_events() {
+        assert_eq!(
+            AwsConverter::in_topic_filter().patterns,
+            vec![
+                "tedge/measurements",
+                "tedge/measurements/+",
+                "tedge/alarms/+/+",
+                "tedge/alarms/+/+/+",
+                "tedge/events/+",
+                "tedge/events/+/+",
+            ]
+        );
+    }
+}
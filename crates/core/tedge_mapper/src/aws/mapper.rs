@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use crate::{
+    aws::converter::AwsConverter,
+    core::{
+        component::TEdgeComponent,
+        mapper::create_mapper,
+        metrics_endpoint::MetricsEndpoint,
+        script_hook::{with_script_hook, SCRIPTS_DIR},
+        size_threshold::SizeThreshold,
+    },
+};
+
+use async_trait::async_trait;
+use clock::WallClock;
+use tedge_config::ConfigSettingAccessor;
+use tedge_config::{AwsMapperTimestamp, TEdgeConfig};
+use tedge_utils::file::create_directory_with_user_group;
+use tracing::{info, info_span, Instrument};
+
+pub(crate) const AWS_MAPPER_NAME: &str = "tedge-mapper-aws";
+
+pub struct AwsMapper {}
+
+impl AwsMapper {
+    pub fn new() -> AwsMapper {
+        AwsMapper {}
+    }
+}
+
+#[async_trait]
+impl TEdgeComponent for AwsMapper {
+    fn session_name(&self) -> &str {
+        AWS_MAPPER_NAME
+    }
+
+    async fn init(&self, config_dir: &Path) -> Result<(), anyhow::Error> {
+        info!("Initialize tedge mapper aws");
+        create_directory_with_user_group(
+            format!("{}/operations/aws", config_dir.display()),
+            "tedge",
+            "tedge",
+            0o775,
+        )?;
+        create_directory_with_user_group(config_dir.join(SCRIPTS_DIR), "tedge", "tedge", 0o775)?;
+
+        self.init_session(AwsConverter::in_topic_filter()).await?;
+        Ok(())
+    }
+
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        config_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
+    ) -> Result<(), anyhow::Error> {
+        let add_timestamp = tedge_config.query(AwsMapperTimestamp)?.is_set();
+        let mqtt_config = tedge_config.mqtt_config()?;
+        let clock = Box::new(WallClock);
+        let size_threshold = SizeThreshold(128 * 1024);
+
+        let converter = Box::new(AwsConverter::new(add_timestamp, clock, size_threshold));
+        let converter = with_script_hook(converter, AWS_MAPPER_NAME, config_dir)?;
+
+        let mut mapper = create_mapper(AWS_MAPPER_NAME, mqtt_config, converter)
+            .await?
+            .with_mapper_settings(&tedge_config)?;
+        metrics_endpoint.register(AWS_MAPPER_NAME, mapper.metrics());
+
+        mapper
+            .run(None)
+            .instrument(info_span!(AWS_MAPPER_NAME))
+            .await?;
+
+        Ok(())
+    }
+}
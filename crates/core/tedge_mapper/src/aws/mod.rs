@@ -0,0 +1,2 @@
+mod converter;
+pub mod mapper;
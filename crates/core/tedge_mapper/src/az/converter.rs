@@ -1,8 +1,15 @@
-use crate::core::{converter::*, error::*, size_threshold::SizeThreshold};
+use crate::az::mapper::AZURE_MAPPER_NAME;
+use crate::core::{
+    converter::*,
+    error::*,
+    inventory::{InventoryFragment, InventoryUpdates, INVENTORY_TOPIC},
+    measurement_policies::MeasurementPolicies,
+    size_threshold::SizeThreshold,
+};
 
 use async_trait::async_trait;
 use clock::Clock;
-use mqtt_channel::{Message, TopicFilter};
+use mqtt_channel::{Message, Topic, TopicFilter};
 use thin_edge_json::serialize::ThinEdgeJsonSerializer;
 
 pub struct AzureConverter {
@@ -10,11 +17,15 @@ pub struct AzureConverter {
     pub(crate) clock: Box<dyn Clock>,
     pub(crate) size_threshold: SizeThreshold,
     pub(crate) mapper_config: MapperConfig,
+    measurement_policies: MeasurementPolicies,
+    reported_properties: InventoryUpdates,
+    twin_request_id: u64,
 }
 
 impl AzureConverter {
     pub fn new(add_timestamp: bool, clock: Box<dyn Clock>, size_threshold: SizeThreshold) -> Self {
         let mapper_config = MapperConfig {
+            mapper_name: AZURE_MAPPER_NAME.to_string(),
             in_topic_filter: Self::in_topic_filter(),
             out_topic: make_valid_topic_or_panic("az/messages/events/"),
             errors_topic: make_valid_topic_or_panic("tedge/errors"),
@@ -24,11 +35,74 @@ impl AzureConverter {
             clock,
             size_threshold,
             mapper_config,
+            measurement_policies: MeasurementPolicies::default(),
+            reported_properties: InventoryUpdates::default(),
+            twin_request_id: 0,
+        }
+    }
+
+    /// Rate limit, deduplicate or downsample the measurements sent to Azure
+    pub fn with_measurement_policies(self, measurement_policies: MeasurementPolicies) -> Self {
+        AzureConverter {
+            measurement_policies,
+            ..self
         }
     }
 
     pub fn in_topic_filter() -> TopicFilter {
-        make_valid_topic_filter_or_panic("tedge/measurements")
+        let mut topic_filter = make_valid_topic_filter_or_panic("tedge/measurements");
+        // Only the inventory fragments of the main device are mapped, child devices having no twin on Azure
+        topic_filter.add_unchecked("tedge/inventory/+");
+        topic_filter
+    }
+
+    /// Register the inventory fragment to be sent on the next flush as a reported property of the device twin
+    fn convert_inventory_fragment(
+        &mut self,
+        input: &Message,
+    ) -> Result<Vec<Message>, ConversionError> {
+        let fragment = InventoryFragment::try_from(input)?;
+        if fragment.child_id().is_some() {
+            return Err(ConversionError::UnsupportedTopic(input.topic.name.clone()));
+        }
+        self.reported_properties.add(fragment);
+        Ok(vec![])
+    }
+
+    /// The reported properties of the device twin updated since the last flush
+    fn flush_reported_properties(&mut self) -> Vec<Message> {
+        let mut messages = vec![];
+        for (_, properties) in self.reported_properties.flush() {
+            self.twin_request_id += 1;
+            let topic = Topic::new_unchecked(&format!(
+                "az/twin/PATCH/properties/reported/?$rid={}",
+                self.twin_request_id
+            ));
+            messages.push(Message::new(&topic, properties.to_string()));
+        }
+        messages
+    }
+
+    fn convert_measurement(&self, input: &Message) -> Result<Vec<Message>, ConversionError> {
+        let default_timestamp = self.add_timestamp.then(|| self.clock.now());
+        let mut serializer = ThinEdgeJsonSerializer::new_with_timestamp(default_timestamp);
+        thin_edge_json::parser::parse_str(input.payload_str()?, &mut serializer)?;
+
+        let payload = serializer.into_string()?;
+        if payload.len() <= self.size_threshold.0 {
+            return Ok(vec![(Message::new(&self.mapper_config.out_topic, payload))]);
+        }
+
+        // Oversized measurements are split, all the parts being given the same timestamp
+        let payloads = self.size_threshold.split_measurements(input, |group| {
+            let mut serializer = ThinEdgeJsonSerializer::new_with_timestamp(default_timestamp);
+            group.accept(&mut serializer)?;
+            Ok(serializer.into_string()?)
+        })?;
+        Ok(payloads
+            .into_iter()
+            .map(|payload| Message::new(&self.mapper_config.out_topic, payload))
+            .collect())
     }
 }
 
@@ -41,13 +115,23 @@ impl Converter for AzureConverter {
     }
 
     async fn try_convert(&mut self, input: &Message) -> Result<Vec<Message>, Self::Error> {
-        self.size_threshold.validate(input)?;
-        let default_timestamp = self.add_timestamp.then(|| self.clock.now());
-        let mut serializer = ThinEdgeJsonSerializer::new_with_timestamp(default_timestamp);
-        thin_edge_json::parser::parse_str(input.payload_str()?, &mut serializer)?;
+        if input.topic.name.starts_with(INVENTORY_TOPIC) {
+            return self.convert_inventory_fragment(input);
+        }
 
-        let payload = serializer.into_string()?;
-        Ok(vec![(Message::new(&self.mapper_config.out_topic, payload))])
+        match self.measurement_policies.apply(input, self.clock.now())? {
+            Some(measurements) => self.convert_measurement(&measurements),
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn try_flush_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+        let mut messages = vec![];
+        for measurements in self.measurement_policies.flush(self.clock.now())? {
+            messages.append(&mut self.convert_measurement(&measurements)?);
+        }
+        messages.append(&mut self.flush_reported_properties());
+        Ok(messages)
     }
 }
 
@@ -55,7 +139,12 @@ impl Converter for AzureConverter {
 mod tests {
     use crate::{
         az::converter::AzureConverter,
-        core::{converter::*, error::ConversionError, size_threshold::SizeThreshold},
+        core::{
+            converter::*,
+            error::ConversionError,
+            measurement_policies::{MeasurementPolicies, POLICIES_DIR},
+            size_threshold::SizeThreshold,
+        },
     };
 
     use assert_json_diff::*;
@@ -63,6 +152,7 @@ mod tests {
     use clock::Clock;
     use mqtt_channel::{Message, Topic};
     use serde_json::json;
+    use tedge_test_utils::fs::TempTedgeDir;
     use time::macros::datetime;
 
     struct TestClock;
@@ -190,20 +280,92 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn exceeding_threshold_returns_error() {
-        let mut converter = AzureConverter::new(false, Box::new(TestClock), SizeThreshold(1));
+    async fn exceeding_threshold_splits_the_measurements() {
+        let mut converter = AzureConverter::new(true, Box::new(TestClock), SizeThreshold(60));
+
+        let input = r#"{"temperature": 23.0, "pressure": 220.0}"#;
+        let output = converter
+            .try_convert(&new_tedge_message(input))
+            .await
+            .unwrap();
+
+        let payloads: Vec<&str> = output
+            .iter()
+            .map(|message| message.payload_str().unwrap())
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![
+                r#"{"pressure":220.0,"time":"2021-04-08T00:00:00+05:00"}"#,
+                r#"{"temperature":23.0,"time":"2021-04-08T00:00:00+05:00"}"#,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_single_measurement_exceeding_threshold_returns_error() {
+        let mut converter = AzureConverter::new(false, Box::new(TestClock), SizeThreshold(10));
 
-        let _topic = "tedge/measurements".to_string();
-        let input = "ABC";
+        let input = r#"{"temperature": 23.0}"#;
         let result = converter.try_convert(&new_tedge_message(input)).await;
 
         assert_matches!(
             result,
-            Err(ConversionError::SizeThresholdExceeded {
-                topic: _topic,
-                actual_size: 3,
-                threshold: 1
-            })
+            Err(ConversionError::TranslatedSizeExceededThreshold { threshold: 10, .. })
+        );
+    }
+
+    #[tokio::test]
+    async fn measurements_are_rate_limited_by_the_policies() {
+        let ttd = TempTedgeDir::new();
+        ttd.dir(POLICIES_DIR)
+            .file("tedge-mapper-az.toml")
+            .with_raw_content("[[policy]]\nmin_interval_seconds = 60");
+        let policies = MeasurementPolicies::load(ttd.path(), "tedge-mapper-az").unwrap();
+        let mut converter =
+            AzureConverter::new(false, Box::new(TestClock), SizeThreshold(255 * 1024))
+                .with_measurement_policies(policies);
+
+        let first = converter
+            .try_convert(&new_tedge_message(r#"{"temperature": 23.0}"#))
+            .await
+            .unwrap();
+        let second = converter
+            .try_convert(&new_tedge_message(r#"{"temperature": 24.0}"#))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            extract_first_message_payload(first),
+            r#"{"temperature":23.0}"#
+        );
+        assert_eq!(second, vec![]);
+    }
+
+    #[tokio::test]
+    async fn inventory_fragments_are_reported_as_twin_properties_on_flush() {
+        let mut converter =
+            AzureConverter::new(false, Box::new(TestClock), SizeThreshold(255 * 1024));
+
+        for (topic, payload) in [
+            ("tedge/inventory/firmware", r#"{"version": "1.0"}"#),
+            ("tedge/inventory/serial", r#""ABC-123""#),
+            ("tedge/inventory/firmware", r#"{"version": "1.1"}"#),
+        ] {
+            let input = Message::new(&Topic::new_unchecked(topic), payload);
+            assert_eq!(converter.try_convert(&input).await.unwrap(), vec![]);
+        }
+
+        let messages = converter.try_flush_messages().await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].topic.name,
+            "az/twin/PATCH/properties/reported/?$rid=1"
+        );
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(messages[0].payload_str().unwrap()).unwrap(),
+            json!({"firmware": {"version": "1.1"}, "serial": "ABC-123"})
         );
+        assert_eq!(converter.try_flush_messages().await.unwrap(), vec![]);
     }
 }
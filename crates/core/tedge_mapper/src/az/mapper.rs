@@ -2,17 +2,27 @@ use std::path::Path;
 
 use crate::{
     az::converter::AzureConverter,
-    core::{component::TEdgeComponent, mapper::create_mapper, size_threshold::SizeThreshold},
+    core::{
+        component::TEdgeComponent,
+        mapper::create_store_and_forward_mapper,
+        measurement_policies::{MeasurementPolicies, POLICIES_DIR},
+        metrics_endpoint::MetricsEndpoint,
+        script_hook::{with_script_hook, SCRIPTS_DIR},
+        size_threshold::SizeThreshold,
+        store_and_forward::StoreAndForward,
+    },
 };
 
 use async_trait::async_trait;
 use clock::WallClock;
-use tedge_config::{AzureMapperTimestamp, MqttBindAddressSetting, TEdgeConfig};
-use tedge_config::{ConfigSettingAccessor, MqttPortSetting};
+use mqtt_channel::TopicFilter;
+use tedge_config::ConfigSettingAccessor;
+use tedge_config::{AzureMapperTimestamp, DataPathSetting, TEdgeConfig};
 use tedge_utils::file::create_directory_with_user_group;
 use tracing::{info, info_span, Instrument};
 
-const AZURE_MAPPER_NAME: &str = "tedge-mapper-az";
+pub(crate) const AZURE_MAPPER_NAME: &str = "tedge-mapper-az";
+const AZURE_BRIDGE_HEALTH_TOPIC: &str = "tedge/health/mosquitto-az-bridge";
 
 pub struct AzureMapper {}
 
@@ -36,6 +46,8 @@ impl TEdgeComponent for AzureMapper {
             "tedge",
             0o775,
         )?;
+        create_directory_with_user_group(config_dir.join(SCRIPTS_DIR), "tedge", "tedge", 0o775)?;
+        create_directory_with_user_group(config_dir.join(POLICIES_DIR), "tedge", "tedge", 0o775)?;
 
         self.init_session(AzureConverter::in_topic_filter()).await?;
         Ok(())
@@ -44,17 +56,39 @@ impl TEdgeComponent for AzureMapper {
     async fn start(
         &self,
         tedge_config: TEdgeConfig,
-        _config_dir: &Path,
+        config_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
     ) -> Result<(), anyhow::Error> {
         let add_timestamp = tedge_config.query(AzureMapperTimestamp)?.is_set();
-        let mqtt_port = tedge_config.query(MqttPortSetting)?.into();
-        let mqtt_host = tedge_config.query(MqttBindAddressSetting)?.to_string();
+        let mqtt_config = tedge_config.mqtt_config()?;
         let clock = Box::new(WallClock);
         let size_threshold = SizeThreshold(255 * 1024);
 
-        let converter = Box::new(AzureConverter::new(add_timestamp, clock, size_threshold));
+        let measurement_policies = MeasurementPolicies::load(config_dir, AZURE_MAPPER_NAME)?;
 
-        let mut mapper = create_mapper(AZURE_MAPPER_NAME, mqtt_host, mqtt_port, converter).await?;
+        let converter = Box::new(
+            AzureConverter::new(add_timestamp, clock, size_threshold)
+                .with_measurement_policies(measurement_policies),
+        );
+        let converter = with_script_hook(converter, AZURE_MAPPER_NAME, config_dir)?;
+
+        let store_and_forward = StoreAndForward::open(
+            AZURE_MAPPER_NAME,
+            tedge_config.query(DataPathSetting)?.as_ref(),
+            AZURE_BRIDGE_HEALTH_TOPIC,
+            TopicFilter::new("az/#")?,
+        )
+        .await?;
+
+        let mut mapper = create_store_and_forward_mapper(
+            AZURE_MAPPER_NAME,
+            mqtt_config,
+            converter,
+            store_and_forward,
+        )
+        .await?
+        .with_mapper_settings(&tedge_config)?;
+        metrics_endpoint.register(AZURE_MAPPER_NAME, mapper.metrics());
 
         mapper
             .run(None)
@@ -1,5 +1,6 @@
 use std::collections::{hash_map::Entry, HashMap};
 
+use c8y_api::json_c8y::C8yCreateAlarm;
 use c8y_smartrest::alarm;
 use mqtt_channel::{Message, Topic};
 use thin_edge_json::alarm::ThinEdgeAlarm;
@@ -9,6 +10,7 @@ use crate::core::error::ConversionError;
 const TEDGE_ALARMS_TOPIC: &str = "tedge/alarms/";
 const INTERNAL_ALARMS_TOPIC: &str = "c8y-internal/alarms/";
 const SMARTREST_PUBLISH_TOPIC: &str = "c8y/s/us";
+const C8Y_JSON_MQTT_ALARMS_TOPIC: &str = "c8y/alarm/alarms/create";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum AlarmConverter {
@@ -51,12 +53,26 @@ impl AlarmConverter {
                     input_message.topic.name.as_str(),
                     input_message.payload_str()?,
                 )?;
-                let smartrest_alarm = alarm::serialize_alarm(tedge_alarm)?;
-                let c8y_alarm_topic = Topic::new_unchecked(
-                    self.get_c8y_alarm_topic(input_message.topic.name.as_str())?
-                        .as_str(),
-                );
-                output_messages.push(Message::new(&c8y_alarm_topic, smartrest_alarm));
+                let has_extras = tedge_alarm
+                    .data
+                    .as_ref()
+                    .map_or(false, |data| !data.extras.is_empty());
+                if has_extras {
+                    // If the alarm contains fields other than `text` and `time`, convert to Cumulocity JSON
+                    let c8y_alarm = C8yCreateAlarm::try_from(tedge_alarm)?;
+                    let json_mqtt_topic = Topic::new_unchecked(C8Y_JSON_MQTT_ALARMS_TOPIC);
+                    output_messages.push(Message::new(
+                        &json_mqtt_topic,
+                        serde_json::to_string(&c8y_alarm)?,
+                    ));
+                } else {
+                    let smartrest_alarm = alarm::serialize_alarm(tedge_alarm)?;
+                    let c8y_alarm_topic = Topic::new_unchecked(
+                        self.get_c8y_alarm_topic(input_message.topic.name.as_str())?
+                            .as_str(),
+                    );
+                    output_messages.push(Message::new(&c8y_alarm_topic, smartrest_alarm));
+                }
 
                 // Persist a copy of the alarm to an internal topic for reconciliation on next restart
                 let alarm_id = input_message
@@ -79,8 +95,12 @@ impl AlarmConverter {
         let topic_split: Vec<&str> = topic.split('/').collect();
         if topic_split.len() == 4 {
             Ok(SMARTREST_PUBLISH_TOPIC.to_string())
-        } else if topic_split.len() == 5 {
-            Ok(format!("{SMARTREST_PUBLISH_TOPIC}/{}", topic_split[4]))
+        } else if topic_split.len() >= 5 {
+            // The alarm is for a child device, possibly nested under other child devices
+            Ok(format!(
+                "{SMARTREST_PUBLISH_TOPIC}/{}",
+                topic_split[4..].join("/")
+            ))
         } else {
             Err(ConversionError::UnsupportedTopic(topic.to_string()))
         }
@@ -1,5 +1,12 @@
 use crate::c8y::dynamic_discovery::*;
-use crate::core::{converter::*, error::*, size_threshold::SizeThreshold};
+use crate::core::{
+    converter::*,
+    error::*,
+    inventory::{InventoryFragment, InventoryUpdates},
+    measurement_policies::MeasurementPolicies,
+    size_threshold::SizeThreshold,
+    sync_journal::SyncJournal,
+};
 use agent_interface::{
     topic::{RequestTopic, ResponseTopic},
     Auth, DownloadInfo, Jsonify, OperationStatus, RestartOperationRequest,
@@ -22,15 +29,17 @@ use c8y_smartrest::{
     },
 };
 use c8y_translator::json;
+use clock::Clock;
 
 use logged_command::LoggedCommand;
-use mqtt_channel::{Message, Topic, TopicFilter};
+use mqtt_channel::{Captures, Message, MqttError, Router, SubscriptionHandle, Topic, TopicFilter};
 use plugin_sm::operation_logs::OperationLogs;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs::File,
     io::Read,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use tedge_config::{get_tedge_config, ConfigSettingAccessor, LogPathSetting};
 use thin_edge_json::event::ThinEdgeEvent;
@@ -42,42 +51,54 @@ use super::alarm_converter::AlarmConverter;
 use super::{
     error::CumulocityMapperError,
     fragments::{C8yAgentFragment, C8yDeviceDataFragment},
-    mapper::CumulocityMapper,
+    mapper::{CumulocityMapper, CUMULOCITY_MAPPER_NAME},
     topic::{C8yTopic, MapperSubscribeTopic},
 };
 
 const C8Y_CLOUD: &str = "c8y";
 const INVENTORY_FRAGMENTS_FILE_LOCATION: &str = "device/inventory.json";
 const SUPPORTED_OPERATIONS_DIRECTORY: &str = "operations";
-const INVENTORY_MANAGED_OBJECTS_TOPIC: &str = "c8y/inventory/managedObjects/update";
+const INVENTORY_MANAGED_OBJECTS_TOPIC: &str = "c8y/inventory/managedObjects/update/";
 const SMARTREST_PUBLISH_TOPIC: &str = "c8y/s/us";
-const INTERNAL_ALARMS_TOPIC: &str = "c8y-internal/alarms/";
+const INTERNAL_CHILDREN_TOPIC: &str = "c8y-internal/children/";
 const TEDGE_EVENTS_TOPIC: &str = "tedge/events/";
+const INTERNAL_EVENTS_TOPIC: &str = "c8y-internal/events/";
+const INTERNAL_INVENTORY_TOPIC: &str = "c8y-internal/inventory/";
 const C8Y_JSON_MQTT_EVENTS_TOPIC: &str = "c8y/event/events/create";
 const TEDGE_AGENT_LOG_DIR: &str = "tedge/agent";
 
 const CREATE_EVENT_SMARTREST_CODE: u16 = 400;
 
-#[derive(Debug)]
+type ConverterRouter<Proxy> =
+    Router<CumulocityConverter<Proxy>, Result<Vec<Message>, ConversionError>>;
+
 pub struct CumulocityConverter<Proxy>
 where
     Proxy: C8YHttpProxy,
 {
     pub(crate) size_threshold: SizeThreshold,
-    children: HashSet<String>,
+    /// The path of each registered child device, indexed by child id
+    children: HashMap<String, String>,
     pub(crate) mapper_config: MapperConfig,
     device_name: String,
     device_type: String,
     alarm_converter: AlarmConverter,
+    events_journal: SyncJournal,
+    inventory_journal: SyncJournal,
+    inventory_updates: InventoryUpdates,
     pub operations: Operations,
     operation_logs: OperationLogs,
     http_proxy: Proxy,
     cfg_dir: PathBuf,
+    subscriptions: Option<SubscriptionHandle>,
+    router: Arc<ConverterRouter<Proxy>>,
+    measurement_policies: MeasurementPolicies,
+    clock: Box<dyn Clock>,
 }
 
 impl<Proxy> CumulocityConverter<Proxy>
 where
-    Proxy: C8YHttpProxy,
+    Proxy: C8YHttpProxy + 'static,
 {
     pub fn new(
         size_threshold: SizeThreshold,
@@ -86,23 +107,14 @@ where
         operations: Operations,
         http_proxy: Proxy,
         cfg_dir: &Path,
+        clock: Box<dyn Clock>,
     ) -> Result<Self, CumulocityMapperError> {
-        let mut topic_filter: TopicFilter = vec![
-            "tedge/measurements",
-            "tedge/measurements/+",
-            "tedge/alarms/+/+",
-            "tedge/alarms/+/+/+",
-            "c8y-internal/alarms/+/+",
-            "c8y-internal/alarms/+/+/+",
-            "tedge/events/+",
-            "tedge/events/+/+",
-        ]
-        .try_into()
-        .expect("topics that mapper should subscribe to");
-
+        let router = Self::router()?;
+        let mut topic_filter = router.topic_filter();
         topic_filter.add_all(CumulocityMapper::subscriptions(&operations).unwrap());
 
         let mapper_config = MapperConfig {
+            mapper_name: CUMULOCITY_MAPPER_NAME.to_string(),
             in_topic_filter: topic_filter,
             out_topic: make_valid_topic_or_panic("c8y/measurement/measurements/create"),
             errors_topic: make_valid_topic_or_panic("tedge/errors"),
@@ -110,7 +122,7 @@ where
 
         let alarm_converter = AlarmConverter::new();
 
-        let children: HashSet<String> = HashSet::new();
+        let children: HashMap<String, String> = HashMap::new();
 
         let tedge_config = get_tedge_config()?;
         let logs_path = tedge_config.query(LogPathSetting)?;
@@ -126,10 +138,20 @@ where
             device_name,
             device_type,
             alarm_converter,
+            events_journal: SyncJournal::new(TEDGE_EVENTS_TOPIC, INTERNAL_EVENTS_TOPIC),
+            inventory_journal: SyncJournal::new(
+                INVENTORY_MANAGED_OBJECTS_TOPIC,
+                INTERNAL_INVENTORY_TOPIC,
+            ),
+            inventory_updates: InventoryUpdates::default(),
             operations,
             operation_logs,
             http_proxy,
             cfg_dir: cfg_dir.to_path_buf(),
+            subscriptions: None,
+            router: Arc::new(router),
+            measurement_policies: MeasurementPolicies::default(),
+            clock,
         })
     }
 
@@ -142,22 +164,12 @@ where
         http_proxy: Proxy,
         logs_path: PathBuf,
     ) -> Result<Self, CumulocityMapperError> {
-        let mut topic_filter: TopicFilter = vec![
-            "tedge/measurements",
-            "tedge/measurements/+",
-            "tedge/alarms/+/+",
-            "tedge/alarms/+/+/+",
-            "c8y-internal/alarms/+/+",
-            "c8y-internal/alarms/+/+/+",
-            "tedge/events/+",
-            "tedge/events/+/+",
-        ]
-        .try_into()
-        .expect("topics that mapper should subscribe to");
-
+        let router = Self::router()?;
+        let mut topic_filter = router.topic_filter();
         topic_filter.add_all(CumulocityMapper::subscriptions(&operations).unwrap());
 
         let mapper_config = MapperConfig {
+            mapper_name: CUMULOCITY_MAPPER_NAME.to_string(),
             in_topic_filter: topic_filter,
             out_topic: make_valid_topic_or_panic("c8y/measurement/measurements/create"),
             errors_topic: make_valid_topic_or_panic("tedge/errors"),
@@ -165,7 +177,7 @@ where
 
         let alarm_converter = AlarmConverter::new();
 
-        let children: HashSet<String> = HashSet::new();
+        let children: HashMap<String, String> = HashMap::new();
 
         let log_dir = PathBuf::from(&format!(
             "{}/{TEDGE_AGENT_LOG_DIR}",
@@ -181,64 +193,272 @@ where
             device_name,
             device_type,
             alarm_converter,
+            events_journal: SyncJournal::new(TEDGE_EVENTS_TOPIC, INTERNAL_EVENTS_TOPIC),
+            inventory_journal: SyncJournal::new(
+                INVENTORY_MANAGED_OBJECTS_TOPIC,
+                INTERNAL_INVENTORY_TOPIC,
+            ),
+            inventory_updates: InventoryUpdates::default(),
             operations,
             operation_logs,
             http_proxy,
             cfg_dir: Path::new("cfg_dir").to_path_buf(),
+            subscriptions: None,
+            router: Arc::new(router),
+            measurement_policies: MeasurementPolicies::default(),
+            clock: Box::new(clock::WallClock),
         })
     }
 
-    fn try_convert_measurement(
+    /// Rate limit, deduplicate or downsample the measurements sent to Cumulocity
+    pub fn with_measurement_policies(self, measurement_policies: MeasurementPolicies) -> Self {
+        CumulocityConverter {
+            measurement_policies,
+            ..self
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_clock(self, clock: Box<dyn Clock>) -> Self {
+        CumulocityConverter { clock, ..self }
+    }
+
+    /// The routes of the thin-edge messages converted to Cumulocity
+    ///
+    /// The path of the child device a message is for is given by the `{child}` level
+    /// and the levels of its nested children, if any, matched by the trailing `#`.
+    fn router() -> Result<ConverterRouter<Proxy>, MqttError> {
+        ConverterRouter::<Proxy>::new()
+            .route("tedge/measurements", |converter, message, _| {
+                Box::pin(converter.try_convert_measurement(message, vec![]))
+            })?
+            .route(
+                "tedge/measurements/{child}/#",
+                |converter, message, captures| {
+                    Box::pin(async move {
+                        let child_path = child_device_path(captures)?;
+                        converter.try_convert_measurement(message, child_path).await
+                    })
+                },
+            )?
+            .route(
+                "tedge/alarms/{severity}/{alarm_type}",
+                |converter, message, _| {
+                    Box::pin(async move { converter.try_convert_alarm(message, vec![]) })
+                },
+            )?
+            .route(
+                "tedge/alarms/{severity}/{alarm_type}/{child}/#",
+                |converter, message, captures| {
+                    Box::pin(async move {
+                        let child_path = child_device_path(captures)?;
+                        converter.try_convert_alarm(message, child_path)
+                    })
+                },
+            )?
+            .route(
+                "c8y-internal/alarms/{severity}/{alarm_type}",
+                |converter, message, _| {
+                    Box::pin(async move { converter.process_internal_alarm(message) })
+                },
+            )?
+            .route(
+                "c8y-internal/alarms/{severity}/{alarm_type}/{child}/#",
+                |converter, message, _| {
+                    Box::pin(async move { converter.process_internal_alarm(message) })
+                },
+            )?
+            .route("tedge/events/{event_type}", |converter, message, _| {
+                Box::pin(converter.try_convert_event(message, vec![]))
+            })?
+            .route(
+                "tedge/events/{event_type}/{child}/#",
+                |converter, message, captures| {
+                    Box::pin(async move {
+                        let child_path = child_device_path(captures)?;
+                        converter.try_convert_event(message, child_path).await
+                    })
+                },
+            )?
+            .route(
+                "c8y-internal/events/{event_type}",
+                |converter, message, _| {
+                    Box::pin(async move { converter.record_events_journal_entry(message) })
+                },
+            )?
+            .route(
+                "c8y-internal/events/{event_type}/{child}/#",
+                |converter, message, _| {
+                    Box::pin(async move { converter.record_events_journal_entry(message) })
+                },
+            )?
+            .route(
+                "tedge/inventory/{fragment}",
+                |converter, message, captures| {
+                    Box::pin(async move {
+                        let levels = captures.get("fragment").into_iter().collect();
+                        converter.process_inventory_fragment(message, levels)
+                    })
+                },
+            )?
+            .route(
+                "tedge/inventory/{child}/#",
+                |converter, message, captures| {
+                    Box::pin(async move {
+                        // The levels are the path of the child device followed by the fragment name
+                        let levels = captures
+                            .get("child")
+                            .into_iter()
+                            .chain(captures.remaining_levels())
+                            .collect();
+                        converter.process_inventory_fragment(message, levels)
+                    })
+                },
+            )?
+            .route(
+                "c8y-internal/children/{child}/#",
+                |converter, _, captures| {
+                    Box::pin(async move {
+                        let child_path = child_device_path(captures)?;
+                        converter.process_internal_child(child_path)
+                    })
+                },
+            )?
+            .route(
+                "c8y-internal/inventory/{device}",
+                |converter, message, _| {
+                    Box::pin(async move { converter.record_inventory_journal_entry(message) })
+                },
+            )
+    }
+
+    /// Subscribe to the topics of the new operations and unsubscribe from those no more used by any operation.
+    fn update_operation_subscriptions(
+        &self,
+        previous_topics: &HashSet<String>,
+    ) -> Result<(), ConversionError> {
+        if let Some(subscriptions) = &self.subscriptions {
+            let current_topics = self.operations.topics_for_operations();
+            // The topics subscribed by the mapper independently of any operation are kept
+            let mapper_topics = CumulocityMapper::subscriptions(&Operations::default())
+                .expect("topics that mapper should subscribe to");
+
+            for topic in current_topics.difference(previous_topics) {
+                subscriptions.subscribe(TopicFilter::new(topic)?)?;
+            }
+            for topic in previous_topics.difference(&current_topics) {
+                if !mapper_topics.patterns.contains(topic) {
+                    subscriptions.unsubscribe(TopicFilter::new(topic)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert the measurements sent according to the measurement policies into c8y json messages
+    async fn try_convert_measurement(
         &mut self,
         input: &Message,
+        child_path: Vec<&str>,
+    ) -> Result<Vec<Message>, ConversionError> {
+        match self.measurement_policies.apply(input, self.clock.now())? {
+            Some(measurements) => self.convert_measurement(&measurements, &child_path).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Convert the measurements into c8y json messages,
+    /// splitting the measurements that would be translated into messages over the size threshold.
+    ///
+    /// A single measurement that is still too large to be sent over MQTT is sent over HTTP.
+    async fn convert_measurement(
+        &mut self,
+        input: &Message,
+        child_path: &[&str],
     ) -> Result<Vec<Message>, ConversionError> {
         let mut vec: Vec<Message> = Vec::new();
 
-        let maybe_child_id = get_child_id_from_measurement_topic(&input.topic.name)?;
-        let c8y_json_payload = match maybe_child_id {
+        let maybe_child_id = child_path.last().map(|child_id| child_id.to_string());
+        let c8y_json_payload = match &maybe_child_id {
             Some(child_id) => {
                 // Need to check if the input Thin Edge JSON is valid before adding a child ID to list
                 let c8y_json_child_payload =
                     json::from_thin_edge_json_with_child(input.payload_str()?, child_id.as_str())?;
 
-                if !self.children.contains(child_id.as_str()) {
-                    self.children.insert(child_id.clone());
-                    vec.push(Message::new(
-                        &Topic::new_unchecked(SMARTREST_PUBLISH_TOPIC),
-                        format!("101,{child_id},{child_id},thin-edge.io-child"),
-                    ));
-                }
+                vec.append(&mut self.register_child_devices(child_path)?);
                 c8y_json_child_payload
             }
             None => json::from_thin_edge_json(input.payload_str()?)?,
         };
 
-        if c8y_json_payload.len() < self.size_threshold.0 {
+        if c8y_json_payload.len() <= self.size_threshold.0 {
             vec.push(Message::new(
                 &self.mapper_config.out_topic,
                 c8y_json_payload,
             ));
-        } else {
-            return Err(ConversionError::TranslatedSizeExceededThreshold {
-                payload: input.payload_str()?[0..50].into(),
-                topic: input.topic.name.clone(),
-                actual_size: c8y_json_payload.len(),
-                threshold: self.size_threshold.0,
-            });
+            return Ok(vec);
+        }
+
+        // All the parts of the measurements are given the same default timestamp
+        let default_timestamp = self.clock.now();
+        let split = self.size_threshold.split_measurements(input, |group| {
+            Ok(json::from_measurement_group(
+                group,
+                default_timestamp,
+                maybe_child_id.as_deref(),
+            )?)
+        });
+        match split {
+            Ok(payloads) => {
+                for payload in payloads {
+                    vec.push(Message::new(&self.mapper_config.out_topic, payload));
+                }
+            }
+            Err(ConversionError::TranslatedSizeExceededThreshold { .. }) => {
+                let c8y_measurement = serde_json::from_str(&c8y_json_payload)?;
+                self.http_proxy
+                    .send_measurement(c8y_measurement, maybe_child_id)
+                    .await?;
+            }
+            Err(err) => return Err(err),
         }
         Ok(vec)
     }
 
+    /// Record an event already sent to Cumulocity, as persisted by the broker across restarts
+    fn record_events_journal_entry(
+        &mut self,
+        entry: &Message,
+    ) -> Result<Vec<Message>, ConversionError> {
+        self.events_journal.record(entry);
+        Ok(vec![])
+    }
+
+    /// Record an inventory fragment already sent to Cumulocity, as persisted by the broker across restarts
+    fn record_inventory_journal_entry(
+        &mut self,
+        entry: &Message,
+    ) -> Result<Vec<Message>, ConversionError> {
+        self.inventory_journal.record(entry);
+        Ok(vec![])
+    }
+
     async fn try_convert_event(
         &mut self,
         input: &Message,
+        child_path: Vec<&str>,
     ) -> Result<Vec<Message>, ConversionError> {
+        // Retained events already sent to Cumulocity before a restart are not sent twice
+        if self.events_journal.hold(input) {
+            return Ok(vec![]);
+        }
+
         let mut messages = Vec::new();
 
         let tedge_event = ThinEdgeEvent::try_from(&input.topic.name, input.payload_str()?)?;
         let child_id = tedge_event.source.clone();
 
-        let need_registration = self.register_external_device(&tedge_event, &mut messages);
+        let need_registration = self.register_external_device(&child_path, &mut messages)?;
 
         let c8y_event = C8yCreateEvent::try_from(tedge_event)?;
 
@@ -260,48 +480,41 @@ where
         } else if !need_registration {
             // The message must be sent over HTTP
             let _ = self.http_proxy.send_event(c8y_event).await?;
-            return Ok(vec![]);
+            return Ok(self.events_journal.entry(input).into_iter().collect());
         } else {
             // The message should be sent over HTTP but this cannot be done
             return Err(ConversionError::ChildDeviceNotRegistered {
                 id: child_id.unwrap_or_else(|| "".into()),
             });
         }
+        messages.extend(self.events_journal.entry(input));
         Ok(messages)
     }
 
-    pub fn process_alarm_messages(
+    fn try_convert_alarm(
         &mut self,
-        topic: &Topic,
         message: &Message,
+        child_path: Vec<&str>,
     ) -> Result<Vec<Message>, ConversionError> {
-        if topic.name.starts_with("tedge/alarms") {
-            let mut mqtt_messages: Vec<Message> = Vec::new();
-            self.size_threshold.validate(message)?;
-            let mut messages = self.alarm_converter.try_convert_alarm(message)?;
-            if !messages.is_empty() {
-                // When there is some messages to be sent on behalf of a child device,
-                // this child device must be declared first, if not done yet
-                let topic_split: Vec<&str> = topic.name.split('/').collect();
-                if topic_split.len() == 5 {
-                    let child_id = topic_split[4];
-                    if !child_id.is_empty() && !self.children.contains(child_id) {
-                        self.children.insert(child_id.to_string());
-                        mqtt_messages.push(Message::new(
-                            &Topic::new_unchecked(SMARTREST_PUBLISH_TOPIC),
-                            format!("101,{child_id},{child_id},thin-edge.io-child"),
-                        ));
-                    }
-                }
-            }
-            mqtt_messages.append(&mut messages);
-            Ok(mqtt_messages)
-        } else if topic.name.starts_with(INTERNAL_ALARMS_TOPIC) {
-            self.alarm_converter.process_internal_alarm(message);
-            Ok(vec![])
-        } else {
-            Err(ConversionError::UnsupportedTopic(topic.name.clone()))
+        let mut mqtt_messages: Vec<Message> = Vec::new();
+        self.size_threshold.validate(message)?;
+        let mut messages = self.alarm_converter.try_convert_alarm(message)?;
+        if !messages.is_empty() {
+            // When there is some messages to be sent on behalf of a child device,
+            // this child device and its parents must be declared first, if not done yet
+            mqtt_messages.append(&mut self.register_child_devices(&child_path)?);
         }
+        mqtt_messages.append(&mut messages);
+        Ok(mqtt_messages)
+    }
+
+    /// Record an alarm already sent to Cumulocity, as persisted by the broker across restarts
+    fn process_internal_alarm(
+        &mut self,
+        message: &Message,
+    ) -> Result<Vec<Message>, ConversionError> {
+        self.alarm_converter.process_internal_alarm(message);
+        Ok(vec![])
     }
 
     fn serialize_to_smartrest(c8y_event: &C8yCreateEvent) -> Result<String, ConversionError> {
@@ -316,32 +529,112 @@ where
 
     fn register_external_device(
         &mut self,
-        tedge_event: &ThinEdgeEvent,
+        child_path: &[&str],
         messages: &mut Vec<Message>,
-    ) -> bool {
-        if let Some(c_id) = tedge_event.source.clone() {
-            // Create the external source if it does not exists
-            if !self.children.contains(&c_id) {
-                self.children.insert(c_id.clone());
+    ) -> Result<bool, ConversionError> {
+        // Create the external source and its parents if they do not exist
+        let mut registration = self.register_child_devices(child_path)?;
+        let need_registration = !registration.is_empty();
+        messages.append(&mut registration);
+        Ok(need_registration)
+    }
+
+    /// Register on Cumulocity the child devices along the given path, if not done yet,
+    /// each child device being registered under its parent.
+    ///
+    /// Along the registration messages, a retained copy is persisted on an internal topic,
+    /// so the child devices already registered are known on the next restart.
+    ///
+    /// The child ids being global to the Cumulocity tenant,
+    /// a child device cannot be registered under two different parents.
+    fn register_child_devices(
+        &mut self,
+        child_path: &[&str],
+    ) -> Result<Vec<Message>, ConversionError> {
+        for (depth, child_id) in child_path.iter().enumerate() {
+            let path = child_path[..=depth].join("/");
+            match self.children.get(*child_id) {
+                Some(registered_path) if *registered_path != path => {
+                    return Err(ConversionError::ChildDeviceRegisteredUnderAnotherParent {
+                        id: child_id.to_string(),
+                        registered_path: registered_path.clone(),
+                        path,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        let mut messages = vec![];
+        for (depth, child_id) in child_path.iter().enumerate() {
+            let path = child_path[..=depth].join("/");
+            if self.children.insert(child_id.to_string(), path).is_none() {
                 messages.push(Message::new(
-                    &Topic::new_unchecked(SMARTREST_PUBLISH_TOPIC),
-                    format!("101,{c_id},{c_id},thin-edge.io-child"),
+                    &smartrest_publish_topic(&child_path[..depth]),
+                    format!("101,{child_id},{child_id},thin-edge.io-child"),
                 ));
-                return true;
+                messages.push(
+                    Message::new(
+                        &Topic::new_unchecked(&format!(
+                            "{INTERNAL_CHILDREN_TOPIC}{}",
+                            child_path[..=depth].join("/")
+                        )),
+                        *child_id,
+                    )
+                    .with_retain(),
+                );
             }
         }
-        false
+        Ok(messages)
+    }
+
+    /// Register an inventory fragment published by a local application,
+    /// to be sent on the next flush along the other fragments updated for the same device.
+    fn process_inventory_fragment(
+        &mut self,
+        input: &Message,
+        levels: Vec<&str>,
+    ) -> Result<Vec<Message>, ConversionError> {
+        let fragment = InventoryFragment::from_levels(levels, input)?;
+        let child_path: Vec<&str> = fragment.child_path.iter().map(String::as_str).collect();
+        let registration = self.register_child_devices(&child_path)?;
+        self.inventory_updates.add(fragment);
+        Ok(registration)
+    }
+
+    /// The inventory updates of the devices which fragments have been updated since the last flush
+    fn flush_inventory_updates(&mut self) -> Vec<Message> {
+        let mut messages = vec![];
+        for (child_path, fragments) in self.inventory_updates.flush() {
+            let device_id = child_path.last().unwrap_or(&self.device_name);
+            let topic =
+                Topic::new_unchecked(&format!("{INVENTORY_MANAGED_OBJECTS_TOPIC}{device_id}"));
+            messages.push(Message::new(&topic, fragments.to_string()));
+        }
+        messages
+    }
+
+    /// Restore a child device registered before a restart
+    fn process_internal_child(
+        &mut self,
+        child_path: Vec<&str>,
+    ) -> Result<Vec<Message>, ConversionError> {
+        if let Some(child_id) = child_path.last() {
+            self.children
+                .insert(child_id.to_string(), child_path.join("/"));
+        }
+        Ok(vec![])
     }
 
     fn can_send_over_mqtt(&self, message: &Message) -> bool {
-        message.payload_bytes().len() < self.size_threshold.0
+        self.size_threshold.validate(message).is_ok()
     }
 }
 
 #[async_trait]
 impl<Proxy> Converter for CumulocityConverter<Proxy>
 where
-    Proxy: C8YHttpProxy,
+    Proxy: C8YHttpProxy + 'static,
 {
     type Error = ConversionError;
 
@@ -349,89 +642,106 @@ where
         &self.mapper_config
     }
     async fn try_convert(&mut self, message: &Message) -> Result<Vec<Message>, ConversionError> {
-        match &message.topic {
-            topic if topic.name.starts_with("tedge/measurements") => {
-                self.size_threshold.validate(message)?;
-                self.try_convert_measurement(message)
-            }
-            topic
-                if topic.name.starts_with("tedge/alarms")
-                    | topic.name.starts_with(INTERNAL_ALARMS_TOPIC) =>
-            {
-                self.process_alarm_messages(topic, message)
-            }
-            topic if topic.name.starts_with(TEDGE_EVENTS_TOPIC) => {
-                self.try_convert_event(message).await
-            }
-            topic => match topic.clone().try_into() {
-                Ok(MapperSubscribeTopic::ResponseTopic(ResponseTopic::SoftwareListResponse)) => {
-                    debug!("Software list");
-                    Ok(validate_and_publish_software_list(
+        let router = self.router.clone();
+        if let Some(result) = router.dispatch(self, message).await {
+            return result;
+        }
+
+        match message.topic.clone().try_into() {
+            Ok(MapperSubscribeTopic::ResponseTopic(ResponseTopic::SoftwareListResponse)) => {
+                debug!("Software list");
+                Ok(
+                    validate_and_publish_software_list(
                         message.payload_str()?,
                         &mut self.http_proxy,
                     )
-                    .await?)
-                }
-                Ok(MapperSubscribeTopic::ResponseTopic(ResponseTopic::SoftwareUpdateResponse)) => {
-                    debug!("Software update");
-                    Ok(
-                        publish_operation_status(message.payload_str()?, &mut self.http_proxy)
-                            .await?,
-                    )
-                }
-                Ok(MapperSubscribeTopic::ResponseTopic(ResponseTopic::RestartResponse)) => {
-                    Ok(publish_restart_operation_status(message.payload_str()?).await?)
-                }
-                Ok(MapperSubscribeTopic::C8yTopic(_)) => {
-                    parse_c8y_topics(
-                        message,
-                        &self.operations,
-                        &mut self.http_proxy,
-                        &self.operation_logs,
-                    )
-                    .await
-                }
-                _ => Err(ConversionError::UnsupportedTopic(
-                    message.topic.name.clone(),
-                )),
-            },
+                    .await?,
+                )
+            }
+            Ok(MapperSubscribeTopic::ResponseTopic(ResponseTopic::SoftwareUpdateResponse)) => {
+                debug!("Software update");
+                Ok(publish_operation_status(message.payload_str()?, &mut self.http_proxy).await?)
+            }
+            Ok(MapperSubscribeTopic::ResponseTopic(ResponseTopic::RestartResponse)) => {
+                Ok(publish_restart_operation_status(message.payload_str()?).await?)
+            }
+            Ok(MapperSubscribeTopic::C8yTopic(_)) => {
+                parse_c8y_topics(
+                    message,
+                    &self.operations,
+                    &mut self.http_proxy,
+                    &self.operation_logs,
+                )
+                .await
+            }
+            _ => Err(ConversionError::UnsupportedTopic(
+                message.topic.name.clone(),
+            )),
         }
     }
 
     fn try_init_messages(&self) -> Result<Vec<Message>, ConversionError> {
-        let inventory_fragments_message = self.wrap_error(create_inventory_fragments_message(
-            &self.device_name,
-            &self.cfg_dir,
-        ));
         let supported_operations_message =
             self.wrap_error(create_supported_operations_fragments_message(&self.cfg_dir));
-        let device_data_message = self.wrap_error(create_device_data_fragments(
-            &self.device_name,
-            &self.device_type,
-        ));
-
         let pending_operations_message = self.wrap_error(create_get_pending_operations_message());
         let software_list_message = self.wrap_error(create_get_software_list_message());
 
         Ok(vec![
-            inventory_fragments_message,
             supported_operations_message,
-            device_data_message,
             pending_operations_message,
             software_list_message,
         ])
     }
 
+    async fn try_flush_messages(&mut self) -> Result<Vec<Message>, ConversionError> {
+        let mut messages = vec![];
+        let router = self.router.clone();
+        for measurements in self.measurement_policies.flush(self.clock.now())? {
+            // The aggregated measurements are published on the topic of the measurements they aggregate
+            let captures = router
+                .matching(&measurements.topic)
+                .map(|(_, captures)| captures)
+                .unwrap_or_default();
+            let child_path = child_device_path(&captures)?;
+            messages.append(&mut self.convert_measurement(&measurements, &child_path).await?);
+        }
+        messages.append(&mut self.flush_inventory_updates());
+        Ok(messages)
+    }
+
     fn sync_messages(&mut self) -> Vec<Message> {
-        let sync_messages: Vec<Message> = self.alarm_converter.sync();
+        let mut sync_messages: Vec<Message> = self.alarm_converter.sync();
         self.alarm_converter = AlarmConverter::Synced;
+        sync_messages.append(&mut self.events_journal.sync());
         sync_messages
     }
 
+    /// The inventory fragments are only updated when changed since the last time they have been sent.
+    fn try_synced_messages(&mut self) -> Result<Vec<Message>, ConversionError> {
+        let inventory_message = create_inventory_fragments_message(
+            &self.device_name,
+            &self.device_type,
+            &self.cfg_dir,
+        )?;
+        if self.inventory_journal.is_recorded(&inventory_message) {
+            return Ok(vec![]);
+        }
+
+        let inventory_entry = self.inventory_journal.entry(&inventory_message);
+        Ok(std::iter::once(inventory_message)
+            .chain(inventory_entry)
+            .collect())
+    }
+
+    fn set_subscription_handle(&mut self, subscriptions: SubscriptionHandle) {
+        self.subscriptions = Some(subscriptions);
+    }
+
     fn try_process_operation_update_message(
         &mut self,
         message: &DiscoverOp,
     ) -> Result<Option<Message>, ConversionError> {
+        let previous_topics = self.operations.topics_for_operations();
         match message.event_type {
             EventType::Add => {
                 let ops_dir = message.ops_dir.clone();
@@ -443,6 +753,7 @@ where
                 self.operations.remove_operation(&message.operation_name);
             }
         }
+        self.update_operation_subscriptions(&previous_topics)?;
         Ok(Some(create_supported_operations_fragments_message(
             &self.cfg_dir,
         )?))
@@ -487,17 +798,6 @@ async fn parse_c8y_topics(
     }
 }
 
-fn create_device_data_fragments(
-    device_name: &str,
-    device_type: &str,
-) -> Result<Message, ConversionError> {
-    let device_data = C8yDeviceDataFragment::from_type(device_type)?;
-    let ops_msg = device_data.to_json()?;
-
-    let topic = Topic::new_unchecked(&format!("{INVENTORY_MANAGED_OBJECTS_TOPIC}/{device_name}",));
-    Ok(Message::new(&topic, ops_msg.to_string()))
-}
-
 fn create_get_software_list_message() -> Result<Message, ConversionError> {
     let request = SoftwareListRequest::default();
     let topic = Topic::new(RequestTopic::SoftwareListRequest.as_str())?;
@@ -525,15 +825,23 @@ fn create_supported_operations_fragments_message(
     Ok(Message::new(&topic, ops_msg.to_smartrest()?))
 }
 
+/// The inventory fragments of the device, including the device data, as a single inventory update.
 fn create_inventory_fragments_message(
     device_name: &str,
+    device_type: &str,
     cfg_dir: &Path,
 ) -> Result<Message, ConversionError> {
     let inventory_file_path = format!("{}/{INVENTORY_FRAGMENTS_FILE_LOCATION}", cfg_dir.display());
-    let ops_msg = get_inventory_fragments(&inventory_file_path)?;
+    let mut inventory = get_inventory_fragments(&inventory_file_path)?;
+    let device_data = C8yDeviceDataFragment::from_type(device_type)?.to_json()?;
+    if let (Some(inventory), Some(device_data)) =
+        (inventory.as_object_mut(), device_data.as_object())
+    {
+        inventory.extend(device_data.clone());
+    }
 
-    let topic = Topic::new_unchecked(&format!("{inventory_file_path}/{device_name}"));
-    Ok(Message::new(&topic, ops_msg.to_string()))
+    let topic = Topic::new_unchecked(&format!("{INVENTORY_MANAGED_OBJECTS_TOPIC}{device_name}"));
+    Ok(Message::new(&topic, inventory.to_string()))
 }
 
 async fn publish_restart_operation_status(
@@ -784,13 +1092,33 @@ fn get_inventory_fragments(
     }
 }
 
-pub fn get_child_id_from_measurement_topic(topic: &str) -> Result<Option<String>, ConversionError> {
-    match topic.strip_prefix("tedge/measurements/").map(String::from) {
-        Some(maybe_id) if maybe_id.is_empty() => {
-            Err(ConversionError::InvalidChildId { id: maybe_id })
-        }
-        option => Ok(option),
+/// The path from the main device to the child device a message is for,
+/// as captured by a route: the `{child}` level followed by the levels matched by the trailing `#`.
+///
+/// For instance, `tedge/measurements/plc/sensor` is for the `sensor` device, a child of the `plc` device,
+/// itself a child of the main device.
+fn child_device_path(captures: &Captures) -> Result<Vec<&str>, ConversionError> {
+    let child_path: Vec<&str> = captures
+        .get("child")
+        .into_iter()
+        .chain(captures.remaining_levels())
+        .collect();
+    if child_path.iter().any(|child_id| child_id.is_empty()) {
+        return Err(ConversionError::InvalidChildId {
+            id: child_path.join("/"),
+        });
+    }
+    Ok(child_path)
+}
+
+/// The topic where to publish SmartREST messages on behalf of the device with the given path
+fn smartrest_publish_topic(device_path: &[&str]) -> Topic {
+    let mut topic = SMARTREST_PUBLISH_TOPIC.to_string();
+    for device_id in device_path {
+        topic.push('/');
+        topic.push_str(device_id);
     }
+    Topic::new_unchecked(&topic)
 }
 
 #[cfg(test)]
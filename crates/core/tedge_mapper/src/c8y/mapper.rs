@@ -2,24 +2,35 @@ use std::path::{Path, PathBuf};
 
 use crate::{
     c8y::converter::CumulocityConverter,
-    core::{component::TEdgeComponent, mapper::create_mapper, size_threshold::SizeThreshold},
+    core::{
+        component::TEdgeComponent,
+        mapper::create_store_and_forward_mapper,
+        measurement_policies::{MeasurementPolicies, POLICIES_DIR},
+        metrics_endpoint::MetricsEndpoint,
+        script_hook::{with_script_hook, SCRIPTS_DIR},
+        size_threshold::SizeThreshold,
+        store_and_forward::StoreAndForward,
+    },
 };
 
 use agent_interface::topic::ResponseTopic;
 use async_trait::async_trait;
-use c8y_api::http_proxy::{C8YHttpProxy, JwtAuthHttpProxy};
+use c8y_api::{
+    http_proxy::{C8YHttpProxy, JwtAuthHttpProxy},
+    utils::bridge::C8Y_BRIDGE_HEALTH_TOPIC,
+};
 use c8y_smartrest::operations::Operations;
+use clock::WallClock;
 use mqtt_channel::TopicFilter;
 use tedge_config::{
-    ConfigSettingAccessor, DeviceIdSetting, DeviceTypeSetting, MqttBindAddressSetting,
-    MqttPortSetting, TEdgeConfig,
+    ConfigSettingAccessor, DataPathSetting, DeviceIdSetting, DeviceTypeSetting, TEdgeConfig,
 };
 use tedge_utils::file::*;
 use tracing::{info, info_span, Instrument};
 
 use super::topic::C8yTopic;
 
-const CUMULOCITY_MAPPER_NAME: &str = "tedge-mapper-c8y";
+pub(crate) const CUMULOCITY_MAPPER_NAME: &str = "tedge-mapper-c8y";
 const MQTT_MESSAGE_SIZE_THRESHOLD: usize = 16184;
 
 pub struct CumulocityMapper {}
@@ -58,7 +69,12 @@ impl TEdgeComponent for CumulocityMapper {
         Ok(())
     }
 
-    async fn start(&self, tedge_config: TEdgeConfig, cfg_dir: &Path) -> Result<(), anyhow::Error> {
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        cfg_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
+    ) -> Result<(), anyhow::Error> {
         let size_threshold = SizeThreshold(MQTT_MESSAGE_SIZE_THRESHOLD);
         let config_dir = cfg_dir.display().to_string();
 
@@ -67,26 +83,41 @@ impl TEdgeComponent for CumulocityMapper {
         http_proxy.init().await?;
         let device_name = tedge_config.query(DeviceIdSetting)?;
         let device_type = tedge_config.query(DeviceTypeSetting)?;
-        let mqtt_port = tedge_config.query(MqttPortSetting)?.into();
-        let mqtt_host = tedge_config.query(MqttBindAddressSetting)?.to_string();
-
-        let converter = Box::new(CumulocityConverter::new(
-            size_threshold,
-            device_name,
-            device_type,
-            operations,
-            http_proxy,
-            cfg_dir,
-        )?);
+        let mqtt_config = tedge_config.mqtt_config()?;
+        let measurement_policies = MeasurementPolicies::load(cfg_dir, CUMULOCITY_MAPPER_NAME)?;
 
-        let mut mapper = create_mapper(
+        let converter = Box::new(
+            CumulocityConverter::new(
+                size_threshold,
+                device_name,
+                device_type,
+                operations,
+                http_proxy,
+                cfg_dir,
+                Box::new(WallClock),
+            )?
+            .with_measurement_policies(measurement_policies),
+        );
+        let converter = with_script_hook(converter, CUMULOCITY_MAPPER_NAME, cfg_dir)?;
+
+        let store_and_forward = StoreAndForward::open(
             CUMULOCITY_MAPPER_NAME,
-            mqtt_host.clone(),
-            mqtt_port,
-            converter,
+            tedge_config.query(DataPathSetting)?.as_ref(),
+            C8Y_BRIDGE_HEALTH_TOPIC,
+            TopicFilter::new("c8y/#")?,
         )
         .await?;
 
+        let mut mapper = create_store_and_forward_mapper(
+            CUMULOCITY_MAPPER_NAME,
+            mqtt_config,
+            converter,
+            store_and_forward,
+        )
+        .await?
+        .with_mapper_settings(&tedge_config)?;
+        metrics_endpoint.register(CUMULOCITY_MAPPER_NAME, mapper.metrics());
+
         let ops_dir = PathBuf::from(format!("{}/operations/c8y", &config_dir));
 
         mapper
@@ -126,12 +157,15 @@ fn create_directories(config_dir: &Path) -> Result<(), anyhow::Error> {
         "tedge",
         0o775,
     )?;
+    create_directory_with_user_group(config_dir.join(SCRIPTS_DIR), "tedge", "tedge", 0o775)?;
+    create_directory_with_user_group(config_dir.join(POLICIES_DIR), "tedge", "tedge", 0o775)?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::mapper::create_mapper;
 
     use c8y_api::http_proxy::MockC8yJwtTokenRetriever;
     use c8y_smartrest::smartrest_deserializer::SmartRestJwtResponse;
@@ -214,8 +248,7 @@ mod tests {
 
         let mut mapper = create_mapper(
             CUMULOCITY_MAPPER_NAME_TEST,
-            MQTT_HOST.into(),
-            broker.port,
+            mqtt_channel::Config::new(MQTT_HOST, broker.port),
             converter,
         )
         .await?;
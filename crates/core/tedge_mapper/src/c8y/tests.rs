@@ -1,29 +1,35 @@
 use crate::core::{
-    converter::Converter, error::ConversionError, mapper::create_mapper,
+    converter::Converter,
+    error::ConversionError,
+    error_report::{ErrorKind, ErrorReport},
+    mapper::create_mapper,
     size_threshold::SizeThreshold,
 };
 use anyhow::Result;
 use assert_json_diff::assert_json_include;
 use assert_matches::assert_matches;
 use c8y_api::{
-    http_proxy::C8YHttpProxy,
+    http_proxy::{C8YHttpProxy, MockC8YHttpProxy},
     json_c8y::{C8yCreateEvent, C8yUpdateSoftwareListResponse},
 };
 use c8y_smartrest::{
     error::SMCumulocityMapperError, operations::Operations,
     smartrest_deserializer::SmartRestJwtResponse,
 };
+use clock::Clock;
 
-use mqtt_channel::{Message, Topic};
+use mqtt_channel::{Message, SubscriptionHandle, Topic};
 use mqtt_tests::test_mqtt_server::MqttProcessHandler;
 use serde_json::json;
 use serial_test::serial;
-use std::{path::Path, time::Duration};
+use std::{collections::HashSet, path::Path, time::Duration};
 use tedge_test_utils::fs::TempTedgeDir;
 use test_case::test_case;
+use time::macros::datetime;
 use tokio::task::JoinHandle;
 
-use super::converter::{get_child_id_from_measurement_topic, CumulocityConverter};
+use super::converter::CumulocityConverter;
+use super::dynamic_discovery::{DiscoverOp, EventType};
 
 const TEST_TIMEOUT_MS: Duration = Duration::from_millis(5000);
 const MQTT_HOST: &str = "127.0.0.1";
@@ -725,6 +731,118 @@ async fn test_sync_child_alarms() {
     assert!(converter.convert(&internal_alarm_message).await.is_empty());
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn test_sync_events() {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+
+    // An event already sent to Cumulocity before a restart, and an event published while the mapper was down
+    let sent_event = Message::new(
+        &Topic::new_unchecked("tedge/events/login_event"),
+        r#"{ "text": "Someone logged in" }"#,
+    )
+    .with_retain();
+    let sent_event_entry = Message::new(
+        &Topic::new_unchecked("c8y-internal/events/login_event"),
+        r#"{ "text": "Someone logged in" }"#,
+    )
+    .with_retain();
+    let missed_event = Message::new(
+        &Topic::new_unchecked("tedge/events/logout_event"),
+        r#"{ "text": "Someone logged out" }"#,
+    )
+    .with_retain();
+
+    // During the sync phase, retained events and journal entries are not converted, but only cached
+    assert!(converter.convert(&sent_event_entry).await.is_empty());
+    assert!(converter.convert(&sent_event).await.is_empty());
+    assert!(converter.convert(&missed_event).await.is_empty());
+
+    // But non-retained events are converted immediately, even during the sync phase
+    let click_event = Message::new(
+        &Topic::new_unchecked("tedge/events/click_event"),
+        r#"{ "text": "Someone clicked" }"#,
+    );
+    assert!(!converter.convert(&click_event).await.is_empty());
+
+    // When sync phase is complete, only the events not sent before are returned
+    let sync_messages = converter.sync_messages();
+    assert_eq!(sync_messages, vec![missed_event.clone()]);
+
+    // These are then converted and recorded in the events journal
+    let converted_events = converter.convert(&missed_event).await;
+    assert_eq!(converted_events.len(), 2);
+    assert_eq!(converted_events[0].topic.name, "c8y/s/us");
+    assert_eq!(
+        converted_events[1],
+        Message::new(
+            &Topic::new_unchecked("c8y-internal/events/logout_event"),
+            r#"{ "text": "Someone logged out" }"#,
+        )
+        .with_retain()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn child_devices_registered_before_a_restart_are_not_registered_again() {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+
+    let child_entry = Message::new(
+        &Topic::new_unchecked("c8y-internal/children/child1"),
+        "child1",
+    )
+    .with_retain();
+    assert!(converter.convert(&child_entry).await.is_empty());
+
+    let in_message = Message::new(
+        &Topic::new_unchecked("tedge/measurements/child1"),
+        r#"{"temp": 1, "time": "2021-11-16T17:45:40.571760714+01:00"}"#,
+    );
+    let out_messages = converter.convert(&in_message).await;
+    assert_eq!(out_messages.len(), 1);
+    assert_eq!(
+        out_messages[0].topic.name,
+        "c8y/measurement/measurements/create"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn inventory_fragments_are_only_sent_when_changed() {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+    converter.sync_messages();
+
+    // On the first start, the inventory fragments are sent and recorded
+    let synced_messages = converter.synced_messages();
+    assert_eq!(synced_messages.len(), 2);
+    let inventory_message = &synced_messages[0];
+    assert_eq!(
+        inventory_message.topic.name,
+        "c8y/inventory/managedObjects/update/test-device"
+    );
+    assert_json_include!(
+        actual: serde_json::from_str::<serde_json::Value>(inventory_message.payload_str().unwrap()).unwrap(),
+        expected: json!({ "type": "test-device-type" })
+    );
+    let inventory_entry = &synced_messages[1];
+    assert_eq!(
+        inventory_entry.topic.name,
+        "c8y-internal/inventory/test-device"
+    );
+    assert_eq!(
+        inventory_entry.payload_bytes(),
+        inventory_message.payload_bytes()
+    );
+    assert!(inventory_entry.retain);
+
+    // On restart, the inventory fragments are not sent again if unchanged
+    let (_temp_dir, mut converter) = create_c8y_converter();
+    assert!(converter.convert(inventory_entry).await.is_empty());
+    converter.sync_messages();
+    assert!(converter.synced_messages().is_empty());
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 #[serial]
 async fn convert_thin_edge_json_with_child_id() {
@@ -738,6 +856,11 @@ async fn convert_thin_edge_json_with_child_id() {
         &Topic::new_unchecked("c8y/s/us"),
         "101,child1,child1,thin-edge.io-child",
     );
+    let expected_child_entry_message = Message::new(
+        &Topic::new_unchecked("c8y-internal/children/child1"),
+        "child1",
+    )
+    .with_retain();
     let expected_c8y_json_message = Message::new(
         &Topic::new_unchecked("c8y/measurement/measurements/create"),
         r#"{"type":"ThinEdgeMeasurement","externalSource":{"externalId":"child1","type":"c8y_Serial"},"temp":{"temp":{"value":1.0}},"time":"2021-11-16T17:45:40.571760714+01:00"}"#,
@@ -749,6 +872,7 @@ async fn convert_thin_edge_json_with_child_id() {
         out_first_messages,
         vec![
             expected_smart_rest_message,
+            expected_child_entry_message,
             expected_c8y_json_message.clone()
         ]
     );
@@ -771,11 +895,17 @@ async fn convert_first_thin_edge_json_invalid_then_valid_with_child_id() {
 
     // First convert invalid Thin Edge JSON message.
     let out_first_messages = converter.convert(&in_first_message).await;
-    let expected_error_message = Message::new(
-        &Topic::new_unchecked("tedge/errors"),
-        r#"Invalid JSON: expected value at line 1 column 10: `invalid}`"#,
+    assert_eq!(out_first_messages.len(), 1);
+    assert_eq!(out_first_messages[0].topic.name, "tedge/errors");
+    let error_report = ErrorReport::from_message(&out_first_messages[0]).expect("an error report");
+    assert_eq!(error_report.mapper, "tedge-mapper-c8y");
+    assert_eq!(error_report.kind, ErrorKind::CumulocityJson);
+    assert_eq!(
+        error_report.message,
+        r#"Invalid JSON: expected value at line 1 column 10: `invalid}`"#
     );
-    assert_eq!(out_first_messages, vec![expected_error_message]);
+    assert_eq!(error_report.topic.as_deref(), Some(in_topic));
+    assert_eq!(error_report.payload.as_deref(), Some(in_invalid_payload));
 
     // Second convert valid Thin Edge JSON message.
     let out_second_messages = converter.convert(&in_second_message).await;
@@ -783,13 +913,22 @@ async fn convert_first_thin_edge_json_invalid_then_valid_with_child_id() {
         &Topic::new_unchecked("c8y/s/us"),
         "101,child1,child1,thin-edge.io-child",
     );
+    let expected_child_entry_message = Message::new(
+        &Topic::new_unchecked("c8y-internal/children/child1"),
+        "child1",
+    )
+    .with_retain();
     let expected_c8y_json_message = Message::new(
         &Topic::new_unchecked("c8y/measurement/measurements/create"),
         r#"{"type":"ThinEdgeMeasurement","externalSource":{"externalId":"child1","type":"c8y_Serial"},"temp":{"temp":{"value":1.0}},"time":"2021-11-16T17:45:40.571760714+01:00"}"#,
     );
     assert_eq!(
         out_second_messages,
-        vec![expected_smart_rest_message, expected_c8y_json_message]
+        vec![
+            expected_smart_rest_message,
+            expected_child_entry_message,
+            expected_c8y_json_message
+        ]
     );
 }
 
@@ -809,6 +948,11 @@ async fn convert_two_thin_edge_json_messages_given_different_child_id() {
         &Topic::new_unchecked("c8y/s/us"),
         "101,child1,child1,thin-edge.io-child",
     );
+    let expected_first_child_entry_message = Message::new(
+        &Topic::new_unchecked("c8y-internal/children/child1"),
+        "child1",
+    )
+    .with_retain();
     let expected_first_c8y_json_message = Message::new(
         &Topic::new_unchecked("c8y/measurement/measurements/create"),
         r#"{"type":"ThinEdgeMeasurement","externalSource":{"externalId":"child1","type":"c8y_Serial"},"temp":{"temp":{"value":1.0}},"time":"2021-11-16T17:45:40.571760714+01:00"}"#,
@@ -817,6 +961,7 @@ async fn convert_two_thin_edge_json_messages_given_different_child_id() {
         out_first_messages,
         vec![
             expected_first_smart_rest_message,
+            expected_first_child_entry_message,
             expected_first_c8y_json_message
         ]
     );
@@ -831,6 +976,11 @@ async fn convert_two_thin_edge_json_messages_given_different_child_id() {
         &Topic::new_unchecked("c8y/s/us"),
         "101,child2,child2,thin-edge.io-child",
     );
+    let expected_second_child_entry_message = Message::new(
+        &Topic::new_unchecked("c8y-internal/children/child2"),
+        "child2",
+    )
+    .with_retain();
     let expected_second_c8y_json_message = Message::new(
         &Topic::new_unchecked("c8y/measurement/measurements/create"),
         r#"{"type":"ThinEdgeMeasurement","externalSource":{"externalId":"child2","type":"c8y_Serial"},"temp":{"temp":{"value":1.0}},"time":"2021-11-16T17:45:40.571760714+01:00"}"#,
@@ -839,25 +989,251 @@ async fn convert_two_thin_edge_json_messages_given_different_child_id() {
         out_second_messages,
         vec![
             expected_second_smart_rest_message,
+            expected_second_child_entry_message,
             expected_second_c8y_json_message
         ]
     );
 }
 
-#[test_case("tedge/measurements/test", Some("test".to_string()); "valid child id")]
-#[test_case("tedge/measurements/", None; "returns an error (empty value)")]
-#[test_case("tedge/measurements", None; "invalid child id (parent topic)")]
-#[test_case("foo/bar", None; "invalid child id (invalid topic)")]
-fn extract_child_id(in_topic: &str, expected_child_id: Option<String>) {
-    match get_child_id_from_measurement_topic(in_topic) {
-        Ok(maybe_id) => assert_eq!(maybe_id, expected_child_id),
-        Err(crate::core::error::ConversionError::InvalidChildId { id }) => {
-            assert_eq!(id, "".to_string())
-        }
-        _ => {
-            panic!("Unexpected error type")
-        }
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn convert_thin_edge_json_with_nested_child_id() {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+
+    let in_message = Message::new(
+        &Topic::new_unchecked("tedge/measurements/plc/sensor"),
+        r#"{"temp": 1, "time": "2021-11-16T17:45:40.571760714+01:00"}"#,
+    );
+
+    // The child devices are registered along the path, each under its parent
+    let out_messages = converter.convert(&in_message).await;
+    assert_eq!(
+        out_messages,
+        vec![
+            Message::new(
+                &Topic::new_unchecked("c8y/s/us"),
+                "101,plc,plc,thin-edge.io-child"
+            ),
+            Message::new(&Topic::new_unchecked("c8y-internal/children/plc"), "plc").with_retain(),
+            Message::new(
+                &Topic::new_unchecked("c8y/s/us/plc"),
+                "101,sensor,sensor,thin-edge.io-child"
+            ),
+            Message::new(
+                &Topic::new_unchecked("c8y-internal/children/plc/sensor"),
+                "sensor"
+            )
+            .with_retain(),
+            Message::new(
+                &Topic::new_unchecked("c8y/measurement/measurements/create"),
+                r#"{"type":"ThinEdgeMeasurement","externalSource":{"externalId":"sensor","type":"c8y_Serial"},"temp":{"temp":{"value":1.0}},"time":"2021-11-16T17:45:40.571760714+01:00"}"#,
+            ),
+        ]
+    );
+
+    // A sibling is registered under the same parent, which is not registered again
+    let in_message = Message::new(
+        &Topic::new_unchecked("tedge/measurements/plc/other_sensor"),
+        r#"{"temp": 1}"#,
+    );
+    let out_messages = converter.convert(&in_message).await;
+    assert_eq!(out_messages[0].topic.name, "c8y/s/us/plc");
+    assert_eq!(
+        out_messages[0].payload_str().unwrap(),
+        "101,other_sensor,other_sensor,thin-edge.io-child"
+    );
+    assert_eq!(out_messages.len(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn convert_alarm_and_event_for_nested_child_device() {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+    converter.sync_messages();
+
+    let alarm_message = Message::new(
+        &Topic::new_unchecked("tedge/alarms/major/temperature_alarm/plc/sensor"),
+        r#"{ "text": "Temperature high", "time": "2021-11-16T17:45:40+01:00" }"#,
+    );
+    let out_messages = converter.convert(&alarm_message).await;
+    let topics: Vec<&str> = out_messages
+        .iter()
+        .map(|message| message.topic.name.as_str())
+        .collect();
+    assert_eq!(
+        topics,
+        vec![
+            "c8y/s/us",
+            "c8y-internal/children/plc",
+            "c8y/s/us/plc",
+            "c8y-internal/children/plc/sensor",
+            "c8y/s/us/plc/sensor",
+            "c8y-internal/alarms/major/temperature_alarm/plc/sensor",
+        ]
+    );
+    assert_eq!(
+        out_messages[4].payload_str().unwrap(),
+        r#"302,temperature_alarm,"Temperature high",2021-11-16T17:45:40+01:00"#
+    );
+
+    // The child devices being already registered, only the event is sent
+    let event_message = Message::new(
+        &Topic::new_unchecked("tedge/events/click_event/plc/sensor"),
+        r#"{ "text": "Someone clicked" }"#,
+    );
+    let out_messages = converter.convert(&event_message).await;
+    assert_eq!(out_messages.len(), 2);
+    assert_eq!(out_messages[0].topic.name, "c8y/event/events/create");
+    assert_json_include!(
+        actual: serde_json::from_str::<serde_json::Value>(out_messages[0].payload_str().unwrap()).unwrap(),
+        expected: json!({
+            "type": "click_event",
+            "text": "Someone clicked",
+            "externalSource": { "externalId": "sensor", "type": "c8y_Serial" },
+        })
+    );
+    assert_eq!(
+        out_messages[1].topic.name,
+        "c8y-internal/events/click_event/plc/sensor"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn a_child_device_cannot_be_registered_under_two_parents() {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+
+    // A child device registered before a restart is restored with its parents
+    let registered_child = Message::new(
+        &Topic::new_unchecked("c8y-internal/children/plc/sensor"),
+        "sensor",
+    )
+    .with_retain();
+    assert!(converter.convert(&registered_child).await.is_empty());
+
+    let measurement = Message::new(
+        &Topic::new_unchecked("tedge/measurements/gateway/sensor"),
+        r#"{"temp": 1}"#,
+    );
+    assert_matches!(
+        converter.try_convert(&measurement).await,
+        Err(ConversionError::ChildDeviceRegisteredUnderAnotherParent { id, registered_path, path })
+        if id == "sensor" && registered_path == "plc/sensor" && path == "gateway/sensor"
+    );
+
+    // Nothing has been registered on the failed attempt
+    let measurement = Message::new(
+        &Topic::new_unchecked("tedge/measurements/gateway"),
+        r#"{"temp": 1}"#,
+    );
+    let out_messages = converter.convert(&measurement).await;
+    assert_eq!(out_messages[0].topic.name, "c8y/s/us");
+    assert_eq!(
+        out_messages[0].payload_str().unwrap(),
+        "101,gateway,gateway,thin-edge.io-child"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn inventory_fragments_are_merged_and_sent_on_flush() {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+
+    for (topic, payload) in [
+        ("tedge/inventory/c8y_Firmware", r#"{"version": "1.0"}"#),
+        ("tedge/inventory/c8y_Firmware", r#"{"version": "1.1"}"#),
+        (
+            "tedge/inventory/c8y_Position",
+            r#"{"lat": 48.8, "lng": 2.3}"#,
+        ),
+    ] {
+        let input = Message::new(&Topic::new_unchecked(topic), payload);
+        assert!(converter.convert(&input).await.is_empty());
     }
+
+    // The fragments of a child device are sent once the child device registered
+    let input = Message::new(
+        &Topic::new_unchecked("tedge/inventory/child1/c8y_Hardware"),
+        r#"{"serialNumber": "ABC-123"}"#,
+    );
+    let registration = converter.convert(&input).await;
+    assert_eq!(registration[0].topic.name, "c8y/s/us");
+    assert_eq!(
+        registration[0].payload_str().unwrap(),
+        "101,child1,child1,thin-edge.io-child"
+    );
+
+    let updates = converter.flush_messages().await;
+    assert_eq!(updates.len(), 2);
+    assert_eq!(
+        updates[0].topic.name,
+        "c8y/inventory/managedObjects/update/test-device"
+    );
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(updates[0].payload_str().unwrap()).unwrap(),
+        json!({
+            "c8y_Firmware": {"version": "1.1"},
+            "c8y_Position": {"lat": 48.8, "lng": 2.3},
+        })
+    );
+    assert_eq!(
+        updates[1].topic.name,
+        "c8y/inventory/managedObjects/update/child1"
+    );
+    assert_eq!(
+        updates[1].payload_str().unwrap(),
+        r#"{"c8y_Hardware":{"serialNumber":"ABC-123"}}"#
+    );
+
+    // Nothing is sent when no fragments have been updated
+    assert!(converter.flush_messages().await.is_empty());
+}
+
+#[test_case("tedge/measurements/"; "empty child id")]
+#[test_case("tedge/measurements/plc//sensor"; "empty parent id")]
+#[test_case("tedge/measurements/plc/"; "empty nested child id")]
+#[tokio::test]
+async fn measurements_with_an_empty_child_id_are_rejected(topic: &str) {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+    let measurement = Message::new(&Topic::new_unchecked(topic), r#"{"temperature": 21}"#);
+
+    assert_matches!(
+        converter.try_convert(&measurement).await,
+        Err(ConversionError::InvalidChildId { .. })
+    );
+}
+
+#[test]
+fn the_topics_of_added_and_removed_operations_are_subscribed_and_unsubscribed() {
+    let (temp_dir, mut converter) = create_c8y_converter();
+    let subscriptions = SubscriptionHandle::detached(&converter.get_in_topic_filter());
+    converter.set_subscription_handle(subscriptions.clone());
+
+    let ops_dir = temp_dir.dir("operations").dir("c8y");
+    ops_dir.file("c8y_Custom").with_raw_content(
+        r#"[exec]
+        topic = "c8y/custom/operations"
+        command = "echo"
+        on_message = "530""#,
+    );
+    converter.process_operation_update_message(DiscoverOp {
+        ops_dir: ops_dir.to_path_buf(),
+        event_type: EventType::Add,
+        operation_name: "c8y_Custom".into(),
+    });
+    assert!(subscriptions
+        .patterns()
+        .contains(&"c8y/custom/operations".to_string()));
+
+    ops_dir.file("c8y_Custom").delete();
+    converter.process_operation_update_message(DiscoverOp {
+        ops_dir: ops_dir.to_path_buf(),
+        event_type: EventType::Remove,
+        operation_name: "c8y_Custom".into(),
+    });
+    assert!(!subscriptions
+        .patterns()
+        .contains(&"c8y/custom/operations".to_string()));
 }
 
 #[tokio::test]
@@ -888,7 +1264,7 @@ async fn convert_event_with_known_fields_to_c8y_smartrest() -> Result<()> {
     let event_message = Message::new(&Topic::new_unchecked(event_topic), event_payload);
 
     let converted_events = converter.convert(&event_message).await;
-    assert_eq!(converted_events.len(), 1);
+    assert_eq!(converted_events.len(), 2);
     let converted_event = converted_events.get(0).unwrap();
     assert_eq!(converted_event.topic.name, "c8y/s/us");
     dbg!(converted_event.payload_str()?);
@@ -908,7 +1284,7 @@ async fn convert_event_with_extra_fields_to_c8y_json() -> Result<()> {
     let event_message = Message::new(&Topic::new_unchecked(event_topic), event_payload);
 
     let converted_events = converter.convert(&event_message).await;
-    assert_eq!(converted_events.len(), 1);
+    assert_eq!(converted_events.len(), 2);
     let converted_event = converted_events.get(0).unwrap();
     assert_eq!(converted_event.topic.name, "c8y/event/events/create");
     let converted_c8y_json = json!({
@@ -925,6 +1301,87 @@ async fn convert_event_with_extra_fields_to_c8y_json() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn convert_alarm_with_custom_fragments_to_c8y_json() -> Result<()> {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+    converter.sync_messages();
+
+    let alarm_topic = "tedge/alarms/major/temperature_alarm";
+    let alarm_payload = r#"{ "text": "Temperature high", "threshold": { "value": 40 } }"#;
+    let alarm_message = Message::new(&Topic::new_unchecked(alarm_topic), alarm_payload);
+
+    let converted_alarms = converter.convert(&alarm_message).await;
+    assert_eq!(converted_alarms.len(), 2);
+    let converted_alarm = &converted_alarms[0];
+    assert_eq!(converted_alarm.topic.name, "c8y/alarm/alarms/create");
+    assert_json_include!(
+        actual: serde_json::from_str::<serde_json::Value>(converted_alarm.payload_str()?)?,
+        expected: json!({
+            "type": "temperature_alarm",
+            "severity": "MAJOR",
+            "text": "Temperature high",
+            "threshold": { "value": 40 },
+        })
+    );
+    assert_eq!(
+        converted_alarms[1].topic.name,
+        "c8y-internal/alarms/major/temperature_alarm"
+    );
+
+    // Clearing the alarm is still done using SmartREST
+    let clear_message = Message::new(&Topic::new_unchecked(alarm_topic), "");
+    let converted_alarms = converter.convert(&clear_message).await;
+    assert_eq!(converted_alarms[0].topic.name, "c8y/s/us");
+    assert_eq!(converted_alarms[0].payload_str()?, "306,temperature_alarm");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial]
+async fn convert_child_alarm_with_custom_fragments_to_c8y_json() -> Result<()> {
+    let (_temp_dir, mut converter) = create_c8y_converter();
+    converter.sync_messages();
+
+    let alarm_message = Message::new(
+        &Topic::new_unchecked("tedge/alarms/critical/temperature_alarm/sensor"),
+        r#"{ "text": "Temperature high", "unit": "C" }"#,
+    );
+    let converted_alarms = converter.convert(&alarm_message).await;
+    let converted_alarm = converted_alarms
+        .iter()
+        .find(|message| message.topic.name == "c8y/alarm/alarms/create")
+        .unwrap();
+    assert_json_include!(
+        actual: serde_json::from_str::<serde_json::Value>(converted_alarm.payload_str()?)?,
+        expected: json!({
+            "type": "temperature_alarm",
+            "severity": "CRITICAL",
+            "unit": "C",
+            "externalSource": { "externalId": "sensor", "type": "c8y_Serial" },
+        })
+    );
+
+    // The source of the alarm can be overridden by the payload
+    let alarm_message = Message::new(
+        &Topic::new_unchecked("tedge/alarms/critical/pressure_alarm/sensor"),
+        r#"{ "externalSource": { "externalId": "plc", "type": "c8y_Serial" } }"#,
+    );
+    let converted_alarms = converter.convert(&alarm_message).await;
+    assert_eq!(converted_alarms[0].topic.name, "c8y/alarm/alarms/create");
+    assert_json_include!(
+        actual: serde_json::from_str::<serde_json::Value>(converted_alarms[0].payload_str()?)?,
+        expected: json!({
+            "type": "pressure_alarm",
+            "text": "pressure_alarm",
+            "externalSource": { "externalId": "plc", "type": "c8y_Serial" },
+        })
+    );
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_convert_big_event() {
     let (_temp_dir, mut converter) = create_c8y_converter();
@@ -934,7 +1391,13 @@ async fn test_convert_big_event() {
     let big_event_payload = json!({ "text": big_event_text }).to_string();
     let big_event_message = Message::new(&Topic::new_unchecked(event_topic), big_event_payload);
 
-    assert!(converter.convert(&big_event_message).await.is_empty());
+    // The event is sent over HTTP, and only recorded in the events journal
+    let converted_events = converter.convert(&big_event_message).await;
+    assert_eq!(converted_events.len(), 1);
+    assert_eq!(
+        converted_events[0].topic.name,
+        "c8y-internal/events/click_event"
+    );
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -949,21 +1412,7 @@ async fn test_convert_big_measurement() {
     );
     let result = converter.convert(&big_measurement_message).await;
 
-    assert!(result.clone()
-        .into_iter()
-        .nth(0)
-        .unwrap()
-        .payload_str()
-        .unwrap()
-        .contains("The payload {\"temperature0\":0,\"temperature1\":1,\"temperature10\" received on tedge/measurements after translation is"));
-
-    assert!(result
-        .into_iter()
-        .nth(0)
-        .unwrap()
-        .payload_str()
-        .unwrap()
-        .contains("greater than the threshold size of 16384."));
+    assert_split_measurements(result, None, 640);
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -1001,23 +1450,99 @@ async fn test_convert_big_measurement_for_child_device() {
         big_measurement_payload,
     );
 
+    let mut result = converter.convert(&big_measurement_message).await;
+
+    assert_eq!(
+        result.remove(0).payload_str().unwrap(),
+        "101,child1,child1,thin-edge.io-child"
+    );
+    assert_eq!(result.remove(0).topic.name, "c8y-internal/children/child1");
+    assert_split_measurements(result, Some("child1"), 640);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn split_measurements_are_timestamped_by_the_clock() {
+    let (_temp_dir, converter) = create_c8y_converter();
+    let mut converter = converter.with_clock(Box::new(TestClock));
+    let big_measurement_message = Message::new(
+        &Topic::new_unchecked("tedge/measurements"),
+        create_thin_edge_measurement(10 * 1024),
+    );
+
     let result = converter.convert(&big_measurement_message).await;
 
-    assert!(result.clone()
-        .into_iter()
-        .nth(0)
-        .unwrap()
-        .payload_str()
-        .unwrap()
-        .contains("The payload {\"temperature0\":0,\"temperature1\":1,\"temperature10\" received on tedge/measurements/child1 after translation is"));
+    assert!(result.len() > 1);
+    for message in result {
+        let payload: serde_json::Value =
+            serde_json::from_str(message.payload_str().unwrap()).unwrap();
+        assert_eq!(payload["time"], json!("2021-04-08T00:00:00+05:00"));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_measurement_too_large_to_be_split_is_sent_over_http() {
+    let mut http_proxy = MockC8YHttpProxy::new();
+    http_proxy
+        .expect_send_measurement()
+        .withf(|c8y_measurement, child_id| {
+            c8y_measurement["temperature"]["temperature"]["value"] == json!(25.0)
+                && child_id.as_deref() == Some("child1")
+        })
+        .times(1)
+        .returning(|_, _| Ok(()));
+    let tmp_dir = TempTedgeDir::new();
+    let mut converter = CumulocityConverter::from_logs_path(
+        SizeThreshold(64),
+        "test-device".into(),
+        "test-device-type".into(),
+        Operations::default(),
+        http_proxy,
+        tmp_dir.path().to_path_buf(),
+    )
+    .unwrap();
 
+    let input = Message::new(
+        &Topic::new_unchecked("tedge/measurements/child1"),
+        r#"{"temperature": 25}"#,
+    );
+    let result = converter.convert(&input).await;
+
+    // Only the child device registration is sent over MQTT
     assert!(result
-        .into_iter()
-        .nth(0)
-        .unwrap()
-        .payload_str()
-        .unwrap()
-        .contains("greater than the threshold size of 16384."));
+        .iter()
+        .all(|message| message.topic.name != "c8y/measurement/measurements/create"));
+}
+
+/// Check that the measurements have been split into messages under the threshold,
+/// all with the same timestamp and external source, and with no measurement lost.
+fn assert_split_measurements(messages: Vec<Message>, child_id: Option<&str>, count: usize) {
+    assert!(messages.len() > 1);
+
+    let mut measurements = HashSet::new();
+    let mut timestamps = HashSet::new();
+    for message in messages {
+        assert_eq!(message.topic.name, "c8y/measurement/measurements/create");
+        assert!(message.payload_bytes().len() <= 16 * 1024);
+
+        let payload: serde_json::Value =
+            serde_json::from_str(message.payload_str().unwrap()).unwrap();
+        let object = payload.as_object().unwrap();
+        assert_eq!(
+            object
+                .get("externalSource")
+                .map(|source| source["externalId"].clone()),
+            child_id.map(|id| json!(id))
+        );
+        timestamps.insert(object["time"].to_string());
+        for key in object.keys() {
+            if key.starts_with("temperature") {
+                assert!(measurements.insert(key.clone()));
+            }
+        }
+    }
+
+    assert_eq!(timestamps.len(), 1);
+    assert_eq!(measurements.len(), count);
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -1043,7 +1568,7 @@ async fn test_convert_small_measurement_for_child_device() {
 
     assert!(result.clone()
         .into_iter()
-        .nth(1)
+        .nth(2)
         .unwrap()
         .payload_str()
         .unwrap()
@@ -1073,6 +1598,14 @@ fn create_thin_edge_measurement(size: usize) -> String {
     serde_json::to_string(&obj).unwrap()
 }
 
+struct TestClock;
+
+impl Clock for TestClock {
+    fn now(&self) -> clock::Timestamp {
+        datetime!(2021-04-08 00:00:00 +05:00)
+    }
+}
+
 pub struct FakeC8YHttpProxy {}
 
 #[async_trait::async_trait]
@@ -1112,6 +1645,14 @@ impl C8YHttpProxy for FakeC8YHttpProxy {
         Ok("123".into())
     }
 
+    async fn send_measurement(
+        &mut self,
+        _c8y_measurement: serde_json::Value,
+        _child_device_id: Option<String>,
+    ) -> Result<(), SMCumulocityMapperError> {
+        Ok(())
+    }
+
     async fn upload_config_file(
         &mut self,
         _config_path: &Path,
@@ -1126,8 +1667,7 @@ async fn start_c8y_mapper(mqtt_port: u16) -> Result<(TempTedgeDir, JoinHandle<()
     let (_temp_dir, converter) = create_c8y_converter();
     let mut mapper = create_mapper(
         "c8y-mapper-test",
-        MQTT_HOST.to_string(),
-        mqtt_port,
+        mqtt_channel::Config::new(MQTT_HOST, mqtt_port),
         Box::new(converter),
     )
     .await?;
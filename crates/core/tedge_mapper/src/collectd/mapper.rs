@@ -2,11 +2,11 @@ use std::path::Path;
 
 use crate::{
     collectd::monitor::{DeviceMonitor, DeviceMonitorConfig},
-    core::component::TEdgeComponent,
+    core::{component::TEdgeComponent, metrics_endpoint::MetricsEndpoint},
 };
 use async_trait::async_trait;
-use mqtt_channel::TopicFilter;
-use tedge_config::{ConfigSettingAccessor, MqttBindAddressSetting, MqttPortSetting, TEdgeConfig};
+use mqtt_channel::{Metrics, TopicFilter};
+use tedge_config::TEdgeConfig;
 use tracing::{info, info_span, Instrument};
 
 const COLLECTD_MAPPER_NAME: &str = "tedge-mapper-collectd";
@@ -38,13 +38,13 @@ impl TEdgeComponent for CollectdMapper {
         &self,
         tedge_config: TEdgeConfig,
         _config_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
     ) -> Result<(), anyhow::Error> {
-        let mqtt_port = tedge_config.query(MqttPortSetting)?.into();
-        let mqtt_host = tedge_config.query(MqttBindAddressSetting)?.to_string();
+        let metrics = Metrics::default();
+        metrics_endpoint.register(COLLECTD_MAPPER_NAME, &metrics);
 
         let device_monitor_config = DeviceMonitorConfig::default()
-            .with_port(mqtt_port)
-            .with_host(mqtt_host);
+            .with_mqtt_config(tedge_config.mqtt_config()?.with_metrics(metrics));
 
         let device_monitor = DeviceMonitor::new(device_monitor_config);
         device_monitor
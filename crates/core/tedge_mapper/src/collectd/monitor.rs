@@ -1,11 +1,9 @@
 use super::{batcher::MessageBatch, collectd::CollectdMessage, error::DeviceMonitorError};
 use batcher::{BatchConfigBuilder, BatchDriver, BatchDriverInput, BatchDriverOutput, Batcher};
 use mqtt_channel::{Connection, Message, QoS, SinkExt, StreamExt, Topic, TopicFilter};
-use thin_edge_json::health::{health_check_topics, send_health_status};
+use thin_edge_json::health::{health_check_topics, send_health_status, with_health_status};
 use tracing::{error, info, instrument};
 
-const DEFAULT_HOST: &str = "localhost";
-const DEFAULT_PORT: u16 = 1883;
 const DEFAULT_MQTT_CLIENT_ID: &str = "collectd-mapper";
 const DEFAULT_BATCHING_WINDOW: u32 = 500;
 const DEFAULT_MAXIMUM_MESSAGE_DELAY: u32 = 400; // Heuristic delay that should work out well on an Rpi
@@ -15,8 +13,7 @@ const DEFAULT_MQTT_TARGET_TOPIC: &str = "tedge/measurements";
 
 #[derive(Debug)]
 pub struct DeviceMonitorConfig {
-    host: String,
-    port: u16,
+    mqtt_config: mqtt_channel::Config,
     mqtt_client_id: &'static str,
     pub mqtt_source_topic: &'static str,
     mqtt_target_topic: &'static str,
@@ -28,8 +25,7 @@ pub struct DeviceMonitorConfig {
 impl Default for DeviceMonitorConfig {
     fn default() -> Self {
         Self {
-            host: DEFAULT_HOST.to_string(),
-            port: DEFAULT_PORT,
+            mqtt_config: mqtt_channel::Config::default(),
             mqtt_client_id: DEFAULT_MQTT_CLIENT_ID,
             mqtt_source_topic: DEFAULT_MQTT_SOURCE_TOPIC,
             mqtt_target_topic: DEFAULT_MQTT_TARGET_TOPIC,
@@ -41,12 +37,11 @@ impl Default for DeviceMonitorConfig {
 }
 
 impl DeviceMonitorConfig {
-    pub fn with_port(self, port: u16) -> Self {
-        Self { port, ..self }
-    }
-
-    pub fn with_host(self, host: String) -> Self {
-        Self { host, ..self }
+    pub fn with_mqtt_config(self, mqtt_config: mqtt_channel::Config) -> Self {
+        Self {
+            mqtt_config,
+            ..self
+        }
     }
 }
 
@@ -70,9 +65,9 @@ impl DeviceMonitor {
             .with_qos(QoS::AtMostOnce);
         input_topic.add_all(health_check_topics.clone());
 
-        let mqtt_config = mqtt_channel::Config::new(
-            self.device_monitor_config.host.to_string(),
-            self.device_monitor_config.port,
+        let mqtt_config = with_health_status(
+            self.device_monitor_config.mqtt_config.clone(),
+            "tedge-mapper-collectd",
         )
         .with_session_name(self.device_monitor_config.mqtt_client_id)
         .with_subscriptions(input_topic);
@@ -1,16 +1,23 @@
 use std::path::Path;
 
+use crate::core::metrics_endpoint::MetricsEndpoint;
 use async_trait::async_trait;
 use mqtt_channel::TopicFilter;
-use tedge_config::{
-    ConfigRepository, ConfigSettingAccessor, MqttBindAddressSetting, MqttPortSetting, TEdgeConfig,
-};
+use tedge_config::{ConfigRepository, TEdgeConfig};
 use tracing::info;
 
 #[async_trait]
 pub trait TEdgeComponent: Sync + Send {
     fn session_name(&self) -> &str;
-    async fn start(&self, tedge_config: TEdgeConfig, cfg_dir: &Path) -> Result<(), anyhow::Error>;
+
+    /// Run the component, registering its metrics to the endpoint of the process
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        cfg_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
+    ) -> Result<(), anyhow::Error>;
+
     async fn init(&self, cfg_dir: &Path) -> Result<(), anyhow::Error>;
     async fn init_session(&self, mqtt_topics: TopicFilter) -> Result<(), anyhow::Error> {
         mqtt_channel::init_session(&self.get_mqtt_config()?.with_subscriptions(mqtt_topics))
@@ -29,9 +36,8 @@ pub trait TEdgeComponent: Sync + Send {
             tedge_config::TEdgeConfigRepository::new(tedge_config::TEdgeConfigLocation::default());
         let tedge_config = config_repository.load()?;
 
-        let mqtt_config = mqtt_channel::Config::default()
-            .with_host(tedge_config.query(MqttBindAddressSetting)?.to_string())
-            .with_port(tedge_config.query(MqttPortSetting)?.into())
+        let mqtt_config = tedge_config
+            .mqtt_config()?
             .with_session_name(self.session_name())
             .with_clean_session(false);
 
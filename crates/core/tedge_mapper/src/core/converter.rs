@@ -1,11 +1,13 @@
 use crate::c8y::dynamic_discovery::DiscoverOp;
+use crate::core::error_report::{ErrorReport, ReportableError};
 use async_trait::async_trait;
-use mqtt_channel::{Message, Topic, TopicFilter};
-use std::fmt::Display;
+use clock::{Clock, WallClock};
+use mqtt_channel::{Message, SubscriptionHandle, Topic, TopicFilter};
 use tracing::error;
 
 #[derive(Debug)]
 pub struct MapperConfig {
+    pub mapper_name: String,
     pub in_topic_filter: TopicFilter,
     pub out_topic: Topic,
     pub errors_topic: Topic,
@@ -13,7 +15,7 @@ pub struct MapperConfig {
 
 #[async_trait]
 pub trait Converter: Send + Sync {
-    type Error: Display;
+    type Error: ReportableError;
 
     fn get_mapper_config(&self) -> &MapperConfig;
 
@@ -25,20 +27,33 @@ pub trait Converter: Send + Sync {
 
     async fn convert(&mut self, input: &Message) -> Vec<Message> {
         let messages_or_err = self.try_convert(input).await;
-        self.wrap_errors(messages_or_err)
+        self.wrap_input_errors(input, messages_or_err)
     }
 
     fn wrap_errors(&self, messages_or_err: Result<Vec<Message>, Self::Error>) -> Vec<Message> {
-        messages_or_err.unwrap_or_else(|error| vec![self.new_error_message(error)])
+        messages_or_err.unwrap_or_else(|error| vec![self.new_error_message(error, None)])
+    }
+
+    /// Wrap the errors raised by the conversion of the given input message,
+    /// so the error reports tell which topic and payload failed to be mapped.
+    fn wrap_input_errors(
+        &self,
+        input: &Message,
+        messages_or_err: Result<Vec<Message>, Self::Error>,
+    ) -> Vec<Message> {
+        messages_or_err.unwrap_or_else(|error| vec![self.new_error_message(error, Some(input))])
     }
 
     fn wrap_error(&self, message_or_err: Result<Message, Self::Error>) -> Message {
-        message_or_err.unwrap_or_else(|error| self.new_error_message(error))
+        message_or_err.unwrap_or_else(|error| self.new_error_message(error, None))
     }
 
-    fn new_error_message(&self, error: Self::Error) -> Message {
+    /// Build the JSON report of an error, to be published on the errors topic
+    fn new_error_message(&self, error: Self::Error, input: Option<&Message>) -> Message {
         error!("Mapping error: {}", error);
-        Message::new(&self.get_mapper_config().errors_topic, error.to_string())
+        let config = self.get_mapper_config();
+        ErrorReport::new(&config.mapper_name, &error, input, WallClock.now())
+            .to_message(&config.errors_topic)
     }
 
     fn try_init_messages(&self) -> Result<Vec<Message>, Self::Error> {
@@ -48,16 +63,8 @@ pub trait Converter: Send + Sync {
     /// This function will be the first method that's called on the converter after it's instantiated.
     /// Return any initialization messages that must be processed before the converter starts converting regular messages.
     fn init_messages(&self) -> Vec<Message> {
-        match self.try_init_messages() {
-            Ok(messages) => messages,
-            Err(error) => {
-                error!("Mapping error: {}", error);
-                vec![Message::new(
-                    &self.get_mapper_config().errors_topic,
-                    error.to_string(),
-                )]
-            }
-        }
+        let messages_or_err = self.try_init_messages();
+        self.wrap_errors(messages_or_err)
     }
 
     /// This function will be the called after a brief period(sync window) after the converter starts converting messages.
@@ -69,6 +76,35 @@ pub trait Converter: Send + Sync {
         vec![]
     }
 
+    fn try_synced_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+        Ok(vec![])
+    }
+
+    /// This function is called once the sync messages have been processed.
+    /// This gives the converter an opportunity to publish as is the state it has reconciled during the sync,
+    /// as the inventory fragments that have changed since the last time they were published.
+    fn synced_messages(&mut self) -> Vec<Message> {
+        let messages_or_err = self.try_synced_messages();
+        self.wrap_errors(messages_or_err)
+    }
+
+    async fn try_flush_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+        Ok(vec![])
+    }
+
+    /// This function is called periodically by the mapper, along the conversion of the messages.
+    /// This gives the converter an opportunity to publish the messages it has held back,
+    /// as the measurements aggregated over a time window that is now over.
+    async fn flush_messages(&mut self) -> Vec<Message> {
+        let messages_or_err = self.try_flush_messages().await;
+        self.wrap_errors(messages_or_err)
+    }
+
+    /// Give the converter a handle to update the subscriptions of the mapper while running.
+    ///
+    /// This function is called once, before any message is converted.
+    fn set_subscription_handle(&mut self, _subscriptions: SubscriptionHandle) {}
+
     fn try_process_operation_update_message(
         &mut self,
         _input: &DiscoverOp,
@@ -76,15 +112,12 @@ pub trait Converter: Send + Sync {
         Ok(None)
     }
 
-    fn process_operation_update_message(&mut self, message: DiscoverOp) -> Message {
+    /// The message to be published on an update of the operations, if any
+    fn process_operation_update_message(&mut self, message: DiscoverOp) -> Option<Message> {
         let message_or_err = self.try_process_operation_update_message(&message);
         match message_or_err {
-            Ok(Some(msg)) => msg,
-            Ok(None) => Message::new(
-                &self.get_mapper_config().errors_topic,
-                "No operation update required",
-            ),
-            Err(err) => self.new_error_message(err),
+            Ok(message) => message,
+            Err(err) => Some(self.new_error_message(err, None)),
         }
     }
 }
@@ -47,6 +47,11 @@ pub enum ConversionError {
     #[error(transparent)]
     FromThinEdgeJsonSerialization(#[from] ThinEdgeJsonSerializationError),
 
+    #[error(transparent)]
+    FromLineProtocolSerialization(
+        #[from] thin_edge_json::line_protocol::LineProtocolSerializationError,
+    ),
+
     #[error(transparent)]
     FromThinEdgeJsonAlarmDeserialization(
         #[from] thin_edge_json::alarm::ThinEdgeJsonDeserializerError,
@@ -60,6 +65,9 @@ pub enum ConversionError {
     #[error(transparent)]
     FromThinEdgeJsonParser(#[from] thin_edge_json::parser::ThinEdgeJsonParserError),
 
+    #[error(transparent)]
+    FromMeasurementGrouper(#[from] thin_edge_json::group::MeasurementGrouperError),
+
     #[error("The size of the message received on {topic} is {actual_size} which is greater than the threshold size of {threshold}.")]
     SizeThresholdExceeded {
         topic: String,
@@ -110,4 +118,20 @@ pub enum ConversionError {
 
     #[error("The given Child ID '{id}' is not registered with Cumulocity. To send the events to the child device, it has to be registered first.")]
     ChildDeviceNotRegistered { id: String },
+
+    #[error("The child device '{id}' is registered as '{registered_path}' and cannot be registered as '{path}' under another parent.")]
+    ChildDeviceRegisteredUnderAnotherParent {
+        id: String,
+        registered_path: String,
+        path: String,
+    },
+
+    #[error("No value found at {path} in the message received on {topic}.")]
+    JsonPathNotFound { path: String, topic: String },
+
+    #[error("The payload received on {topic} is not a JSON object.")]
+    NotAJsonObject { topic: String },
+
+    #[error(transparent)]
+    FromScript(#[from] crate::core::script::ScriptError),
 }
@@ -0,0 +1,343 @@
+use crate::core::error::ConversionError;
+use mqtt_channel::{Message, Topic};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt::Display;
+use tedge_config::ErrorForwarding;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// The maximum number of bytes of the original payload copied into an error report
+pub const MAX_REPORTED_PAYLOAD_SIZE: usize = 256;
+
+/// Topic where the error reports are forwarded as thin-edge events
+pub const ERROR_EVENT_TOPIC: &str = "tedge/events/mapping_error";
+
+/// Topic where the error reports are forwarded as thin-edge alarms
+pub const ERROR_ALARM_TOPIC: &str = "tedge/alarms/major/mapping_error";
+
+/// The retained message clearing the alarm raised on a mapping error
+pub fn clear_error_alarm_message() -> Message {
+    Message::new(&Topic::new_unchecked(ERROR_ALARM_TOPIC), "").with_retain()
+}
+
+/// The kind of a mapping error, one per variant of `ConversionError`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Mapper,
+    CumulocityJson,
+    CumulocityMapper,
+    CumulocitySmartRestMapper,
+    ThinEdgeJsonSerialization,
+    LineProtocolSerialization,
+    ThinEdgeJsonAlarmDeserialization,
+    ThinEdgeJsonEventDeserialization,
+    ThinEdgeJsonParser,
+    MeasurementGrouper,
+    SizeThresholdExceeded,
+    InvalidChildId,
+    MqttClient,
+    Operations,
+    SmartRestSerializer,
+    UnsupportedTopic,
+    SerdeJson,
+    StdIo,
+    Option,
+    Utf8,
+    TimeFormat,
+    TranslatedSizeExceededThreshold,
+    OperationLogs,
+    ChildDeviceNotRegistered,
+    ChildDeviceRegisteredUnderAnotherParent,
+    JsonPathNotFound,
+    NotAJsonObject,
+    Script,
+}
+
+/// An error that can be reported on the errors topic of a mapper
+pub trait ReportableError: Display {
+    fn kind(&self) -> ErrorKind;
+}
+
+impl ReportableError for ConversionError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ConversionError::FromMapper(_) => ErrorKind::Mapper,
+            ConversionError::FromCumulocityJsonError(_) => ErrorKind::CumulocityJson,
+            ConversionError::FromCumulocityMapperError(_) => ErrorKind::CumulocityMapper,
+            ConversionError::FromCumulocitySmartRestMapperError(_) => {
+                ErrorKind::CumulocitySmartRestMapper
+            }
+            ConversionError::FromThinEdgeJsonSerialization(_) => {
+                ErrorKind::ThinEdgeJsonSerialization
+            }
+            ConversionError::FromLineProtocolSerialization(_) => {
+                ErrorKind::LineProtocolSerialization
+            }
+            ConversionError::FromThinEdgeJsonAlarmDeserialization(_) => {
+                ErrorKind::ThinEdgeJsonAlarmDeserialization
+            }
+            ConversionError::FromThinEdgeJsonEventDeserialization(_) => {
+                ErrorKind::ThinEdgeJsonEventDeserialization
+            }
+            ConversionError::FromThinEdgeJsonParser(_) => ErrorKind::ThinEdgeJsonParser,
+            ConversionError::FromMeasurementGrouper(_) => ErrorKind::MeasurementGrouper,
+            ConversionError::SizeThresholdExceeded { .. } => ErrorKind::SizeThresholdExceeded,
+            ConversionError::InvalidChildId { .. } => ErrorKind::InvalidChildId,
+            ConversionError::FromMqttClient(_) => ErrorKind::MqttClient,
+            ConversionError::FromOperationsError(_) => ErrorKind::Operations,
+            ConversionError::FromSmartRestSerializerError(_) => ErrorKind::SmartRestSerializer,
+            ConversionError::UnsupportedTopic(_) => ErrorKind::UnsupportedTopic,
+            ConversionError::FromSerdeJson(_) => ErrorKind::SerdeJson,
+            ConversionError::FromStdIo(_) => ErrorKind::StdIo,
+            ConversionError::FromOptionError => ErrorKind::Option,
+            ConversionError::FromUtf8Error(_) => ErrorKind::Utf8,
+            ConversionError::FromTimeFormatError(_) => ErrorKind::TimeFormat,
+            ConversionError::TranslatedSizeExceededThreshold { .. } => {
+                ErrorKind::TranslatedSizeExceededThreshold
+            }
+            ConversionError::FromOperationLogsError(_) => ErrorKind::OperationLogs,
+            ConversionError::ChildDeviceNotRegistered { .. } => ErrorKind::ChildDeviceNotRegistered,
+            ConversionError::ChildDeviceRegisteredUnderAnotherParent { .. } => {
+                ErrorKind::ChildDeviceRegisteredUnderAnotherParent
+            }
+            ConversionError::JsonPathNotFound { .. } => ErrorKind::JsonPathNotFound,
+            ConversionError::NotAJsonObject { .. } => ErrorKind::NotAJsonObject,
+            ConversionError::FromScript(_) => ErrorKind::Script,
+        }
+    }
+}
+
+/// The report of a mapping error, published as a JSON object on the errors topic of a mapper
+///
+/// ```json
+/// {
+///   "mapper": "tedge-mapper-c8y",
+///   "kind": "thin_edge_json_parser",
+///   "message": "Invalid JSON: expected value at line 1 column 1: `not json`",
+///   "topic": "tedge/measurements",
+///   "payload": "not json",
+///   "time": "2021-04-08T10:12:34Z"
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    pub mapper: String,
+    pub kind: ErrorKind,
+    pub message: String,
+
+    /// The topic of the message that failed to be mapped, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+
+    /// The payload of the message that failed to be mapped,
+    /// truncated to `MAX_REPORTED_PAYLOAD_SIZE` bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub time: OffsetDateTime,
+}
+
+impl ErrorReport {
+    pub fn new(
+        mapper: &str,
+        error: &impl ReportableError,
+        input: Option<&Message>,
+        time: OffsetDateTime,
+    ) -> ErrorReport {
+        ErrorReport {
+            mapper: mapper.to_string(),
+            kind: error.kind(),
+            message: error.to_string(),
+            topic: input.map(|message| message.topic.name.clone()),
+            payload: input.map(|message| truncate_payload(message.payload_bytes())),
+            time,
+        }
+    }
+
+    /// Parse a report published on an errors topic
+    pub fn from_message(message: &Message) -> Option<ErrorReport> {
+        serde_json::from_slice(message.payload_bytes()).ok()
+    }
+
+    pub fn to_message(&self, errors_topic: &Topic) -> Message {
+        let payload = serde_json::to_string(self).expect("an error report is always serializable");
+        Message::new(errors_topic, payload)
+    }
+
+    /// The thin-edge event or alarm to be published for this report, if any.
+    ///
+    /// No event nor alarm is published for the errors raised by the mapping of such an event or alarm,
+    /// so a mapper never loops on its own error reports.
+    pub fn forwarded_message(&self, forwarding: ErrorForwarding) -> Option<Message> {
+        let (topic, payload) = match forwarding {
+            ErrorForwarding::None => return None,
+            ErrorForwarding::Event => (
+                ERROR_EVENT_TOPIC,
+                json!({
+                    "text": self.text(),
+                    "time": self.formatted_time(),
+                    "mapper": self.mapper,
+                    "kind": self.kind,
+                    "topic": self.topic,
+                }),
+            ),
+            ErrorForwarding::Alarm => (
+                ERROR_ALARM_TOPIC,
+                json!({
+                    "text": self.text(),
+                    "time": self.formatted_time(),
+                }),
+            ),
+        };
+        if self.topic.as_deref() == Some(topic) {
+            return None;
+        }
+
+        let message = Message::new(&Topic::new_unchecked(topic), payload.to_string());
+        Some(match forwarding {
+            ErrorForwarding::Alarm => message.with_retain(),
+            _ => message,
+        })
+    }
+
+    fn text(&self) -> String {
+        match &self.topic {
+            Some(topic) => format!("{}: {} (on {})", self.mapper, self.message, topic),
+            None => format!("{}: {}", self.mapper, self.message),
+        }
+    }
+
+    fn formatted_time(&self) -> Option<String> {
+        self.time.format(&Rfc3339).ok()
+    }
+}
+
+fn truncate_payload(payload: &[u8]) -> String {
+    let payload = String::from_utf8_lossy(payload);
+    if payload.len() <= MAX_REPORTED_PAYLOAD_SIZE {
+        return payload.into_owned();
+    }
+
+    let mut end = MAX_REPORTED_PAYLOAD_SIZE;
+    while !payload.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &payload[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use time::macros::datetime;
+
+    fn report_of(error: ConversionError, input: &Message) -> ErrorReport {
+        ErrorReport::new(
+            "tedge-mapper-test",
+            &error,
+            Some(input),
+            datetime!(2021-04-08 10:12:34 UTC),
+        )
+    }
+
+    #[test]
+    fn an_error_report_is_published_as_json() {
+        let input = Message::new(&Topic::new_unchecked("tedge/measurements"), "not json");
+        let report = report_of(
+            ConversionError::UnsupportedTopic("tedge/measurements".into()),
+            &input,
+        );
+        let errors_topic = Topic::new_unchecked("tedge/errors");
+
+        let message = report.to_message(&errors_topic);
+
+        assert_eq!(message.topic, errors_topic);
+        let payload: Value = serde_json::from_str(message.payload_str().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "mapper": "tedge-mapper-test",
+                "kind": "unsupported_topic",
+                "message": "Unsupported topic: tedge/measurements",
+                "topic": "tedge/measurements",
+                "payload": "not json",
+                "time": "2021-04-08T10:12:34Z",
+            })
+        );
+        assert_eq!(ErrorReport::from_message(&message), Some(report));
+    }
+
+    #[test]
+    fn an_error_report_without_input_has_no_topic_nor_payload() {
+        let report = ErrorReport::new(
+            "tedge-mapper-test",
+            &ConversionError::FromOptionError,
+            None,
+            datetime!(2021-04-08 10:12:34 UTC),
+        );
+
+        let payload: Value = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(payload.get("kind"), Some(&json!("option")));
+        assert_eq!(payload.get("topic"), None);
+        assert_eq!(payload.get("payload"), None);
+    }
+
+    #[test]
+    fn the_reported_payload_is_truncated() {
+        let long_payload = "é".repeat(MAX_REPORTED_PAYLOAD_SIZE);
+        let input = Message::new(&Topic::new_unchecked("tedge/measurements"), long_payload);
+
+        let report = report_of(ConversionError::FromOptionError, &input);
+
+        let payload = report.payload.unwrap();
+        assert!(payload.len() <= MAX_REPORTED_PAYLOAD_SIZE + 3);
+        assert!(payload.ends_with("é..."));
+    }
+
+    #[test]
+    fn an_error_report_is_forwarded_as_an_event_or_an_alarm() {
+        let input = Message::new(&Topic::new_unchecked("tedge/measurements"), "not json");
+        let report = report_of(ConversionError::FromOptionError, &input);
+
+        assert_eq!(report.forwarded_message(ErrorForwarding::None), None);
+
+        let event = report.forwarded_message(ErrorForwarding::Event).unwrap();
+        assert_eq!(event.topic.name, ERROR_EVENT_TOPIC);
+        assert!(!event.retain);
+        let payload: Value = serde_json::from_str(event.payload_str().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "text": "tedge-mapper-test: Error converting json option (on tedge/measurements)",
+                "time": "2021-04-08T10:12:34Z",
+                "mapper": "tedge-mapper-test",
+                "kind": "option",
+                "topic": "tedge/measurements",
+            })
+        );
+
+        let alarm = report.forwarded_message(ErrorForwarding::Alarm).unwrap();
+        assert_eq!(alarm.topic.name, ERROR_ALARM_TOPIC);
+        assert!(alarm.retain);
+        let payload: Value = serde_json::from_str(alarm.payload_str().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "text": "tedge-mapper-test: Error converting json option (on tedge/measurements)",
+                "time": "2021-04-08T10:12:34Z",
+            })
+        );
+    }
+
+    #[test]
+    fn the_errors_raised_by_a_forwarded_report_are_not_forwarded() {
+        let input = Message::new(&Topic::new_unchecked(ERROR_EVENT_TOPIC), "{}");
+        let report = report_of(ConversionError::FromOptionError, &input);
+
+        assert_eq!(report.forwarded_message(ErrorForwarding::Event), None);
+        assert!(report.forwarded_message(ErrorForwarding::Alarm).is_some());
+    }
+}
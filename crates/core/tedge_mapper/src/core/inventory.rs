@@ -0,0 +1,168 @@
+use crate::core::error::ConversionError;
+use mqtt_channel::Message;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// The topic prefix where local applications publish inventory fragments
+pub const INVENTORY_TOPIC: &str = "tedge/inventory/";
+
+/// An inventory fragment published on `tedge/inventory/[<child>/]<fragment>`.
+///
+/// The payload is the JSON value of the fragment, an empty payload removing the fragment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryFragment {
+    /// The path from the main device to the child device the fragment is for, empty for the main device
+    pub child_path: Vec<String>,
+    pub name: String,
+    pub value: Value,
+}
+
+impl InventoryFragment {
+    pub fn try_from(message: &Message) -> Result<Self, ConversionError> {
+        let topic = &message.topic.name;
+        let levels: Vec<&str> = topic
+            .strip_prefix(INVENTORY_TOPIC)
+            .ok_or_else(|| ConversionError::UnsupportedTopic(topic.clone()))?
+            .split('/')
+            .collect();
+        InventoryFragment::from_levels(levels, message)
+    }
+
+    /// The fragment published on the topic levels following `tedge/inventory/`,
+    /// i.e. the path of the child device, if any, followed by the fragment name.
+    pub fn from_levels(mut levels: Vec<&str>, message: &Message) -> Result<Self, ConversionError> {
+        let name = levels.pop().unwrap_or_default();
+        if name.is_empty() {
+            return Err(ConversionError::UnsupportedTopic(
+                message.topic.name.clone(),
+            ));
+        }
+        if levels.iter().any(|child_id| child_id.is_empty()) {
+            return Err(ConversionError::InvalidChildId {
+                id: levels.join("/"),
+            });
+        }
+
+        let value = if message.payload_bytes().is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(message.payload_str()?)?
+        };
+
+        Ok(InventoryFragment {
+            child_path: levels.into_iter().map(String::from).collect(),
+            name: name.to_string(),
+            value,
+        })
+    }
+
+    /// The id of the child device the fragment is for, if any
+    pub fn child_id(&self) -> Option<&str> {
+        self.child_path.last().map(String::as_str)
+    }
+}
+
+/// The inventory fragments received since the last updates sent to the cloud, merged per device.
+///
+/// Successive values of a fragment are not sent one by one,
+/// only the latest value being sent when the updates are flushed.
+#[derive(Debug, Default)]
+pub struct InventoryUpdates {
+    pending: BTreeMap<Vec<String>, Map<String, Value>>,
+}
+
+impl InventoryUpdates {
+    /// Add a fragment, replacing any value of the same fragment not sent yet
+    pub fn add(&mut self, fragment: InventoryFragment) {
+        self.pending
+            .entry(fragment.child_path)
+            .or_default()
+            .insert(fragment.name, fragment.value);
+    }
+
+    /// The merged fragments of each device, the main device being first, along the path of the device.
+    pub fn flush(&mut self) -> Vec<(Vec<String>, Value)> {
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|(child_path, fragments)| (child_path, Value::Object(fragments)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use mqtt_channel::Topic;
+    use serde_json::json;
+
+    fn message(topic: &str, payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload)
+    }
+
+    #[test]
+    fn parse_inventory_fragments() {
+        let fragment = InventoryFragment::try_from(&message(
+            "tedge/inventory/c8y_Firmware",
+            r#"{"version": "1.0"}"#,
+        ))
+        .unwrap();
+        assert_eq!(fragment.child_id(), None);
+        assert_eq!(fragment.name, "c8y_Firmware");
+        assert_eq!(fragment.value, json!({"version": "1.0"}));
+
+        let fragment = InventoryFragment::try_from(&message(
+            "tedge/inventory/plc/sensor/serial",
+            r#""ABC-123""#,
+        ))
+        .unwrap();
+        assert_eq!(fragment.child_path, vec!["plc", "sensor"]);
+        assert_eq!(fragment.child_id(), Some("sensor"));
+        assert_eq!(fragment.value, json!("ABC-123"));
+
+        let fragment =
+            InventoryFragment::try_from(&message("tedge/inventory/location", "")).unwrap();
+        assert_eq!(fragment.value, Value::Null);
+    }
+
+    #[test]
+    fn reject_invalid_inventory_fragments() {
+        assert_matches!(
+            InventoryFragment::try_from(&message("tedge/inventory/", "{}")),
+            Err(ConversionError::UnsupportedTopic(_))
+        );
+        assert_matches!(
+            InventoryFragment::try_from(&message("tedge/inventory//serial", "{}")),
+            Err(ConversionError::InvalidChildId { .. })
+        );
+        assert_matches!(
+            InventoryFragment::try_from(&message("tedge/inventory/serial", "not json")),
+            Err(ConversionError::FromSerdeJson(_))
+        );
+    }
+
+    #[test]
+    fn fragments_are_merged_per_device_until_flushed() {
+        let mut updates = InventoryUpdates::default();
+        for (topic, payload) in [
+            ("tedge/inventory/child/serial", r#""123""#),
+            ("tedge/inventory/c8y_Firmware", r#"{"version": "1.0"}"#),
+            ("tedge/inventory/c8y_Firmware", r#"{"version": "1.1"}"#),
+            ("tedge/inventory/location", r#""Paris""#),
+        ] {
+            updates.add(InventoryFragment::try_from(&message(topic, payload)).unwrap());
+        }
+
+        assert_eq!(
+            updates.flush(),
+            vec![
+                (
+                    vec![],
+                    json!({"c8y_Firmware": {"version": "1.1"}, "location": "Paris"})
+                ),
+                (vec!["child".to_string()], json!({"serial": "123"})),
+            ]
+        );
+        assert!(updates.flush().is_empty());
+    }
+}
@@ -1,24 +1,65 @@
 use crate::c8y::dynamic_discovery::*;
-use crate::core::{converter::*, error::*};
+use crate::core::{
+    converter::*,
+    error::*,
+    error_report::{clear_error_alarm_message, ErrorReport, ERROR_ALARM_TOPIC},
+    store_and_forward::StoreAndForward,
+};
 use mqtt_channel::{
-    Connection, Message, MqttError, SinkExt, StreamExt, TopicFilter, UnboundedReceiver,
-    UnboundedSender,
+    topic_prefix, Connection, Message, Metrics, MqttError, SinkExt, StreamExt, Topic, TopicFilter,
+    UnboundedReceiver, UnboundedSender,
 };
 
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tedge_config::{
+    ConfigSettingAccessor, ConfigSettingError, ErrorForwarding, MapperErrorsForwardSetting,
+    MapperSyncWindowSecondsSetting, TEdgeConfig,
+};
 use tedge_utils::fs_notify::{fs_notify_stream, pin_mut, FileEvent};
-use thin_edge_json::health::{health_check_topics, send_health_status};
+use thin_edge_json::health::{
+    health_check_topics, health_metrics_topic, send_health_status, with_health_status,
+};
+use tokio::time::MissedTickBehavior;
 
 use tracing::{error, info, instrument, warn};
+/// Default maximum duration of the sync phase, which usually ends earlier on the sync marker
 const SYNC_WINDOW: Duration = Duration::from_secs(3);
+const METRICS_PERIOD: Duration = Duration::from_secs(60);
+/// Delay between two stored messages forwarded to the cloud once the bridge is up again
+const FORWARD_PERIOD: Duration = Duration::from_millis(10);
+/// Delay between two requests to the converter for the messages it has held back
+const FLUSH_PERIOD: Duration = Duration::from_secs(1);
+
+const CONVERSIONS: &str = "mapper_conversions_total";
+const CONVERSION_ERRORS: &str = "mapper_conversion_errors_total";
+const SIZE_THRESHOLD_REJECTIONS: &str = "mapper_size_threshold_rejections_total";
+const CONVERSION_DURATION: &str = "mapper_conversion_duration_seconds";
 use std::result::Result::Ok;
 
 pub async fn create_mapper(
     app_name: &str,
-    mqtt_host: String,
-    mqtt_port: u16,
+    mqtt_config: mqtt_channel::Config,
     converter: Box<dyn Converter<Error = ConversionError>>,
+) -> Result<Mapper, anyhow::Error> {
+    create_mapper_with_store(app_name, mqtt_config, converter, None).await
+}
+
+/// Create a mapper that stores the messages to the cloud while the bridge is down.
+pub async fn create_store_and_forward_mapper(
+    app_name: &str,
+    mqtt_config: mqtt_channel::Config,
+    converter: Box<dyn Converter<Error = ConversionError>>,
+    store_and_forward: StoreAndForward,
+) -> Result<Mapper, anyhow::Error> {
+    create_mapper_with_store(app_name, mqtt_config, converter, Some(store_and_forward)).await
+}
+
+async fn create_mapper_with_store(
+    app_name: &str,
+    mqtt_config: mqtt_channel::Config,
+    mut converter: Box<dyn Converter<Error = ConversionError>>,
+    store_and_forward: Option<StoreAndForward>,
 ) -> Result<Mapper, anyhow::Error> {
     info!("{} starting", app_name);
 
@@ -27,33 +68,55 @@ pub async fn create_mapper(
     let mapper_config = converter.get_mapper_config();
     let mut topic_filter = mapper_config.in_topic_filter.clone();
     topic_filter.add_all(health_check_topics.clone());
+    topic_filter.add(&sync_marker_topic(app_name).name)?;
+    if let Some(store_and_forward) = &store_and_forward {
+        topic_filter.add_all(store_and_forward.bridge_status_topic());
+    }
 
-    let mqtt_client =
-        Connection::new(&mqtt_config(app_name, &mqtt_host, mqtt_port, topic_filter)?).await?;
+    let metrics = Metrics::default();
+    let mqtt_config =
+        mapper_mqtt_config(app_name, mqtt_config, topic_filter).with_metrics(metrics.clone());
+    let mqtt_client = Connection::new(&mqtt_config).await?;
 
     Mapper::subscribe_errors(mqtt_client.errors);
-
-    Ok(Mapper::new(
+    converter.set_subscription_handle(mqtt_client.subscriptions);
+    metrics.publish_periodically(
+        health_metrics_topic(app_name),
+        METRICS_PERIOD,
+        mqtt_client.published.clone(),
+    );
+
+    let mapper = Mapper::new(
         app_name.to_string(),
         mqtt_client.received,
         mqtt_client.published,
         converter,
         health_check_topics,
-    ))
+    )
+    .with_metrics(metrics);
+    Ok(match store_and_forward {
+        Some(store_and_forward) => mapper.with_store_and_forward(store_and_forward),
+        None => mapper,
+    })
+}
+
+/// The topic where a mapper publishes a marker to itself on startup.
+///
+/// The broker sends the retained messages to the mapper as soon as subscribed,
+/// hence before this marker that is published after the subscriptions.
+fn sync_marker_topic(mapper_name: &str) -> Topic {
+    Topic::new_unchecked(&format!("tedge/sync/{}", mapper_name))
 }
 
-pub fn mqtt_config(
+fn mapper_mqtt_config(
     name: &str,
-    host: &str,
-    port: u16,
+    mqtt_config: mqtt_channel::Config,
     topic_filter: TopicFilter,
-) -> Result<mqtt_channel::Config, anyhow::Error> {
-    Ok(mqtt_channel::Config::default()
-        .with_host(host)
-        .with_port(port)
+) -> mqtt_channel::Config {
+    with_health_status(mqtt_config, name)
         .with_session_name(name)
         .with_subscriptions(topic_filter)
-        .with_max_packet_size(10 * 1024 * 1024))
+        .with_max_packet_size(10 * 1024 * 1024)
 }
 
 pub struct Mapper {
@@ -62,6 +125,13 @@ pub struct Mapper {
     output: UnboundedSender<Message>,
     converter: Box<dyn Converter<Error = ConversionError>>,
     health_check_topics: TopicFilter,
+    metrics: Metrics,
+    store_and_forward: Option<StoreAndForward>,
+    error_forwarding: ErrorForwarding,
+    /// Set while a mapping error alarm is raised, to be cleared by the next successful conversion
+    error_alarm_raised: bool,
+    sync_window: Duration,
+    sync_marker_topic: Topic,
 }
 
 impl Mapper {
@@ -72,15 +142,66 @@ impl Mapper {
         converter: Box<dyn Converter<Error = ConversionError>>,
         health_check_topics: TopicFilter,
     ) -> Self {
+        let sync_marker_topic = sync_marker_topic(&mapper_name);
         Self {
             mapper_name,
             input,
             output,
             converter,
             health_check_topics,
+            metrics: Metrics::default(),
+            store_and_forward: None,
+            error_forwarding: ErrorForwarding::None,
+            error_alarm_raised: false,
+            sync_window: SYNC_WINDOW,
+            sync_marker_topic,
+        }
+    }
+
+    /// Record the conversions in the given metrics
+    pub fn with_metrics(self, metrics: Metrics) -> Self {
+        Self { metrics, ..self }
+    }
+
+    /// Store the messages to the cloud while the bridge is down
+    pub fn with_store_and_forward(self, store_and_forward: StoreAndForward) -> Self {
+        Self {
+            store_and_forward: Some(store_and_forward),
+            ..self
+        }
+    }
+
+    /// Also publish the error reports as thin-edge events or alarms, to be forwarded to the cloud
+    pub fn with_error_forwarding(self, error_forwarding: ErrorForwarding) -> Self {
+        Self {
+            error_forwarding,
+            ..self
         }
     }
 
+    /// Wait at most the given duration on startup for the retained messages to be synced
+    pub fn with_sync_window(self, sync_window: Duration) -> Self {
+        Self {
+            sync_window,
+            ..self
+        }
+    }
+
+    /// Apply the settings shared by all the mappers
+    pub fn with_mapper_settings(
+        self,
+        tedge_config: &TEdgeConfig,
+    ) -> Result<Self, ConfigSettingError> {
+        Ok(self
+            .with_error_forwarding(tedge_config.query(MapperErrorsForwardSetting)?)
+            .with_sync_window(tedge_config.query(MapperSyncWindowSecondsSetting)?.into()))
+    }
+
+    /// The metrics of this mapper and of its MQTT connection
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
     pub(crate) async fn run(&mut self, ops_dir: Option<&Path>) -> Result<(), MapperError> {
         info!("Running");
         self.process_messages(ops_dir).await?;
@@ -100,12 +221,21 @@ impl Mapper {
     async fn process_messages(&mut self, ops_dir: Option<&Path>) -> Result<(), MapperError> {
         let init_messages = self.converter.init_messages();
         for init_message in init_messages.into_iter() {
-            let _ = self.output.send(init_message).await;
+            self.publish(init_message).await;
         }
 
-        // Start the sync phase here and process messages until the sync window times out
-        let _ = tokio::time::timeout(SYNC_WINDOW, async {
+        // Start the sync phase here and process messages until the broker sends back the sync marker,
+        // i.e. until all the retained messages have been received, or until the sync window times out
+        let sync_marker = self.sync_marker();
+        let _ = self.output.send(sync_marker.clone()).await;
+        let _ = tokio::time::timeout(self.sync_window, async {
             while let Some(message) = self.input.next().await {
+                // The marker is compared on its topic and payload only, the broker setting the other fields
+                if message.topic == sync_marker.topic
+                    && message.payload_bytes() == sync_marker.payload_bytes()
+                {
+                    break;
+                }
                 self.process_message(message).await;
             }
         })
@@ -117,24 +247,123 @@ impl Mapper {
             self.process_message(message).await;
         }
 
+        // Then publish the state reconciled by the converter
+        let synced_messages = self.converter.synced_messages();
+        for message in synced_messages {
+            self.publish(message).await;
+        }
+
         process_messages(self, ops_dir).await?;
         Ok(())
     }
 
+    /// A marker unique to this run of the mapper, so a marker left by a previous run is ignored
+    fn sync_marker(&self) -> Message {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos());
+        Message::new(&self.sync_marker_topic, format!("{}", nanos))
+    }
+
     async fn process_message(&mut self, message: Message) {
         if self.health_check_topics.accept(&message) {
             send_health_status(&mut self.output, &self.mapper_name).await;
-        } else {
-            let converted_messages = self.converter.convert(&message).await;
+        } else if message.topic == self.sync_marker_topic {
+            // A sync marker received after the sync phase is over
+        } else if !self.update_bridge_status(&message) {
+            let converted_messages = self.convert(&message).await;
 
             for converted_message in converted_messages.into_iter() {
-                let _ = self.output.send(converted_message).await;
+                self.publish(converted_message).await;
+            }
+        }
+    }
+
+    /// Update the bridge status, returning false if the message is not a bridge notification
+    fn update_bridge_status(&mut self, message: &Message) -> bool {
+        self.store_and_forward
+            .as_mut()
+            .map_or(false, |store| store.update_bridge_status(message))
+    }
+
+    /// Publish a message, unless stored to be forwarded later to the cloud
+    async fn publish(&mut self, message: Message) {
+        if let Some(forwarded_error) = self.forwarded_error(&message) {
+            if forwarded_error.topic.name == ERROR_ALARM_TOPIC {
+                self.error_alarm_raised = true;
+            }
+            let _ = self.output.send(forwarded_error).await;
+        }
+
+        let message = match self.store_and_forward.as_mut() {
+            Some(store) => store.forward_or_store(message).await,
+            None => Some(message),
+        };
+        if let Some(message) = message {
+            let _ = self.output.send(message).await;
+        }
+    }
+
+    /// The event or alarm to be published along an error report, if configured so
+    fn forwarded_error(&self, message: &Message) -> Option<Message> {
+        if self.error_forwarding == ErrorForwarding::None
+            || message.topic != self.converter.get_mapper_config().errors_topic
+        {
+            return None;
+        }
+        ErrorReport::from_message(message)
+            .and_then(|report| report.forwarded_message(self.error_forwarding))
+    }
+
+    fn has_messages_to_forward(&self) -> bool {
+        self.store_and_forward
+            .as_ref()
+            .map_or(false, |store| store.has_messages_to_forward())
+    }
+
+    async fn flush_converter(&mut self) {
+        for message in self.converter.flush_messages().await {
+            self.publish(message).await;
+        }
+    }
+
+    async fn forward_stored_message(&mut self) {
+        if let Some(store) = self.store_and_forward.as_mut() {
+            store.forward_next_message(&mut self.output).await;
+        }
+    }
+
+    async fn convert(&mut self, message: &Message) -> Vec<Message> {
+        let labels = [("prefix", topic_prefix(&message.topic))];
+        let start = Instant::now();
+        let messages_or_err = self.converter.try_convert(message).await;
+        self.metrics
+            .observe(CONVERSION_DURATION, &labels, start.elapsed().as_secs_f64());
+
+        match &messages_or_err {
+            Ok(_) => self.metrics.increment(CONVERSIONS, &labels),
+            Err(ConversionError::SizeThresholdExceeded { .. }) => {
+                self.metrics.increment(SIZE_THRESHOLD_REJECTIONS, &labels);
+                self.metrics.increment(CONVERSION_ERRORS, &labels);
             }
+            Err(_) => self.metrics.increment(CONVERSION_ERRORS, &labels),
+        }
+        if messages_or_err.is_ok() && self.error_alarm_raised {
+            self.error_alarm_raised = false;
+            let _ = self.output.send(clear_error_alarm_message()).await;
         }
+
+        self.converter.wrap_input_errors(message, messages_or_err)
     }
 }
 
 async fn process_messages(mapper: &mut Mapper, path: Option<&Path>) -> Result<(), MapperError> {
+    // The stored messages are forwarded at a limited rate, not to flood the bridge
+    let mut forward_ticks = tokio::time::interval(FORWARD_PERIOD);
+    forward_ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut flush_ticks = tokio::time::interval(FLUSH_PERIOD);
+    flush_ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
     if let Some(path) = path {
         let fs_notification_stream = fs_notify_stream(&[(
             path,
@@ -148,12 +377,20 @@ async fn process_messages(mapper: &mut Mapper, path: Option<&Path>) -> Result<()
                 Some(message) =  mapper.input.next() => {
                     mapper.process_message(message).await;
                 }
+                _ = forward_ticks.tick(), if mapper.has_messages_to_forward() => {
+                    mapper.forward_stored_message().await;
+                }
+                _ = flush_ticks.tick() => {
+                    mapper.flush_converter().await;
+                }
                 Some(event_or_error) = fs_notification_stream.next() => {
                     match event_or_error {
                         Ok((path, mask)) =>  {
                             match  process_inotify_events(&path, mask) {
                                 Ok(Some(discovered_ops)) => {
-                                     let _ = mapper.output.send(mapper.converter.process_operation_update_message(discovered_ops)).await;
+                                    if let Some(message) = mapper.converter.process_operation_update_message(discovered_ops) {
+                                        let _ = mapper.output.send(message).await;
+                                    }
                                 }
                                 Ok(None) => {}
                                 Err(e) => {eprintln!("Processing inotify event failed due to {}", e);}
@@ -167,10 +404,20 @@ async fn process_messages(mapper: &mut Mapper, path: Option<&Path>) -> Result<()
             }
         }
     } else {
-        while let Some(message) = mapper.input.next().await {
-            mapper.process_message(message).await;
+        loop {
+            tokio::select! {
+                message = mapper.input.next() => match message {
+                    Some(message) => mapper.process_message(message).await,
+                    None => return Ok(()),
+                },
+                _ = forward_ticks.tick(), if mapper.has_messages_to_forward() => {
+                    mapper.forward_stored_message().await;
+                }
+                _ = flush_ticks.tick() => {
+                    mapper.flush_converter().await;
+                }
+            }
         }
-        Ok(())
     }
 }
 
@@ -194,8 +441,7 @@ mod tests {
         let name = "mapper_under_test";
         let mut mapper = create_mapper(
             name,
-            "localhost".into(),
-            broker.port,
+            mqtt_channel::Config::default().with_port(broker.port),
             Box::new(UppercaseConverter::new()),
         )
         .await?;
@@ -219,12 +465,132 @@ mod tests {
 
         // Ill-formed input
         let input = "éèê";
-        let expected = Some(format!("{}", UppercaseConverter::conversion_error()));
         let actual = broker
             .wait_for_response_on_publish("in_topic", input, "err_topic", timeout)
+            .await
+            .expect("JSON error report");
+        let report: Value = serde_json::from_str(actual.as_str())?;
+        assert_json_include!(
+            actual: &report,
+            expected: json!({
+                "mapper": "test-mapper",
+                "kind": "mapper",
+                "message": UppercaseConverter::conversion_error().to_string(),
+                "topic": "in_topic",
+                "payload": input,
+            })
+        );
+        assert!(report["time"].is_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_reports_are_forwarded_as_configured() {
+        let (_input_sender, input) = futures::channel::mpsc::unbounded();
+        let (output, _output_receiver) = futures::channel::mpsc::unbounded();
+        let converter = UppercaseConverter::new();
+        let error_report = converter.new_error_message(
+            UppercaseConverter::conversion_error(),
+            Some(&Message::new(&Topic::new_unchecked("in_topic"), "éèê")),
+        );
+        let mapper = Mapper::new(
+            "mapper_under_test".into(),
+            input,
+            output,
+            Box::new(converter),
+            TopicFilter::empty(),
+        );
+        assert_eq!(mapper.forwarded_error(&error_report), None);
+
+        let mapper = mapper.with_error_forwarding(ErrorForwarding::Alarm);
+        let alarm = mapper.forwarded_error(&error_report).expect("an alarm");
+        assert_eq!(alarm.topic.name, "tedge/alarms/major/mapping_error");
+
+        let output = Message::new(&Topic::new_unchecked("out_topic"), "ABCDE");
+        assert_eq!(mapper.forwarded_error(&output), None);
+    }
+
+    #[tokio::test]
+    async fn the_mapping_error_alarm_is_cleared_by_the_next_successful_conversion() {
+        let (_input_sender, input) = futures::channel::mpsc::unbounded();
+        let (output, mut output_receiver) = futures::channel::mpsc::unbounded();
+        let mut mapper = Mapper::new(
+            "mapper_under_test".into(),
+            input,
+            output,
+            Box::new(UppercaseConverter::new()),
+            TopicFilter::empty(),
+        )
+        .with_error_forwarding(ErrorForwarding::Alarm);
+        let in_topic = Topic::new_unchecked("in_topic");
+
+        mapper.process_message(Message::new(&in_topic, "éèê")).await;
+        mapper.process_message(Message::new(&in_topic, "abc")).await;
+        mapper.process_message(Message::new(&in_topic, "def")).await;
+        drop(mapper);
+
+        let published: Vec<(String, String, bool)> = output_receiver
+            .map(|message| {
+                let payload = message.payload_str().unwrap().to_string();
+                (message.topic.name, payload, message.retain)
+            })
+            .collect()
             .await;
-        assert_eq!(expected, actual);
+        let topics: Vec<&str> = published
+            .iter()
+            .map(|(topic, _, _)| topic.as_str())
+            .collect();
+        assert_eq!(
+            topics,
+            vec![
+                ERROR_ALARM_TOPIC,
+                "err_topic",
+                ERROR_ALARM_TOPIC,
+                "out_topic",
+                "out_topic"
+            ]
+        );
+        assert_eq!(published[2].1, "");
+        assert!(published[2].2);
+    }
+
+    #[tokio::test]
+    async fn the_sync_phase_ends_when_the_sync_marker_is_received() -> Result<(), anyhow::Error> {
+        let (mut input_sender, input) = futures::channel::mpsc::unbounded();
+        let (output, mut output_receiver) = futures::channel::mpsc::unbounded();
+        let mut mapper = Mapper::new(
+            "mapper_under_test".into(),
+            input,
+            output,
+            Box::new(UppercaseConverter::new()),
+            TopicFilter::empty(),
+        )
+        .with_sync_window(Duration::from_secs(60));
+        tokio::spawn(async move {
+            let _ = mapper.run(None).await;
+        });
 
+        // The mapper publishes a marker to itself, that the broker sends back after the retained messages
+        let sync_marker = output_receiver.next().await.expect("a sync marker");
+        assert_eq!(sync_marker.topic.name, "tedge/sync/mapper_under_test");
+        input_sender
+            .send(Message::new(&Topic::new_unchecked("in_topic"), "abcde"))
+            .await?;
+        // The broker might deliver the marker with a lower QoS than the one used to publish it
+        input_sender
+            .send(sync_marker.with_qos(mqtt_channel::QoS::AtMostOnce))
+            .await?;
+
+        // The messages received during the sync phase are converted
+        let output = output_receiver.next().await.expect("a converted message");
+        assert_eq!(output.payload_str()?, "ABCDE");
+
+        // And the reconciled state is published as soon as the marker is received
+        let synced = tokio::time::timeout(Duration::from_secs(1), output_receiver.next())
+            .await?
+            .expect("a synced message");
+        assert_eq!(synced.topic.name, "synced_topic");
         Ok(())
     }
 
@@ -241,8 +607,7 @@ mod tests {
 
         let mut mapper = create_mapper(
             name,
-            "localhost".to_string(),
-            broker.port,
+            mqtt_channel::Config::default().with_port(broker.port),
             Box::new(UppercaseConverter::new()),
         )
         .await?;
@@ -293,6 +658,7 @@ mod tests {
     impl UppercaseConverter {
         pub fn new() -> UppercaseConverter {
             let mapper_config = MapperConfig {
+                mapper_name: "test-mapper".into(),
                 in_topic_filter: TopicFilter::new("in_topic").expect("invalid topic filter"),
                 out_topic: Topic::new_unchecked("out_topic"),
                 errors_topic: Topic::new_unchecked("err_topic"),
@@ -326,5 +692,12 @@ mod tests {
                 Err(UppercaseConverter::conversion_error())
             }
         }
+
+        fn try_synced_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+            Ok(vec![Message::new(
+                &Topic::new_unchecked("synced_topic"),
+                "synced",
+            )])
+        }
     }
 }
@@ -0,0 +1,781 @@
+use crate::core::error::ConversionError;
+
+use batcher::TimeWindow;
+use mqtt_channel::{Message, Topic, TopicFilter};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use thin_edge_json::measurement::MeasurementVisitor;
+use thin_edge_json::serialize::ThinEdgeJsonSerializer;
+use time::{Duration, OffsetDateTime};
+
+/// The directory, relative to the config directory, where the measurement policies are stored
+pub const POLICIES_DIR: &str = "measurement-policies";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    #[error("Failed to read the measurement policies from {path}: {error}")]
+    FromIo {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+
+    #[error("Invalid measurement policies in {path}: {error}")]
+    FromToml {
+        path: PathBuf,
+        #[source]
+        error: toml::de::Error,
+    },
+
+    #[error("Invalid topic filter '{filter}' in {path}")]
+    InvalidTopicFilter { path: PathBuf, filter: String },
+
+    #[error("Invalid measurement policy in {path}: {reason}")]
+    InvalidPolicy { path: PathBuf, reason: &'static str },
+}
+
+/// How the values of a series are aggregated over a time window
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    Avg,
+    Min,
+    Max,
+    Last,
+}
+
+/// The content of a policy file, as a list of `[[policy]]` tables
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    policy: Vec<PolicySpec>,
+}
+
+/// A policy as written by the user
+///
+/// ```toml
+/// [[policy]]
+/// topic = "tedge/measurements/+"
+/// series = "temperature"
+/// min_interval_seconds = 60
+/// deadband = 0.5
+///
+/// [[policy]]
+/// series = "location"
+/// aggregate = "last"
+/// window_seconds = 300
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicySpec {
+    topic: Option<String>,
+    series: Option<String>,
+    min_interval_seconds: Option<u64>,
+    deadband: Option<f64>,
+    aggregate: Option<Aggregation>,
+    window_seconds: Option<u64>,
+}
+
+/// A policy applied to the measurement series matching a topic filter and a series name
+#[derive(Debug, Clone)]
+struct Policy {
+    topic: Option<TopicFilter>,
+    series: Option<String>,
+    rule: PolicyRule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PolicyRule {
+    /// Forward a value only if received at least `min_interval` after the latest value forwarded,
+    /// and differing from this value by more than `deadband`.
+    ///
+    /// The interval is measured on the receive times, not to be fooled by the clocks of the devices.
+    Throttle {
+        min_interval: Duration,
+        deadband: Option<f64>,
+    },
+
+    /// Forward a single value per time window, aggregating the values received during the window.
+    Aggregate {
+        aggregation: Aggregation,
+        window: Duration,
+    },
+}
+
+impl Policy {
+    /// A policy applies to a measurement `name` of a `group` when the series is not given,
+    /// or is either the `name` of a measurement out of any group, the `group.name`, or the `group`.
+    fn applies_to(&self, topic: &Topic, group: Option<&str>, name: &str) -> bool {
+        if let Some(filter) = &self.topic {
+            if !filter.accept_topic(topic) {
+                return false;
+            }
+        }
+        match (&self.series, group) {
+            (None, _) => true,
+            (Some(series), None) => series == name,
+            (Some(series), Some(group)) => {
+                series == group
+                    || series
+                        .strip_prefix(group)
+                        .and_then(|rest| rest.strip_prefix('.'))
+                        == Some(name)
+            }
+        }
+    }
+}
+
+/// Rate limiting, deduplication and downsampling of the measurement series sent to the cloud
+///
+/// The policies of a mapper are read from `<config_dir>/measurement-policies/<mapper-name>.toml`,
+/// a series being the values of a measurement name, grouped or not, published on a topic.
+/// The first policy that applies to a series is used, the series with no policy being forwarded unchanged.
+///
+/// The aggregated values are published when their time window is over,
+/// using the start of the window as timestamp.
+#[derive(Debug, Default)]
+pub struct MeasurementPolicies {
+    policies: Vec<Policy>,
+    series: HashMap<SeriesKey, SeriesState>,
+    closed_windows: Vec<(SeriesKey, TimeWindow, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct SeriesKey {
+    topic: String,
+    group: Option<String>,
+    name: String,
+}
+
+#[derive(Debug)]
+enum SeriesState {
+    Throttled {
+        received: OffsetDateTime,
+        value: f64,
+    },
+    Aggregated {
+        window: TimeWindow,
+        aggregate: Aggregate,
+    },
+}
+
+#[derive(Debug)]
+struct Aggregate {
+    aggregation: Aggregation,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    last: f64,
+}
+
+impl Aggregate {
+    fn new(aggregation: Aggregation, value: f64) -> Self {
+        Aggregate {
+            aggregation,
+            count: 1,
+            sum: value,
+            min: value,
+            max: value,
+            last: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.last = value;
+    }
+
+    fn value(&self) -> f64 {
+        match self.aggregation {
+            Aggregation::Avg => self.sum / self.count as f64,
+            Aggregation::Min => self.min,
+            Aggregation::Max => self.max,
+            Aggregation::Last => self.last,
+        }
+    }
+}
+
+impl MeasurementPolicies {
+    /// Load the policies of the given mapper, none being applied if there is no policy file.
+    pub fn load(config_dir: &Path, mapper_name: &str) -> Result<Self, PolicyError> {
+        let path = config_dir
+            .join(POLICIES_DIR)
+            .join(format!("{}.toml", mapper_name));
+        if !path.exists() {
+            return Ok(MeasurementPolicies::default());
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(|error| PolicyError::FromIo {
+            path: path.clone(),
+            error,
+        })?;
+        MeasurementPolicies::parse(&path, &content)
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Self, PolicyError> {
+        let file: PolicyFile = toml::from_str(content).map_err(|error| PolicyError::FromToml {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let invalid_policy = |reason| PolicyError::InvalidPolicy {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        let mut policies = vec![];
+        for spec in file.policy {
+            let topic = match spec.topic {
+                Some(filter) => Some(TopicFilter::new(&filter).map_err(|_| {
+                    PolicyError::InvalidTopicFilter {
+                        path: path.to_path_buf(),
+                        filter,
+                    }
+                })?),
+                None => None,
+            };
+
+            let rule = match (spec.aggregate, spec.window_seconds) {
+                (Some(_), _) | (_, Some(_))
+                    if spec.min_interval_seconds.is_some() || spec.deadband.is_some() =>
+                {
+                    return Err(invalid_policy(
+                        "an aggregation cannot be combined with a minimum interval or a deadband",
+                    ))
+                }
+                (Some(aggregation), Some(window)) if window > 0 => PolicyRule::Aggregate {
+                    aggregation,
+                    window: Duration::seconds(window as i64),
+                },
+                (Some(_), _) => {
+                    return Err(invalid_policy(
+                        "an aggregation requires a non-zero window_seconds",
+                    ))
+                }
+                (None, Some(_)) => {
+                    return Err(invalid_policy("a window requires an aggregate function"))
+                }
+                (None, None) if spec.min_interval_seconds.is_none() && spec.deadband.is_none() => {
+                    return Err(invalid_policy(
+                        "a policy requires a minimum interval, a deadband or an aggregation",
+                    ))
+                }
+                (None, None) => PolicyRule::Throttle {
+                    min_interval: Duration::seconds(spec.min_interval_seconds.unwrap_or(0) as i64),
+                    deadband: spec.deadband,
+                },
+            };
+
+            policies.push(Policy {
+                topic,
+                series: spec.series,
+                rule,
+            });
+        }
+
+        Ok(MeasurementPolicies {
+            policies,
+            ..MeasurementPolicies::default()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Apply the policies to the measurements of a message received `now`,
+    /// returning the measurements to be sent, if any.
+    ///
+    /// The measurements are rate limited on their receive time,
+    /// and aggregated on their timestamp, the measurements with no timestamp being considered as measured now.
+    ///
+    /// The message is returned unchanged when no policy applies to its measurements.
+    pub fn apply(
+        &mut self,
+        input: &Message,
+        now: OffsetDateTime,
+    ) -> Result<Option<Message>, ConversionError> {
+        if self.is_empty() {
+            return Ok(Some(input.clone()));
+        }
+
+        let mut measurements = MeasurementCollector::default();
+        thin_edge_json::parser::parse_str(input.payload_str()?, &mut measurements)?;
+        let time = measurements.timestamp.unwrap_or(now);
+
+        let mut forwarded = vec![];
+        let mut unchanged = true;
+        for (group, name, value) in measurements.values {
+            let rule = self
+                .policies
+                .iter()
+                .find(|policy| policy.applies_to(&input.topic, group.as_deref(), &name))
+                .map(|policy| policy.rule);
+            let key = SeriesKey {
+                topic: input.topic.name.clone(),
+                group,
+                name,
+            };
+            let forward = match rule {
+                None => true,
+                Some(rule) => {
+                    unchanged = false;
+                    self.update_series(key.clone(), rule, time, now, value)
+                }
+            };
+            if forward {
+                forwarded.push((key.group, key.name, value));
+            }
+        }
+
+        if unchanged {
+            Ok(Some(input.clone()))
+        } else if forwarded.is_empty() {
+            Ok(None)
+        } else {
+            let payload = serialize(measurements.timestamp, forwarded)?;
+            Ok(Some(
+                Message::new(&input.topic, payload).with_qos(input.qos),
+            ))
+        }
+    }
+
+    /// Update the state of a series with a new value, measured at `time` and received at `now`,
+    /// returning true if this value has to be sent.
+    fn update_series(
+        &mut self,
+        key: SeriesKey,
+        rule: PolicyRule,
+        time: OffsetDateTime,
+        now: OffsetDateTime,
+        value: f64,
+    ) -> bool {
+        match rule {
+            PolicyRule::Throttle {
+                min_interval,
+                deadband,
+            } => {
+                let forward = match self.series.get(&key) {
+                    Some(SeriesState::Throttled {
+                        received: last_received,
+                        value: last_value,
+                    }) => {
+                        now - *last_received >= min_interval
+                            && deadband
+                                .map_or(true, |deadband| (value - last_value).abs() > deadband)
+                    }
+                    _ => true,
+                };
+                if forward {
+                    self.series.insert(
+                        key,
+                        SeriesState::Throttled {
+                            received: now,
+                            value,
+                        },
+                    );
+                }
+                forward
+            }
+
+            PolicyRule::Aggregate {
+                aggregation,
+                window,
+            } => {
+                let value_window = TimeWindow::containing(time, window);
+                match self.series.get_mut(&key) {
+                    Some(SeriesState::Aggregated { window, aggregate })
+                        if *window == value_window =>
+                    {
+                        aggregate.add(value);
+                    }
+                    // The values received after the end of their window are dropped
+                    Some(SeriesState::Aggregated { window, .. }) if *window > value_window => {}
+                    _ => {
+                        let state = SeriesState::Aggregated {
+                            window: value_window,
+                            aggregate: Aggregate::new(aggregation, value),
+                        };
+                        if let Some(SeriesState::Aggregated { window, aggregate }) =
+                            self.series.insert(key.clone(), state)
+                        {
+                            self.closed_windows.push((key, window, aggregate.value()));
+                        }
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// Return the measurement messages aggregating the values of the time windows over at the given time
+    pub fn flush(&mut self, now: OffsetDateTime) -> Result<Vec<Message>, ConversionError> {
+        let mut closed_windows = std::mem::take(&mut self.closed_windows);
+        self.series.retain(|key, state| match state {
+            SeriesState::Aggregated { window, aggregate } if window.is_closed_at(now) => {
+                closed_windows.push((key.clone(), *window, aggregate.value()));
+                false
+            }
+            _ => true,
+        });
+
+        // The values of the same topic and window are sent together
+        let mut messages: BTreeMap<(String, OffsetDateTime), Vec<_>> = BTreeMap::new();
+        for (key, window, value) in closed_windows {
+            messages
+                .entry((key.topic, window.start()))
+                .or_default()
+                .push((key.group, key.name, value));
+        }
+
+        let mut output = vec![];
+        for ((topic, time), mut values) in messages {
+            values.sort_by(|(g1, n1, _), (g2, n2, _)| (g1, n1).cmp(&(g2, n2)));
+            let payload = serialize(Some(time), values)?;
+            output.push(Message::new(&Topic::new_unchecked(&topic), payload));
+        }
+        Ok(output)
+    }
+}
+
+/// Serialize measurements as Thin Edge JSON, the measurements of a group being contiguous
+fn serialize(
+    timestamp: Option<OffsetDateTime>,
+    values: Vec<(Option<String>, String, f64)>,
+) -> Result<String, ConversionError> {
+    let mut serializer = ThinEdgeJsonSerializer::new();
+    if let Some(timestamp) = timestamp {
+        serializer.visit_timestamp(timestamp)?;
+    }
+
+    let mut current_group: Option<String> = None;
+    for (group, name, value) in values {
+        if group != current_group {
+            if current_group.is_some() {
+                serializer.visit_end_group()?;
+            }
+            if let Some(group) = &group {
+                serializer.visit_start_group(group)?;
+            }
+            current_group = group;
+        }
+        serializer.visit_measurement(&name, value)?;
+    }
+    if current_group.is_some() {
+        serializer.visit_end_group()?;
+    }
+
+    Ok(serializer.into_string()?)
+}
+
+/// Collect the measurements of a Thin Edge JSON message
+#[derive(Debug, Default)]
+struct MeasurementCollector {
+    group: Option<String>,
+    timestamp: Option<OffsetDateTime>,
+    values: Vec<(Option<String>, String, f64)>,
+}
+
+impl MeasurementVisitor for MeasurementCollector {
+    type Error = Infallible;
+
+    fn visit_timestamp(&mut self, value: OffsetDateTime) -> Result<(), Self::Error> {
+        self.timestamp = Some(value);
+        Ok(())
+    }
+
+    fn visit_measurement(&mut self, name: &str, value: f64) -> Result<(), Self::Error> {
+        self.values
+            .push((self.group.clone(), name.to_string(), value));
+        Ok(())
+    }
+
+    fn visit_start_group(&mut self, group: &str) -> Result<(), Self::Error> {
+        self.group = Some(group.to_string());
+        Ok(())
+    }
+
+    fn visit_end_group(&mut self) -> Result<(), Self::Error> {
+        self.group = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_json_diff::assert_json_eq;
+    use assert_matches::assert_matches;
+    use serde_json::json;
+    use time::macros::datetime;
+
+    const T0: OffsetDateTime = datetime!(2021-04-08 10:00:00 UTC);
+
+    fn policies(content: &str) -> MeasurementPolicies {
+        MeasurementPolicies::parse(Path::new("test.toml"), content).unwrap()
+    }
+
+    fn measurements(topic: &str, payload: serde_json::Value) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload.to_string())
+    }
+
+    fn payload(message: Option<Message>) -> Option<serde_json::Value> {
+        message.map(|message| serde_json::from_str(message.payload_str().unwrap()).unwrap())
+    }
+
+    fn seconds(seconds: i64) -> OffsetDateTime {
+        T0 + Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn messages_are_unchanged_without_policies() {
+        let mut policies = MeasurementPolicies::default();
+        let input = measurements("tedge/measurements", json!({"temperature": 25}));
+
+        assert_eq!(policies.apply(&input, T0).unwrap(), Some(input));
+    }
+
+    #[test]
+    fn values_are_rate_limited_per_series() {
+        let mut policies = policies(
+            r#"
+            [[policy]]
+            series = "temperature"
+            min_interval_seconds = 60
+            "#,
+        );
+        let input = |value| {
+            measurements(
+                "tedge/measurements",
+                json!({"temperature": value, "pressure": 1013}),
+            )
+        };
+
+        assert_json_eq!(
+            payload(policies.apply(&input(20), seconds(0)).unwrap()).unwrap(),
+            json!({"temperature": 20.0, "pressure": 1013.0})
+        );
+        assert_json_eq!(
+            payload(policies.apply(&input(21), seconds(30)).unwrap()).unwrap(),
+            json!({"pressure": 1013.0})
+        );
+        assert_json_eq!(
+            payload(policies.apply(&input(22), seconds(60)).unwrap()).unwrap(),
+            json!({"temperature": 22.0, "pressure": 1013.0})
+        );
+    }
+
+    #[test]
+    fn the_series_of_each_topic_are_independent() {
+        let mut policies = policies(
+            r#"
+            [[policy]]
+            topic = "tedge/measurements/+"
+            min_interval_seconds = 60
+            "#,
+        );
+        let child1 = measurements("tedge/measurements/child1", json!({"temperature": 20}));
+        let child2 = measurements("tedge/measurements/child2", json!({"temperature": 20}));
+        let main = measurements("tedge/measurements", json!({"temperature": 20}));
+
+        assert!(policies.apply(&child1, seconds(0)).unwrap().is_some());
+        assert!(policies.apply(&child2, seconds(1)).unwrap().is_some());
+        assert!(policies.apply(&child1, seconds(2)).unwrap().is_none());
+        assert_eq!(
+            policies.apply(&main, seconds(3)).unwrap(),
+            Some(main.clone())
+        );
+    }
+
+    #[test]
+    fn values_within_the_deadband_are_dropped() {
+        let mut policies = policies(
+            r#"
+            [[policy]]
+            series = "location.altitude"
+            deadband = 0.5
+            "#,
+        );
+        let input = |altitude| {
+            measurements(
+                "tedge/measurements",
+                json!({"location": {"altitude": altitude}}),
+            )
+        };
+
+        assert!(policies.apply(&input(100.0), seconds(0)).unwrap().is_some());
+        assert!(policies.apply(&input(100.5), seconds(1)).unwrap().is_none());
+        assert!(policies.apply(&input(99.6), seconds(2)).unwrap().is_none());
+        assert!(policies.apply(&input(100.6), seconds(3)).unwrap().is_some());
+        assert!(policies.apply(&input(100.2), seconds(4)).unwrap().is_none());
+    }
+
+    #[test]
+    fn the_receive_time_is_used_to_rate_limit() {
+        let mut policies = policies(
+            r#"
+            [[policy]]
+            min_interval_seconds = 10
+            "#,
+        );
+        let input = |time: &str| {
+            measurements(
+                "tedge/measurements",
+                json!({"time": time, "temperature": 20}),
+            )
+        };
+
+        assert!(policies
+            .apply(&input("2021-04-08T10:00:00Z"), seconds(100))
+            .unwrap()
+            .is_some());
+        assert!(policies
+            .apply(&input("2021-04-08T10:00:30Z"), seconds(105))
+            .unwrap()
+            .is_none());
+        assert_json_eq!(
+            payload(
+                policies
+                    .apply(&input("2021-04-08T10:00:00Z"), seconds(110))
+                    .unwrap()
+            )
+            .unwrap(),
+            json!({"time": "2021-04-08T10:00:00Z", "temperature": 20.0})
+        );
+    }
+
+    #[test]
+    fn values_are_aggregated_over_time_windows() {
+        let mut policies = policies(
+            r#"
+            [[policy]]
+            series = "temperature"
+            aggregate = "avg"
+            window_seconds = 60
+
+            [[policy]]
+            series = "location"
+            aggregate = "max"
+            window_seconds = 60
+            "#,
+        );
+        let input = |temperature, altitude| {
+            measurements(
+                "tedge/measurements",
+                json!({"temperature": temperature, "location": {"altitude": altitude}}),
+            )
+        };
+
+        assert_eq!(policies.apply(&input(20, 100), seconds(0)).unwrap(), None);
+        assert_eq!(policies.apply(&input(22, 300), seconds(20)).unwrap(), None);
+        assert_eq!(policies.apply(&input(27, 200), seconds(59)).unwrap(), None);
+        assert_eq!(policies.flush(seconds(59)).unwrap(), vec![]);
+
+        let aggregated = policies.flush(seconds(60)).unwrap();
+        assert_eq!(aggregated.len(), 1);
+        assert_eq!(aggregated[0].topic.name, "tedge/measurements");
+        assert_json_eq!(
+            payload(aggregated.into_iter().next()).unwrap(),
+            json!({
+                "time": "2021-04-08T10:00:00Z",
+                "temperature": 23.0,
+                "location": {"altitude": 300.0}
+            })
+        );
+        assert_eq!(policies.flush(seconds(120)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn a_window_is_closed_by_a_value_of_the_next_window() {
+        let mut policies = policies(
+            r#"
+            [[policy]]
+            aggregate = "last"
+            window_seconds = 60
+            "#,
+        );
+        let input = |value| measurements("tedge/measurements", json!({"temperature": value}));
+
+        policies.apply(&input(20), seconds(10)).unwrap();
+        policies.apply(&input(21), seconds(50)).unwrap();
+        policies.apply(&input(22), seconds(70)).unwrap();
+        // This value is late: its window is closed
+        policies.apply(&input(23), seconds(30)).unwrap();
+
+        let aggregated = policies.flush(seconds(70)).unwrap();
+        assert_eq!(aggregated.len(), 1);
+        assert_json_eq!(
+            payload(aggregated.into_iter().next()).unwrap(),
+            json!({"time": "2021-04-08T10:00:00Z", "temperature": 21.0})
+        );
+
+        let aggregated = policies.flush(seconds(120)).unwrap();
+        assert_json_eq!(
+            payload(aggregated.into_iter().next()).unwrap(),
+            json!({"time": "2021-04-08T10:01:00Z", "temperature": 22.0})
+        );
+    }
+
+    #[test]
+    fn the_first_applicable_policy_is_used() {
+        let mut policies = policies(
+            r#"
+            [[policy]]
+            series = "temperature"
+            deadband = 1.0
+
+            [[policy]]
+            aggregate = "min"
+            window_seconds = 60
+            "#,
+        );
+        let input = measurements(
+            "tedge/measurements",
+            json!({"temperature": 20, "pressure": 1013}),
+        );
+
+        assert_json_eq!(
+            payload(policies.apply(&input, seconds(0)).unwrap()).unwrap(),
+            json!({"temperature": 20.0})
+        );
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        for content in [
+            "[[policy]]\nseries = \"temperature\"",
+            "[[policy]]\naggregate = \"avg\"",
+            "[[policy]]\nwindow_seconds = 60",
+            "[[policy]]\naggregate = \"avg\"\nwindow_seconds = 0",
+            "[[policy]]\naggregate = \"avg\"\nwindow_seconds = 60\ndeadband = 1.0",
+        ] {
+            assert_matches!(
+                MeasurementPolicies::parse(Path::new("test.toml"), content),
+                Err(PolicyError::InvalidPolicy { .. }),
+                "{}",
+                content
+            );
+        }
+
+        assert_matches!(
+            MeasurementPolicies::parse(
+                Path::new("test.toml"),
+                "[[policy]]\ntopic = \"tedge/#/x\"\ndeadband = 1.0"
+            ),
+            Err(PolicyError::InvalidTopicFilter { .. })
+        );
+        assert_matches!(
+            MeasurementPolicies::parse(
+                Path::new("test.toml"),
+                "[[policy]]\naggregate = \"median\"\nwindow_seconds = 60"
+            ),
+            Err(PolicyError::FromToml { .. })
+        );
+    }
+}
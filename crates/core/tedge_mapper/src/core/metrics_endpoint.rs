@@ -0,0 +1,218 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use mqtt_channel::Metrics;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tedge_config::{
+    ConfigSettingAccessor, ConfigSettingError, IpAddress, MetricsPrometheusBindAddressSetting,
+    MetricsPrometheusPortSetting, TEdgeConfig,
+};
+use tracing::{error, info};
+
+const METRICS_PATH: &str = "/metrics";
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// The label telling which mapper a series belongs to
+const MAPPER_LABEL: &str = "mapper";
+
+/// The offset of each mapper process from `metrics.prometheus.port`,
+/// so several mapper processes running on the same device don't compete for the same port.
+const PROMETHEUS_PORT_OFFSETS: &[(&str, u16)] = &[
+    ("tedge-mapper-c8y", 0),
+    ("tedge-mapper-az", 1),
+    ("tedge-mapper-aws", 2),
+    ("tedge-mapper-collectd", 3),
+    ("tedge-mapper-generic", 4),
+    ("tedge-mapper-prometheus", 5),
+    ("tedge-mapper-influxdb", 6),
+    ("tedge-mapper-multi", 7),
+];
+
+/// The metrics of the mappers running in a process, served to Prometheus on a single endpoint.
+///
+/// The clones of an endpoint share the same mappers.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsEndpoint {
+    mappers: Arc<Mutex<Vec<(String, Metrics)>>>,
+}
+
+impl MetricsEndpoint {
+    /// Serve the metrics of the mappers of this process, if an endpoint port is configured.
+    ///
+    /// The process is served on its own port, offset from the configured base port.
+    pub fn serve(
+        &self,
+        tedge_config: &TEdgeConfig,
+        process_name: &str,
+    ) -> Result<(), anyhow::Error> {
+        let base_port = match tedge_config.query(MetricsPrometheusPortSetting) {
+            Ok(port) => port.into(),
+            Err(ConfigSettingError::ConfigNotSet { .. }) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let IpAddress(ip) = tedge_config.query(MetricsPrometheusBindAddressSetting)?;
+        let address = SocketAddr::new(ip, prometheus_port(base_port, process_name)?);
+
+        let endpoint = self.clone();
+        serve_metrics(address, move || endpoint.to_prometheus()).map_err(|err| {
+            anyhow::anyhow!(
+                "Failed to serve Prometheus metrics of {} on {}: {}",
+                process_name,
+                address,
+                err
+            )
+        })
+    }
+
+    /// Add the metrics of a mapper to those served by this endpoint
+    pub fn register(&self, mapper_name: &str, metrics: &Metrics) {
+        self.with_mappers(|mappers| mappers.push((mapper_name.to_string(), metrics.clone())))
+    }
+
+    /// The metrics of all the mappers, each series being labelled with the name of its mapper
+    pub fn to_prometheus(&self) -> String {
+        self.with_mappers(|mappers| Metrics::merge_to_prometheus(MAPPER_LABEL, mappers))
+    }
+
+    fn with_mappers<T>(&self, f: impl FnOnce(&mut Vec<(String, Metrics)>) -> T) -> T {
+        // The list is only appended to, so it is consistent even if the lock is poisoned
+        let mut mappers = self
+            .mappers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut mappers)
+    }
+}
+
+fn prometheus_port(base_port: u16, process_name: &str) -> Result<u16, anyhow::Error> {
+    let offset = PROMETHEUS_PORT_OFFSETS
+        .iter()
+        .find(|(name, _)| *name == process_name)
+        .map(|(_, offset)| *offset)
+        .ok_or_else(|| anyhow::anyhow!("No Prometheus port assigned to {}", process_name))?;
+    base_port.checked_add(offset).ok_or_else(|| {
+        anyhow::anyhow!(
+            "The Prometheus port of {} is out of range: {} + {}",
+            process_name,
+            base_port,
+            offset
+        )
+    })
+}
+
+/// Serve on `/metrics` the Prometheus text exposition rendered by `render` for each request.
+///
+/// The address is bound before returning, so a port already in use is reported as a startup error.
+/// The requests are then served in the background.
+pub fn serve_metrics<F>(address: SocketAddr, render: F) -> Result<(), hyper::Error>
+where
+    F: Fn() -> String + Clone + Send + Sync + 'static,
+{
+    let make_service = make_service_fn(move |_connection| {
+        let render = render.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let render = render.clone();
+                async move {
+                    Ok::<_, Infallible>(response(request.method(), request.uri().path(), render))
+                }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&address)?.serve(make_service);
+    info!(
+        "Serving Prometheus metrics on http://{}{}",
+        address, METRICS_PATH
+    );
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            error!("Failed to serve Prometheus metrics on {}: {}", address, err);
+        }
+    });
+    Ok(())
+}
+
+/// The response to an HTTP request
+fn response(method: &Method, path: &str, render: impl Fn() -> String) -> Response<Body> {
+    if path != METRICS_PATH {
+        return status_response(StatusCode::NOT_FOUND);
+    }
+    if method != Method::GET {
+        return status_response(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)
+        .body(Body::from(render()))
+        .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener};
+    use test_case::test_case;
+
+    #[test_case(Method::GET, "/metrics", StatusCode::OK)]
+    #[test_case(Method::GET, "/metrics/other", StatusCode::NOT_FOUND)]
+    #[test_case(Method::GET, "/", StatusCode::NOT_FOUND)]
+    #[test_case(Method::POST, "/metrics", StatusCode::METHOD_NOT_ALLOWED)]
+    fn responding_to_http_requests(method: Method, path: &str, status: StatusCode) {
+        let response = response(&method, path, || "up 1\n".to_string());
+
+        assert_eq!(response.status(), status);
+    }
+
+    #[test]
+    fn the_metrics_are_served_as_prometheus_text() {
+        let response = response(&Method::GET, "/metrics", || "up 1\n".to_string());
+
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROMETHEUS_CONTENT_TYPE
+        );
+    }
+
+    #[test]
+    fn each_process_serves_its_metrics_on_its_own_port() {
+        assert_eq!(prometheus_port(9100, "tedge-mapper-c8y").unwrap(), 9100);
+        assert_eq!(prometheus_port(9100, "tedge-mapper-az").unwrap(), 9101);
+        assert_eq!(prometheus_port(9100, "tedge-mapper-multi").unwrap(), 9107);
+        assert!(prometheus_port(9100, "mapper_under_test").is_err());
+        assert!(prometheus_port(u16::MAX, "tedge-mapper-aws").is_err());
+    }
+
+    #[test]
+    fn the_metrics_of_all_the_registered_mappers_are_served() {
+        let endpoint = MetricsEndpoint::default();
+        let c8y = Metrics::default();
+        let az = Metrics::default();
+        endpoint.register("tedge-mapper-c8y", &c8y);
+        endpoint.clone().register("tedge-mapper-az", &az);
+
+        c8y.increment("mapper_conversions_total", &[]);
+        az.increment("mapper_conversions_total", &[]);
+
+        let text = endpoint.to_prometheus();
+        assert!(text.contains("mapper_conversions_total{mapper=\"tedge-mapper-c8y\"} 1\n"));
+        assert!(text.contains("mapper_conversions_total{mapper=\"tedge-mapper-az\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn a_port_already_in_use_is_reported_on_startup() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let result = serve_metrics(address, String::new);
+
+        assert!(result.is_err());
+    }
+}
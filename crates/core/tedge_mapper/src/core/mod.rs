@@ -1,5 +1,13 @@
 pub mod component;
 pub mod converter;
 pub mod error;
+pub mod error_report;
+pub mod inventory;
 pub mod mapper;
+pub mod measurement_policies;
+pub mod metrics_endpoint;
+pub mod script;
+pub mod script_hook;
 pub mod size_threshold;
+pub mod store_and_forward;
+pub mod sync_journal;
@@ -0,0 +1,452 @@
+use mqtt_channel::{Message, MqttError, Topic};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// The script function called on each message received by the mapper, before the built-in conversion
+const BEFORE_FN: &str = "before";
+
+/// The script function called on each message produced by the built-in conversion
+const AFTER_FN: &str = "after";
+
+/// The minimum size of a value stored in an array or a map
+const VALUE_SIZE: usize = std::mem::size_of::<Dynamic>();
+
+const DEFAULT_TIMEOUT_MS: u64 = 100;
+const DEFAULT_MAX_VALUE_SIZE_KB: u64 = 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("Failed to read the script {path}: {error}")]
+    FromIo {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+
+    #[error("Invalid script limits in {path}: {error}")]
+    FromToml {
+        path: PathBuf,
+        #[source]
+        error: toml::de::Error,
+    },
+
+    #[error("Failed to compile the script {path}: {error}")]
+    Compile {
+        path: PathBuf,
+        #[source]
+        error: rhai::ParseError,
+    },
+
+    #[error(transparent)]
+    FromMqttClient(#[from] MqttError),
+
+    #[error("The script function {function} has been stopped after {timeout_ms} ms")]
+    Timeout {
+        function: &'static str,
+        timeout_ms: u64,
+    },
+
+    #[error("The script function {function} built a value larger than {max_value_size_kb} kB")]
+    ValueTooLarge {
+        function: &'static str,
+        max_value_size_kb: u64,
+    },
+
+    #[error("The script function {function} failed: {error}")]
+    Runtime {
+        function: &'static str,
+        #[source]
+        error: Box<EvalAltResult>,
+    },
+
+    #[error("The script function {function} returned an invalid message: {reason}")]
+    InvalidResult {
+        function: &'static str,
+        reason: String,
+    },
+}
+
+/// The resources a script is given to process a message
+///
+/// ```toml
+/// timeout_ms = 100
+/// max_value_size_kb = 1024
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptLimits {
+    /// The time after which a script function is stopped
+    ///
+    /// The script runs on the thread of the mapper, which is blocked until the function returns:
+    /// a script stopped on timeout delays the mapper by up to this duration.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// The maximum size of each string, array or map built by a script function
+    ///
+    /// This is not a memory budget: a script can build several values, each up to this size.
+    #[serde(default = "default_max_value_size_kb")]
+    pub max_value_size_kb: u64,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        ScriptLimits {
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            max_value_size_kb: DEFAULT_MAX_VALUE_SIZE_KB,
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+fn default_max_value_size_kb() -> u64 {
+    DEFAULT_MAX_VALUE_SIZE_KB
+}
+
+/// A user script, written in [Rhai](https://rhai.rs), transforming the messages of a mapper
+///
+/// The script defines a `before(topic, payload)` function, called on the messages received by the mapper,
+/// and/or an `after(topic, payload)` function, called on the messages produced by the built-in conversion.
+/// Each function returns zero or more messages:
+/// - `()` to drop the message,
+/// - a string, as the new payload of the message,
+/// - a map `#{ topic: "...", payload: "..." }`, the topic being unchanged if not given,
+/// - or an array of strings and maps.
+///
+/// A script is sandboxed: it can neither load modules nor evaluate code,
+/// and is stopped if running for too long or building too large values.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    limits: ScriptLimits,
+    started: Arc<Mutex<Instant>>,
+    has_before: bool,
+    has_after: bool,
+}
+
+impl Script {
+    pub fn compile(source: &str, limits: ScriptLimits) -> Result<Script, rhai::ParseError> {
+        let started = Arc::new(Mutex::new(Instant::now()));
+        let engine = sandboxed_engine(limits, started.clone());
+        let ast = engine.compile(source)?;
+        let defines = |name: &str| {
+            ast.iter_functions()
+                .any(|function| function.name == name && function.params.len() == 2)
+        };
+        let has_before = defines(BEFORE_FN);
+        let has_after = defines(AFTER_FN);
+
+        Ok(Script {
+            engine,
+            ast,
+            limits,
+            started,
+            has_before,
+            has_after,
+        })
+    }
+
+    /// Transform a message received by the mapper, before the built-in conversion
+    pub fn before(&self, message: &Message) -> Result<Vec<Message>, ScriptError> {
+        if self.has_before {
+            self.call(BEFORE_FN, message)
+        } else {
+            Ok(vec![message.clone()])
+        }
+    }
+
+    /// Transform a message produced by the built-in conversion
+    pub fn after(&self, message: Message) -> Result<Vec<Message>, ScriptError> {
+        if self.has_after {
+            self.call(AFTER_FN, &message)
+        } else {
+            Ok(vec![message])
+        }
+    }
+
+    fn call(&self, function: &'static str, message: &Message) -> Result<Vec<Message>, ScriptError> {
+        let args = (
+            message.topic.name.clone(),
+            message.payload_str()?.to_string(),
+        );
+        *self.started.lock().unwrap_or_else(|err| err.into_inner()) = Instant::now();
+        let options = CallFnOptions::new().eval_ast(false);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, function, args)
+            .map_err(|error| match *error {
+                EvalAltResult::ErrorTerminated(..) => ScriptError::Timeout {
+                    function,
+                    timeout_ms: self.limits.timeout_ms,
+                },
+                EvalAltResult::ErrorDataTooLarge(..) => ScriptError::ValueTooLarge {
+                    function,
+                    max_value_size_kb: self.limits.max_value_size_kb,
+                },
+                _ => ScriptError::Runtime { function, error },
+            })?;
+
+        if result.is_unit() {
+            Ok(vec![])
+        } else if result.is_array() {
+            result
+                .cast::<rhai::Array>()
+                .into_iter()
+                .map(|item| output_message(function, message, item))
+                .collect()
+        } else {
+            Ok(vec![output_message(function, message, result)?])
+        }
+    }
+}
+
+fn sandboxed_engine(limits: ScriptLimits, started: Arc<Mutex<Instant>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.set_max_modules(0);
+    engine.disable_symbol("eval");
+
+    // A zero size would disable the limits
+    let max_value_size = (limits.max_value_size_kb as usize * 1024).max(1);
+    engine.set_max_string_size(max_value_size);
+    engine.set_max_array_size((max_value_size / VALUE_SIZE).max(1));
+    engine.set_max_map_size((max_value_size / VALUE_SIZE).max(1));
+
+    // The progress of the script is checked between two operations, on the calling thread:
+    // the call is not preempted, but returns an error once the timeout has elapsed.
+    let timeout = Duration::from_millis(limits.timeout_ms);
+    engine.on_progress(move |_| {
+        let started = *started.lock().unwrap_or_else(|err| err.into_inner());
+        if started.elapsed() > timeout {
+            Some(Dynamic::UNIT)
+        } else {
+            None
+        }
+    });
+
+    engine.on_print(|text| info!("Script: {}", text));
+    engine.on_debug(|text, _, position| debug!("Script at {}: {}", position, text));
+    engine
+}
+
+/// Build a message from an item returned by a script function, using the input message as a template
+fn output_message(
+    function: &'static str,
+    input: &Message,
+    item: Dynamic,
+) -> Result<Message, ScriptError> {
+    let invalid_result = |reason: String| ScriptError::InvalidResult { function, reason };
+
+    let (topic, payload) = if item.is_string() {
+        (input.topic.clone(), item.cast::<String>())
+    } else if item.is_map() {
+        let mut map = item.cast::<Map>();
+        let topic = match map.remove("topic") {
+            None => input.topic.clone(),
+            Some(topic) if topic.is_string() => {
+                let topic = topic.cast::<String>();
+                Topic::new(&topic)
+                    .map_err(|_| invalid_result(format!("invalid topic '{}'", topic)))?
+            }
+            Some(topic) => {
+                return Err(invalid_result(format!(
+                    "the topic is a {}, not a string",
+                    topic.type_name()
+                )))
+            }
+        };
+        let payload = match map.remove("payload") {
+            Some(payload) if payload.is_string() => payload.cast::<String>(),
+            Some(payload) => {
+                return Err(invalid_result(format!(
+                    "the payload is a {}, not a string",
+                    payload.type_name()
+                )))
+            }
+            None => return Err(invalid_result("no payload".into())),
+        };
+        (topic, payload)
+    } else {
+        return Err(invalid_result(format!(
+            "expected a string or a map, found a {}",
+            item.type_name()
+        )));
+    };
+
+    let output = Message::new(&topic, payload).with_qos(input.qos);
+    if input.retain {
+        Ok(output.with_retain())
+    } else {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    fn message(topic: &str, payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload)
+    }
+
+    fn compile(source: &str) -> Script {
+        Script::compile(source, ScriptLimits::default()).unwrap()
+    }
+
+    #[test]
+    fn scripts_transform_the_payloads() {
+        let script = compile(
+            r#"
+            fn before(topic, payload) {
+                let m = parse_json(payload);
+                m.temperature = m.temperature_f.to_float() * 1.8 + 32.0;
+                m.remove("temperature_f");
+                m.to_json()
+            }
+            "#,
+        );
+        let input = message("tedge/measurements", r#"{"temperature_f": 25}"#);
+
+        let output = script.before(&input).unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].topic.name, "tedge/measurements");
+        assert_eq!(output[0].payload_str().unwrap(), r#"{"temperature":77.0}"#);
+    }
+
+    #[test]
+    fn scripts_can_filter_and_duplicate_messages() {
+        let script = compile(
+            r#"
+            fn after(topic, payload) {
+                if payload == "drop" {
+                    return ();
+                }
+                [payload, #{ topic: "copy/" + topic, payload: payload }]
+            }
+            "#,
+        );
+
+        assert_eq!(script.after(message("c8y/s/us", "drop")).unwrap(), vec![]);
+        assert_eq!(
+            script.after(message("c8y/s/us", "200,temp")).unwrap(),
+            vec![
+                message("c8y/s/us", "200,temp"),
+                message("copy/c8y/s/us", "200,temp")
+            ]
+        );
+    }
+
+    #[test]
+    fn messages_are_unchanged_when_no_function_is_defined() {
+        let script = compile(
+            r#"
+            fn before(topic, payload) { () }
+            "#,
+        );
+        let input = message("tedge/alarms/critical/temp", "{}");
+
+        assert_eq!(script.before(&input).unwrap(), vec![]);
+        assert_eq!(script.after(input.clone()).unwrap(), vec![input]);
+    }
+
+    #[test]
+    fn invalid_results_are_rejected() {
+        let script = compile(
+            r#"
+            fn before(topic, payload) {
+                if payload == "number" { 42 }
+                else if payload == "no payload" { #{ topic: "a/b" } }
+                else { #{ topic: "a/+", payload: payload } }
+            }
+            "#,
+        );
+
+        for payload in ["number", "no payload", "bad topic"] {
+            assert_matches!(
+                script.before(&message("a/b", payload)),
+                Err(ScriptError::InvalidResult {
+                    function: "before",
+                    ..
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn long_running_scripts_are_stopped() {
+        let limits = ScriptLimits {
+            timeout_ms: 10,
+            ..ScriptLimits::default()
+        };
+        let script = Script::compile("fn before(topic, payload) { loop {} }", limits).unwrap();
+
+        assert_matches!(
+            script.before(&message("a/b", "")),
+            Err(ScriptError::Timeout {
+                function: "before",
+                timeout_ms: 10
+            })
+        );
+    }
+
+    #[test]
+    fn scripts_building_too_large_values_are_stopped() {
+        let limits = ScriptLimits {
+            max_value_size_kb: 1,
+            ..ScriptLimits::default()
+        };
+        let script = Script::compile(
+            r#"fn before(topic, payload) { let s = ""; loop { s += payload; } }"#,
+            limits,
+        )
+        .unwrap();
+
+        assert_matches!(
+            script.before(&message("a/b", "0123456789")),
+            Err(ScriptError::ValueTooLarge {
+                function: "before",
+                max_value_size_kb: 1
+            })
+        );
+    }
+
+    #[test]
+    fn scripts_cannot_load_modules() {
+        let script = compile(
+            r#"
+            fn before(topic, payload) {
+                import "/etc/tedge/secret" as secret;
+                payload
+            }
+            "#,
+        );
+
+        assert_matches!(
+            script.before(&message("a/b", "")),
+            Err(ScriptError::Runtime { .. })
+        );
+    }
+
+    #[test]
+    fn script_limits_have_defaults() {
+        let limits: ScriptLimits = toml::from_str("timeout_ms = 20").unwrap();
+
+        assert_eq!(
+            limits,
+            ScriptLimits {
+                timeout_ms: 20,
+                max_value_size_kb: DEFAULT_MAX_VALUE_SIZE_KB,
+            }
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use crate::c8y::dynamic_discovery::DiscoverOp;
+use crate::core::converter::{Converter, MapperConfig};
+use crate::core::error::ConversionError;
+use crate::core::script::{Script, ScriptError, ScriptLimits};
+
+use async_trait::async_trait;
+use mqtt_channel::{Message, StreamExt, SubscriptionHandle};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tedge_utils::fs_notify::{fs_notify_stream, pin_mut, FileEvent, NotifyStreamError};
+use tracing::{error, info};
+
+/// The directory, relative to the config directory, where the mapper scripts are stored
+pub const SCRIPTS_DIR: &str = "scripts";
+
+type SharedScript = Arc<Mutex<Option<Script>>>;
+
+/// A converter running the user script of a mapper before and after the built-in conversion
+///
+/// The script of a mapper is read from `<config_dir>/scripts/<mapper-name>.rhai`,
+/// and its limits from the optional `<config_dir>/scripts/<mapper-name>.toml`.
+/// Both files are watched, the script being reloaded whenever updated.
+pub struct ScriptedConverter {
+    converter: Box<dyn Converter<Error = ConversionError>>,
+    script: SharedScript,
+}
+
+impl ScriptedConverter {
+    pub fn new(
+        converter: Box<dyn Converter<Error = ConversionError>>,
+        script: Option<Script>,
+    ) -> Self {
+        ScriptedConverter {
+            converter,
+            script: Arc::new(Mutex::new(script)),
+        }
+    }
+
+    fn script(&self) -> MutexGuard<'_, Option<Script>> {
+        lock(&self.script)
+    }
+
+    fn before(&self, input: &Message) -> Result<Vec<Message>, ScriptError> {
+        match self.script().as_ref() {
+            Some(script) => script.before(input),
+            None => Ok(vec![input.clone()]),
+        }
+    }
+
+    fn after(&self, converted: Vec<Message>) -> Result<Vec<Message>, ScriptError> {
+        match self.script().as_ref() {
+            Some(script) => {
+                let mut outputs = vec![];
+                for message in converted {
+                    outputs.append(&mut script.after(message)?);
+                }
+                Ok(outputs)
+            }
+            None => Ok(converted),
+        }
+    }
+}
+
+#[async_trait]
+impl Converter for ScriptedConverter {
+    type Error = ConversionError;
+
+    fn get_mapper_config(&self) -> &MapperConfig {
+        self.converter.get_mapper_config()
+    }
+
+    async fn try_convert(&mut self, input: &Message) -> Result<Vec<Message>, Self::Error> {
+        let mut converted = vec![];
+        for message in self.before(input)? {
+            converted.append(&mut self.converter.try_convert(&message).await?);
+        }
+        Ok(self.after(converted)?)
+    }
+
+    fn try_init_messages(&self) -> Result<Vec<Message>, Self::Error> {
+        self.converter.try_init_messages()
+    }
+
+    async fn try_flush_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+        let flushed = self.converter.try_flush_messages().await?;
+        Ok(self.after(flushed)?)
+    }
+
+    fn sync_messages(&mut self) -> Vec<Message> {
+        self.converter.sync_messages()
+    }
+
+    fn try_synced_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+        self.converter.try_synced_messages()
+    }
+
+    fn set_subscription_handle(&mut self, subscriptions: SubscriptionHandle) {
+        self.converter.set_subscription_handle(subscriptions)
+    }
+
+    fn try_process_operation_update_message(
+        &mut self,
+        input: &DiscoverOp,
+    ) -> Result<Option<Message>, Self::Error> {
+        self.converter.try_process_operation_update_message(input)
+    }
+}
+
+/// Run the script of the given mapper along the converter, reloading the script whenever updated.
+///
+/// The converter is returned unchanged if there is no scripts directory.
+pub fn with_script_hook(
+    converter: Box<dyn Converter<Error = ConversionError>>,
+    mapper_name: &str,
+    config_dir: &Path,
+) -> Result<Box<dyn Converter<Error = ConversionError>>, ScriptError> {
+    let scripts_dir = config_dir.join(SCRIPTS_DIR);
+    if !scripts_dir.is_dir() {
+        return Ok(converter);
+    }
+
+    let script = load_script(&scripts_dir, mapper_name)?;
+    if script.is_some() {
+        info!(
+            "Loaded the script of {} from {}",
+            mapper_name,
+            scripts_dir.display()
+        );
+    }
+    let converter = ScriptedConverter::new(converter, script);
+
+    let shared_script = converter.script.clone();
+    let mapper_name = mapper_name.to_string();
+    tokio::spawn(async move {
+        if let Err(err) = reload_on_update(&scripts_dir, &mapper_name, &shared_script).await {
+            error!("Failed to watch the script of {}: {}", mapper_name, err);
+        }
+    });
+
+    Ok(Box::new(converter))
+}
+
+/// Load the script of the given mapper, returning `None` if there is no such script.
+pub fn load_script(scripts_dir: &Path, mapper_name: &str) -> Result<Option<Script>, ScriptError> {
+    let script_path = scripts_dir.join(script_file(mapper_name));
+    if !script_path.exists() {
+        return Ok(None);
+    }
+    let source = read_file(&script_path)?;
+
+    let limits_path = scripts_dir.join(limits_file(mapper_name));
+    let limits = if limits_path.exists() {
+        toml::from_str(&read_file(&limits_path)?).map_err(|error| ScriptError::FromToml {
+            path: limits_path,
+            error,
+        })?
+    } else {
+        ScriptLimits::default()
+    };
+
+    let script = Script::compile(&source, limits).map_err(|error| ScriptError::Compile {
+        path: script_path,
+        error,
+    })?;
+    Ok(Some(script))
+}
+
+async fn reload_on_update(
+    scripts_dir: &Path,
+    mapper_name: &str,
+    script: &SharedScript,
+) -> Result<(), NotifyStreamError> {
+    let events = [FileEvent::Created, FileEvent::Modified, FileEvent::Deleted];
+    let fs_notification_stream = fs_notify_stream(&[
+        (scripts_dir, Some(script_file(mapper_name)), &events),
+        (scripts_dir, Some(limits_file(mapper_name)), &events),
+    ])?;
+    pin_mut!(fs_notification_stream);
+
+    while let Some(event_or_error) = fs_notification_stream.next().await {
+        match event_or_error {
+            Ok(_) => reload_script(scripts_dir, mapper_name, script),
+            Err(err) => error!("Failed to extract event {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// Replace the current script by its latest version, keeping the current one if the latest is invalid.
+fn reload_script(scripts_dir: &Path, mapper_name: &str, script: &SharedScript) {
+    match load_script(scripts_dir, mapper_name) {
+        Ok(Some(latest)) => {
+            info!("Reloaded the script of {}", mapper_name);
+            *lock(script) = Some(latest);
+        }
+        Ok(None) => {
+            info!("Removed the script of {}", mapper_name);
+            *lock(script) = None;
+        }
+        Err(err) => error!("{}: keeping the current script", err),
+    }
+}
+
+fn script_file(mapper_name: &str) -> String {
+    format!("{}.rhai", mapper_name)
+}
+
+fn limits_file(mapper_name: &str) -> String {
+    format!("{}.toml", mapper_name)
+}
+
+fn read_file(path: &Path) -> Result<String, ScriptError> {
+    std::fs::read_to_string(path).map_err(|error| ScriptError::FromIo {
+        path: PathBuf::from(path),
+        error,
+    })
+}
+
+fn lock(script: &SharedScript) -> MutexGuard<'_, Option<Script>> {
+    script
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use mqtt_channel::{Topic, TopicFilter};
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    const MAPPER: &str = "tedge-mapper-test";
+
+    /// A converter forwarding the payloads in upper case on `out/<topic>`
+    struct UppercaseConverter {
+        mapper_config: MapperConfig,
+    }
+
+    impl UppercaseConverter {
+        fn boxed() -> Box<dyn Converter<Error = ConversionError>> {
+            Box::new(UppercaseConverter {
+                mapper_config: MapperConfig {
+                    mapper_name: "test-mapper".into(),
+                    in_topic_filter: TopicFilter::new_unchecked("#"),
+                    out_topic: Topic::new_unchecked("out"),
+                    errors_topic: Topic::new_unchecked("errors"),
+                },
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Converter for UppercaseConverter {
+        type Error = ConversionError;
+
+        fn get_mapper_config(&self) -> &MapperConfig {
+            &self.mapper_config
+        }
+
+        async fn try_convert(&mut self, input: &Message) -> Result<Vec<Message>, Self::Error> {
+            let topic = Topic::new_unchecked(&format!("out/{}", input.topic.name));
+            let payload = input.payload_str()?.to_uppercase();
+            Ok(vec![Message::new(&topic, payload)])
+        }
+    }
+
+    fn message(topic: &str, payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload)
+    }
+
+    fn script(source: &str) -> Option<Script> {
+        Some(Script::compile(source, ScriptLimits::default()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn the_script_runs_before_and_after_the_conversion() {
+        let mut converter = ScriptedConverter::new(
+            UppercaseConverter::boxed(),
+            script(
+                r#"
+                fn before(topic, payload) { if topic == "ignored" { () } else { payload + "!" } }
+                fn after(topic, payload) { [payload, #{ topic: "copy", payload: topic }] }
+                "#,
+            ),
+        );
+
+        assert_eq!(
+            converter
+                .try_convert(&message("in", "hello"))
+                .await
+                .unwrap(),
+            vec![message("out/in", "HELLO!"), message("copy", "out/in")]
+        );
+        assert_eq!(
+            converter
+                .try_convert(&message("ignored", "hello"))
+                .await
+                .unwrap(),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn without_script_the_conversion_is_unchanged() {
+        let mut converter = ScriptedConverter::new(UppercaseConverter::boxed(), None);
+
+        assert_eq!(
+            converter
+                .try_convert(&message("in", "hello"))
+                .await
+                .unwrap(),
+            vec![message("out/in", "HELLO")]
+        );
+    }
+
+    #[tokio::test]
+    async fn script_errors_are_conversion_errors() {
+        let mut converter = ScriptedConverter::new(
+            UppercaseConverter::boxed(),
+            script("fn after(topic, payload) { throw \"invalid\" }"),
+        );
+
+        assert_matches!(
+            converter.try_convert(&message("in", "hello")).await,
+            Err(ConversionError::FromScript(ScriptError::Runtime {
+                function: "after",
+                ..
+            }))
+        );
+    }
+
+    #[test]
+    fn scripts_are_loaded_with_their_limits() {
+        let ttd = TempTedgeDir::new();
+        ttd.file(&script_file(MAPPER))
+            .with_raw_content("fn before(topic, payload) { loop {} }");
+        ttd.file(&limits_file(MAPPER))
+            .with_raw_content("timeout_ms = 5");
+
+        let script = load_script(ttd.path(), MAPPER).unwrap().unwrap();
+
+        assert_matches!(
+            script.before(&message("in", "")),
+            Err(ScriptError::Timeout { timeout_ms: 5, .. })
+        );
+        assert!(load_script(ttd.path(), "tedge-mapper-other")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn invalid_scripts_are_rejected() {
+        let ttd = TempTedgeDir::new();
+        ttd.file(&script_file(MAPPER))
+            .with_raw_content("fn before(topic, payload) {");
+
+        assert_matches!(
+            load_script(ttd.path(), MAPPER).err(),
+            Some(ScriptError::Compile { .. })
+        );
+    }
+
+    #[tokio::test]
+    async fn updated_scripts_are_reloaded_unless_invalid() {
+        let ttd = TempTedgeDir::new();
+        let mut converter = ScriptedConverter::new(UppercaseConverter::boxed(), None);
+        let shared_script = converter.script.clone();
+        let script_path = ttd.path().join(script_file(MAPPER));
+
+        std::fs::write(&script_path, "fn before(topic, payload) { \"v1\" }").unwrap();
+        reload_script(ttd.path(), MAPPER, &shared_script);
+        assert_eq!(
+            converter.try_convert(&message("in", "")).await.unwrap(),
+            vec![message("out/in", "V1")]
+        );
+
+        std::fs::write(&script_path, "fn before(topic, payload) {").unwrap();
+        reload_script(ttd.path(), MAPPER, &shared_script);
+        assert_eq!(
+            converter.try_convert(&message("in", "")).await.unwrap(),
+            vec![message("out/in", "V1")]
+        );
+
+        std::fs::remove_file(&script_path).unwrap();
+        reload_script(ttd.path(), MAPPER, &shared_script);
+        assert_eq!(
+            converter.try_convert(&message("in", "v2")).await.unwrap(),
+            vec![message("out/in", "V2")]
+        );
+    }
+}
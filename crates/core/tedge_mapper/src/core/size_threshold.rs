@@ -1,4 +1,5 @@
 use mqtt_channel::Message;
+use thin_edge_json::group::{MeasurementGroup, MeasurementGrouper};
 
 use super::error::ConversionError;
 
@@ -19,4 +20,117 @@ impl SizeThreshold {
             Ok(())
         }
     }
+
+    /// Translate the measurements received in the input message into payloads under the threshold,
+    /// splitting the measurements into as many payloads as required.
+    ///
+    /// Fails if a single measurement is translated into a payload exceeding the threshold.
+    pub fn split_measurements<F>(
+        &self,
+        input: &Message,
+        translate: F,
+    ) -> Result<Vec<String>, ConversionError>
+    where
+        F: Fn(&MeasurementGroup) -> Result<String, ConversionError>,
+    {
+        let mut grouper = MeasurementGrouper::new();
+        thin_edge_json::parser::parse_str(input.payload_str()?, &mut grouper)?;
+        let group = grouper.end()?;
+
+        let mut payloads = Vec::new();
+        self.split_group(input, &group, &translate, &mut payloads)?;
+        Ok(payloads)
+    }
+
+    fn split_group<F>(
+        &self,
+        input: &Message,
+        group: &MeasurementGroup,
+        translate: &F,
+        payloads: &mut Vec<String>,
+    ) -> Result<(), ConversionError>
+    where
+        F: Fn(&MeasurementGroup) -> Result<String, ConversionError>,
+    {
+        let payload = translate(group)?;
+        if payload.len() <= self.0 {
+            payloads.push(payload);
+            return Ok(());
+        }
+
+        match group.split() {
+            Some((first, second)) => {
+                self.split_group(input, &first, translate, payloads)?;
+                self.split_group(input, &second, translate, payloads)
+            }
+            None => Err(ConversionError::TranslatedSizeExceededThreshold {
+                payload: input.payload_str()?.chars().take(50).collect(),
+                topic: input.topic.name.clone(),
+                actual_size: payload.len(),
+                threshold: self.0,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use mqtt_channel::Topic;
+    use thin_edge_json::serialize::ThinEdgeJsonSerializer;
+
+    fn translate(group: &MeasurementGroup) -> Result<String, ConversionError> {
+        let mut serializer = ThinEdgeJsonSerializer::new();
+        group.accept(&mut serializer)?;
+        Ok(serializer.into_string()?)
+    }
+
+    fn measurements(payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked("tedge/measurements"), payload)
+    }
+
+    #[test]
+    fn measurements_under_the_threshold_are_not_split() {
+        let input = measurements(r#"{"temperature": 23.0, "pressure": 220.0}"#);
+
+        let payloads = SizeThreshold(1024)
+            .split_measurements(&input, translate)
+            .unwrap();
+
+        assert_eq!(payloads.len(), 1);
+    }
+
+    #[test]
+    fn measurements_over_the_threshold_are_split() {
+        let input = measurements(
+            r#"{"time": "2021-04-08T00:00:00+05:00", "a": 1, "b": 2, "c": {"x": 3, "y": 4}}"#,
+        );
+
+        let payloads = SizeThreshold(50)
+            .split_measurements(&input, translate)
+            .unwrap();
+
+        assert_eq!(
+            payloads,
+            vec![
+                r#"{"time":"2021-04-08T00:00:00+05:00","a":1.0}"#,
+                r#"{"time":"2021-04-08T00:00:00+05:00","b":2.0}"#,
+                r#"{"time":"2021-04-08T00:00:00+05:00","c":{"x":3.0}}"#,
+                r#"{"time":"2021-04-08T00:00:00+05:00","c":{"y":4.0}}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn a_single_measurement_over_the_threshold_is_rejected() {
+        let input = measurements(r#"{"temperature": 23.0}"#);
+
+        let result = SizeThreshold(10).split_measurements(&input, translate);
+
+        assert_matches!(
+            result,
+            Err(ConversionError::TranslatedSizeExceededThreshold { threshold: 10, .. })
+        );
+    }
 }
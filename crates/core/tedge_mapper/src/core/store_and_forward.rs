@@ -0,0 +1,236 @@
+use mqtt_channel::{
+    DiskQueueConfig, Message, MessageStore, MqttError, OverflowPolicy, PubChannel, Topic,
+    TopicFilter,
+};
+use std::path::Path;
+use tracing::{error, info, warn};
+
+/// Maximum number of messages kept on disk while the bridge is down, the oldest being dropped first
+const STORE_CAPACITY: usize = 100_000;
+
+/// Payload published by mosquitto on the notification topic of a bridge when the bridge is connected
+const BRIDGE_UP_PAYLOAD: &str = "1";
+
+/// Store on disk the messages to be forwarded to the cloud while the bridge is down,
+/// and replay them in order once the bridge is up again.
+///
+/// The bridge status is given by the notification messages published by mosquitto
+/// on the `notification_topic` of the bridge: `1` when connected, `0` when disconnected.
+pub struct StoreAndForward {
+    bridge_status_topic: Topic,
+    cloud_topics: TopicFilter,
+    queue: MessageStore,
+    bridge_up: bool,
+}
+
+impl StoreAndForward {
+    /// Open the store of the given mapper, loading the messages not forwarded by a previous run.
+    ///
+    /// Only the messages published on the `cloud_topics` are stored, the others being local messages.
+    pub async fn open(
+        mapper_name: &str,
+        data_dir: &Path,
+        bridge_status_topic: &str,
+        cloud_topics: TopicFilter,
+    ) -> Result<Self, MqttError> {
+        let config = DiskQueueConfig {
+            dir: data_dir.join("store-and-forward"),
+            max_messages: STORE_CAPACITY,
+            overflow_policy: OverflowPolicy::DropOldest,
+        };
+        let queue = MessageStore::open(&config, mapper_name).await?;
+        if !queue.is_empty() {
+            info!(
+                "{} messages stored by a previous run will be forwarded to the cloud",
+                queue.len()
+            );
+        }
+
+        Ok(StoreAndForward {
+            bridge_status_topic: Topic::new(bridge_status_topic)?,
+            cloud_topics,
+            queue,
+            // Until notified otherwise, the bridge is assumed to be up
+            bridge_up: true,
+        })
+    }
+
+    /// The topic of the bridge notifications, to which the mapper has to subscribe
+    pub fn bridge_status_topic(&self) -> TopicFilter {
+        self.bridge_status_topic.filter()
+    }
+
+    /// Update the bridge status, returning false if the message is not a bridge notification.
+    pub fn update_bridge_status(&mut self, message: &Message) -> bool {
+        if message.topic != self.bridge_status_topic {
+            return false;
+        }
+
+        let bridge_up = message.payload_str().ok() == Some(BRIDGE_UP_PAYLOAD);
+        if bridge_up != self.bridge_up {
+            if bridge_up {
+                info!(
+                    "Bridge is up: forwarding {} stored messages",
+                    self.queue.len()
+                );
+            } else {
+                warn!("Bridge is down: storing the messages to the cloud");
+            }
+            self.bridge_up = bridge_up;
+        }
+        true
+    }
+
+    /// Return the message if it can be published right now, or store it to be forwarded later.
+    ///
+    /// A cloud message is stored when the bridge is down,
+    /// but also when previously stored messages have still to be forwarded, so the order is preserved.
+    pub async fn forward_or_store(&mut self, message: Message) -> Option<Message> {
+        if !self.cloud_topics.accept(&message) || (self.bridge_up && self.queue.is_empty()) {
+            return Some(message);
+        }
+
+        match self.queue.push(&message).await {
+            Ok(()) => None,
+            Err(err) => {
+                // Better to hand the message over to the bridge than to lose it
+                error!("Failed to store a message to the cloud: {}", err);
+                Some(message)
+            }
+        }
+    }
+
+    /// True if the bridge is up and there are stored messages to forward
+    pub fn has_messages_to_forward(&self) -> bool {
+        self.bridge_up && !self.queue.is_empty()
+    }
+
+    /// Forward the oldest stored message to the given output, if the bridge is up.
+    ///
+    /// The message is removed from the store only once handed over to the output.
+    pub async fn forward_next_message(&mut self, output: &mut impl PubChannel) {
+        if !self.bridge_up {
+            return;
+        }
+
+        if let Err(err) = self.queue.forward_next(output).await {
+            error!("Failed to forward a stored message: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    const BRIDGE_STATUS_TOPIC: &str = "tedge/health/mosquitto-c8y-bridge";
+
+    async fn open_store(dir: &TempTedgeDir) -> StoreAndForward {
+        StoreAndForward::open(
+            "tedge-mapper-test",
+            dir.path(),
+            BRIDGE_STATUS_TOPIC,
+            TopicFilter::new_unchecked("c8y/#"),
+        )
+        .await
+        .unwrap()
+    }
+
+    fn bridge_status(payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(BRIDGE_STATUS_TOPIC), payload)
+    }
+
+    fn cloud_message(payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked("c8y/s/us"), payload)
+    }
+
+    async fn forward_all(store: &mut StoreAndForward) -> Vec<String> {
+        let (mut output, forwarded) = mpsc::unbounded();
+        while store.has_messages_to_forward() {
+            store.forward_next_message(&mut output).await;
+        }
+        output.close_channel();
+        forwarded
+            .map(|message: Message| message.payload_str().unwrap().to_string())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn messages_are_forwarded_while_the_bridge_is_up() {
+        let dir = TempTedgeDir::new();
+        let mut store = open_store(&dir).await;
+
+        assert!(store.update_bridge_status(&bridge_status("1")));
+
+        assert!(store.forward_or_store(cloud_message("1")).await.is_some());
+        assert!(!store.has_messages_to_forward());
+    }
+
+    #[tokio::test]
+    async fn cloud_messages_are_stored_while_the_bridge_is_down() {
+        let dir = TempTedgeDir::new();
+        let mut store = open_store(&dir).await;
+
+        store.update_bridge_status(&bridge_status("0"));
+        let local_message = Message::new(&Topic::new_unchecked("tedge/commands/req"), "local");
+
+        assert!(store.forward_or_store(cloud_message("1")).await.is_none());
+        assert!(store.forward_or_store(cloud_message("2")).await.is_none());
+        assert!(store.forward_or_store(local_message).await.is_some());
+        assert!(!store.has_messages_to_forward());
+        assert_eq!(forward_all(&mut store).await, Vec::<String>::new());
+
+        store.update_bridge_status(&bridge_status("1"));
+
+        assert!(store.has_messages_to_forward());
+        // New messages are queued behind the stored ones
+        assert!(store.forward_or_store(cloud_message("3")).await.is_none());
+        assert_eq!(forward_all(&mut store).await, vec!["1", "2", "3"]);
+        assert!(store.forward_or_store(cloud_message("4")).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn stored_messages_survive_a_restart() {
+        let dir = TempTedgeDir::new();
+        {
+            let mut store = open_store(&dir).await;
+            store.update_bridge_status(&bridge_status("0"));
+            store.forward_or_store(cloud_message("1")).await;
+            store.forward_or_store(cloud_message("2")).await;
+        }
+
+        let mut store = open_store(&dir).await;
+
+        assert!(store.has_messages_to_forward());
+        assert_eq!(forward_all(&mut store).await, vec!["1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn other_messages_are_not_bridge_notifications() {
+        let dir = TempTedgeDir::new();
+        let mut store = open_store(&dir).await;
+
+        assert!(!store.update_bridge_status(&cloud_message("0")));
+        assert!(store.forward_or_store(cloud_message("1")).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn messages_are_kept_until_handed_over() {
+        let dir = TempTedgeDir::new();
+        let mut store = open_store(&dir).await;
+        store.update_bridge_status(&bridge_status("0"));
+        store.forward_or_store(cloud_message("1")).await;
+        store.update_bridge_status(&bridge_status("1"));
+
+        let (mut closed_output, _) = mpsc::unbounded();
+        closed_output.close_channel();
+        store.forward_next_message(&mut closed_output).await;
+
+        assert!(store.has_messages_to_forward());
+        assert_eq!(forward_all(&mut store).await, vec!["1"]);
+    }
+}
@@ -0,0 +1,152 @@
+use mqtt_channel::{Message, Topic};
+use std::collections::HashMap;
+
+/// A journal of the messages processed by a mapper, persisted by the broker as retained messages on internal topics.
+///
+/// On startup, the broker sends back the journal entries along the retained messages of the tracked topics.
+/// Comparing both, the mapper can tell which retained messages have already been processed before a restart
+/// and which ones have been published while the mapper was down.
+///
+/// During the sync phase, the retained messages received on the tracked topics are held.
+/// When the sync is over, only the held messages without a journal entry with the same payload
+/// are released to be converted.
+#[derive(Debug)]
+pub struct SyncJournal {
+    tracked_prefix: &'static str,
+    journal_prefix: &'static str,
+    syncing: bool,
+    held: HashMap<String, Message>,
+    entries: HashMap<String, Vec<u8>>,
+}
+
+impl SyncJournal {
+    /// A journal of the messages received on the topics starting with `tracked_prefix`,
+    /// which entries are published on the topics starting with `journal_prefix`.
+    pub fn new(tracked_prefix: &'static str, journal_prefix: &'static str) -> Self {
+        SyncJournal {
+            tracked_prefix,
+            journal_prefix,
+            syncing: true,
+            held: HashMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Record a journal entry sent back by the broker.
+    ///
+    /// The messages that are not on the journal topics are ignored.
+    pub fn record(&mut self, entry: &Message) {
+        if let Some(key) = entry.topic.name.strip_prefix(self.journal_prefix) {
+            self.entries
+                .insert(key.to_string(), entry.payload_bytes().to_vec());
+        }
+    }
+
+    /// Hold a retained message received on a tracked topic during the sync phase.
+    ///
+    /// Return true if the message has been held and must not be converted now.
+    pub fn hold(&mut self, message: &Message) -> bool {
+        if self.syncing && message.retain {
+            if let Some(key) = message.topic.name.strip_prefix(self.tracked_prefix) {
+                self.held.insert(key.to_string(), message.clone());
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Check if the given message has already been processed, i.e. has a journal entry with the same payload.
+    pub fn is_recorded(&self, message: &Message) -> bool {
+        message
+            .topic
+            .name
+            .strip_prefix(self.tracked_prefix)
+            .and_then(|key| self.entries.get(key))
+            .map_or(false, |payload| {
+                payload.as_slice() == message.payload_bytes()
+            })
+    }
+
+    /// The journal entry to be published to record that the given message has been processed.
+    ///
+    /// The entry of a message with an empty payload removes the entry of the topic from the journal.
+    pub fn entry(&self, message: &Message) -> Option<Message> {
+        let key = message.topic.name.strip_prefix(self.tracked_prefix)?;
+        let topic = Topic::new_unchecked(&format!("{}{}", self.journal_prefix, key));
+        Some(Message::new(&topic, message.payload_bytes().to_vec()).with_retain())
+    }
+
+    /// End the sync phase, returning the held messages that have not been processed before.
+    pub fn sync(&mut self) -> Vec<Message> {
+        self.syncing = false;
+        let mut messages: Vec<Message> = self.held.drain().map(|(_, message)| message).collect();
+        messages.retain(|message| !self.is_recorded(message));
+        messages.sort_by(|a, b| a.topic.name.cmp(&b.topic.name));
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACKED: &str = "tedge/events/";
+    const JOURNAL: &str = "c8y-internal/events/";
+
+    fn message(topic: &str, payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload)
+    }
+
+    fn retained(topic: &str, payload: &str) -> Message {
+        message(topic, payload).with_retain()
+    }
+
+    #[test]
+    fn retained_messages_are_held_during_the_sync() {
+        let mut journal = SyncJournal::new(TRACKED, JOURNAL);
+
+        assert!(journal.hold(&retained("tedge/events/login", "{}")));
+        assert!(!journal.hold(&message("tedge/events/logout", "{}")));
+        assert!(!journal.hold(&retained("tedge/measurements", "{}")));
+
+        assert_eq!(journal.sync(), vec![retained("tedge/events/login", "{}")]);
+        assert!(!journal.hold(&retained("tedge/events/login", "{}")));
+    }
+
+    #[test]
+    fn messages_already_recorded_are_not_released() {
+        let mut journal = SyncJournal::new(TRACKED, JOURNAL);
+
+        journal.record(&retained("c8y-internal/events/login", "old"));
+        journal.record(&retained("c8y-internal/events/logout", "old"));
+        assert!(journal.hold(&retained("tedge/events/login", "old")));
+        assert!(journal.hold(&retained("tedge/events/logout", "new")));
+        assert!(journal.hold(&retained("tedge/events/reboot", "new")));
+
+        assert_eq!(
+            journal.sync(),
+            vec![
+                retained("tedge/events/logout", "new"),
+                retained("tedge/events/reboot", "new"),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_entries_are_retained_messages_on_the_journal_topics() {
+        let mut journal = SyncJournal::new(TRACKED, JOURNAL);
+        let event = message("tedge/events/login/child", "{}");
+
+        let entry = journal.entry(&event).unwrap();
+        assert_eq!(entry, retained("c8y-internal/events/login/child", "{}"));
+        assert!(journal
+            .entry(&message("tedge/measurements", "{}"))
+            .is_none());
+
+        assert!(!journal.is_recorded(&event));
+        journal.record(&entry);
+        assert!(journal.is_recorded(&event));
+        assert!(!journal.hold(&entry));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::core::{converter::*, error::*};
+use crate::generic::{mapper::GENERIC_MAPPER_NAME, rules::Rule};
+
+use async_trait::async_trait;
+use mqtt_channel::{Message, TopicFilter};
+
+/// A converter applying user-defined rules
+///
+/// A message is mapped by all the rules whose input filter matches the message topic.
+pub struct GenericConverter {
+    rules: Vec<Rule>,
+    mapper_config: MapperConfig,
+}
+
+impl GenericConverter {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let mapper_config = MapperConfig {
+            mapper_name: GENERIC_MAPPER_NAME.to_string(),
+            in_topic_filter: Self::in_topic_filter(&rules),
+            // The output topics are given by the rules
+            out_topic: make_valid_topic_or_panic("tedge/generic"),
+            errors_topic: make_valid_topic_or_panic("tedge/errors"),
+        };
+        GenericConverter {
+            rules,
+            mapper_config,
+        }
+    }
+
+    pub fn in_topic_filter(rules: &[Rule]) -> TopicFilter {
+        let mut topic_filter = TopicFilter::empty();
+        for rule in rules.iter() {
+            topic_filter.add_all(rule.input_filter());
+        }
+        topic_filter
+    }
+}
+
+#[async_trait]
+impl Converter for GenericConverter {
+    type Error = ConversionError;
+
+    fn get_mapper_config(&self) -> &MapperConfig {
+        &self.mapper_config
+    }
+
+    async fn try_convert(&mut self, input: &Message) -> Result<Vec<Message>, Self::Error> {
+        let messages = self
+            .rules
+            .iter()
+            .filter_map(|rule| rule.apply(input))
+            .map(|message_or_err| {
+                message_or_err.unwrap_or_else(|error| self.new_error_message(error, Some(input)))
+            })
+            .collect();
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generic::rules::load_rules;
+    use mqtt_channel::Topic;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    fn converter(rules: &str) -> GenericConverter {
+        let dir = TempTedgeDir::new();
+        dir.file("rules.toml").with_raw_content(rules);
+        GenericConverter::new(load_rules(dir.path()).unwrap())
+    }
+
+    #[test]
+    fn the_converter_subscribes_to_the_input_of_all_the_rules() {
+        let converter = converter(
+            r#"
+            [[rule]]
+            input = "sensors/+/temperature"
+            output = "onprem/{1}"
+
+            [[rule]]
+            input = "tedge/measurements"
+            output = "onprem/measurements"
+            "#,
+        );
+
+        assert_eq!(
+            converter.get_in_topic_filter().patterns,
+            vec!["sensors/+/temperature", "tedge/measurements"]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_message_is_mapped_by_all_the_matching_rules() {
+        let mut converter = converter(
+            r#"
+            [[rule]]
+            input = "sensors/+/temperature"
+            output = "onprem/raw/{1}"
+
+            [[rule]]
+            input = "sensors/#"
+            output = "onprem/value/{1}"
+            transform = { type = "json-path", path = "value" }
+
+            [[rule]]
+            input = "other/topic"
+            output = "onprem/other"
+            "#,
+        );
+        let input = Message::new(
+            &Topic::new_unchecked("sensors/s1/temperature"),
+            r#"{"value": 21.5}"#,
+        );
+
+        let output = converter.convert(&input).await;
+
+        let output: Vec<_> = output
+            .iter()
+            .map(|message| (message.topic.name.as_str(), message.payload_str().unwrap()))
+            .collect();
+        assert_eq!(
+            output,
+            vec![
+                ("onprem/raw/s1", r#"{"value": 21.5}"#),
+                ("onprem/value/s1/temperature", "21.5"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_rule_failing_to_map_a_message_produces_an_error_message() {
+        let mut converter = converter(
+            r#"
+            [[rule]]
+            input = "sensors/+"
+            output = "onprem/{1}"
+            transform = { type = "flat" }
+
+            [[rule]]
+            input = "sensors/+"
+            output = "onprem/raw/{1}"
+            "#,
+        );
+        let input = Message::new(&Topic::new_unchecked("sensors/s1"), "not json");
+
+        let output = converter.convert(&input).await;
+
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0].topic.name, "tedge/errors");
+        assert_eq!(output[1].topic.name, "onprem/raw/s1");
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use crate::{
+    core::{
+        component::TEdgeComponent,
+        mapper::create_mapper,
+        metrics_endpoint::MetricsEndpoint,
+        script_hook::{with_script_hook, SCRIPTS_DIR},
+    },
+    generic::{converter::GenericConverter, rules::load_rules},
+};
+
+use async_trait::async_trait;
+use tedge_config::TEdgeConfig;
+use tedge_utils::file::create_directory_with_user_group;
+use tracing::{info, info_span, warn, Instrument};
+
+pub(crate) const GENERIC_MAPPER_NAME: &str = "tedge-mapper-generic";
+
+/// The directory, relative to the config directory, where the rule files are stored
+const RULES_DIR: &str = "mappers";
+
+pub struct GenericMapper {}
+
+impl GenericMapper {
+    pub fn new() -> GenericMapper {
+        GenericMapper {}
+    }
+}
+
+#[async_trait]
+impl TEdgeComponent for GenericMapper {
+    fn session_name(&self) -> &str {
+        GENERIC_MAPPER_NAME
+    }
+
+    async fn init(&self, config_dir: &Path) -> Result<(), anyhow::Error> {
+        info!("Initialize tedge mapper generic");
+        let rules_dir = config_dir.join(RULES_DIR);
+        create_directory_with_user_group(&rules_dir, "tedge", "tedge", 0o775)?;
+        create_directory_with_user_group(config_dir.join(SCRIPTS_DIR), "tedge", "tedge", 0o775)?;
+
+        let rules = load_rules(&rules_dir)?;
+        self.init_session(GenericConverter::in_topic_filter(&rules))
+            .await?;
+        Ok(())
+    }
+
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        config_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
+    ) -> Result<(), anyhow::Error> {
+        let rules_dir = config_dir.join(RULES_DIR);
+        let rules = load_rules(&rules_dir)?;
+        if rules.is_empty() {
+            warn!("No mapping rules found in {}", rules_dir.display());
+        } else {
+            info!(
+                "Loaded {} mapping rules from {}",
+                rules.len(),
+                rules_dir.display()
+            );
+        }
+
+        let mqtt_config = tedge_config.mqtt_config()?;
+        let converter = Box::new(GenericConverter::new(rules));
+        let converter = with_script_hook(converter, GENERIC_MAPPER_NAME, config_dir)?;
+
+        let mut mapper = create_mapper(GENERIC_MAPPER_NAME, mqtt_config, converter)
+            .await?
+            .with_mapper_settings(&tedge_config)?;
+        metrics_endpoint.register(GENERIC_MAPPER_NAME, mapper.metrics());
+
+        mapper
+            .run(None)
+            .instrument(info_span!(GENERIC_MAPPER_NAME))
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,4 @@
+mod converter;
+pub mod mapper;
+mod rules;
+mod transform;
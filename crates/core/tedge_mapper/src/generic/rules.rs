@@ -0,0 +1,421 @@
+use crate::core::error::ConversionError;
+use crate::generic::transform::Transform;
+
+use mqtt_channel::{Message, Topic, TopicFilter};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RuleError {
+    #[error("Failed to read the mapping rules from {path}: {error}")]
+    FromIo {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+
+    #[error("Invalid mapping rules in {path}: {error}")]
+    FromToml {
+        path: PathBuf,
+        #[source]
+        error: toml::de::Error,
+    },
+
+    #[error("Invalid input topic filter '{filter}' in {path}")]
+    InvalidInputFilter { path: PathBuf, filter: String },
+
+    #[error("Invalid output topic template '{template}' in {path}")]
+    InvalidOutputTemplate { path: PathBuf, template: String },
+}
+
+/// The content of a rule file, as a list of `[[rule]]` tables
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    rule: Vec<RuleSpec>,
+}
+
+/// A rule as written by the user
+///
+/// ```toml
+/// [[rule]]
+/// input = "sensors/+/temperature"
+/// output = "onprem/{1}/temperature"
+/// transform = { type = "json-path", path = "values.temperature" }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    input: String,
+    output: String,
+    #[serde(default)]
+    transform: Transform,
+}
+
+/// A mapping rule: the messages received on the `input` topic filter
+/// are transformed and published on the `output` topic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    input: String,
+    output: TopicTemplate,
+    transform: Transform,
+}
+
+impl Rule {
+    /// The topic filter of the messages to be mapped by this rule
+    pub fn input_filter(&self) -> TopicFilter {
+        TopicFilter::new_unchecked(&self.input)
+    }
+
+    /// Map the given message, returning `None` if the message topic doesn't match this rule.
+    pub fn apply(&self, message: &Message) -> Option<Result<Message, ConversionError>> {
+        let wildcards = wildcard_values(&self.input, &message.topic.name)?;
+        Some(self.apply_with_wildcards(message, &wildcards))
+    }
+
+    fn apply_with_wildcards(
+        &self,
+        message: &Message,
+        wildcards: &[String],
+    ) -> Result<Message, ConversionError> {
+        let topic = Topic::new(&self.output.render(wildcards))?;
+        let payload = self
+            .transform
+            .apply(&message.topic, message.payload_str()?, wildcards)?;
+
+        let output = Message::new(&topic, payload).with_qos(message.qos);
+        if message.retain {
+            Ok(output.with_retain())
+        } else {
+            Ok(output)
+        }
+    }
+}
+
+/// Load the rules of all the `*.toml` files of the given directory, in file name order.
+///
+/// A missing directory is not an error and simply provides no rules.
+pub fn load_rules(dir: &Path) -> Result<Vec<Rule>, RuleError> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let io_error = |error| RuleError::FromIo {
+        path: dir.to_path_buf(),
+        error,
+    };
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.extension().map_or(false, |ext| ext == "toml") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut rules = vec![];
+    for path in paths {
+        let content = std::fs::read_to_string(&path).map_err(|error| RuleError::FromIo {
+            path: path.clone(),
+            error,
+        })?;
+        rules.append(&mut parse_rules(&path, &content)?);
+    }
+    Ok(rules)
+}
+
+fn parse_rules(path: &Path, content: &str) -> Result<Vec<Rule>, RuleError> {
+    let file: RuleFile = toml::from_str(content).map_err(|error| RuleError::FromToml {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    file.rule
+        .into_iter()
+        .map(|spec| {
+            if TopicFilter::new(&spec.input).is_err() {
+                return Err(RuleError::InvalidInputFilter {
+                    path: path.to_path_buf(),
+                    filter: spec.input,
+                });
+            }
+
+            let wildcard_count = spec
+                .input
+                .split('/')
+                .filter(|level| *level == "+" || *level == "#")
+                .count();
+            let output = TopicTemplate {
+                template: spec.output,
+            };
+            if !output.is_valid(wildcard_count) {
+                return Err(RuleError::InvalidOutputTemplate {
+                    path: path.to_path_buf(),
+                    template: output.template,
+                });
+            }
+
+            Ok(Rule {
+                input: spec.input,
+                output,
+                transform: spec.transform,
+            })
+        })
+        .collect()
+}
+
+/// A topic name where `{1}`, `{2}`, ... are replaced by the topic levels
+/// matched by the first, second, ... wildcard of the input filter.
+///
+/// A `#` wildcard matches all the remaining levels, separated by `/`.
+#[derive(Debug, Clone, PartialEq)]
+struct TopicTemplate {
+    template: String,
+}
+
+impl TopicTemplate {
+    fn render(&self, wildcards: &[String]) -> String {
+        render_template(&self.template, wildcards)
+    }
+
+    fn is_valid(&self, wildcard_count: usize) -> bool {
+        let placeholders = vec!["x".to_string(); wildcard_count];
+        let topic = self.render(&placeholders);
+        !topic.contains(['{', '}'].as_ref()) && Topic::new(&topic).is_ok()
+    }
+}
+
+/// Replace the `{1}`, `{2}`, ... placeholders of a template by the given values.
+///
+/// The template is rendered in a single pass, so the placeholders found in the values are kept as is.
+/// The placeholders with no values are left unchanged.
+pub fn render_template(template: &str, values: &[String]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let index: usize = rest[1..end].parse().ok()?;
+            let value = values.get(index.checked_sub(1)?)?;
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// The topic levels matched by the wildcards of a filter, if the topic matches the filter.
+///
+/// Unlike MQTT, a trailing `#` has to match at least one non-empty level,
+/// so `a/#` doesn't match `a`, nor `a/`: a rule is only applied when all its placeholders have a value.
+fn wildcard_values(filter: &str, topic: &str) -> Option<Vec<String>> {
+    let mut values = vec![];
+    let mut levels = topic.split('/');
+    for filter_level in filter.split('/') {
+        match filter_level {
+            "#" => {
+                let value = levels.collect::<Vec<_>>().join("/");
+                if value.is_empty() {
+                    return None;
+                }
+                values.push(value);
+                return Some(values);
+            }
+            "+" => values.push(levels.next()?.to_string()),
+            literal => {
+                if levels.next()? != literal {
+                    return None;
+                }
+            }
+        }
+    }
+    match levels.next() {
+        None => Some(values),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use tedge_test_utils::fs::TempTedgeDir;
+    use test_case::test_case;
+
+    fn rules(content: &str) -> Result<Vec<Rule>, RuleError> {
+        parse_rules(Path::new("/etc/tedge/mappers/test.toml"), content)
+    }
+
+    #[test_case("a/b", "a/b", Some(vec![]))]
+    #[test_case("a/b", "a/c", None)]
+    #[test_case("a/+/c", "a/b/c", Some(vec!["b"]))]
+    #[test_case("a/+/c", "a/b/c/d", None)]
+    #[test_case("+/+", "a/b", Some(vec!["a", "b"]))]
+    #[test_case("a/#", "a/b/c", Some(vec!["b/c"]))]
+    #[test_case("a/+/#", "a/b/c/d", Some(vec!["b", "c/d"]))]
+    #[test_case("a/#", "a", None)]
+    #[test_case("a/#", "a/", None)]
+    #[test_case("#", "a", Some(vec!["a"]))]
+    fn matching_wildcards(filter: &str, topic: &str, expected: Option<Vec<&str>>) {
+        let expected = expected.map(|values| values.into_iter().map(String::from).collect());
+        assert_eq!(wildcard_values(filter, topic), expected);
+    }
+
+    #[test_case("onprem/{1}/{2}", &["a", "b"], "onprem/a/b")]
+    #[test_case("onprem/{2}/{1}", &["{2}", "b"], "onprem/b/{2}"; "placeholders in values")]
+    #[test_case("onprem/{1}{1}", &["a"], "onprem/aa")]
+    #[test_case("onprem/{3}/{x}/{", &["a"], "onprem/{3}/{x}/{"; "placeholders with no values")]
+    #[test_case("{10}", &["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"], "j")]
+    fn rendering_templates(template: &str, values: &[&str], expected: &str) {
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        assert_eq!(render_template(template, &values), expected);
+    }
+
+    #[test]
+    fn parsing_rules() {
+        let rules = rules(
+            r#"
+            [[rule]]
+            input = "sensors/+/temperature"
+            output = "onprem/{1}/temperature"
+
+            [[rule]]
+            input = "tedge/measurements/#"
+            output = "onprem/measurements"
+            transform = { type = "line-protocol", measurement = "{1}" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].transform, Transform::PassThrough);
+        assert_eq!(
+            rules[0].input_filter().patterns,
+            vec!["sensors/+/temperature"]
+        );
+        assert_eq!(
+            rules[1].transform,
+            Transform::LineProtocol {
+                measurement: "{1}".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn a_file_with_no_rule_is_valid() {
+        assert_eq!(rules("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rejecting_invalid_input_filters() {
+        let error = rules(
+            r#"
+            [[rule]]
+            input = "sensors/#/temperature"
+            output = "onprem/temperature"
+            "#,
+        )
+        .unwrap_err();
+
+        assert_matches!(error, RuleError::InvalidInputFilter { filter, .. } if filter == "sensors/#/temperature");
+    }
+
+    #[test_case("onprem/{2}/temperature"; "unknown wildcard")]
+    #[test_case("onprem/{child}"; "named placeholder")]
+    #[test_case("onprem/+/temperature"; "wildcard in topic name")]
+    fn rejecting_invalid_output_templates(template: &str) {
+        let content = format!(
+            r#"
+            [[rule]]
+            input = "sensors/+/temperature"
+            output = "{}"
+            "#,
+            template
+        );
+
+        let error = rules(&content).unwrap_err();
+
+        assert_matches!(error, RuleError::InvalidOutputTemplate { .. });
+    }
+
+    #[test]
+    fn rejecting_unknown_transforms() {
+        let error = rules(
+            r#"
+            [[rule]]
+            input = "sensors/+/temperature"
+            output = "onprem/temperature"
+            transform = { type = "xml" }
+            "#,
+        )
+        .unwrap_err();
+
+        assert_matches!(error, RuleError::FromToml { .. });
+    }
+
+    #[test]
+    fn applying_a_rule_substitutes_the_wildcards() {
+        let rules = rules(
+            r#"
+            [[rule]]
+            input = "sensors/+/+/#"
+            output = "onprem/{2}/{1}/{3}"
+            "#,
+        )
+        .unwrap();
+        let input = Message::new(&Topic::new_unchecked("sensors/s1/temp/a/b"), "21.5");
+
+        let output = rules[0].apply(&input).unwrap().unwrap();
+
+        assert_eq!(output.topic.name, "onprem/temp/s1/a/b");
+        assert_eq!(output.payload_str().unwrap(), "21.5");
+    }
+
+    #[test]
+    fn a_rule_is_not_applied_to_a_message_not_matching_the_input_filter() {
+        let rules = rules(
+            r#"
+            [[rule]]
+            input = "sensors/+/temperature"
+            output = "onprem/temperature"
+            "#,
+        )
+        .unwrap();
+        let input = Message::new(&Topic::new_unchecked("sensors/s1/pressure"), "1013");
+
+        assert!(rules[0].apply(&input).is_none());
+    }
+
+    #[test]
+    fn loading_rules_from_a_directory() {
+        let dir = TempTedgeDir::new();
+        dir.file("b.toml")
+            .with_raw_content("[[rule]]\ninput = \"b\"\noutput = \"out/b\"\n");
+        dir.file("a.toml")
+            .with_raw_content("[[rule]]\ninput = \"a\"\noutput = \"out/a\"\n");
+        dir.file("README").with_raw_content("not a rule file");
+
+        let rules = load_rules(dir.path()).unwrap();
+
+        let inputs: Vec<_> = rules.iter().map(|rule| rule.input.as_str()).collect();
+        assert_eq!(inputs, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn a_missing_rule_directory_provides_no_rules() {
+        let rules = load_rules(Path::new("/does/not/exist")).unwrap();
+        assert!(rules.is_empty());
+    }
+}
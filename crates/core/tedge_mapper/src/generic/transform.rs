@@ -0,0 +1,281 @@
+use crate::core::error::ConversionError;
+use crate::generic::rules::render_template;
+
+use mqtt_channel::Topic;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use thin_edge_json::line_protocol::LineProtocolSerializer;
+use thin_edge_json::measurement::MeasurementVisitor;
+use time::{format_description, OffsetDateTime};
+
+/// The transformation applied by a rule to the payload of a message
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Transform {
+    /// The payload is forwarded unchanged.
+    PassThrough,
+
+    /// Extract a value from a JSON payload.
+    ///
+    /// The path is a dot-separated list of object keys and array indices, as `values.temperature`,
+    /// possibly prefixed by `$.`. A string value is published as is, any other value as JSON.
+    JsonPath { path: String },
+
+    /// Rename the top-level fields of a JSON object, the other fields being kept unchanged.
+    Rename { fields: BTreeMap<String, String> },
+
+    /// Translate Thin Edge JSON measurements into a flat JSON object,
+    /// with the grouped measurements named after their group and name joined by the separator.
+    Flat {
+        #[serde(default = "default_separator")]
+        separator: String,
+    },
+
+    /// Translate Thin Edge JSON measurements into a line of InfluxDB line protocol.
+    ///
+    /// The measurement name can refer to the wildcards of the input filter, as `{1}`.
+    LineProtocol { measurement: String },
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::PassThrough
+    }
+}
+
+fn default_separator() -> String {
+    ".".into()
+}
+
+impl Transform {
+    pub fn apply(
+        &self,
+        topic: &Topic,
+        payload: &str,
+        wildcards: &[String],
+    ) -> Result<String, ConversionError> {
+        match self {
+            Transform::PassThrough => Ok(payload.to_string()),
+
+            Transform::JsonPath { path } => {
+                let json: Value = serde_json::from_str(payload)?;
+                match json.pointer(&json_pointer(path)) {
+                    Some(Value::String(value)) => Ok(value.clone()),
+                    Some(value) => Ok(value.to_string()),
+                    None => Err(ConversionError::JsonPathNotFound {
+                        path: path.clone(),
+                        topic: topic.name.clone(),
+                    }),
+                }
+            }
+
+            Transform::Rename { fields } => {
+                let mut object = match serde_json::from_str(payload)? {
+                    Value::Object(object) => object,
+                    _ => {
+                        return Err(ConversionError::NotAJsonObject {
+                            topic: topic.name.clone(),
+                        })
+                    }
+                };
+                for (from, to) in fields.iter() {
+                    if let Some(value) = object.remove(from) {
+                        object.insert(to.clone(), value);
+                    }
+                }
+                Ok(serde_json::to_string(&object)?)
+            }
+
+            Transform::Flat { separator } => {
+                let measurements = Flattener::parse(payload, separator)?;
+                let mut object = Map::new();
+                if let Some(timestamp) = measurements.timestamp {
+                    let time = timestamp.format(&format_description::well_known::Rfc3339)?;
+                    object.insert("time".into(), time.into());
+                }
+                for (name, value) in measurements.values {
+                    object.insert(name, value.into());
+                }
+                Ok(serde_json::to_string(&object)?)
+            }
+
+            Transform::LineProtocol { measurement } => {
+                let measurement = render_template(measurement, wildcards);
+                let mut serializer = LineProtocolSerializer::new(&measurement);
+                thin_edge_json::parser::parse_str(payload, &mut serializer)?;
+                Ok(serializer.into_string()?)
+            }
+        }
+    }
+}
+
+/// Convert a dotted path, as `$.values.temperature`, into a JSON pointer, as `/values/temperature`
+fn json_pointer(path: &str) -> String {
+    let path = path.strip_prefix("$.").unwrap_or(path);
+    path.split('.')
+        .map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Collect the measurements of a Thin Edge JSON message as a flat list
+#[derive(Debug, Default)]
+struct Flattener {
+    separator: String,
+    group: Option<String>,
+    timestamp: Option<OffsetDateTime>,
+    values: Vec<(String, f64)>,
+}
+
+impl Flattener {
+    fn parse(payload: &str, separator: &str) -> Result<Self, ConversionError> {
+        let mut flattener = Flattener {
+            separator: separator.to_string(),
+            ..Flattener::default()
+        };
+        thin_edge_json::parser::parse_str(payload, &mut flattener)?;
+        Ok(flattener)
+    }
+}
+
+impl MeasurementVisitor for Flattener {
+    type Error = Infallible;
+
+    fn visit_timestamp(&mut self, value: OffsetDateTime) -> Result<(), Self::Error> {
+        self.timestamp = Some(value);
+        Ok(())
+    }
+
+    fn visit_measurement(&mut self, name: &str, value: f64) -> Result<(), Self::Error> {
+        let name = match &self.group {
+            Some(group) => format!("{}{}{}", group, self.separator, name),
+            None => name.to_string(),
+        };
+        self.values.push((name, value));
+        Ok(())
+    }
+
+    fn visit_start_group(&mut self, group: &str) -> Result<(), Self::Error> {
+        self.group = Some(group.to_string());
+        Ok(())
+    }
+
+    fn visit_end_group(&mut self) -> Result<(), Self::Error> {
+        self.group = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_json_diff::assert_json_eq;
+    use assert_matches::assert_matches;
+    use serde_json::json;
+
+    const MEASUREMENTS: &str = r#"{
+        "time": "2021-04-08T00:00:00+05:00",
+        "temperature": 25,
+        "location": { "latitude": 32.54, "longitude": -117.67 }
+    }"#;
+
+    fn apply(transform: Transform, payload: &str) -> Result<String, ConversionError> {
+        transform.apply(
+            &Topic::new_unchecked("sensors/s1"),
+            payload,
+            &["s1".to_string()],
+        )
+    }
+
+    #[test]
+    fn pass_through_forwards_the_payload_unchanged() {
+        let output = apply(Transform::PassThrough, "not even json").unwrap();
+        assert_eq!(output, "not even json");
+    }
+
+    #[test]
+    fn extracting_a_json_value() {
+        let payload = r#"{"values": [{"temperature": 21.5, "unit": "C"}]}"#;
+        let path = |path: &str| Transform::JsonPath { path: path.into() };
+
+        assert_eq!(
+            apply(path("values.0.temperature"), payload).unwrap(),
+            "21.5"
+        );
+        assert_eq!(apply(path("$.values.0.unit"), payload).unwrap(), "C");
+        assert_eq!(
+            apply(path("values.0"), payload).unwrap(),
+            r#"{"temperature":21.5,"unit":"C"}"#
+        );
+        assert_matches!(
+            apply(path("values.1"), payload),
+            Err(ConversionError::JsonPathNotFound { .. })
+        );
+    }
+
+    #[test]
+    fn renaming_fields() {
+        let transform = Transform::Rename {
+            fields: vec![("temp".to_string(), "temperature".to_string())]
+                .into_iter()
+                .collect(),
+        };
+
+        let output = apply(transform.clone(), r#"{"temp": 21.5, "unit": "C"}"#).unwrap();
+
+        assert_json_eq!(
+            serde_json::from_str::<Value>(&output).unwrap(),
+            json!({"temperature": 21.5, "unit": "C"})
+        );
+        assert_matches!(
+            apply(transform, "[1, 2]"),
+            Err(ConversionError::NotAJsonObject { .. })
+        );
+    }
+
+    #[test]
+    fn flattening_thin_edge_json() {
+        let transform = Transform::Flat {
+            separator: "_".into(),
+        };
+
+        let output = apply(transform, MEASUREMENTS).unwrap();
+
+        assert_json_eq!(
+            serde_json::from_str::<Value>(&output).unwrap(),
+            json!({
+                "time": "2021-04-08T00:00:00+05:00",
+                "temperature": 25.0,
+                "location_latitude": 32.54,
+                "location_longitude": -117.67
+            })
+        );
+    }
+
+    #[test]
+    fn translating_thin_edge_json_into_line_protocol() {
+        let transform = Transform::LineProtocol {
+            measurement: "sensor {1}".into(),
+        };
+
+        let output = apply(transform, MEASUREMENTS).unwrap();
+
+        assert_eq!(
+            output,
+            "sensor\\ s1 temperature=25,location_latitude=32.54,location_longitude=-117.67 1617822000000000000"
+        );
+    }
+
+    #[test]
+    fn invalid_thin_edge_json_is_rejected() {
+        let transform = Transform::Flat {
+            separator: ".".into(),
+        };
+
+        assert_matches!(
+            apply(transform, r#"{"temperature": "hot"}"#),
+            Err(ConversionError::FromThinEdgeJsonParser(_))
+        );
+    }
+}
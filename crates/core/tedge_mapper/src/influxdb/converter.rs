@@ -0,0 +1,173 @@
+use crate::core::{converter::*, error::*};
+use crate::influxdb::exporter::LineBatcher;
+use crate::influxdb::mapper::INFLUXDB_MAPPER_NAME;
+
+use async_trait::async_trait;
+use mqtt_channel::{Message, Topic, TopicFilter};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// Where the batches of measurements are sent
+pub enum InfluxDbOutput {
+    /// Publish the lines on an MQTT topic, as consumed by Telegraf
+    Mqtt { topic: Topic },
+
+    /// Hand the lines over to the task writing them to InfluxDB
+    Http { batches: mpsc::Sender<String> },
+}
+
+/// A converter batching the thin-edge measurements as InfluxDB line protocol
+///
+/// A batch is sent when full, or on flush once the batch period is over.
+pub struct InfluxDbConverter {
+    mapper_config: MapperConfig,
+    batcher: LineBatcher,
+    output: InfluxDbOutput,
+    batch_period: Duration,
+    batch_start: Instant,
+}
+
+impl InfluxDbConverter {
+    pub fn new(batcher: LineBatcher, output: InfluxDbOutput, batch_period: Duration) -> Self {
+        let out_topic = match &output {
+            InfluxDbOutput::Mqtt { topic } => topic.clone(),
+            // Nothing is published when the measurements are posted over HTTP
+            InfluxDbOutput::Http { .. } => make_valid_topic_or_panic("tedge/influxdb"),
+        };
+        let mapper_config = MapperConfig {
+            mapper_name: INFLUXDB_MAPPER_NAME.to_string(),
+            in_topic_filter: Self::in_topic_filter(),
+            out_topic,
+            errors_topic: make_valid_topic_or_panic("tedge/errors"),
+        };
+        InfluxDbConverter {
+            mapper_config,
+            batcher,
+            output,
+            batch_period,
+            batch_start: Instant::now(),
+        }
+    }
+
+    pub fn in_topic_filter() -> TopicFilter {
+        let mut topic_filter = make_valid_topic_filter_or_panic("tedge/measurements");
+        topic_filter.add_unchecked("tedge/measurements/+");
+        topic_filter
+    }
+
+    fn send(&mut self, batch: String) -> Vec<Message> {
+        self.batch_start = Instant::now();
+        match &self.output {
+            InfluxDbOutput::Mqtt { topic } => vec![Message::new(topic, batch)],
+            InfluxDbOutput::Http { batches } => {
+                if let Err(err) = batches.try_send(batch) {
+                    error!("Dropping a batch of measurements: {}", err);
+                }
+                vec![]
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Converter for InfluxDbConverter {
+    type Error = ConversionError;
+
+    fn get_mapper_config(&self) -> &MapperConfig {
+        &self.mapper_config
+    }
+
+    async fn try_convert(&mut self, input: &Message) -> Result<Vec<Message>, Self::Error> {
+        match self.batcher.push(input)? {
+            Some(batch) => Ok(self.send(batch)),
+            None => Ok(vec![]),
+        }
+    }
+
+    async fn try_flush_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+        if self.batch_start.elapsed() < self.batch_period {
+            return Ok(vec![]);
+        }
+        match self.batcher.take_batch() {
+            Some(batch) => Ok(self.send(batch)),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clock::WallClock;
+
+    fn measurements(payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked("tedge/measurements"), payload)
+    }
+
+    fn mqtt_converter(max_lines: usize, batch_period: Duration) -> InfluxDbConverter {
+        InfluxDbConverter::new(
+            LineBatcher::new("gateway", Box::new(WallClock), max_lines),
+            InfluxDbOutput::Mqtt {
+                topic: Topic::new_unchecked("influxdb/measurements"),
+            },
+            batch_period,
+        )
+    }
+
+    #[tokio::test]
+    async fn full_batches_are_published_on_mqtt() {
+        let mut converter = mqtt_converter(2, Duration::from_secs(3600));
+
+        let first = converter
+            .try_convert(&measurements(r#"{"temperature": 25}"#))
+            .await
+            .unwrap();
+        let second = converter
+            .try_convert(&measurements(r#"{"temperature": 26}"#))
+            .await
+            .unwrap();
+
+        assert!(first.is_empty());
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].topic.name, "influxdb/measurements");
+        assert_eq!(second[0].payload_str().unwrap().lines().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn partial_batches_are_sent_once_the_batch_period_is_over() {
+        let mut converter = mqtt_converter(10, Duration::from_millis(50));
+        converter
+            .try_convert(&measurements(r#"{"temperature": 25}"#))
+            .await
+            .unwrap();
+
+        assert!(converter.flush_messages().await.is_empty());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(converter.flush_messages().await.len(), 1);
+        assert!(converter.flush_messages().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn batches_are_handed_over_to_the_http_writer() {
+        let (batches, mut pending_batches) = mpsc::channel(1);
+        let mut converter = InfluxDbConverter::new(
+            LineBatcher::new("gateway", Box::new(WallClock), 1),
+            InfluxDbOutput::Http { batches },
+            Duration::ZERO,
+        );
+
+        let published = converter
+            .try_convert(&measurements(r#"{"temperature": 25}"#))
+            .await
+            .unwrap();
+
+        assert!(published.is_empty());
+        assert!(pending_batches
+            .recv()
+            .await
+            .unwrap()
+            .starts_with("tedge,device=gateway temperature=25 "));
+    }
+}
@@ -0,0 +1,23 @@
+#[derive(thiserror::Error, Debug)]
+pub enum InfluxDbMapperError {
+    #[error(transparent)]
+    FromReqwest(#[from] reqwest::Error),
+
+    #[error("InfluxDB rejected the measurements with status {status}: {reason}")]
+    WriteRejected {
+        status: reqwest::StatusCode,
+        reason: String,
+    },
+}
+
+impl InfluxDbMapperError {
+    /// True if the write might succeed when retried later
+    pub fn is_transient(&self) -> bool {
+        match self {
+            InfluxDbMapperError::FromReqwest(_) => true,
+            InfluxDbMapperError::WriteRejected { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+}
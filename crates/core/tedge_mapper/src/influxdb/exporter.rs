@@ -0,0 +1,296 @@
+use crate::core::error::ConversionError;
+use crate::influxdb::error::InfluxDbMapperError;
+
+use clock::Clock;
+use mqtt_channel::Message;
+use std::time::Duration;
+use thin_edge_json::line_protocol::LineProtocolSerializer;
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+
+/// The InfluxDB measurement of the lines produced by the mapper
+const MEASUREMENT: &str = "tedge";
+
+/// The tag giving the device of the measurements
+const DEVICE_TAG: &str = "device";
+
+/// The maximum number of lines sent at once, as recommended by InfluxDB
+pub const MAX_BATCH_LINES: usize = 5000;
+
+/// The maximum number of batches waiting to be written, while InfluxDB is unreachable
+const MAX_PENDING_BATCHES: usize = 100;
+
+/// The delay before retrying a failed write, doubled on each new failure
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Post batches of lines to an InfluxDB write endpoint
+pub struct InfluxDbWriter {
+    client: reqwest::Client,
+    url: String,
+    token: Option<String>,
+    min_retry_delay: Duration,
+}
+
+impl InfluxDbWriter {
+    pub fn new(url: String, token: Option<String>) -> Self {
+        InfluxDbWriter {
+            client: reqwest::Client::new(),
+            url,
+            token,
+            min_retry_delay: MIN_RETRY_DELAY,
+        }
+    }
+
+    /// Write the batches sent to the returned channel, in order, from a background task.
+    ///
+    /// A batch is retried, with an exponential backoff, as long as the failure is transient.
+    /// When the channel is full, the new batches are rejected rather than delaying the mapper.
+    pub fn spawn(self) -> mpsc::Sender<String> {
+        let (batches, mut pending_batches) = mpsc::channel::<String>(MAX_PENDING_BATCHES);
+        tokio::spawn(async move {
+            while let Some(batch) = pending_batches.recv().await {
+                self.write_with_retries(&batch).await;
+            }
+        });
+        batches
+    }
+
+    async fn write_with_retries(&self, lines: &str) {
+        let mut retry_delay = self.min_retry_delay;
+        loop {
+            match self.write(lines).await {
+                Ok(()) => return,
+                Err(err) if err.is_transient() => {
+                    warn!(
+                        "Failed to write the measurements to InfluxDB, retrying in {:?}: {}",
+                        retry_delay, err
+                    );
+                    tokio::time::sleep(retry_delay).await;
+                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                }
+                Err(err) => {
+                    error!("Failed to write the measurements to InfluxDB: {}", err);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Post a batch of lines, separated by new lines
+    pub async fn write(&self, lines: &str) -> Result<(), InfluxDbMapperError> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(lines.to_string());
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let reason = response.text().await.unwrap_or_default();
+            return Err(InfluxDbMapperError::WriteRejected { status, reason });
+        }
+        Ok(())
+    }
+}
+
+/// Translate the thin-edge measurements into batches of lines of InfluxDB line protocol
+///
+/// The measurements are tagged with the device they are related to,
+/// and timestamped when received if they have no timestamp,
+/// so the batching delay doesn't shift the measurement times.
+pub struct LineBatcher {
+    main_device: String,
+    clock: Box<dyn Clock>,
+    max_lines: usize,
+    lines: Vec<String>,
+}
+
+impl LineBatcher {
+    pub fn new(main_device: &str, clock: Box<dyn Clock>, max_lines: usize) -> Self {
+        LineBatcher {
+            main_device: main_device.to_string(),
+            clock,
+            max_lines: max_lines.max(1),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Add the measurements of a message to the current batch,
+    /// returning the batch if full.
+    pub fn push(&mut self, input: &Message) -> Result<Option<String>, ConversionError> {
+        let device = match input.topic.name.strip_prefix("tedge/measurements/") {
+            Some(child) => child,
+            None => &self.main_device,
+        };
+        let mut serializer =
+            LineProtocolSerializer::new_with_timestamp(MEASUREMENT, Some(self.clock.now()))
+                .with_tag(DEVICE_TAG, device);
+        thin_edge_json::parser::parse_str(input.payload_str()?, &mut serializer)?;
+
+        self.lines.push(serializer.into_string()?);
+        if self.lines.len() >= self.max_lines {
+            Ok(self.take_batch())
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Return the current batch, if not empty, starting a new one.
+    pub fn take_batch(&mut self) -> Option<String> {
+        if self.lines.is_empty() {
+            None
+        } else {
+            let batch = self.lines.join("\n");
+            self.lines.clear();
+            Some(batch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use mockito::{mock, Matcher};
+    use mqtt_channel::Topic;
+    use time::macros::datetime;
+
+    struct TestClock;
+
+    impl Clock for TestClock {
+        fn now(&self) -> clock::Timestamp {
+            datetime!(2021-04-08 00:00:00 UTC)
+        }
+    }
+
+    fn measurements(topic: &str, payload: &str) -> Message {
+        Message::new(&Topic::new_unchecked(topic), payload)
+    }
+
+    #[test]
+    fn measurements_are_batched() {
+        let mut batcher = LineBatcher::new("gateway", Box::new(TestClock), 3);
+
+        let first = batcher.push(&measurements(
+            "tedge/measurements",
+            r#"{"temperature": 25, "time": "2021-04-08T00:00:01Z"}"#,
+        ));
+        let second = batcher.push(&measurements(
+            "tedge/measurements/child1",
+            r#"{"location": {"x": 1, "y": 2}}"#,
+        ));
+
+        assert_matches!(first, Ok(None));
+        assert_matches!(second, Ok(None));
+        assert_eq!(
+            batcher.take_batch().unwrap(),
+            concat!(
+                "tedge,device=gateway temperature=25 1617840001000000000\n",
+                "tedge,device=child1 location_x=1,location_y=2 1617840000000000000"
+            )
+        );
+        assert_eq!(batcher.take_batch(), None);
+    }
+
+    #[test]
+    fn a_full_batch_is_returned() {
+        let mut batcher = LineBatcher::new("gateway", Box::new(TestClock), 2);
+        let message = measurements("tedge/measurements", r#"{"temperature": 25}"#);
+
+        assert_matches!(batcher.push(&message), Ok(None));
+        assert_eq!(batcher.push(&message).unwrap().unwrap().lines().count(), 2);
+        assert_eq!(batcher.take_batch(), None);
+    }
+
+    #[test]
+    fn invalid_measurements_are_rejected() {
+        let mut batcher = LineBatcher::new("gateway", Box::new(TestClock), 2);
+
+        assert_matches!(
+            batcher.push(&measurements("tedge/measurements", "{}")),
+            Err(ConversionError::FromThinEdgeJsonParser(_))
+        );
+        assert_matches!(
+            batcher.push(&measurements("tedge/measurements", "not json")),
+            Err(ConversionError::FromThinEdgeJsonParser(_))
+        );
+        assert_eq!(batcher.take_batch(), None);
+    }
+
+    #[tokio::test]
+    async fn batches_are_posted_to_influxdb() {
+        let write = mock("POST", "/api/v2/write")
+            .match_query(Matcher::UrlEncoded("bucket".into(), "tedge".into()))
+            .match_header("authorization", "Token secret")
+            .match_body("tedge temperature=25\ntedge temperature=26")
+            .with_status(204)
+            .create();
+        let writer = InfluxDbWriter::new(
+            format!("{}/api/v2/write?bucket=tedge", mockito::server_url()),
+            Some("secret".into()),
+        );
+
+        writer
+            .write("tedge temperature=25\ntedge temperature=26")
+            .await
+            .unwrap();
+
+        write.assert();
+    }
+
+    #[tokio::test]
+    async fn rejected_batches_are_reported() {
+        let _write = mock("POST", "/write")
+            .with_status(400)
+            .with_body("unable to parse")
+            .create();
+        let writer = InfluxDbWriter::new(format!("{}/write", mockito::server_url()), None);
+
+        let result = writer.write("tedge").await;
+
+        assert_matches!(
+            result,
+            Err(InfluxDbMapperError::WriteRejected { status, reason })
+                if status == reqwest::StatusCode::BAD_REQUEST && reason == "unable to parse"
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_writes_are_retried() {
+        let unavailable = mock("POST", "/retried").with_status(503).expect(2).create();
+        let written = mock("POST", "/retried")
+            .match_body("tedge temperature=25")
+            .with_status(204)
+            .create();
+        let writer = InfluxDbWriter {
+            min_retry_delay: Duration::from_millis(10),
+            ..InfluxDbWriter::new(format!("{}/retried", mockito::server_url()), None)
+        };
+
+        writer.write_with_retries("tedge temperature=25").await;
+
+        unavailable.assert();
+        written.assert();
+    }
+
+    #[tokio::test]
+    async fn rejected_writes_are_not_retried() {
+        let rejected = mock("POST", "/not_retried")
+            .with_status(400)
+            .expect(1)
+            .create();
+        let writer = InfluxDbWriter {
+            min_retry_delay: Duration::from_millis(10),
+            ..InfluxDbWriter::new(format!("{}/not_retried", mockito::server_url()), None)
+        };
+
+        writer.write_with_retries("tedge").await;
+
+        rejected.assert();
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::core::{
+    component::TEdgeComponent, mapper::create_mapper, metrics_endpoint::MetricsEndpoint,
+};
+use crate::influxdb::{
+    converter::{InfluxDbConverter, InfluxDbOutput},
+    exporter::{InfluxDbWriter, LineBatcher, MAX_BATCH_LINES},
+};
+
+use async_trait::async_trait;
+use clock::WallClock;
+use mqtt_channel::Topic;
+use tedge_config::{
+    ConfigSettingAccessor, ConfigSettingError, DeviceIdSetting, InfluxDbBatchPeriodSecondsSetting,
+    InfluxDbHttpTokenSetting, InfluxDbHttpUrlSetting, InfluxDbMqttTopicSetting, TEdgeConfig,
+};
+use tracing::{info, info_span, Instrument};
+
+pub(crate) const INFLUXDB_MAPPER_NAME: &str = "tedge-mapper-influxdb";
+
+/// The device tag of the measurements of the main device, when no device id is configured
+const MAIN_DEVICE_TAG: &str = "main";
+
+pub struct InfluxDbMapper {}
+
+impl InfluxDbMapper {
+    pub fn new() -> InfluxDbMapper {
+        InfluxDbMapper {}
+    }
+}
+
+#[async_trait]
+impl TEdgeComponent for InfluxDbMapper {
+    fn session_name(&self) -> &str {
+        INFLUXDB_MAPPER_NAME
+    }
+
+    async fn init(&self, _config_dir: &Path) -> Result<(), anyhow::Error> {
+        info!("Initialize tedge mapper influxdb");
+        self.init_session(InfluxDbConverter::in_topic_filter())
+            .await?;
+        Ok(())
+    }
+
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        _config_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
+    ) -> Result<(), anyhow::Error> {
+        let main_device = tedge_config
+            .query(DeviceIdSetting)
+            .unwrap_or_else(|_| MAIN_DEVICE_TAG.to_string());
+        let batch_period: Duration = tedge_config
+            .query(InfluxDbBatchPeriodSecondsSetting)?
+            .into();
+
+        let output = match tedge_config.query(InfluxDbHttpUrlSetting) {
+            Ok(url) => {
+                info!("Posting the measurements to {}", url);
+                let token = optional(tedge_config.query(InfluxDbHttpTokenSetting))?;
+                InfluxDbOutput::Http {
+                    batches: InfluxDbWriter::new(url, token).spawn(),
+                }
+            }
+            Err(ConfigSettingError::ConfigNotSet { .. }) => {
+                let topic = Topic::new(&tedge_config.query(InfluxDbMqttTopicSetting)?)?;
+                info!("Publishing the measurements on {}", topic.name);
+                InfluxDbOutput::Mqtt { topic }
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        // With no batch period, the measurements are sent as soon as received
+        let max_lines = if batch_period.is_zero() {
+            1
+        } else {
+            MAX_BATCH_LINES
+        };
+        let batcher = LineBatcher::new(&main_device, Box::new(WallClock), max_lines);
+
+        let mqtt_config = tedge_config.mqtt_config()?;
+        let converter = Box::new(InfluxDbConverter::new(batcher, output, batch_period));
+
+        let mut mapper = create_mapper(INFLUXDB_MAPPER_NAME, mqtt_config, converter)
+            .await?
+            .with_mapper_settings(&tedge_config)?;
+        metrics_endpoint.register(INFLUXDB_MAPPER_NAME, mapper.metrics());
+
+        mapper
+            .run(None)
+            .instrument(info_span!(INFLUXDB_MAPPER_NAME))
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn optional<T>(value: Result<T, ConfigSettingError>) -> Result<Option<T>, ConfigSettingError> {
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(ConfigSettingError::ConfigNotSet { .. }) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,4 @@
+mod converter;
+mod error;
+mod exporter;
+pub mod mapper;
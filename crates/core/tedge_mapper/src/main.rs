@@ -1,24 +1,47 @@
-use std::{fmt, path::PathBuf};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::{
-    az::mapper::AzureMapper, c8y::mapper::CumulocityMapper, collectd::mapper::CollectdMapper,
-    core::component::TEdgeComponent,
+    aws::mapper::AwsMapper,
+    az::mapper::AzureMapper,
+    c8y::mapper::CumulocityMapper,
+    collectd::mapper::CollectdMapper,
+    core::{component::TEdgeComponent, metrics_endpoint::MetricsEndpoint},
+    generic::mapper::GenericMapper,
+    influxdb::mapper::InfluxDbMapper,
+    prometheus::mapper::PrometheusMapper,
 };
+use anyhow::{anyhow, bail};
 use clap::Parser;
 use flockfile::check_another_instance_is_not_running;
+use futures::future::select_all;
 use tedge_config::DEFAULT_TEDGE_CONFIG_PATH;
 use tedge_config::*;
 
+mod aws;
 mod az;
 mod c8y;
 mod collectd;
 mod core;
+mod generic;
+mod influxdb;
+mod prometheus;
+
+/// The name of a process running several mappers
+const MULTI_MAPPER_NAME: &str = "tedge-mapper-multi";
 
 fn lookup_component(component_name: &MapperName) -> Box<dyn TEdgeComponent> {
     match component_name {
         MapperName::Az => Box::new(AzureMapper::new()),
+        MapperName::Aws => Box::new(AwsMapper::new()),
         MapperName::Collectd => Box::new(CollectdMapper::new()),
         MapperName::C8y => Box::new(CumulocityMapper::new()),
+        MapperName::Generic => Box::new(GenericMapper::new()),
+        MapperName::Prometheus => Box::new(PrometheusMapper::new()),
+        MapperName::Influxdb => Box::new(InfluxDbMapper::new()),
     }
 }
 
@@ -30,7 +53,7 @@ fn lookup_component(component_name: &MapperName) -> Box<dyn TEdgeComponent> {
 )]
 pub struct MapperOpt {
     #[clap(subcommand)]
-    pub name: MapperName,
+    pub command: MapperCommand,
 
     /// Turn-on the debug log level.
     ///
@@ -56,19 +79,60 @@ pub struct MapperOpt {
     pub config_dir: PathBuf,
 }
 
+#[derive(Debug, clap::Subcommand)]
+pub enum MapperCommand {
+    #[clap(flatten)]
+    Single(MapperName),
+
+    /// Run several mappers in a single process
+    ///
+    /// Each mapper keeps its own MQTT session, health topic and lock file,
+    /// while their metrics are served to Prometheus on a single endpoint.
+    /// If no mapper is given, the mappers listed by the `mapper.multi.names` setting are run.
+    Multi {
+        /// The mappers to run, e.g. `c8y collectd az`
+        names: Vec<MapperName>,
+    },
+}
+
 #[derive(Debug, clap::Subcommand)]
 pub enum MapperName {
     Az,
+    Aws,
     C8y,
     Collectd,
+    Generic,
+    Prometheus,
+    Influxdb,
+}
+
+impl FromStr for MapperName {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim() {
+            "az" => Ok(MapperName::Az),
+            "aws" => Ok(MapperName::Aws),
+            "c8y" => Ok(MapperName::C8y),
+            "collectd" => Ok(MapperName::Collectd),
+            "generic" => Ok(MapperName::Generic),
+            "prometheus" => Ok(MapperName::Prometheus),
+            "influxdb" => Ok(MapperName::Influxdb),
+            _ => Err(anyhow!("Unknown mapper: {}", name)),
+        }
+    }
 }
 
 impl fmt::Display for MapperName {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             MapperName::Az => write!(f, "tedge-mapper-az"),
+            MapperName::Aws => write!(f, "tedge-mapper-aws"),
             MapperName::C8y => write!(f, "tedge-mapper-c8y"),
             MapperName::Collectd => write!(f, "tedge-mapper-collectd"),
+            MapperName::Generic => write!(f, "tedge-mapper-generic"),
+            MapperName::Prometheus => write!(f, "tedge-mapper-prometheus"),
+            MapperName::Influxdb => write!(f, "tedge-mapper-influxdb"),
         }
     }
 }
@@ -78,21 +142,94 @@ async fn main() -> anyhow::Result<()> {
     let mapper_opt = MapperOpt::parse();
     tedge_utils::logging::initialise_tracing_subscriber(mapper_opt.debug);
 
-    let component = lookup_component(&mapper_opt.name);
-
     let tedge_config_location =
         tedge_config::TEdgeConfigLocation::from_custom_root(&mapper_opt.config_dir);
-    let config = tedge_config::TEdgeConfigRepository::new(tedge_config_location.clone()).load()?;
-    // Run only one instance of a mapper
+    let config_repository = tedge_config::TEdgeConfigRepository::new(tedge_config_location);
+    let config = config_repository.load()?;
 
+    let (process_name, names) = match mapper_opt.command {
+        MapperCommand::Single(name) => (name.to_string(), vec![name]),
+        MapperCommand::Multi { names } if names.is_empty() => {
+            let names = config
+                .query(MapperMultiNamesSetting)?
+                .0
+                .iter()
+                .map(|name| name.parse())
+                .collect::<Result<Vec<MapperName>, _>>()?;
+            (MULTI_MAPPER_NAME.to_string(), names)
+        }
+        MapperCommand::Multi { names } => (MULTI_MAPPER_NAME.to_string(), names),
+    };
+    if names.is_empty() {
+        bail!("No mapper to run: give the mappers on the command line or set `mapper.multi.names`");
+    }
+
+    // Run only one instance of a mapper
     let run_dir: PathBuf = config.query(RunPathSetting)?.into();
-    let _flock = check_another_instance_is_not_running(&mapper_opt.name.to_string(), &run_dir)?;
+    let mut _flocks = Vec::new();
+    for name in names.iter() {
+        _flocks.push(check_another_instance_is_not_running(
+            &name.to_string(),
+            &run_dir,
+        )?);
+    }
 
+    let components: Vec<Box<dyn TEdgeComponent>> = names.iter().map(lookup_component).collect();
     if mapper_opt.init {
-        component.init(&mapper_opt.config_dir).await
+        for component in components.iter() {
+            component.init(&mapper_opt.config_dir).await?;
+        }
+        Ok(())
     } else if mapper_opt.clear {
-        component.clear_session().await
+        for component in components.iter() {
+            component.clear_session().await?;
+        }
+        Ok(())
     } else {
-        component.start(config, &mapper_opt.config_dir).await
+        // A single endpoint serves the metrics of all the mappers of this process
+        let metrics_endpoint = MetricsEndpoint::default();
+        metrics_endpoint.serve(&config, &process_name)?;
+
+        if components.len() == 1 {
+            components[0]
+                .start(config, &mapper_opt.config_dir, &metrics_endpoint)
+                .await
+        } else {
+            start_all(
+                components,
+                &config_repository,
+                &mapper_opt.config_dir,
+                metrics_endpoint,
+            )
+            .await
+        }
+    }
+}
+
+/// Run several mappers as tasks of the same runtime,
+/// until all of them are done or one of them fails.
+async fn start_all(
+    components: Vec<Box<dyn TEdgeComponent>>,
+    config_repository: &tedge_config::TEdgeConfigRepository,
+    config_dir: &Path,
+    metrics_endpoint: MetricsEndpoint,
+) -> anyhow::Result<()> {
+    let mut tasks = Vec::new();
+    for component in components {
+        let config = config_repository.load()?;
+        let config_dir = config_dir.to_path_buf();
+        let metrics_endpoint = metrics_endpoint.clone();
+        tasks.push(tokio::spawn(async move {
+            component
+                .start(config, &config_dir, &metrics_endpoint)
+                .await
+        }));
+    }
+
+    while !tasks.is_empty() {
+        let (result, _, remaining_tasks) = select_all(tasks).await;
+        result??;
+        tasks = remaining_tasks;
     }
+    Ok(())
 }
@@ -0,0 +1,136 @@
+use crate::core::{converter::*, error::*};
+use crate::prometheus::{gauges::MeasurementGauges, mapper::PROMETHEUS_MAPPER_NAME};
+
+use async_trait::async_trait;
+use mqtt_channel::{Message, TopicFilter};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// A converter updating the gauges exposed to Prometheus with the thin-edge measurements
+///
+/// No messages are published: the measurements are scraped by Prometheus over HTTP.
+pub struct PrometheusConverter {
+    gauges: Arc<Mutex<MeasurementGauges>>,
+    mapper_config: MapperConfig,
+}
+
+impl PrometheusConverter {
+    pub fn new(gauges: Arc<Mutex<MeasurementGauges>>) -> Self {
+        let mapper_config = MapperConfig {
+            mapper_name: PROMETHEUS_MAPPER_NAME.to_string(),
+            in_topic_filter: Self::in_topic_filter(),
+            // Nothing is published on the output topic
+            out_topic: make_valid_topic_or_panic("tedge/prometheus"),
+            errors_topic: make_valid_topic_or_panic("tedge/errors"),
+        };
+        PrometheusConverter {
+            gauges,
+            mapper_config,
+        }
+    }
+
+    pub fn in_topic_filter() -> TopicFilter {
+        let mut topic_filter = make_valid_topic_filter_or_panic("tedge/measurements");
+        topic_filter.add_unchecked("tedge/measurements/+");
+        topic_filter
+    }
+}
+
+#[async_trait]
+impl Converter for PrometheusConverter {
+    type Error = ConversionError;
+
+    fn get_mapper_config(&self) -> &MapperConfig {
+        &self.mapper_config
+    }
+
+    async fn try_convert(&mut self, input: &Message) -> Result<Vec<Message>, Self::Error> {
+        let child = input.topic.name.strip_prefix("tedge/measurements/");
+        let payload = input.payload_str()?;
+
+        let mut gauges = self
+            .gauges
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        gauges.update(child, payload, Instant::now())?;
+        Ok(vec![])
+    }
+
+    /// Remove the stale gauges, even if Prometheus doesn't scrape them
+    async fn try_flush_messages(&mut self) -> Result<Vec<Message>, Self::Error> {
+        let mut gauges = self
+            .gauges
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        gauges.expire(Instant::now());
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use mqtt_channel::Topic;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn measurements_update_the_gauges_of_the_device() {
+        let gauges = Arc::new(Mutex::new(MeasurementGauges::new(
+            "gateway",
+            Duration::from_secs(300),
+        )));
+        let mut converter = PrometheusConverter::new(gauges.clone());
+
+        let main = Message::new(
+            &Topic::new_unchecked("tedge/measurements"),
+            r#"{"temperature": 25}"#,
+        );
+        let child = Message::new(
+            &Topic::new_unchecked("tedge/measurements/child1"),
+            r#"{"temperature": 20}"#,
+        );
+
+        assert!(converter.try_convert(&main).await.unwrap().is_empty());
+        assert!(converter.try_convert(&child).await.unwrap().is_empty());
+
+        let text = gauges.lock().unwrap().to_prometheus();
+        assert!(text.contains("tedge_measurement{device=\"gateway\",name=\"temperature\"} 25\n"));
+        assert!(text.contains("tedge_measurement{device=\"child1\",name=\"temperature\"} 20\n"));
+    }
+
+    #[tokio::test]
+    async fn stale_gauges_are_removed_on_flush() {
+        let gauges = Arc::new(Mutex::new(MeasurementGauges::new(
+            "gateway",
+            Duration::from_secs(0),
+        )));
+        let mut converter = PrometheusConverter::new(gauges.clone());
+
+        let input = Message::new(
+            &Topic::new_unchecked("tedge/measurements"),
+            r#"{"temperature": 25}"#,
+        );
+        converter.try_convert(&input).await.unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+
+        assert!(converter.flush_messages().await.is_empty());
+        assert!(gauges.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn invalid_measurements_are_reported() {
+        let gauges = Arc::new(Mutex::new(MeasurementGauges::new(
+            "gateway",
+            Duration::from_secs(300),
+        )));
+        let mut converter = PrometheusConverter::new(gauges);
+
+        let input = Message::new(&Topic::new_unchecked("tedge/measurements"), "not json");
+
+        assert_matches!(
+            converter.try_convert(&input).await,
+            Err(ConversionError::FromThinEdgeJsonParser(_))
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use crate::core::metrics_endpoint::serve_metrics;
+use crate::prometheus::gauges::MeasurementGauges;
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Serve the measurement gauges over HTTP on `/metrics`, using the Prometheus text exposition format.
+///
+/// The stale gauges are removed before each response.
+/// The address is bound before returning, so a port already in use is reported as a startup error.
+pub fn serve_measurements(
+    gauges: Arc<Mutex<MeasurementGauges>>,
+    address: SocketAddr,
+) -> Result<(), hyper::Error> {
+    serve_metrics(address, move || render(&gauges, Instant::now()))
+}
+
+/// The fresh gauges at the given time, using the Prometheus text exposition format
+fn render(gauges: &Mutex<MeasurementGauges>, now: Instant) -> String {
+    let mut gauges = gauges
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    gauges.expire(now);
+    gauges.to_prometheus()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn gauges() -> Mutex<MeasurementGauges> {
+        let mut gauges = MeasurementGauges::new("gateway", Duration::from_secs(300));
+        gauges
+            .update(None, r#"{"temperature": 25}"#, Instant::now())
+            .unwrap();
+        Mutex::new(gauges)
+    }
+
+    #[test]
+    fn the_gauges_are_rendered_for_prometheus() {
+        let text = render(&gauges(), Instant::now());
+
+        assert_eq!(
+            text,
+            concat!(
+                "# TYPE tedge_measurement gauge\n",
+                "tedge_measurement{device=\"gateway\",name=\"temperature\"} 25\n"
+            )
+        );
+    }
+
+    #[test]
+    fn stale_gauges_are_not_rendered() {
+        let text = render(&gauges(), Instant::now() + Duration::from_secs(301));
+
+        assert_eq!(text, "");
+    }
+}
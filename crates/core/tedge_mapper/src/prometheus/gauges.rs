@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use thin_edge_json::measurement::MeasurementVisitor;
+use time::OffsetDateTime;
+
+/// The name of the Prometheus metric exposing the thin-edge measurements
+const MEASUREMENT_METRIC: &str = "tedge_measurement";
+
+/// The latest value of each thin-edge measurement, exposed as Prometheus gauges
+///
+/// A gauge is labelled by the device, the group and the name of the measurement.
+/// A gauge that has not been updated for longer than the staleness period is removed,
+/// so Prometheus stops scraping the values of a sensor or a child device that disappeared.
+#[derive(Debug)]
+pub struct MeasurementGauges {
+    main_device: String,
+    staleness: Duration,
+    gauges: BTreeMap<GaugeLabels, Gauge>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+struct GaugeLabels {
+    device: String,
+    group: Option<String>,
+    name: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Gauge {
+    value: f64,
+    updated: Instant,
+}
+
+impl MeasurementGauges {
+    pub fn new(main_device: &str, staleness: Duration) -> Self {
+        MeasurementGauges {
+            main_device: main_device.to_string(),
+            staleness,
+            gauges: BTreeMap::new(),
+        }
+    }
+
+    /// Update the gauges with the measurements of a Thin Edge JSON payload,
+    /// the child device being `None` for the measurements of the main device.
+    pub fn update(
+        &mut self,
+        child: Option<&str>,
+        payload: &str,
+        now: Instant,
+    ) -> Result<(), thin_edge_json::parser::ThinEdgeJsonParserError> {
+        let mut updater = GaugeUpdater {
+            device: child.unwrap_or(&self.main_device).to_string(),
+            group: None,
+            now,
+            updates: Vec::new(),
+        };
+        thin_edge_json::parser::parse_str(payload, &mut updater)?;
+
+        // The gauges are only updated once the whole payload has been parsed successfully
+        self.gauges.extend(updater.updates);
+        Ok(())
+    }
+
+    /// Remove the gauges that have not been updated during the staleness period
+    pub fn expire(&mut self, now: Instant) {
+        let staleness = self.staleness;
+        self.gauges
+            .retain(|_, gauge| now.saturating_duration_since(gauge.updated) <= staleness);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gauges.is_empty()
+    }
+
+    /// All the gauges using the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+
+        let mut text = format!("# TYPE {} gauge\n", MEASUREMENT_METRIC);
+        for (labels, gauge) in self.gauges.iter() {
+            let _ = write!(
+                text,
+                "{}{{device=\"{}\"",
+                MEASUREMENT_METRIC,
+                prometheus_escape(&labels.device)
+            );
+            if let Some(group) = &labels.group {
+                let _ = write!(text, ",group=\"{}\"", prometheus_escape(group));
+            }
+            let _ = writeln!(
+                text,
+                ",name=\"{}\"}} {}",
+                prometheus_escape(&labels.name),
+                gauge.value
+            );
+        }
+        text
+    }
+}
+
+/// Collect the gauge updates of a Thin Edge JSON payload
+struct GaugeUpdater {
+    device: String,
+    group: Option<String>,
+    now: Instant,
+    updates: Vec<(GaugeLabels, Gauge)>,
+}
+
+impl MeasurementVisitor for GaugeUpdater {
+    type Error = Infallible;
+
+    fn visit_timestamp(&mut self, _value: OffsetDateTime) -> Result<(), Self::Error> {
+        // Prometheus timestamps the values when scraped
+        Ok(())
+    }
+
+    fn visit_measurement(&mut self, name: &str, value: f64) -> Result<(), Self::Error> {
+        let labels = GaugeLabels {
+            device: self.device.clone(),
+            group: self.group.clone(),
+            name: name.to_string(),
+        };
+        let gauge = Gauge {
+            value,
+            updated: self.now,
+        };
+        self.updates.push((labels, gauge));
+        Ok(())
+    }
+
+    fn visit_start_group(&mut self, group: &str) -> Result<(), Self::Error> {
+        self.group = Some(group.to_string());
+        Ok(())
+    }
+
+    fn visit_end_group(&mut self) -> Result<(), Self::Error> {
+        self.group = None;
+        Ok(())
+    }
+}
+
+fn prometheus_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STALENESS: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn measurements_are_exposed_as_gauges() {
+        let mut gauges = MeasurementGauges::new("gateway", STALENESS);
+        let now = Instant::now();
+
+        gauges
+            .update(
+                None,
+                r#"{"temperature": 25.5, "location": {"x": 1, "y": 2}}"#,
+                now,
+            )
+            .unwrap();
+        gauges
+            .update(Some("child1"), r#"{"temperature": 20}"#, now)
+            .unwrap();
+
+        assert_eq!(
+            gauges.to_prometheus(),
+            concat!(
+                "# TYPE tedge_measurement gauge\n",
+                "tedge_measurement{device=\"child1\",name=\"temperature\"} 20\n",
+                "tedge_measurement{device=\"gateway\",name=\"temperature\"} 25.5\n",
+                "tedge_measurement{device=\"gateway\",group=\"location\",name=\"x\"} 1\n",
+                "tedge_measurement{device=\"gateway\",group=\"location\",name=\"y\"} 2\n",
+            )
+        );
+    }
+
+    #[test]
+    fn gauges_hold_the_latest_value() {
+        let mut gauges = MeasurementGauges::new("gateway", STALENESS);
+        let now = Instant::now();
+
+        gauges.update(None, r#"{"temperature": 25}"#, now).unwrap();
+        gauges.update(None, r#"{"temperature": 26}"#, now).unwrap();
+
+        assert_eq!(
+            gauges.to_prometheus(),
+            concat!(
+                "# TYPE tedge_measurement gauge\n",
+                "tedge_measurement{device=\"gateway\",name=\"temperature\"} 26\n",
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_payloads_leave_the_gauges_unchanged() {
+        let mut gauges = MeasurementGauges::new("gateway", STALENESS);
+
+        let result = gauges.update(
+            None,
+            r#"{"temperature": 25, "pressure": "high"}"#,
+            Instant::now(),
+        );
+
+        assert!(result.is_err());
+        assert!(gauges.is_empty());
+        assert_eq!(gauges.to_prometheus(), "");
+    }
+
+    #[test]
+    fn stale_gauges_are_removed() {
+        let mut gauges = MeasurementGauges::new("gateway", STALENESS);
+        let start = Instant::now();
+
+        gauges
+            .update(None, r#"{"temperature": 25}"#, start)
+            .unwrap();
+        gauges
+            .update(
+                None,
+                r#"{"pressure": 1013}"#,
+                start + Duration::from_secs(200),
+            )
+            .unwrap();
+
+        gauges.expire(start + Duration::from_secs(300));
+        assert_eq!(gauges.to_prometheus().lines().count(), 3);
+
+        gauges.expire(start + Duration::from_secs(301));
+        assert_eq!(
+            gauges.to_prometheus(),
+            concat!(
+                "# TYPE tedge_measurement gauge\n",
+                "tedge_measurement{device=\"gateway\",name=\"pressure\"} 1013\n",
+            )
+        );
+    }
+}
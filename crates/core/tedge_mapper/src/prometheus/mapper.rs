@@ -0,0 +1,82 @@
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    core::{component::TEdgeComponent, mapper::create_mapper, metrics_endpoint::MetricsEndpoint},
+    prometheus::{
+        converter::PrometheusConverter, exporter::serve_measurements, gauges::MeasurementGauges,
+    },
+};
+
+use async_trait::async_trait;
+use tedge_config::{
+    ConfigSettingAccessor, DeviceIdSetting, IpAddress, PrometheusHttpBindAddressSetting,
+    PrometheusHttpPortSetting, PrometheusStalenessSecondsSetting, TEdgeConfig,
+};
+use tracing::{info, info_span, Instrument};
+
+pub(crate) const PROMETHEUS_MAPPER_NAME: &str = "tedge-mapper-prometheus";
+
+/// The device label of the measurements of the main device, when no device id is configured
+const MAIN_DEVICE_LABEL: &str = "main";
+
+pub struct PrometheusMapper {}
+
+impl PrometheusMapper {
+    pub fn new() -> PrometheusMapper {
+        PrometheusMapper {}
+    }
+}
+
+#[async_trait]
+impl TEdgeComponent for PrometheusMapper {
+    fn session_name(&self) -> &str {
+        PROMETHEUS_MAPPER_NAME
+    }
+
+    async fn init(&self, _config_dir: &Path) -> Result<(), anyhow::Error> {
+        info!("Initialize tedge mapper prometheus");
+        self.init_session(PrometheusConverter::in_topic_filter())
+            .await?;
+        Ok(())
+    }
+
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        _config_dir: &Path,
+        metrics_endpoint: &MetricsEndpoint,
+    ) -> Result<(), anyhow::Error> {
+        let main_device = tedge_config
+            .query(DeviceIdSetting)
+            .unwrap_or_else(|_| MAIN_DEVICE_LABEL.to_string());
+        let staleness = tedge_config.query(PrometheusStalenessSecondsSetting)?;
+        let IpAddress(ip) = tedge_config.query(PrometheusHttpBindAddressSetting)?;
+        let address = SocketAddr::new(ip, tedge_config.query(PrometheusHttpPortSetting)?.into());
+
+        let gauges = Arc::new(Mutex::new(MeasurementGauges::new(
+            &main_device,
+            staleness.into(),
+        )));
+
+        serve_measurements(gauges.clone(), address).map_err(|err| {
+            anyhow::anyhow!("Failed to serve the measurements on {}: {}", address, err)
+        })?;
+
+        let mqtt_config = tedge_config.mqtt_config()?;
+        let converter = Box::new(PrometheusConverter::new(gauges));
+
+        let mut mapper = create_mapper(PROMETHEUS_MAPPER_NAME, mqtt_config, converter)
+            .await?
+            .with_mapper_settings(&tedge_config)?;
+        metrics_endpoint.register(PROMETHEUS_MAPPER_NAME, mapper.metrics());
+
+        mapper
+            .run(None)
+            .instrument(info_span!(PROMETHEUS_MAPPER_NAME))
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,4 @@
+mod converter;
+mod exporter;
+mod gauges;
+pub mod mapper;
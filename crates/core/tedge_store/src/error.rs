@@ -0,0 +1,39 @@
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    #[error(transparent)]
+    FromSqlite(#[from] rusqlite::Error),
+
+    #[error(transparent)]
+    FromIo(#[from] std::io::Error),
+
+    #[error(transparent)]
+    FromMqttClient(#[from] mqtt_channel::MqttError),
+
+    #[error(transparent)]
+    FromThinEdgeJsonParser(#[from] thin_edge_json::parser::ThinEdgeJsonParserError),
+
+    #[error(transparent)]
+    FromThinEdgeJsonAlarmDeserialization(
+        #[from] thin_edge_json::alarm::ThinEdgeJsonDeserializerError,
+    ),
+
+    #[error(transparent)]
+    FromThinEdgeJsonEventDeserialization(
+        #[from] thin_edge_json::event::error::ThinEdgeJsonDeserializerError,
+    ),
+
+    #[error(transparent)]
+    FromSerdeJson(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    FromTimeFormat(#[from] time::error::Format),
+
+    #[error("Unsupported topic: {0}")]
+    UnsupportedTopic(String),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("The store thread is no longer running")]
+    Closed,
+}
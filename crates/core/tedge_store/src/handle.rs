@@ -0,0 +1,77 @@
+use crate::error::StoreError;
+use crate::store::Store;
+
+use tokio::sync::{mpsc, oneshot};
+
+type Job = Box<dyn FnOnce(&mut Store) + Send>;
+
+/// A handle to a store owned by a dedicated thread.
+///
+/// SQLite calls are blocking: they are run one after the other on the store thread,
+/// so neither the MQTT loop nor the HTTP server stall the async runtime while the database is busy.
+/// The clones of a handle share the same store.
+#[derive(Clone)]
+pub struct StoreHandle {
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+impl StoreHandle {
+    /// Move the store to a new thread, that runs until all the handles are dropped.
+    pub fn spawn(mut store: Store) -> Result<StoreHandle, StoreError> {
+        let (jobs, mut pending_jobs) = mpsc::unbounded_channel::<Job>();
+        std::thread::Builder::new()
+            .name("tedge-store-db".into())
+            .spawn(move || {
+                while let Some(job) = pending_jobs.blocking_recv() {
+                    job(&mut store);
+                }
+            })?;
+        Ok(StoreHandle { jobs })
+    }
+
+    /// Run the given function on the store thread, returning its result.
+    pub async fn call<T, F>(&self, f: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Store) -> Result<T, StoreError> + Send + 'static,
+    {
+        let (result_sender, result) = oneshot::channel();
+        let job: Job = Box::new(move |store| {
+            // The caller might have given up on the result
+            let _ = result_sender.send(f(store));
+        });
+        self.jobs.send(job).map_err(|_| StoreError::Closed)?;
+        result.await.map_err(|_| StoreError::Closed)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{Query, RecordKind};
+    use serde_json::json;
+    use time::macros::datetime;
+
+    #[tokio::test]
+    async fn the_store_is_used_from_its_own_thread() {
+        let store = StoreHandle::spawn(Store::open_in_memory().unwrap()).unwrap();
+        let thread_name = store
+            .call(|_| Ok(std::thread::current().name().map(str::to_string)))
+            .await
+            .unwrap();
+        assert_eq!(thread_name.as_deref(), Some("tedge-store-db"));
+
+        let removed = store
+            .clone()
+            .call(|store| store.purge(datetime!(2022-08-01 10:00:00 UTC)))
+            .await
+            .unwrap();
+        assert_eq!(removed, 0);
+
+        let records = store
+            .call(|store| store.query(RecordKind::Events, &Query::default()))
+            .await
+            .unwrap();
+        assert_eq!(records, json!([]));
+    }
+}
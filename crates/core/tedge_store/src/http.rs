@@ -0,0 +1,110 @@
+use crate::error::StoreError;
+use crate::handle::StoreHandle;
+use crate::query::{Query, RecordKind};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tracing::{error, info};
+
+/// Serve the queries received over HTTP on `/measurements`, `/events` and `/alarms`,
+/// the query criteria being given as URL parameters.
+pub async fn serve_queries(store: StoreHandle, address: SocketAddr) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_connection| {
+        let store = store.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let store = store.clone();
+                async move {
+                    Ok::<_, Infallible>(
+                        response(
+                            &store,
+                            request.method(),
+                            request.uri().path(),
+                            request.uri().query().unwrap_or(""),
+                        )
+                        .await,
+                    )
+                }
+            }))
+        }
+    });
+
+    info!("Serving store queries on http://{}", address);
+    Server::bind(&address).serve(make_service).await
+}
+
+/// The response to an HTTP request
+pub async fn response(
+    store: &StoreHandle,
+    method: &Method,
+    path: &str,
+    query: &str,
+) -> Response<Body> {
+    if method != Method::GET {
+        return status_response(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    let kind = match path.trim_start_matches('/').parse::<RecordKind>() {
+        Ok(kind) => kind,
+        Err(_) => return status_response(StatusCode::NOT_FOUND),
+    };
+
+    let result = match Query::from_query_string(query) {
+        Ok(query) => store.call(move |store| store.query(kind, &query)).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(records) => Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(records.to_string()))
+            .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR)),
+        Err(StoreError::InvalidQuery(reason)) => error_response(StatusCode::BAD_REQUEST, reason),
+        Err(err) => {
+            error!("Failed to query the store: {}", err);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        }
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+fn error_response(status: StatusCode, reason: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(reason));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Store;
+    use test_case::test_case;
+
+    #[test_case(Method::GET, "/measurements", "", StatusCode::OK)]
+    #[test_case(Method::GET, "/events", "series=door&limit=10", StatusCode::OK)]
+    #[test_case(Method::GET, "/alarms", "child=child1", StatusCode::OK)]
+    #[test_case(Method::POST, "/measurements", "", StatusCode::METHOD_NOT_ALLOWED)]
+    #[test_case(Method::GET, "/logs", "", StatusCode::NOT_FOUND)]
+    #[test_case(Method::GET, "/measurements", "limit=-1", StatusCode::BAD_REQUEST)]
+    #[test_case(Method::GET, "/events", "interval=60", StatusCode::BAD_REQUEST)]
+    #[tokio::test]
+    async fn responding_to_http_requests(
+        method: Method,
+        path: &str,
+        query: &str,
+        status: StatusCode,
+    ) {
+        let store = StoreHandle::spawn(Store::open_in_memory().unwrap()).unwrap();
+
+        let response = response(&store, &method, path, query).await;
+
+        assert_eq!(response.status(), status);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::error::StoreError;
+
+use mqtt_channel::{Message, TopicFilter};
+use std::convert::Infallible;
+use thin_edge_json::alarm::ThinEdgeAlarm;
+use thin_edge_json::event::ThinEdgeEvent;
+use thin_edge_json::measurement::MeasurementVisitor;
+use time::OffsetDateTime;
+
+/// The topics of the messages stored by tedge_store
+pub fn input_topics() -> TopicFilter {
+    let mut topics = TopicFilter::new_unchecked("tedge/measurements/#");
+    topics.add_unchecked("tedge/events/#");
+    topics.add_unchecked("tedge/alarms/#");
+    topics
+}
+
+/// A thin-edge message as stored by tedge_store
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    /// All the measurements of a Thin Edge JSON message,
+    /// named after their group and name joined by a dot, as `location.latitude`
+    Measurements {
+        time: OffsetDateTime,
+        child: Option<String>,
+        values: Vec<(String, f64)>,
+    },
+
+    Event {
+        time: OffsetDateTime,
+        child: Option<String>,
+        event_type: String,
+        payload: String,
+    },
+
+    /// An alarm, which is cleared when the payload is empty
+    Alarm {
+        time: OffsetDateTime,
+        child: Option<String>,
+        alarm_type: String,
+        severity: String,
+        payload: String,
+    },
+}
+
+impl Record {
+    /// Parse a message received on the input topics,
+    /// using the given time when the message has no timestamp.
+    pub fn try_from(message: &Message, now: OffsetDateTime) -> Result<Record, StoreError> {
+        let topic = message.topic.name.as_str();
+        let payload = message.payload_str()?;
+        let levels: Vec<&str> = topic.split('/').collect();
+
+        match levels.as_slice() {
+            ["tedge", "measurements"] | ["tedge", "measurements", _] => {
+                let mut collector = MeasurementCollector::default();
+                thin_edge_json::parser::parse_str(payload, &mut collector)?;
+                Ok(Record::Measurements {
+                    time: collector.time.unwrap_or(now),
+                    child: child_id(levels.get(2)),
+                    values: collector.values,
+                })
+            }
+
+            ["tedge", "events", ..] => {
+                let event = ThinEdgeEvent::try_from(topic, payload)?;
+                Ok(Record::Event {
+                    time: event.data.and_then(|data| data.time).unwrap_or(now),
+                    child: event.source,
+                    event_type: event.name,
+                    payload: payload.to_string(),
+                })
+            }
+
+            ["tedge", "alarms", severity, ..] => {
+                let alarm = ThinEdgeAlarm::try_from(topic, payload)?;
+                Ok(Record::Alarm {
+                    time: alarm.data.and_then(|data| data.time).unwrap_or(now),
+                    child: child_id(levels.get(4)),
+                    alarm_type: alarm.name,
+                    severity: severity.to_string(),
+                    payload: payload.to_string(),
+                })
+            }
+
+            _ => Err(StoreError::UnsupportedTopic(topic.to_string())),
+        }
+    }
+}
+
+fn child_id(level: Option<&&str>) -> Option<String> {
+    level.map(|child| child.to_string())
+}
+
+/// Collect the measurements of a Thin Edge JSON message as a flat list
+#[derive(Debug, Default)]
+struct MeasurementCollector {
+    group: Option<String>,
+    time: Option<OffsetDateTime>,
+    values: Vec<(String, f64)>,
+}
+
+impl MeasurementVisitor for MeasurementCollector {
+    type Error = Infallible;
+
+    fn visit_timestamp(&mut self, value: OffsetDateTime) -> Result<(), Self::Error> {
+        self.time = Some(value);
+        Ok(())
+    }
+
+    fn visit_measurement(&mut self, name: &str, value: f64) -> Result<(), Self::Error> {
+        let name = match &self.group {
+            Some(group) => format!("{}.{}", group, name),
+            None => name.to_string(),
+        };
+        self.values.push((name, value));
+        Ok(())
+    }
+
+    fn visit_start_group(&mut self, group: &str) -> Result<(), Self::Error> {
+        self.group = Some(group.to_string());
+        Ok(())
+    }
+
+    fn visit_end_group(&mut self) -> Result<(), Self::Error> {
+        self.group = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use mqtt_channel::Topic;
+    use time::macros::datetime;
+
+    const NOW: OffsetDateTime = datetime!(2022-08-01 12:00:00 UTC);
+
+    fn record(topic: &str, payload: &str) -> Result<Record, StoreError> {
+        Record::try_from(&Message::new(&Topic::new_unchecked(topic), payload), NOW)
+    }
+
+    #[test]
+    fn parsing_measurements() {
+        let record = record(
+            "tedge/measurements/child1",
+            r#"{"time": "2022-08-01T10:00:00Z", "temperature": 25, "location": {"x": 1, "y": 2}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            record,
+            Record::Measurements {
+                time: datetime!(2022-08-01 10:00:00 UTC),
+                child: Some("child1".into()),
+                values: vec![
+                    ("temperature".into(), 25.0),
+                    ("location.x".into(), 1.0),
+                    ("location.y".into(), 2.0),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn measurements_with_no_timestamp_are_given_the_current_time() {
+        let record = record("tedge/measurements", r#"{"temperature": 25}"#).unwrap();
+
+        assert_matches!(
+            record,
+            Record::Measurements {
+                time,
+                child: None,
+                ..
+            } if time == NOW
+        );
+    }
+
+    #[test]
+    fn parsing_events() {
+        let payload = r#"{"text": "Door opened", "time": "2022-08-01T10:00:00Z"}"#;
+
+        let record = record("tedge/events/door/child1", payload).unwrap();
+
+        assert_eq!(
+            record,
+            Record::Event {
+                time: datetime!(2022-08-01 10:00:00 UTC),
+                child: Some("child1".into()),
+                event_type: "door".into(),
+                payload: payload.into(),
+            }
+        );
+    }
+
+    #[test]
+    fn parsing_alarms() {
+        let record = record("tedge/alarms/critical/overheat", "").unwrap();
+
+        assert_eq!(
+            record,
+            Record::Alarm {
+                time: NOW,
+                child: None,
+                alarm_type: "overheat".into(),
+                severity: "critical".into(),
+                payload: "".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_messages_are_rejected() {
+        assert_matches!(
+            record("tedge/measurements", r#"{"temperature": "hot"}"#),
+            Err(StoreError::FromThinEdgeJsonParser(_))
+        );
+        assert_matches!(
+            record("tedge/alarms/unknown/overheat", ""),
+            Err(StoreError::FromThinEdgeJsonAlarmDeserialization(_))
+        );
+        assert_matches!(
+            record("tedge/measurements/child1/sensor", "{}"),
+            Err(StoreError::UnsupportedTopic(_))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+mod error;
+mod handle;
+mod http;
+mod input;
+mod query;
+mod store;
+
+use anyhow::Result;
+use clap::Parser;
+use clock::{Clock, WallClock};
+use mqtt_channel::{Connection, Message, SinkExt, StreamExt, TopicFilter};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tedge_config::{
+    ConfigRepository, ConfigSettingAccessor, DataPathSetting, StoreHttpBindAddressSetting,
+    StoreHttpPortSetting, StoreRetentionDaysSetting, TEdgeConfig, DEFAULT_TEDGE_CONFIG_PATH,
+};
+use tedge_utils::file::create_directory_with_user_group;
+use thin_edge_json::health::{health_check_topics, send_health_status, with_health_status};
+use tracing::{debug, error, info};
+
+use crate::handle::StoreHandle;
+use crate::input::{input_topics, Record};
+use crate::query::QueryRequest;
+use crate::store::Store;
+
+const TEDGE_STORE: &str = "tedge-store";
+const STORE_FILE: &str = "store.db";
+const PURGE_PERIOD: Duration = Duration::from_secs(60 * 60);
+const AFTER_HELP_TEXT: &str = r#"`tedge_store` keeps a local history of the measurements, events and alarms published on `tedge/measurements`, `tedge/events` and `tedge/alarms`,
+removing the records older than `store.retention.days`.
+
+The stored records can be queried:
+  * over MQTT, publishing on `tedge/store/req/<measurements|events|alarms>` a JSON request with an `id` and the query criteria,
+    the response being published on `tedge/store/res/<measurements|events|alarms>`
+  * over HTTP, with a GET request on `http://<store.http.bind_address>:<store.http.port>/<measurements|events|alarms>`,
+    the query criteria being given as URL parameters
+
+The query criteria are all optional: `from` and `to` (RFC 3339 times), `series`, `child`, `interval` (seconds) and `limit`.
+
+The records are stored in `DATA_PATH/tedge-store/store.db`."#;
+
+#[derive(Debug, clap::Parser, Clone)]
+#[clap(
+name = clap::crate_name!(),
+version = clap::crate_version!(),
+about = clap::crate_description!(),
+after_help = AFTER_HELP_TEXT
+)]
+pub struct StoreOpt {
+    /// Turn-on the debug log level.
+    ///
+    /// If off only reports ERROR, WARN, and INFO
+    /// If on also reports DEBUG and TRACE
+    #[clap(long)]
+    pub debug: bool,
+
+    /// Create the store directory and the MQTT session
+    #[clap(short, long)]
+    pub init: bool,
+
+    #[clap(long = "config-dir", default_value = DEFAULT_TEDGE_CONFIG_PATH)]
+    pub config_dir: PathBuf,
+}
+
+fn mqtt_config(tedge_config: &TEdgeConfig) -> Result<mqtt_channel::Config, anyhow::Error> {
+    let mut topics = input_topics();
+    topics.add_all(QueryRequest::topic_filter());
+    topics.add_all(health_check_topics(TEDGE_STORE));
+
+    let mqtt_config = with_health_status(tedge_config.mqtt_config()?, TEDGE_STORE)
+        .with_session_name(TEDGE_STORE)
+        .with_clean_session(false)
+        .with_subscriptions(topics);
+    Ok(mqtt_config)
+}
+
+async fn run(
+    store: StoreHandle,
+    mqtt_client: &mut Connection,
+    retention: Duration,
+) -> Result<(), anyhow::Error> {
+    let health_check_topics: TopicFilter = health_check_topics(TEDGE_STORE);
+    let clock = WallClock;
+    let mut purge_ticks = tokio::time::interval(PURGE_PERIOD);
+
+    loop {
+        tokio::select! {
+            message = mqtt_client.received.next() => {
+                if let Some(message) = message {
+                    if health_check_topics.accept(&message) {
+                        send_health_status(&mut mqtt_client.published, TEDGE_STORE).await;
+                    } else if let Some(response) = process_message(&store, &message, clock.now()).await {
+                        mqtt_client.published.send(response).await?;
+                    }
+                } else {
+                    // message is None and the connection has been closed
+                    return Ok(());
+                }
+            }
+            _ = purge_ticks.tick() => {
+                let before = clock.now() - retention;
+                match store.call(move |store| store.purge(before)).await {
+                    Ok(removed) => debug!("Removed {} records older than {}", removed, before),
+                    Err(err) => error!("Failed to remove the old records: {}", err),
+                }
+            }
+        }
+    }
+}
+
+/// Store the record or run the query received over MQTT, returning the response to a query
+async fn process_message(
+    store: &StoreHandle,
+    message: &Message,
+    now: clock::Timestamp,
+) -> Option<Message> {
+    if let Some(request) = QueryRequest::try_from(message) {
+        return match request {
+            Ok((kind, request)) => {
+                let query = request.query;
+                let result = store.call(move |store| store.query(kind, &query)).await;
+                Some(QueryRequest::response(kind, Some(&request.id), result))
+            }
+            Err(err) => {
+                error!("Invalid store query on {}: {}", message.topic.name, err);
+                None
+            }
+        };
+    }
+
+    let result = match Record::try_from(message, now) {
+        Ok(record) => store.call(move |store| store.insert(&record)).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        error!(
+            "Failed to store the message on {}: {}",
+            message.topic.name, err
+        );
+    }
+    None
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let store_opt = StoreOpt::parse();
+
+    tedge_utils::logging::initialise_tracing_subscriber(store_opt.debug);
+
+    // Load tedge config from the provided location
+    let tedge_config_location =
+        tedge_config::TEdgeConfigLocation::from_custom_root(&store_opt.config_dir);
+    let config_repository = tedge_config::TEdgeConfigRepository::new(tedge_config_location);
+    let tedge_config = config_repository.load()?;
+
+    let store_dir = PathBuf::from(tedge_config.query(DataPathSetting)?.as_ref()).join(TEDGE_STORE);
+    let mqtt_config = mqtt_config(&tedge_config)?;
+
+    if store_opt.init {
+        init(&store_dir, &mqtt_config).await?;
+        return Ok(());
+    }
+
+    let retention: Duration = tedge_config.query(StoreRetentionDaysSetting)?.into();
+    let http_address = SocketAddr::new(
+        tedge_config.query(StoreHttpBindAddressSetting)?.into(),
+        tedge_config.query(StoreHttpPortSetting)?.into(),
+    );
+
+    let store = StoreHandle::spawn(Store::open(&store_dir.join(STORE_FILE))?)?;
+    let mut mqtt_client = Connection::new(&mqtt_config).await?;
+
+    let http_store = store.clone();
+    tokio::spawn(async move {
+        if let Err(err) = http::serve_queries(http_store, http_address).await {
+            error!("The HTTP query server failed: {}", err);
+        }
+    });
+
+    run(store, &mut mqtt_client, retention).await
+}
+
+async fn init(store_dir: &Path, mqtt_config: &mqtt_channel::Config) -> Result<(), anyhow::Error> {
+    info!("Creating the store directory {}", store_dir.display());
+    create_directory_with_user_group(store_dir, "tedge", "tedge", 0o775)?;
+
+    // Keep the messages published while tedge_store is not running
+    info!("Creating the {} MQTT session", TEDGE_STORE);
+    mqtt_channel::init_session(mqtt_config).await?;
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use crate::error::StoreError;
+
+use mqtt_channel::{Message, Topic, TopicFilter};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use time::OffsetDateTime;
+
+const REQUEST_TOPIC_PREFIX: &str = "tedge/store/req/";
+const RESPONSE_TOPIC_PREFIX: &str = "tedge/store/res/";
+
+/// The maximum number of records returned by a query, unless a limit is given
+pub const DEFAULT_LIMIT: u32 = 1000;
+
+/// The kind of records targeted by a query
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RecordKind {
+    Measurements,
+    Events,
+    Alarms,
+}
+
+impl RecordKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordKind::Measurements => "measurements",
+            RecordKind::Events => "events",
+            RecordKind::Alarms => "alarms",
+        }
+    }
+}
+
+impl FromStr for RecordKind {
+    type Err = StoreError;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "measurements" => Ok(RecordKind::Measurements),
+            "events" => Ok(RecordKind::Events),
+            "alarms" => Ok(RecordKind::Alarms),
+            _ => Err(StoreError::InvalidQuery(format!(
+                "unknown record kind '{}'",
+                kind
+            ))),
+        }
+    }
+}
+
+/// A query over the stored records
+///
+/// All the criteria are optional, a query with no criteria returning the oldest records.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Query {
+    /// Only the records at or after this time
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+
+    /// Only the records before this time
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+
+    /// Only the records of this measurement series, as `location.latitude`, or of this event or alarm type
+    pub series: Option<String>,
+
+    /// Only the records of this child device
+    pub child: Option<String>,
+
+    /// Downsample the measurements, averaging the values over intervals of the given number of seconds
+    pub interval: Option<u64>,
+
+    /// The maximum number of records to return
+    pub limit: Option<u32>,
+}
+
+impl Query {
+    /// Parse a query given as a URL query string, as `series=temperature&interval=60`
+    pub fn from_query_string(query: &str) -> Result<Query, StoreError> {
+        serde_urlencoded::from_str(query).map_err(|err| StoreError::InvalidQuery(err.to_string()))
+    }
+}
+
+/// A query received over MQTT on `tedge/store/req/<kind>`
+///
+/// The response is published on `tedge/store/res/<kind>` with the same `id`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QueryRequest {
+    pub id: String,
+
+    #[serde(flatten)]
+    pub query: Query,
+}
+
+impl QueryRequest {
+    /// The topics of the query requests
+    pub fn topic_filter() -> TopicFilter {
+        TopicFilter::new_unchecked(&format!("{}+", REQUEST_TOPIC_PREFIX))
+    }
+
+    /// Parse a query request, returning `None` if the message is not a query request
+    pub fn try_from(message: &Message) -> Option<Result<(RecordKind, QueryRequest), StoreError>> {
+        let kind = message.topic.name.strip_prefix(REQUEST_TOPIC_PREFIX)?;
+        Some(Self::parse(kind, message))
+    }
+
+    fn parse(kind: &str, message: &Message) -> Result<(RecordKind, QueryRequest), StoreError> {
+        let kind = kind.parse()?;
+        let request = serde_json::from_str(message.payload_str()?)
+            .map_err(|err| StoreError::InvalidQuery(err.to_string()))?;
+        Ok((kind, request))
+    }
+
+    /// The response to this request, given the result of the query
+    pub fn response(
+        kind: RecordKind,
+        id: Option<&str>,
+        result: Result<Value, StoreError>,
+    ) -> Message {
+        let topic = Topic::new_unchecked(&format!("{}{}", RESPONSE_TOPIC_PREFIX, kind.as_str()));
+        let payload = match result {
+            Ok(records) => json!({ "id": id, "status": "successful", "result": records }),
+            Err(err) => json!({ "id": id, "status": "failed", "reason": err.to_string() }),
+        };
+        Message::new(&topic, payload.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use time::macros::datetime;
+
+    #[test]
+    fn parsing_a_query_string() {
+        let query = Query::from_query_string(
+            "from=2022-08-01T10:00:00%2B02:00&series=temperature&child=child1&interval=60&limit=10",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query,
+            Query {
+                from: Some(datetime!(2022-08-01 10:00:00 +02:00)),
+                to: None,
+                series: Some("temperature".into()),
+                child: Some("child1".into()),
+                interval: Some(60),
+                limit: Some(10),
+            }
+        );
+    }
+
+    #[test]
+    fn an_empty_query_string_has_no_criteria() {
+        assert_eq!(Query::from_query_string("").unwrap(), Query::default());
+    }
+
+    #[test]
+    fn rejecting_invalid_query_strings() {
+        assert_matches!(
+            Query::from_query_string("from=yesterday"),
+            Err(StoreError::InvalidQuery(_))
+        );
+    }
+
+    #[test]
+    fn parsing_a_query_request() {
+        let message = Message::new(
+            &Topic::new_unchecked("tedge/store/req/events"),
+            r#"{"id": "123", "to": "2022-08-01T10:00:00Z", "series": "door"}"#,
+        );
+
+        let (kind, request) = QueryRequest::try_from(&message).unwrap().unwrap();
+
+        assert_eq!(kind, RecordKind::Events);
+        assert_eq!(request.id, "123");
+        assert_eq!(request.query.to, Some(datetime!(2022-08-01 10:00:00 UTC)));
+        assert_eq!(request.query.series, Some("door".into()));
+    }
+
+    #[test]
+    fn rejecting_requests_for_unknown_records() {
+        let message = Message::new(
+            &Topic::new_unchecked("tedge/store/req/logs"),
+            r#"{"id": "123"}"#,
+        );
+
+        assert_matches!(
+            QueryRequest::try_from(&message),
+            Some(Err(StoreError::InvalidQuery(_)))
+        );
+    }
+
+    #[test]
+    fn building_responses() {
+        let response = QueryRequest::response(
+            RecordKind::Alarms,
+            Some("123"),
+            Err(StoreError::InvalidQuery("unknown field".into())),
+        );
+
+        assert_eq!(response.topic.name, "tedge/store/res/alarms");
+        assert_eq!(
+            serde_json::from_str::<Value>(response.payload_str().unwrap()).unwrap(),
+            json!({"id": "123", "status": "failed", "reason": "Invalid query: unknown field"})
+        );
+    }
+}
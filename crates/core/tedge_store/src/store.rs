@@ -0,0 +1,447 @@
+use crate::error::StoreError;
+use crate::input::Record;
+use crate::query::{Query, RecordKind, DEFAULT_LIMIT};
+
+use rusqlite::{named_params, Connection, Row};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS measurements (
+    time INTEGER NOT NULL,
+    child TEXT NOT NULL,
+    series TEXT NOT NULL,
+    value REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS measurements_by_time ON measurements (time);
+
+CREATE TABLE IF NOT EXISTS events (
+    time INTEGER NOT NULL,
+    child TEXT NOT NULL,
+    type TEXT NOT NULL,
+    payload TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_by_time ON events (time);
+
+CREATE TABLE IF NOT EXISTS alarms (
+    time INTEGER NOT NULL,
+    child TEXT NOT NULL,
+    type TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    payload TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS alarms_by_time ON alarms (time);
+"#;
+
+// The criteria of a query are all optional: a NULL parameter matches any record
+const QUERY_CRITERIA: &str = r#"
+    (:from IS NULL OR time >= :from)
+    AND (:to IS NULL OR time < :to)
+    AND (:series IS NULL OR {series} = :series)
+    AND (:child IS NULL OR child = :child)
+"#;
+
+/// The measurements, events and alarms stored in an SQLite database
+///
+/// The times are stored as milliseconds since the Unix epoch,
+/// and the child id of the records of the main device is an empty string.
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// Open the database at the given path, creating it if missing.
+    pub fn open(path: &Path) -> Result<Store, StoreError> {
+        Store::init(Connection::open(path)?)
+    }
+
+    /// Open a database that is not persisted
+    pub fn open_in_memory() -> Result<Store, StoreError> {
+        Store::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Store, StoreError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Store { connection })
+    }
+
+    pub fn insert(&mut self, record: &Record) -> Result<(), StoreError> {
+        match record {
+            Record::Measurements {
+                time,
+                child,
+                values,
+            } => {
+                let transaction = self.connection.transaction()?;
+                {
+                    let mut statement = transaction.prepare_cached(
+                        "INSERT INTO measurements (time, child, series, value) VALUES (?1, ?2, ?3, ?4)",
+                    )?;
+                    for (series, value) in values {
+                        statement.execute(rusqlite::params![
+                            millis(time),
+                            child_id(child),
+                            series,
+                            value
+                        ])?;
+                    }
+                }
+                transaction.commit()?;
+            }
+
+            Record::Event {
+                time,
+                child,
+                event_type,
+                payload,
+            } => {
+                self.connection.execute(
+                    "INSERT INTO events (time, child, type, payload) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![millis(time), child_id(child), event_type, payload],
+                )?;
+            }
+
+            Record::Alarm {
+                time,
+                child,
+                alarm_type,
+                severity,
+                payload,
+            } => {
+                self.connection.execute(
+                    "INSERT INTO alarms (time, child, type, severity, payload) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![millis(time), child_id(child), alarm_type, severity, payload],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove all the records older than the given time, returning the number of removed records.
+    pub fn purge(&self, before: OffsetDateTime) -> Result<usize, StoreError> {
+        let mut removed = 0;
+        for table in ["measurements", "events", "alarms"] {
+            removed += self.connection.execute(
+                &format!("DELETE FROM {} WHERE time < ?1", table),
+                [millis(&before)],
+            )?;
+        }
+        Ok(removed)
+    }
+
+    /// Return the records matching the query, ordered by time, as a JSON array.
+    ///
+    /// For the events and alarms, the `series` criterion is the event or alarm type.
+    pub fn query(&self, kind: RecordKind, query: &Query) -> Result<Value, StoreError> {
+        match (kind, query.interval) {
+            (RecordKind::Measurements, None) => self.select(
+                "SELECT time, child, series, value FROM measurements",
+                "series",
+                query,
+                |row, record| {
+                    record.insert("series".into(), row.get::<_, String>(2)?.into());
+                    record.insert("value".into(), row.get::<_, f64>(3)?.into());
+                    Ok(())
+                },
+            ),
+
+            (RecordKind::Measurements, Some(0)) => Err(StoreError::InvalidQuery(
+                "the downsampling interval cannot be 0".into(),
+            )),
+
+            (RecordKind::Measurements, Some(interval)) => self.select_downsampled(interval, query),
+
+            (_, Some(_)) => Err(StoreError::InvalidQuery(
+                "only measurements can be downsampled".into(),
+            )),
+
+            (RecordKind::Events, None) => self.select(
+                "SELECT time, child, type, payload FROM events",
+                "type",
+                query,
+                |row, record| {
+                    record.insert("type".into(), row.get::<_, String>(2)?.into());
+                    record.insert("payload".into(), payload(row.get(3)?));
+                    Ok(())
+                },
+            ),
+
+            (RecordKind::Alarms, None) => self.select(
+                "SELECT time, child, type, payload, severity FROM alarms",
+                "type",
+                query,
+                |row, record| {
+                    record.insert("type".into(), row.get::<_, String>(2)?.into());
+                    record.insert("payload".into(), payload(row.get(3)?));
+                    record.insert("severity".into(), row.get::<_, String>(4)?.into());
+                    Ok(())
+                },
+            ),
+        }
+    }
+
+    fn select<F>(
+        &self,
+        select: &str,
+        series_column: &str,
+        query: &Query,
+        mut add_fields: F,
+    ) -> Result<Value, StoreError>
+    where
+        F: FnMut(&Row, &mut Map<String, Value>) -> Result<(), rusqlite::Error>,
+    {
+        let sql = format!(
+            "{} WHERE {} ORDER BY time LIMIT :limit",
+            select,
+            QUERY_CRITERIA.replace("{series}", series_column)
+        );
+        let mut statement = self.connection.prepare(&sql)?;
+        let mut rows = statement.query(named_params! {
+            ":from": query.from.as_ref().map(millis),
+            ":to": query.to.as_ref().map(millis),
+            ":series": query.series,
+            ":child": query.child,
+            ":limit": query.limit.unwrap_or(DEFAULT_LIMIT),
+        })?;
+
+        let mut records = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut record = Map::new();
+            record.insert("time".into(), time(row.get(0)?)?.into());
+            if let Some(child) = child(row.get(1)?) {
+                record.insert("child".into(), child.into());
+            }
+            add_fields(row, &mut record)?;
+            records.push(Value::Object(record));
+        }
+        Ok(Value::Array(records))
+    }
+
+    fn select_downsampled(&self, interval: u64, query: &Query) -> Result<Value, StoreError> {
+        let sql = format!(
+            "SELECT (time / :interval) * :interval AS bucket, child, series, AVG(value), MIN(value), MAX(value), COUNT(*)
+             FROM measurements WHERE {}
+             GROUP BY bucket, child, series ORDER BY bucket, child, series LIMIT :limit",
+            QUERY_CRITERIA.replace("{series}", "series")
+        );
+        let mut statement = self.connection.prepare(&sql)?;
+        let mut rows = statement.query(named_params! {
+            ":interval": interval.saturating_mul(1000) as i64,
+            ":from": query.from.as_ref().map(millis),
+            ":to": query.to.as_ref().map(millis),
+            ":series": query.series,
+            ":child": query.child,
+            ":limit": query.limit.unwrap_or(DEFAULT_LIMIT),
+        })?;
+
+        let mut records = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut record = json!({
+                "time": time(row.get(0)?)?,
+                "series": row.get::<_, String>(2)?,
+                "value": row.get::<_, f64>(3)?,
+                "min": row.get::<_, f64>(4)?,
+                "max": row.get::<_, f64>(5)?,
+                "count": row.get::<_, u64>(6)?,
+            });
+            if let Some(child) = child(row.get(1)?) {
+                record["child"] = child.into();
+            }
+            records.push(record);
+        }
+        Ok(Value::Array(records))
+    }
+}
+
+fn millis(time: &OffsetDateTime) -> i64 {
+    (time.unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+fn time(millis: i64) -> Result<String, StoreError> {
+    let time = OffsetDateTime::from_unix_timestamp_nanos(millis as i128 * 1_000_000)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    Ok(time.format(&Rfc3339)?)
+}
+
+fn child_id(child: &Option<String>) -> &str {
+    child.as_deref().unwrap_or("")
+}
+
+fn child(child_id: String) -> Option<String> {
+    if child_id.is_empty() {
+        None
+    } else {
+        Some(child_id)
+    }
+}
+
+/// The payload of an event or alarm as JSON, an empty payload being null
+fn payload(payload: String) -> Value {
+    if payload.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(&payload).unwrap_or(Value::String(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use time::macros::datetime;
+
+    fn measurements(time: OffsetDateTime, child: Option<&str>, values: &[(&str, f64)]) -> Record {
+        Record::Measurements {
+            time,
+            child: child.map(str::to_string),
+            values: values
+                .iter()
+                .map(|(series, value)| (series.to_string(), *value))
+                .collect(),
+        }
+    }
+
+    fn store_with_measurements() -> Store {
+        let mut store = Store::open_in_memory().unwrap();
+        for (minute, child) in [(0, None), (1, Some("child1")), (2, None), (3, None)] {
+            let time = datetime!(2022-08-01 10:00:00 UTC) + time::Duration::minutes(minute);
+            let value = minute as f64;
+            store
+                .insert(&measurements(
+                    time,
+                    child,
+                    &[("temperature", value), ("location.x", 10.0 * value)],
+                ))
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn querying_measurements() {
+        let store = store_with_measurements();
+
+        let query = Query {
+            from: Some(datetime!(2022-08-01 10:01:00 UTC)),
+            to: Some(datetime!(2022-08-01 10:03:00 UTC)),
+            series: Some("temperature".into()),
+            ..Query::default()
+        };
+
+        assert_eq!(
+            store.query(RecordKind::Measurements, &query).unwrap(),
+            json!([
+                {"time": "2022-08-01T10:01:00Z", "child": "child1", "series": "temperature", "value": 1.0},
+                {"time": "2022-08-01T10:02:00Z", "series": "temperature", "value": 2.0},
+            ])
+        );
+    }
+
+    #[test]
+    fn querying_the_measurements_of_a_child_device() {
+        let store = store_with_measurements();
+
+        let query = Query {
+            child: Some("child1".into()),
+            limit: Some(1),
+            ..Query::default()
+        };
+
+        assert_eq!(
+            store.query(RecordKind::Measurements, &query).unwrap(),
+            json!([
+                {"time": "2022-08-01T10:01:00Z", "child": "child1", "series": "temperature", "value": 1.0},
+            ])
+        );
+    }
+
+    #[test]
+    fn downsampling_measurements() {
+        let store = store_with_measurements();
+
+        let query = Query {
+            series: Some("location.x".into()),
+            interval: Some(120),
+            ..Query::default()
+        };
+
+        assert_eq!(
+            store.query(RecordKind::Measurements, &query).unwrap(),
+            json!([
+                {"time": "2022-08-01T10:00:00Z", "series": "location.x", "value": 0.0, "min": 0.0, "max": 0.0, "count": 1},
+                {"time": "2022-08-01T10:00:00Z", "child": "child1", "series": "location.x", "value": 10.0, "min": 10.0, "max": 10.0, "count": 1},
+                {"time": "2022-08-01T10:02:00Z", "series": "location.x", "value": 25.0, "min": 20.0, "max": 30.0, "count": 2},
+            ])
+        );
+    }
+
+    #[test]
+    fn only_measurements_can_be_downsampled() {
+        let store = Store::open_in_memory().unwrap();
+
+        let query = Query {
+            interval: Some(60),
+            ..Query::default()
+        };
+
+        assert_matches!(
+            store.query(RecordKind::Events, &query),
+            Err(StoreError::InvalidQuery(_))
+        );
+    }
+
+    #[test]
+    fn querying_events_and_alarms() {
+        let mut store = Store::open_in_memory().unwrap();
+        store
+            .insert(&Record::Event {
+                time: datetime!(2022-08-01 10:00:00 UTC),
+                child: None,
+                event_type: "door".into(),
+                payload: r#"{"text": "Door opened"}"#.into(),
+            })
+            .unwrap();
+        store
+            .insert(&Record::Alarm {
+                time: datetime!(2022-08-01 10:00:00 UTC),
+                child: Some("child1".into()),
+                alarm_type: "overheat".into(),
+                severity: "major".into(),
+                payload: "".into(),
+            })
+            .unwrap();
+
+        let query = Query {
+            series: Some("door".into()),
+            ..Query::default()
+        };
+        assert_eq!(
+            store.query(RecordKind::Events, &query).unwrap(),
+            json!([{"time": "2022-08-01T10:00:00Z", "type": "door", "payload": {"text": "Door opened"}}])
+        );
+        assert_eq!(
+            store.query(RecordKind::Alarms, &Query::default()).unwrap(),
+            json!([{"time": "2022-08-01T10:00:00Z", "child": "child1", "type": "overheat", "severity": "major", "payload": null}])
+        );
+    }
+
+    #[test]
+    fn purging_old_records() {
+        let store = store_with_measurements();
+
+        let removed = store.purge(datetime!(2022-08-01 10:02:00 UTC)).unwrap();
+
+        assert_eq!(removed, 4);
+        assert_eq!(
+            store
+                .query(RecordKind::Measurements, &Query::default())
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+}
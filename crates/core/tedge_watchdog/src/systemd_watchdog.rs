@@ -1,28 +1,36 @@
 use crate::error::WatchdogError;
 use freedesktop_entry_parser::parse_entry;
-use futures::channel::mpsc;
 use futures::stream::FuturesUnordered;
-use futures::StreamExt;
-use mqtt_channel::{Config, Message, PubChannel, Topic};
+use mqtt_channel::{Config, Message, MqttError, RequestClient, Topic};
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{
     path::PathBuf,
     process::{self, Command, ExitStatus, Stdio},
 };
-use tedge_config::{
-    ConfigRepository, ConfigSettingAccessor, MqttBindAddressSetting, MqttPortSetting,
-    TEdgeConfigLocation,
-};
+use tedge_config::{ConfigRepository, TEdgeConfigLocation};
 use time::OffsetDateTime;
 use tracing::{debug, error, info, warn};
 
+/// The health status published by a service
+///
+/// The `down` status published by the broker when a service is gone has neither a pid nor a time.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HealthStatus {
     status: String,
-    pid: u32,
-    time: i64,
+    pid: Option<u32>,
+    time: Option<i64>,
+}
+
+impl HealthStatus {
+    /// The pid of the service, if this is a response to a health check sent at the given time
+    fn responding_pid(&self, request_timestamp: i64) -> Option<u32> {
+        match (self.status.as_str(), self.pid, self.time) {
+            ("up", Some(pid), Some(time)) if time >= request_timestamp => Some(pid),
+            _ => None,
+        }
+    }
 }
 
 pub async fn start_watchdog(tedge_config_dir: PathBuf) -> Result<(), anyhow::Error> {
@@ -66,10 +74,15 @@ async fn start_watchdog_for_tedge_services(tedge_config_dir: PathBuf) {
     let tedge_services = vec![
         "tedge-mapper-c8y",
         "tedge-mapper-az",
+        "tedge-mapper-aws",
         "tedge-mapper-collectd",
+        "tedge-mapper-generic",
+        "tedge-mapper-prometheus",
+        "tedge-mapper-influxdb",
         "tedge-agent",
         "c8y-log-plugin",
         "c8y-configuration-plugin",
+        "tedge-store",
     ];
 
     let watchdog_tasks = FuturesUnordered::new();
@@ -111,40 +124,38 @@ async fn monitor_tedge_service(
     interval: u64,
 ) -> Result<(), WatchdogError> {
     let client_id: &str = &format!("{}_{}", name, nanoid!());
-    let mqtt_config = get_mqtt_config(tedge_config_location, client_id)?
-        .with_subscriptions(res_topic.try_into()?);
-    let client = mqtt_channel::Connection::new(&mqtt_config).await?;
-    let mut received = client.received;
-    let mut publisher = client.published;
+    let mqtt_config = get_mqtt_config(tedge_config_location, client_id)?;
+    let (client, _other_statuses) = RequestClient::connect(
+        &mqtt_config,
+        res_topic.try_into()?,
+        health_status_response,
+        Duration::from_secs(interval),
+    )
+    .await?;
 
     info!("Starting watchdog for {} service", name);
 
     loop {
         let message = Message::new(&Topic::new(req_topic)?, "");
-        let _ = publisher
-            .publish(message)
-            .await
-            .map_err(|e| warn!("Publish failed with error: {}", e));
 
         let start = Instant::now();
 
         let request_timestamp = OffsetDateTime::now_utc().unix_timestamp();
-        match tokio::time::timeout(
-            tokio::time::Duration::from_secs(interval),
-            get_latest_health_status_message(request_timestamp, &mut received),
-        )
-        .await
+        match get_latest_health_status_message(request_timestamp, &client, res_topic, message).await
         {
-            Ok(health_status) => {
-                debug!(
-                    "Sending notification for {} with pid: {}",
-                    name, health_status.pid
-                );
-                notify_systemd(health_status.pid, "WATCHDOG=1")?;
+            Ok(Some((pid, _))) => {
+                debug!("Sending notification for {} with pid: {}", name, pid);
+                notify_systemd(pid, "WATCHDOG=1")?;
             }
-            Err(_) => {
+            Ok(None) => {
+                warn!("Invalid health check response received from {name}");
+            }
+            Err(MqttError::RequestTimeout { .. }) => {
                 warn!("No health check response received from {name} in time");
             }
+            Err(err) => {
+                warn!("Health check of {name} failed with error: {err}");
+            }
         }
 
         let elapsed = start.elapsed();
@@ -155,27 +166,50 @@ async fn monitor_tedge_service(
     }
 }
 
+/// The health statuses of a service are correlated to the health check requests by topic
+///
+/// The `down` statuses, published by the broker on behalf of a service, are not responses.
+fn health_status_response(message: &Message) -> Option<String> {
+    let payload = message.payload_str().ok()?;
+    debug!("Health status received: {}", payload);
+    match serde_json::from_str::<HealthStatus>(payload) {
+        Ok(health_status) if health_status.status == "up" => Some(message.topic.name.clone()),
+        Ok(_) => None,
+        Err(_) => {
+            error!("Invalid health response received: {}", payload);
+            None
+        }
+    }
+}
+
+/// Send a health check request, returning the pid of the service if it responds in time.
+///
+/// The stale health statuses, older than the request, are ignored while waiting for a fresh one.
 async fn get_latest_health_status_message(
     request_timestamp: i64,
-    messages: &mut mpsc::UnboundedReceiver<Message>,
-) -> HealthStatus {
-    loop {
-        if let Some(message) = messages.next().await {
-            if let Ok(message) = message.payload_str() {
-                debug!("Health response received: {}", message);
-                if let Ok(health_status) = serde_json::from_str::<HealthStatus>(message) {
-                    if health_status.time >= request_timestamp {
-                        return health_status;
-                    } else {
-                        debug!(
-                            "Ignoring stale health response: {:?} older than request time: {}",
-                            health_status, request_timestamp
-                        );
-                    }
-                } else {
-                    error!("Invalid health response received: {}", message);
-                }
-            }
+    client: &RequestClient,
+    response_topic: &str,
+    request: Message,
+) -> Result<Option<(u32, HealthStatus)>, MqttError> {
+    let response = client
+        .request_matching(response_topic, request, move |response| {
+            fresh_health_status(response, request_timestamp).is_some()
+        })
+        .await?;
+    Ok(fresh_health_status(&response, request_timestamp))
+}
+
+/// The pid and health status of a service, if this message is a response to a request sent at the given time
+fn fresh_health_status(message: &Message, request_timestamp: i64) -> Option<(u32, HealthStatus)> {
+    let health_status = serde_json::from_str::<HealthStatus>(message.payload_str().ok()?).ok()?;
+    match health_status.responding_pid(request_timestamp) {
+        Some(pid) => Some((pid, health_status)),
+        None => {
+            debug!(
+                "Ignoring health status: {:?} not a response to the request sent at: {}",
+                health_status, request_timestamp
+            );
+            None
         }
     }
 }
@@ -186,10 +220,7 @@ fn get_mqtt_config(
 ) -> Result<Config, WatchdogError> {
     let config_repository = tedge_config::TEdgeConfigRepository::new(tedge_config_location);
     let tedge_config = config_repository.load()?;
-    let mqtt_config = Config::default()
-        .with_session_name(client_id)
-        .with_host(tedge_config.query(MqttBindAddressSetting)?.to_string())
-        .with_port(tedge_config.query(MqttPortSetting)?.into());
+    let mqtt_config = tedge_config.mqtt_config()?.with_session_name(client_id);
     Ok(mqtt_config)
 }
 
@@ -228,36 +259,107 @@ fn get_watchdog_sec(service_file: &str) -> Result<u64, WatchdogError> {
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use mqtt_channel::PubChannel;
     use serde_json::json;
 
     use super::*;
 
+    const HEALTH_TOPIC: &str = "tedge/health/test-service";
+
+    fn health_check_request() -> Message {
+        Message::new(&Topic::new_unchecked("tedge/health-check/test-service"), "")
+    }
+
+    fn health_status(status: serde_json::Value) -> Message {
+        Message::new(&Topic::new_unchecked(HEALTH_TOPIC), status.to_string())
+    }
+
+    /// A service responding to each health check with the given statuses
+    fn client_of_service_responding(statuses: Vec<Message>) -> RequestClient {
+        let (mut responses, received) = mpsc::unbounded::<Message>();
+        let (requests, mut published) = mpsc::unbounded::<Message>();
+        let (client, _other_statuses) = RequestClient::from_channels(
+            requests,
+            received,
+            health_status_response,
+            Duration::from_secs(1),
+        );
+        tokio::spawn(async move {
+            while published.next().await.is_some() {
+                for status in statuses.iter() {
+                    let _ = responses.publish(status.clone()).await;
+                }
+            }
+        });
+        client
+    }
+
     #[tokio::test]
     async fn test_get_latest_health_status_message() -> Result<()> {
-        let (mut sender, mut receiver) = mpsc::unbounded::<Message>();
-        let health_topic = Topic::new("tedge/health/test-service").expect("Valid topic");
-
-        for x in 1..5i64 {
-            let health_status = json!({
-                "status": "up",
-                "pid": 123u32,
-                "time": x,
-            })
-            .to_string();
-            let health_message = Message::new(&health_topic, health_status);
-            sender.publish(health_message).await?;
-        }
+        let client = client_of_service_responding(vec![health_status(
+            json!({ "status": "up", "pid": 123u32, "time": 3i64 }),
+        )]);
 
-        let health_status = get_latest_health_status_message(3, &mut receiver).await;
-        assert_eq!(health_status.time, 3);
+        let response =
+            get_latest_health_status_message(3, &client, HEALTH_TOPIC, health_check_request())
+                .await?;
+        let (pid, health_status) = response.expect("a fresh health status");
+        assert_eq!(pid, 123);
+        assert_eq!(health_status.time, Some(3));
 
-        let timeout_error = tokio::time::timeout(
-            tokio::time::Duration::from_secs(1),
-            get_latest_health_status_message(5, &mut receiver),
-        )
-        .await;
-        assert!(timeout_error.is_err());
+        let stale_response =
+            get_latest_health_status_message(5, &client, HEALTH_TOPIC, health_check_request())
+                .await;
+        assert!(matches!(
+            stale_response,
+            Err(MqttError::RequestTimeout { .. })
+        ));
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn stale_health_statuses_are_skipped_until_a_fresh_one_is_received() -> Result<()> {
+        let client = client_of_service_responding(vec![
+            health_status(json!({ "status": "up", "pid": 42u32, "time": 3i64 })),
+            health_status(json!({ "status": "up", "pid": 42u32, "time": 7i64 })),
+        ]);
+
+        let response =
+            get_latest_health_status_message(5, &client, HEALTH_TOPIC, health_check_request())
+                .await?;
+        assert_eq!(response.map(|(_, status)| status.time), Some(Some(7)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn down_health_status_messages_are_not_health_check_responses() -> Result<()> {
+        let client = client_of_service_responding(vec![
+            health_status(json!({ "status": "down" })),
+            health_status(json!({ "status": "up", "pid": 42u32, "time": 7i64 })),
+        ]);
+
+        let response =
+            get_latest_health_status_message(7, &client, HEALTH_TOPIC, health_check_request())
+                .await?;
+        assert_eq!(response.map(|(pid, _)| pid), Some(42));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn a_service_not_responding_in_time_is_reported() {
+        let client = client_of_service_responding(vec![]);
+
+        let timeout_error =
+            get_latest_health_status_message(5, &client, HEALTH_TOPIC, health_check_request())
+                .await;
+        assert!(matches!(
+            timeout_error,
+            Err(MqttError::RequestTimeout { .. })
+        ));
+    }
 }
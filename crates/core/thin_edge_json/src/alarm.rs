@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 
 use clock::Timestamp;
 use serde::Deserialize;
+use serde_json::Value;
 
 /// In-memory representation of ThinEdge JSON alarm.
 #[derive(Debug, Deserialize, Eq, PartialEq)]
@@ -9,6 +11,7 @@ pub struct ThinEdgeAlarm {
     pub name: String,
     pub severity: AlarmSeverity,
     pub data: Option<ThinEdgeAlarmData>,
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Eq, PartialEq)]
@@ -27,6 +30,9 @@ pub struct ThinEdgeAlarmData {
     #[serde(default)]
     #[serde(with = "time::serde::rfc3339::option")]
     pub time: Option<Timestamp>,
+
+    #[serde(flatten)]
+    pub extras: HashMap<String, Value>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -66,7 +72,7 @@ impl ThinEdgeAlarm {
         mqtt_payload: &str,
     ) -> Result<Self, ThinEdgeJsonDeserializerError> {
         let topic_split: Vec<&str> = mqtt_topic.split('/').collect();
-        if topic_split.len() == 4 || topic_split.len() == 5 {
+        if topic_split.len() >= 4 {
             let alarm_severity = topic_split[2];
             let alarm_name = topic_split[3];
 
@@ -82,8 +88,9 @@ impl ThinEdgeAlarm {
                 ));
             }
 
-            // Return error if child id in the topic is empty
-            if topic_split.len() == 5 && topic_split[4].is_empty() {
+            // Return error if a child id in the topic is empty,
+            // the trailing levels being the path to a child device, each child device being the parent of the next one
+            if topic_split[4..].iter().any(|child_id| child_id.is_empty()) {
                 return Err(ThinEdgeJsonDeserializerError::UnsupportedExternalDeviceId(
                     mqtt_topic.into(),
                 ));
@@ -95,10 +102,18 @@ impl ThinEdgeAlarm {
                 Some(serde_json::from_str(mqtt_payload)?)
             };
 
+            // The last part of the topic name is the alarm source - if any
+            let external_source = if topic_split.len() >= 5 {
+                topic_split.last().map(|child_id| child_id.to_string())
+            } else {
+                None
+            };
+
             Ok(Self {
                 name: alarm_name.into(),
                 severity: alarm_severity.try_into()?,
                 data: alarm_data,
+                source: external_source,
             })
         } else {
             Err(ThinEdgeJsonDeserializerError::UnsupportedTopic(
@@ -128,7 +143,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: Some("I raised it".into()),
                 time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::new(),
             }),
+            source: None,
         };
         "critical alarm parsing"
     )]
@@ -143,7 +160,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: Some("I raised it".into()),
                 time: None,
+                extras: HashMap::new(),
             }),
+            source: None,
         };
         "major alarm parsing without timestamp"
     )]
@@ -158,7 +177,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: None,
                 time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::new(),
             }),
+            source: None,
         };
         "minor alarm parsing without text"
     )]
@@ -171,7 +192,9 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: None,
                 time: None,
+                extras: HashMap::new(),
             }),
+            source: None,
         };
         "warning alarm parsing without text or timestamp"
     )]
@@ -187,10 +210,51 @@ mod tests {
             data: Some(ThinEdgeAlarmData {
                 text: Some("I raised it".into()),
                 time: Some(datetime!(2021-04-23 19:00:00 +05:00)),
+                extras: HashMap::new(),
             }),
+            source: Some("extern_sensor".into()),
         };
         "critical alarm parsing with childId"
     )]
+    #[test_case(
+        "tedge/alarms/major/temperature_alarm/plc/extern_sensor",
+        json!({
+            "text": "I raised it",
+        }),
+        ThinEdgeAlarm {
+            name: "temperature_alarm".into(),
+            severity: AlarmSeverity::Major,
+            data: Some(ThinEdgeAlarmData {
+                text: Some("I raised it".into()),
+                time: None,
+                extras: HashMap::new(),
+            }),
+            source: Some("extern_sensor".into()),
+        };
+        "major alarm parsing with the path to a nested child device"
+    )]
+    #[test_case(
+        "tedge/alarms/minor/temperature_alarm",
+        json!({
+            "text": "I raised it",
+            "threshold": 40,
+            "sensor": { "name": "probe" },
+        }),
+        ThinEdgeAlarm {
+            name: "temperature_alarm".into(),
+            severity: AlarmSeverity::Minor,
+            data: Some(ThinEdgeAlarmData {
+                text: Some("I raised it".into()),
+                time: None,
+                extras: HashMap::from([
+                    ("threshold".to_string(), json!(40)),
+                    ("sensor".to_string(), json!({ "name": "probe" })),
+                ]),
+            }),
+            source: None,
+        };
+        "minor alarm parsing with custom fragments"
+    )]
     fn parse_thin_edge_alarm_json(
         alarm_topic: &str,
         alarm_payload: Value,
@@ -249,11 +313,13 @@ mod tests {
     }
 
     #[test]
-    fn alarm_translation_invalid_topic_levels() {
+    fn nested_child_alarm_translation_empty_external_device_names() {
         let result = ThinEdgeAlarm::try_from("tedge/alarms/critical/temperature_alarm//", "{}");
         assert_matches!(
             result,
-            Err(ThinEdgeJsonDeserializerError::UnsupportedTopic(_))
+            Err(ThinEdgeJsonDeserializerError::UnsupportedExternalDeviceId(
+                _
+            ))
         );
     }
 
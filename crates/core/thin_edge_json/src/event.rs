@@ -50,21 +50,28 @@ impl ThinEdgeEvent {
         mqtt_payload: &str,
     ) -> Result<Self, ThinEdgeJsonDeserializerError> {
         let topic_split: Vec<&str> = mqtt_topic.split('/').collect();
-        if topic_split.len() == 3 || topic_split.len() == 4 {
+        if topic_split.len() >= 3 {
             let event_name = topic_split[2];
             if event_name.is_empty() {
                 return Err(ThinEdgeJsonDeserializerError::EmptyEventName);
             }
 
+            // The trailing levels are the path to a child device, each child device being the parent of the next one
+            if topic_split[3..].iter().any(|child_id| child_id.is_empty()) {
+                return Err(ThinEdgeJsonDeserializerError::UnsupportedTopic(
+                    mqtt_topic.into(),
+                ));
+            }
+
             let event_data = if mqtt_payload.is_empty() {
                 None
             } else {
                 Some(serde_json::from_str(mqtt_payload)?)
             };
 
-            // The 4th part of the topic name is the event source - if any
-            let external_source = if topic_split.len() == 4 {
-                Some(topic_split[3].to_string())
+            // The last part of the topic name is the event source - if any
+            let external_source = if topic_split.len() >= 4 {
+                topic_split.last().map(|child_id| child_id.to_string())
             } else {
                 None
             };
@@ -204,8 +211,17 @@ mod tests {
     }
 
     #[test]
-    fn event_translation_more_than_four_topic_levels() {
-        let result = ThinEdgeEvent::try_from("tedge/events/page/click/click", "{}");
+    fn event_translation_for_a_nested_child_device() -> Result<()> {
+        let event = ThinEdgeEvent::try_from("tedge/events/click_event/plc/sensor", "{}")?;
+
+        assert_eq!(event.name, "click_event");
+        assert_eq!(event.source.as_deref(), Some("sensor"));
+        Ok(())
+    }
+
+    #[test]
+    fn event_translation_empty_child_id() {
+        let result = ThinEdgeEvent::try_from("tedge/events/click_event//sensor", "{}");
 
         assert_matches!(
             result,
@@ -42,6 +42,58 @@ impl MeasurementGroup {
         }
     }
 
+    /// Split this group into two halves of measurements, both with the timestamp of this group.
+    ///
+    /// The measurements of a multi-value measurement might be spread over the two halves.
+    /// Returns `None` when the group holds less than two measurements and cannot be split.
+    pub fn split(&self) -> Option<(MeasurementGroup, MeasurementGroup)> {
+        let mut measurements: Vec<(&str, Option<&str>, f64)> = Vec::new();
+        for (key, value) in self.values.iter() {
+            match value {
+                Measurement::Single(sv) => measurements.push((key, None, *sv)),
+                Measurement::Multi(m) => {
+                    for (sub_key, sv) in m.iter() {
+                        measurements.push((key, Some(sub_key), *sv));
+                    }
+                }
+            }
+        }
+        if measurements.len() < 2 {
+            return None;
+        }
+
+        // Sort the measurements so the split doesn't depend on the hash map order
+        measurements.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        let (first, second) = measurements.split_at(measurements.len() / 2);
+        Some((
+            self.with_measurements(first),
+            self.with_measurements(second),
+        ))
+    }
+
+    fn with_measurements(&self, measurements: &[(&str, Option<&str>, f64)]) -> MeasurementGroup {
+        let mut values = HashMap::new();
+        for (key, sub_key, value) in measurements {
+            match sub_key {
+                None => {
+                    values.insert(key.to_string(), Measurement::Single(*value));
+                }
+                Some(sub_key) => {
+                    if let Measurement::Multi(group_map) = values
+                        .entry(key.to_string())
+                        .or_insert_with(|| Measurement::Multi(HashMap::new()))
+                    {
+                        group_map.insert(sub_key.to_string(), *value);
+                    }
+                }
+            }
+        }
+        MeasurementGroup {
+            timestamp: self.timestamp,
+            values,
+        }
+    }
+
     pub fn accept<V, E>(&self, visitor: &mut V) -> Result<(), E>
     where
         V: MeasurementVisitor<Error = E>,
@@ -347,6 +399,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn split_measurement_group() -> anyhow::Result<()> {
+        let mut grouper = MeasurementGrouper::new();
+        grouper.visit_timestamp(test_timestamp(4))?;
+        grouper.visit_measurement("temperature", 32.5)?;
+        grouper.visit_start_group("coordinate")?;
+        grouper.visit_measurement("x", 50.0)?;
+        grouper.visit_measurement("y", 70.0)?;
+        grouper.visit_measurement("z", 90.0)?;
+        grouper.visit_end_group()?;
+
+        let group = grouper.end()?;
+        let (first, second) = group.split().unwrap();
+
+        assert_eq!(first.timestamp(), Some(test_timestamp(4)));
+        assert_eq!(second.timestamp(), Some(test_timestamp(4)));
+        assert_eq!(
+            first.get_measurement_value(Some("coordinate"), "x"),
+            Some(50.0)
+        );
+        assert_eq!(
+            first.get_measurement_value(Some("coordinate"), "y"),
+            Some(70.0)
+        );
+        assert_eq!(
+            second.get_measurement_value(Some("coordinate"), "z"),
+            Some(90.0)
+        );
+        assert_eq!(
+            second.get_measurement_value(None, "temperature"),
+            Some(32.5)
+        );
+        assert_eq!(first.get_measurement_value(None, "temperature"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn a_single_measurement_cannot_be_split() -> anyhow::Result<()> {
+        let mut grouper = MeasurementGrouper::new();
+        grouper.visit_measurement("temperature", 32.5)?;
+
+        let group = grouper.end()?;
+
+        assert!(group.split().is_none());
+        assert!(MeasurementGroup::new().split().is_none());
+
+        Ok(())
+    }
+
     fn test_timestamp(minute: u32) -> OffsetDateTime {
         let mut dt = datetime!(2021-04-08 13:00:00 +05:00);
         dt += Duration::minutes(minute as i64);
@@ -13,10 +13,52 @@ pub fn health_check_topics(daemon_name: &str) -> TopicFilter {
     .expect("Invalid topic filter")
 }
 
+pub fn health_status_topic(daemon_name: &str) -> Topic {
+    Topic::new_unchecked(format!("tedge/health/{daemon_name}").as_str())
+}
+
+/// The topic where a daemon publishes its metrics
+pub fn health_metrics_topic(daemon_name: &str) -> Topic {
+    Topic::new_unchecked(format!("tedge/health/{daemon_name}/metrics").as_str())
+}
+
 pub async fn send_health_status(responses: &mut impl PubChannel, daemon_name: &str) {
-    let response_topic_health =
-        Topic::new_unchecked(format!("tedge/health/{daemon_name}").as_str());
+    let _ = responses.send(health_status_up(daemon_name)).await;
+}
+
+/// The retained message published by a daemon on connect to tell it is up
+pub fn health_status_up_message(daemon_name: &str) -> Message {
+    health_status_up(daemon_name).with_retain()
+}
+
+/// The retained message published by the broker on behalf of a daemon when the daemon is down
+///
+/// This last will is registered on connect and only published by the broker once the daemon is gone.
+/// Hence, it carries neither a pid, that might have been reused by another process, nor a time.
+pub fn health_status_down_message(daemon_name: &str) -> Message {
+    let health_status = json!({
+        "status": "down",
+    })
+    .to_string();
+
+    Message::new(&health_status_topic(daemon_name), health_status).with_retain()
+}
+
+/// Configure the MQTT connection of a daemon to publish its retained health status,
+/// `up` on connect and `down` when disconnected.
+///
+/// The `up` status is stamped with the time of each connection.
+pub fn with_health_status(
+    mqtt_config: mqtt_channel::Config,
+    daemon_name: &str,
+) -> mqtt_channel::Config {
+    let name = daemon_name.to_string();
+    mqtt_config
+        .with_birth_message_builder(move || health_status_up_message(&name))
+        .with_last_will_message(health_status_down_message(daemon_name))
+}
 
+fn health_status_up(daemon_name: &str) -> Message {
     let health_status = json!({
         "status": "up",
         "pid": process::id(),
@@ -24,6 +66,30 @@ pub async fn send_health_status(responses: &mut impl PubChannel, daemon_name: &s
     })
     .to_string();
 
-    let health_message = Message::new(&response_topic_health, health_status);
-    let _ = responses.send(health_message).await;
+    Message::new(&health_status_topic(daemon_name), health_status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn the_health_status_of_a_daemon_is_retained() {
+        let mqtt_config = with_health_status(mqtt_channel::Config::default(), "tedge-agent");
+
+        let birth_message = mqtt_config.birth_message.expect("a birth message").build();
+        let status: Value = serde_json::from_str(birth_message.payload_str().unwrap()).unwrap();
+        assert_eq!(birth_message.topic.name, "tedge/health/tedge-agent");
+        assert!(birth_message.retain);
+        assert_eq!(status["status"], "up");
+        assert!(status["time"].is_number());
+
+        let last_will = mqtt_config.last_will_message.expect("a last will");
+        let status: Value = serde_json::from_str(last_will.payload_str().unwrap()).unwrap();
+        assert_eq!(last_will.topic.name, "tedge/health/tedge-agent");
+        assert!(last_will.retain);
+        assert_eq!(status["status"], "down");
+        assert!(status.get("pid").is_none());
+    }
 }
@@ -7,6 +7,7 @@ pub mod data;
 pub mod event;
 pub mod group;
 pub mod health;
+pub mod line_protocol;
 pub mod measurement;
 pub mod parser;
 pub mod serialize;
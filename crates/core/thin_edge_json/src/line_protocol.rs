@@ -0,0 +1,231 @@
+use crate::measurement::MeasurementVisitor;
+use crate::serialize::MeasurementStreamError;
+use std::fmt::Write;
+use time::OffsetDateTime;
+
+/// Serialize Thin Edge JSON measurements into a line of [InfluxDB line protocol][1]
+///
+/// All the measurements of a message are given as fields of the same line,
+/// the grouped measurements being named after their group and name joined by an underscore,
+/// as `location_latitude`. The timestamp is given in nanoseconds.
+///
+/// [1]: https://docs.influxdata.com/influxdb/v2.0/reference/syntax/line-protocol/
+pub struct LineProtocolSerializer {
+    line: String,
+    fields: String,
+    group: Option<String>,
+    default_timestamp: Option<OffsetDateTime>,
+    timestamp: Option<OffsetDateTime>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LineProtocolSerializationError {
+    #[error(transparent)]
+    FormatError(#[from] std::fmt::Error),
+
+    #[error(transparent)]
+    MeasurementCollectorError(#[from] MeasurementStreamError),
+
+    #[error("A line of line protocol must have at least one field")]
+    NoFields,
+}
+
+impl LineProtocolSerializer {
+    pub fn new(measurement: &str) -> Self {
+        Self::new_with_timestamp(measurement, None)
+    }
+
+    pub fn new_with_timestamp(
+        measurement: &str,
+        default_timestamp: Option<OffsetDateTime>,
+    ) -> Self {
+        LineProtocolSerializer {
+            line: escape(measurement, &[',', ' ']),
+            fields: String::new(),
+            group: None,
+            default_timestamp,
+            timestamp: None,
+        }
+    }
+
+    /// Add a tag to the line, as the device the measurements are related to.
+    ///
+    /// The tags have to be added in lexical order, as recommended by InfluxDB.
+    pub fn with_tag(mut self, key: &str, value: &str) -> Self {
+        let _ = write!(
+            self.line,
+            ",{}={}",
+            escape(key, &[',', '=', ' ']),
+            escape(value, &[',', '=', ' '])
+        );
+        self
+    }
+
+    pub fn into_string(self) -> Result<String, LineProtocolSerializationError> {
+        if self.group.is_some() {
+            return Err(MeasurementStreamError::UnexpectedEndOfData.into());
+        }
+        if self.fields.is_empty() {
+            return Err(LineProtocolSerializationError::NoFields);
+        }
+
+        let mut line = self.line;
+        write!(line, " {}", self.fields)?;
+        if let Some(timestamp) = self.timestamp.or(self.default_timestamp) {
+            write!(line, " {}", timestamp.unix_timestamp_nanos())?;
+        }
+        Ok(line)
+    }
+}
+
+impl MeasurementVisitor for LineProtocolSerializer {
+    type Error = LineProtocolSerializationError;
+
+    fn visit_timestamp(&mut self, timestamp: OffsetDateTime) -> Result<(), Self::Error> {
+        if self.group.is_some() {
+            return Err(MeasurementStreamError::UnexpectedTimestamp.into());
+        }
+
+        self.timestamp = Some(timestamp);
+        Ok(())
+    }
+
+    fn visit_measurement(&mut self, name: &str, value: f64) -> Result<(), Self::Error> {
+        if !self.fields.is_empty() {
+            self.fields.push(',');
+        }
+        let field = match &self.group {
+            Some(group) => format!("{}_{}", group, name),
+            None => name.to_string(),
+        };
+        write!(
+            self.fields,
+            "{}={}",
+            escape(&field, &[',', '=', ' ']),
+            value
+        )?;
+        Ok(())
+    }
+
+    fn visit_start_group(&mut self, group: &str) -> Result<(), Self::Error> {
+        if self.group.is_some() {
+            return Err(MeasurementStreamError::UnexpectedStartOfGroup.into());
+        }
+
+        self.group = Some(group.to_string());
+        Ok(())
+    }
+
+    fn visit_end_group(&mut self) -> Result<(), Self::Error> {
+        if self.group.is_none() {
+            return Err(MeasurementStreamError::UnexpectedEndOfGroup.into());
+        }
+
+        self.group = None;
+        Ok(())
+    }
+}
+
+/// Escape the given special characters, and the backslashes, with a backslash
+fn escape(name: &str, special_chars: &[char]) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '\\' || special_chars.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use time::macros::datetime;
+
+    #[test]
+    fn serialize_single_value_message() -> anyhow::Result<()> {
+        let mut serializer = LineProtocolSerializer::new("tedge");
+
+        serializer.visit_timestamp(datetime!(2021-04-08 00:00:00 +05:00))?;
+        serializer.visit_measurement("temperature", 25.5)?;
+
+        assert_eq!(
+            serializer.into_string()?,
+            "tedge temperature=25.5 1617822000000000000"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_multi_value_message() -> anyhow::Result<()> {
+        let mut serializer = LineProtocolSerializer::new("tedge").with_tag("device", "child1");
+
+        serializer.visit_measurement("temperature", 25.0)?;
+        serializer.visit_start_group("location")?;
+        serializer.visit_measurement("latitude", 32.54)?;
+        serializer.visit_measurement("longitude", -117.67)?;
+        serializer.visit_end_group()?;
+        serializer.visit_measurement("pressure", 98.0)?;
+
+        assert_eq!(
+            serializer.into_string()?,
+            "tedge,device=child1 temperature=25,location_latitude=32.54,location_longitude=-117.67,pressure=98"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn the_default_timestamp_is_used_when_none_is_given() -> anyhow::Result<()> {
+        let timestamp = datetime!(2021-04-08 00:00:00 UTC);
+        let mut serializer = LineProtocolSerializer::new_with_timestamp("tedge", Some(timestamp));
+
+        serializer.visit_measurement("temperature", 25.0)?;
+
+        assert_eq!(
+            serializer.into_string()?,
+            "tedge temperature=25 1617840000000000000"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn special_characters_are_escaped() -> anyhow::Result<()> {
+        let mut serializer =
+            LineProtocolSerializer::new("tedge data,v1").with_tag("device", "my device=1");
+
+        serializer.visit_measurement("temp,inside", 25.0)?;
+
+        assert_eq!(
+            serializer.into_string()?,
+            r"tedge\ data\,v1,device=my\ device\=1 temp\,inside=25"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn a_line_without_fields_is_rejected() {
+        let serializer = LineProtocolSerializer::new("tedge");
+
+        assert_matches!(
+            serializer.into_string(),
+            Err(LineProtocolSerializationError::NoFields)
+        );
+    }
+
+    #[test]
+    fn nested_groups_are_rejected() -> anyhow::Result<()> {
+        let mut serializer = LineProtocolSerializer::new("tedge");
+
+        serializer.visit_start_group("location")?;
+
+        assert_matches!(
+            serializer.visit_start_group("position"),
+            Err(LineProtocolSerializationError::MeasurementCollectorError(
+                MeasurementStreamError::UnexpectedStartOfGroup
+            ))
+        );
+        Ok(())
+    }
+}
@@ -13,7 +13,7 @@ use c8y_smartrest::smartrest_serializer::{
     SmartRestSetOperationToExecuting, SmartRestSetOperationToFailed,
     SmartRestSetOperationToSuccessful, TryIntoOperationStatusMessage,
 };
-use download::{Auth, DownloadInfo, Downloader};
+use download::{Auth, DownloadInfo, DownloadSettings, Downloader};
 use mqtt_channel::{Connection, Message, SinkExt, Topic};
 
 use serde_json::json;
@@ -33,6 +33,7 @@ const FILE_TRANSFER_ROOT_PATH: &str = "/var/tedge/file-transfer";
 pub async fn handle_config_download_request(
     smartrest_request: SmartRestConfigDownloadRequest,
     tmp_dir: PathBuf,
+    download_settings: &DownloadSettings,
     mqtt_client: &mut Connection,
     http_client: &mut impl C8YHttpProxy,
     local_http_host: &str,
@@ -43,6 +44,7 @@ pub async fn handle_config_download_request(
         handle_config_download_request_tedge_device(
             smartrest_request,
             tmp_dir,
+            download_settings,
             mqtt_client,
             http_client,
             config_dir,
@@ -51,6 +53,7 @@ pub async fn handle_config_download_request(
     } else {
         handle_config_download_request_child_device(
             smartrest_request,
+            download_settings,
             mqtt_client,
             http_client,
             local_http_host,
@@ -63,6 +66,7 @@ pub async fn handle_config_download_request(
 pub async fn handle_config_download_request_tedge_device(
     smartrest_request: SmartRestConfigDownloadRequest,
     tmp_dir: PathBuf,
+    download_settings: &DownloadSettings,
     mqtt_client: &mut Connection,
     http_client: &mut impl C8YHttpProxy,
     config_dir: &Path,
@@ -84,6 +88,7 @@ pub async fn handle_config_download_request_tedge_device(
                     PathBuf::from(&target_file_entry.path),
                     tmp_dir,
                     target_file_entry.file_permissions,
+                    download_settings,
                     http_client,
                 )
                 .await
@@ -122,6 +127,7 @@ pub async fn handle_config_download_request_tedge_device(
 /// The child device can use this URL to download the config file update from the file transfer service.
 pub async fn handle_config_download_request_child_device(
     smartrest_request: SmartRestConfigDownloadRequest,
+    download_settings: &DownloadSettings,
     mqtt_client: &mut Connection,
     http_client: &mut impl C8YHttpProxy,
     local_http_host: &str,
@@ -157,7 +163,8 @@ pub async fn handle_config_download_request_child_device(
         config_management.http_file_repository_relative_path()
     ));
     let temp_download_file_name = &format!(".tmp_{}", config_file_name);
-    let downloader = Downloader::new(temp_download_file_name, &None, FILE_TRANSFER_ROOT_PATH);
+    let downloader = Downloader::new(temp_download_file_name, &None, FILE_TRANSFER_ROOT_PATH)
+        .with_settings(download_settings.clone());
     downloader.download(&download_info).await?;
     downloader.rename(&path_to).await?;
 
@@ -216,6 +223,7 @@ async fn download_config_file(
     file_path: PathBuf,
     tmp_dir: PathBuf,
     file_permissions: PermissionEntry,
+    download_settings: &DownloadSettings,
     http_client: &mut impl C8YHttpProxy,
 ) -> Result<(), anyhow::Error> {
     // Convert smartrest request to config download request struct
@@ -232,7 +240,7 @@ async fn download_config_file(
     }
 
     // Download a file to tmp dir
-    let downloader = config_download_request.create_downloader();
+    let downloader = config_download_request.create_downloader(download_settings);
     downloader
         .download(&config_download_request.download_info)
         .await?;
@@ -301,8 +309,9 @@ impl ConfigDownloadRequest {
         }
     }
 
-    fn create_downloader(&self) -> Downloader {
+    fn create_downloader(&self, download_settings: &DownloadSettings) -> Downloader {
         Downloader::new(&self.file_name, &None, &self.tmp_dir)
+            .with_settings(download_settings.clone())
     }
 
     fn move_file(&self) -> Result<(), ConfigManagementError> {
@@ -12,6 +12,7 @@ use crate::{
 };
 use crate::{config::PluginConfig, upload::handle_child_device_config_snapshot_response};
 
+use ::download::DownloadSettings;
 use anyhow::Result;
 use c8y_api::http_proxy::{C8YHttpProxy, JwtAuthHttpProxy};
 use c8y_smartrest::smartrest_deserializer::{
@@ -24,11 +25,10 @@ use mqtt_channel::{Connection, Message, PubChannel, SinkExt, StreamExt, Topic, T
 use std::path::{Path, PathBuf};
 use tedge_config::{
     ConfigRepository, ConfigSettingAccessor, DeviceIdSetting, IpAddress, MqttBindAddressSetting,
-    MqttExternalBindAddressSetting, MqttPortSetting, TEdgeConfig, TmpPathSetting,
-    DEFAULT_TEDGE_CONFIG_PATH,
+    MqttExternalBindAddressSetting, TEdgeConfig, TmpPathSetting, DEFAULT_TEDGE_CONFIG_PATH,
 };
 use tedge_utils::file::{create_directory_with_user_group, create_file_with_user_group};
-use thin_edge_json::health::{health_check_topics, send_health_status};
+use thin_edge_json::health::{health_check_topics, send_health_status, with_health_status};
 use topic::ConfigOperationResponseTopic;
 
 use tedge_utils::fs_notify::{fs_notify_stream, pin_mut, FileEvent};
@@ -70,7 +70,9 @@ pub struct ConfigPluginOpt {
     pub config_dir: PathBuf,
 }
 
-async fn create_mqtt_client(mqtt_port: u16) -> Result<mqtt_channel::Connection, anyhow::Error> {
+async fn create_mqtt_client(
+    mqtt_config: mqtt_channel::Config,
+) -> Result<mqtt_channel::Connection, anyhow::Error> {
     let mut topic_filter =
         mqtt_channel::TopicFilter::new_unchecked(C8yTopic::SmartRestRequest.as_str());
     topic_filter.add_all(health_check_topics("c8y-configuration-plugin"));
@@ -78,9 +80,8 @@ async fn create_mqtt_client(mqtt_port: u16) -> Result<mqtt_channel::Connection,
     topic_filter.add_all(ConfigOperationResponseTopic::SnapshotResponse.into());
     topic_filter.add_all(ConfigOperationResponseTopic::UpdateResponse.into());
 
-    let mqtt_config = mqtt_channel::Config::default()
+    let mqtt_config = with_health_status(mqtt_config, "c8y-configuration-plugin")
         .with_session_name("c8y-configuration-plugin")
-        .with_port(mqtt_port)
         .with_subscriptions(topic_filter);
 
     let mqtt_client = mqtt_channel::Connection::new(&mqtt_config).await?;
@@ -122,18 +123,20 @@ async fn main() -> Result<(), anyhow::Error> {
         Err(_) => internal_bind_address,
     };
 
-    let mqtt_port = tedge_config.query(MqttPortSetting)?.into();
+    let mqtt_config = tedge_config.mqtt_config()?;
     let mut http_client = create_http_client(&tedge_config).await?;
     let tmp_dir = tedge_config.query(TmpPathSetting)?.into();
+    let download_settings = tedge_config.download_settings()?;
 
     let local_http_host = format!("{}:80", bind_address.to_string().as_str());
 
     run(
         tedge_device_id,
-        mqtt_port,
+        mqtt_config,
         &mut http_client,
         &local_http_host,
         tmp_dir,
+        &download_settings,
         &config_plugin_opt.config_dir,
         DEFAULT_PLUGIN_CONFIG_FILE,
     )
@@ -142,16 +145,17 @@ async fn main() -> Result<(), anyhow::Error> {
 
 async fn run(
     tedge_device_id: String,
-    mqtt_port: u16,
+    mqtt_config: mqtt_channel::Config,
     http_client: &mut impl C8YHttpProxy,
     local_http_host: &str,
     tmp_dir: PathBuf,
+    download_settings: &DownloadSettings,
     config_dir: &Path,
     config_file: &str,
 ) -> Result<(), anyhow::Error> {
     let config_file_path = config_dir.join(config_file);
     let mut plugin_config = PluginConfig::new(&config_file_path);
-    let mut mqtt_client = create_mqtt_client(mqtt_port).await?;
+    let mut mqtt_client = create_mqtt_client(mqtt_config).await?;
 
     // Publish supported configuration types
     let msg = plugin_config.to_supported_config_types_message()?;
@@ -182,6 +186,7 @@ async fn run(
                     http_client,
                     local_http_host,
                     tmp_dir.clone(),
+                    download_settings,
                     tedge_device_id.as_str(),
                     config_dir
                 )
@@ -209,6 +214,7 @@ async fn process_mqtt_message(
     http_client: &mut impl C8YHttpProxy,
     local_http_host: &str,
     tmp_dir: PathBuf,
+    download_settings: &DownloadSettings,
     tedge_device_id: &str,
     config_dir: &Path,
 ) -> Result<(), anyhow::Error> {
@@ -261,6 +267,7 @@ async fn process_mqtt_message(
                             handle_config_download_request(
                                 config_download_request,
                                 tmp_dir.clone(),
+                                download_settings,
                                 mqtt_client,
                                 http_client,
                                 local_http_host,
@@ -411,10 +418,11 @@ mod tests {
         tokio::spawn(async move {
             let _ = run(
                 tedge_device_id.into(),
-                broker.port,
+                mqtt_channel::Config::default().with_port(broker.port),
                 &mut http_client,
                 "localhost",
                 ttd.path().to_path_buf(),
+                &DownloadSettings::default(),
                 ttd.path(),
                 test_config_path,
             )
@@ -488,10 +496,11 @@ mod tests {
         tokio::spawn(async move {
             let _ = run(
                 tedge_device_id.into(),
-                broker.port,
+                mqtt_channel::Config::default().with_port(broker.port),
                 &mut c8y_http_client,
                 &server_address,
                 tmp_dir.path().to_path_buf(),
+                &DownloadSettings::default(),
                 tmp_dir.path(),
                 test_config_path,
             )
@@ -540,10 +549,11 @@ mod tests {
         tokio::spawn(async move {
             let _ = run(
                 tedge_device_id.into(),
-                broker.port,
+                mqtt_channel::Config::default().with_port(broker.port),
                 &mut c8y_http_client,
                 &mockito::server_url(),
                 tmp_dir.path().to_path_buf(),
+                &DownloadSettings::default(),
                 tmp_dir.path(),
                 test_config_path,
             )
@@ -597,10 +607,11 @@ mod tests {
         tokio::spawn(async move {
             let _ = run(
                 tedge_device_id.into(),
-                broker.port,
+                mqtt_channel::Config::default().with_port(broker.port),
                 &mut c8y_http_client,
                 &mockito::server_url(),
                 tmp_dir.path().to_path_buf(),
+                &DownloadSettings::default(),
                 tmp_dir.path(),
                 test_config_path,
             )
@@ -665,10 +676,11 @@ mod tests {
         tokio::spawn(async move {
             let _ = run(
                 tedge_device_id.into(),
-                broker.port,
+                mqtt_channel::Config::default().with_port(broker.port),
                 &mut c8y_http_client,
                 &local_http_host,
                 tmp_dir.path().to_path_buf(),
+                &DownloadSettings::default(),
                 tmp_dir.path(),
                 test_config_path,
             )
@@ -742,10 +754,11 @@ mod tests {
         tokio::spawn(async move {
             let _ = run(
                 tedge_device_id.into(),
-                broker.port,
+                mqtt_channel::Config::default().with_port(broker.port),
                 &mut c8y_http_client,
                 local_http_host.as_str(),
                 tmp_dir.path().to_path_buf(),
+                &DownloadSettings::default(),
                 tmp_dir.path(),
                 test_config_path,
             )
@@ -827,10 +840,11 @@ mod tests {
         tokio::spawn(async move {
             let _ = run(
                 tedge_device_id.into(),
-                broker.port,
+                mqtt_channel::Config::default().with_port(broker.port),
                 &mut c8y_http_client,
                 &local_http_host,
                 tmp_dir.path().to_path_buf(),
+                &DownloadSettings::default(),
                 tmp_dir.path(),
                 test_config_path,
             )
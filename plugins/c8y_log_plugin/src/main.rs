@@ -12,14 +12,13 @@ use clap::Parser;
 use mqtt_channel::{Connection, Message, StreamExt, TopicFilter};
 use std::path::{Path, PathBuf};
 use tedge_config::{
-    ConfigRepository, ConfigSettingAccessor, LogPathSetting, MqttPortSetting, TEdgeConfig,
-    DEFAULT_TEDGE_CONFIG_PATH,
+    ConfigRepository, ConfigSettingAccessor, LogPathSetting, TEdgeConfig, DEFAULT_TEDGE_CONFIG_PATH,
 };
 use tedge_utils::{
     file::{create_directory_with_user_group, create_file_with_user_group},
     fs_notify::{fs_notify_stream, pin_mut, FileEvent},
 };
-use thin_edge_json::health::{health_check_topics, send_health_status};
+use thin_edge_json::health::{health_check_topics, send_health_status, with_health_status};
 use tracing::{error, info};
 
 use crate::config::LogPluginConfig;
@@ -60,16 +59,14 @@ pub struct LogfileRequestPluginOpt {
 async fn create_mqtt_client(
     tedge_config: &TEdgeConfig,
 ) -> Result<mqtt_channel::Connection, anyhow::Error> {
-    let mqtt_port = tedge_config.query(MqttPortSetting)?.into();
     let mut topics: TopicFilter = health_check_topics("c8y-log-plugin");
 
     topics.add_unchecked(C8yTopic::SmartRestRequest.as_str());
     // subscribing also to c8y bridge health topic to know when the bridge is up
     topics.add(C8Y_BRIDGE_HEALTH_TOPIC)?;
 
-    let mqtt_config = mqtt_channel::Config::default()
+    let mqtt_config = with_health_status(tedge_config.mqtt_config()?, "c8y-log-plugin")
         .with_session_name("c8y-log-plugin")
-        .with_port(mqtt_port)
         .with_subscriptions(topics);
 
     let mqtt_client = mqtt_channel::Connection::new(&mqtt_config).await?;
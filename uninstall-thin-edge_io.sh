@@ -4,9 +4,9 @@ set -e
 
 # Here don't need to remove/purge the tedge_mapper, tedge_agent, and tedge_watchdog packages explicitly,
 # as they will be removed by removing the tedge package.
-packages=("tedge" "tedge_apt_plugin" "tedge_apama_plugin" "c8y_log_plugin" "c8y_configuration_plugin")
+packages=("tedge" "tedge_apt_plugin" "tedge_apama_plugin" "c8y_log_plugin" "c8y_configuration_plugin" "tedge_store")
 
-extension_services=("tedge-watchdog.service" "tedge-mapper-collectd.service" "c8y-log-plugin.service" "c8y-configuration-plugin.service")
+extension_services=("tedge-watchdog.service" "tedge-mapper-collectd.service" "c8y-log-plugin.service" "c8y-configuration-plugin.service" "tedge-store.service")
 
 clouds=("c8y" "az")
 